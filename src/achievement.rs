@@ -0,0 +1,202 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// A criterion that must be met to complete an achievement.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AchievementCriterion<K, C> {
+    /// The stat condition must be true.
+    Stat(StatCondition<K>),
+    /// The counter must reach the target value.
+    Counter {
+        /// The key of the counter, like "mobs_killed".
+        counter: C,
+        /// The value to reach.
+        target: f64,
+    },
+}
+
+impl<K: Hash + Eq + Debug, C> AchievementCriterion<K, C> {
+    /// Returns the progress towards meeting this criterion, between 0.0 and 1.0.
+    /// `counters` must return the current value of the counter with the provided key.
+    pub fn progress<F: Fn(&C) -> f64>(
+        &self,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        counters: F,
+    ) -> f64 {
+        match self {
+            AchievementCriterion::Stat(condition) => {
+                if condition.check(stats, stat_defs) {
+                    1.0
+                } else if let StatConditionType::MinValue(min) = condition.condition {
+                    let value = stats
+                        .stats
+                        .get(&condition.stat_key)
+                        .map(|s| s.value)
+                        .unwrap_or(0.0);
+                    if min > 0.0 {
+                        (value / min).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    }
+                } else {
+                    0.0
+                }
+            }
+            AchievementCriterion::Counter { counter, target } => {
+                if *target <= 0.0 {
+                    1.0
+                } else {
+                    (counters(counter) / target).clamp(0.0, 1.0)
+                }
+            }
+        }
+    }
+}
+
+/// The definition of an achievement.
+///
+/// # Generics
+/// - A: Achievement Key
+/// - K: Stat Key
+/// - C: Counter Key
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct AchievementDefinition<A, K, C> {
+    /// The key of this achievement.
+    pub key: A,
+    /// The display name of this achievement.
+    pub name: String,
+    /// The display description of this achievement.
+    pub description: String,
+    /// Whether this achievement is hidden until it is completed.
+    #[new(default)]
+    #[builder(default)]
+    pub hidden: bool,
+    /// The criteria that must all be met to complete this achievement.
+    pub criteria: Vec<AchievementCriterion<K, C>>,
+}
+
+impl<A, K: Hash + Eq + Debug, C> AchievementDefinition<A, K, C> {
+    /// Returns the progress towards completing this achievement, between 0.0 and 1.0.
+    /// This is the average progress of all the criteria.
+    /// `counters` must return the current value of the counter with the provided key.
+    pub fn progress<F: Fn(&C) -> f64>(
+        &self,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        counters: F,
+    ) -> f64 {
+        if self.criteria.is_empty() {
+            return 1.0;
+        }
+        self.criteria
+            .iter()
+            .map(|c| c.progress(stats, stat_defs, &counters))
+            .sum::<f64>()
+            / self.criteria.len() as f64
+    }
+
+    /// Checks if all the criteria are met.
+    pub fn is_met<F: Fn(&C) -> f64>(
+        &self,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        counters: F,
+    ) -> bool {
+        self.criteria
+            .iter()
+            .all(|c| c.progress(stats, stat_defs, &counters) >= 1.0)
+    }
+}
+
+/// The definitions of all known achievements.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct AchievementDefinitions<A: Hash + Eq, K, C> {
+    /// The definitions.
+    pub defs: HashMap<A, AchievementDefinition<A, K, C>>,
+}
+
+impl<A: Hash + Eq, K, C> Default for AchievementDefinitions<A, K, C> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<A: Hash + Eq + Clone, K, C> From<Vec<AchievementDefinition<A, K, C>>>
+    for AchievementDefinitions<A, K, C>
+{
+    fn from(t: Vec<AchievementDefinition<A, K, C>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+/// The achievements completed by a player.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct AchievementSet<A: Hash + Eq> {
+    /// The keys of the completed achievements.
+    #[new(default)]
+    pub completed: HashSet<A>,
+}
+
+impl<A: Hash + Eq + Clone> AchievementSet<A> {
+    /// Checks if the achievement is completed.
+    pub fn is_completed(&self, key: &A) -> bool {
+        self.completed.contains(key)
+    }
+
+    /// Returns the progress towards completing the achievement, between 0.0 and 1.0.
+    /// `counters` must return the current value of the counter with the provided key.
+    pub fn progress<K: Hash + Eq + Debug, C, F: Fn(&C) -> f64>(
+        &self,
+        key: &A,
+        achievement_defs: &AchievementDefinitions<A, K, C>,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        counters: F,
+    ) -> f64 {
+        if self.is_completed(key) {
+            1.0
+        } else {
+            achievement_defs
+                .defs
+                .get(key)
+                .expect("Tried to get unknown achievement key.")
+                .progress(stats, stat_defs, counters)
+        }
+    }
+
+    /// Completes all the achievements whose criteria are met.
+    /// Returns an event for each newly completed achievement.
+    /// `counters` must return the current value of the counter with the provided key.
+    pub fn update<K: Hash + Eq + Debug, C, F: Fn(&C) -> f64>(
+        &mut self,
+        achievement_defs: &AchievementDefinitions<A, K, C>,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        counters: F,
+    ) -> Vec<AchievementEvent<A>> {
+        let mut events = vec![];
+        for (key, def) in achievement_defs.defs.iter() {
+            if !self.is_completed(key) && def.is_met(stats, stat_defs, &counters) {
+                self.completed.insert(key.clone());
+                events.push(AchievementEvent::Completed(key.clone()));
+            }
+        }
+        events
+    }
+}
+
+/// An event emitted by an `AchievementSet`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AchievementEvent<A> {
+    /// The achievement was completed.
+    Completed(A),
+}
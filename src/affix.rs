@@ -0,0 +1,192 @@
+use crate::*;
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// Where the name of an affix goes relative to the name of the item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AffixKind {
+    /// Before the name of the item, like "Burning" in "Burning Sword".
+    Prefix,
+    /// After the name of the item, like "of the Bear" in "Sword of the Bear".
+    Suffix,
+}
+
+/// The definition of an affix, or enchantment, that can be rolled on items.
+///
+/// # Generics
+/// - K: Affix Key
+/// - E: Effector Key
+/// - S: Item Slot Type
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct AffixDefinition<K, E, S> {
+    /// The key of this affix.
+    pub key: K,
+    /// The display name of this affix.
+    pub name: String,
+    /// Whether this affix is a prefix or a suffix.
+    pub kind: AffixKind,
+    /// The effectors granted to the holder of an item with this affix.
+    pub stat_effectors: Vec<E>,
+    /// The slot types of the items this affix can be rolled on.
+    /// Empty means all slot types.
+    #[new(default)]
+    #[builder(default)]
+    pub allowed_slot_types: Vec<S>,
+    /// The minimum item tier at which this affix can be rolled, inclusive.
+    pub min_tier: u32,
+    /// The maximum item tier at which this affix can be rolled, inclusive.
+    pub max_tier: u32,
+    /// The weight of this affix when rolling. See `LootTreeNode::chances`.
+    pub weight: i32,
+}
+
+impl<K, E, S: PartialEq> AffixDefinition<K, E, S> {
+    /// Checks if this affix can be rolled on an item of this slot type and tier.
+    pub fn can_roll_on(&self, slot_type: &S, tier: u32) -> bool {
+        (self.allowed_slot_types.is_empty() || self.allowed_slot_types.contains(slot_type))
+            && tier >= self.min_tier
+            && tier <= self.max_tier
+    }
+}
+
+/// The definitions of all known affixes.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct AffixDefinitions<K: Hash + Eq, E, S> {
+    /// The definitions.
+    pub defs: HashMap<K, AffixDefinition<K, E, S>>,
+}
+
+impl<K: Hash + Eq, E, S> Default for AffixDefinitions<K, E, S> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, E, S> From<Vec<AffixDefinition<K, E, S>>> for AffixDefinitions<K, E, S> {
+    fn from(t: Vec<AffixDefinition<K, E, S>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<K: Hash + Eq + Ord + Clone + 'static, E, S: PartialEq> AffixDefinitions<K, E, S> {
+    /// Rolls up to `prefixes` prefixes and `suffixes` suffixes that can be rolled on an item of
+    /// this slot type and tier, using the weight of the affixes.
+    /// The same affix is never rolled twice.
+    /// The affixes are considered in the order of their keys, so the same random number
+    /// generator state always rolls the same affixes.
+    #[cfg(feature = "std")]
+    pub fn roll(&self, slot_type: &S, tier: u32, prefixes: usize, suffixes: usize) -> Vec<K> {
+        self.roll_with(slot_type, tier, prefixes, suffixes, &mut thread_rng())
+    }
+
+    /// Same as `AffixDefinitions::roll`, using the provided random number generator.
+    pub fn roll_with<G: Rng>(
+        &self,
+        slot_type: &S,
+        tier: u32,
+        prefixes: usize,
+        suffixes: usize,
+        rng: &mut G,
+    ) -> Vec<K> {
+        let mut rolled = vec![];
+        for (kind, count) in [(AffixKind::Prefix, prefixes), (AffixKind::Suffix, suffixes)].iter() {
+            for _ in 0..*count {
+                let mut candidates = self
+                    .defs
+                    .values()
+                    .filter(|d| {
+                        d.kind == *kind
+                            && d.can_roll_on(slot_type, tier)
+                            && !rolled.contains(&d.key)
+                    })
+                    .collect::<Vec<_>>();
+                candidates.sort_by(|a, b| a.key.cmp(&b.key));
+                let mut builder = LootTreeBuilder::new();
+                builder.nodes = candidates
+                    .into_iter()
+                    .map(|d| LootTreeNode {
+                        chances: d.weight,
+                        result: d.key.clone(),
+                        guaranteed: false,
+                    })
+                    .collect();
+                match builder.build().roll_with(rng) {
+                    Some(key) => rolled.push(key),
+                    None => break,
+                }
+            }
+        }
+        rolled
+    }
+}
+
+/// An `ItemInstance` with the affixes that were rolled on it.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct EnchantedItem<K, U: Default, A> {
+    /// The item.
+    pub item: ItemInstance<K, U>,
+    /// The keys of the affixes of the item.
+    pub affixes: Vec<A>,
+}
+
+impl<K, U: Default, A: Hash + Eq> EnchantedItem<K, U, A> {
+    /// Returns the effectors granted by all the affixes of the item.
+    pub fn stat_effectors<'a, E, S>(
+        &self,
+        affix_defs: &'a AffixDefinitions<A, E, S>,
+    ) -> Vec<&'a E> {
+        self.affixes
+            .iter()
+            .flat_map(|a| {
+                affix_defs
+                    .defs
+                    .get(a)
+                    .expect("Tried to get unknown affix key.")
+                    .stat_effectors
+                    .iter()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn affix_defs() -> AffixDefinitions<u32, u32, ()> {
+        AffixDefinitions::from(
+            (0..20)
+                .map(|key| {
+                    let kind = if key % 2 == 0 {
+                        AffixKind::Prefix
+                    } else {
+                        AffixKind::Suffix
+                    };
+                    AffixDefinition::new(key, format!("Affix {}", key), kind, vec![], 0, 10, 1)
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn same_seed_rolls_same_affixes() {
+        // Each `AffixDefinitions` has its own hash map iteration order.
+        let expected = affix_defs().roll_with(&(), 1, 2, 2, &mut seeded_rng(7));
+        for _ in 0..10 {
+            assert_eq!(
+                affix_defs().roll_with(&(), 1, 2, 2, &mut seeded_rng(7)),
+                expected
+            );
+        }
+        assert_eq!(expected.len(), 4);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The classic alignment axes.
+/// Games can use their own axis keys instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StandardAxis {
+    /// Positive values are good, negative values are evil.
+    GoodEvil,
+    /// Positive values are lawful, negative values are chaotic.
+    LawfulChaotic,
+}
+
+/// The rules of an alignment axis.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct AlignmentAxis {
+    /// The minimum value of the axis.
+    pub min_value: f64,
+    /// The maximum value of the axis.
+    pub max_value: f64,
+    /// The value the axis decays toward. It is also the value of new alignments.
+    #[new(default)]
+    #[serde(default)]
+    pub neutral: f64,
+    /// How much the value moves toward neutral each second.
+    #[new(default)]
+    #[serde(default)]
+    pub decay_per_second: f64,
+}
+
+/// The alignment axes and how the actions reported to an `Alignment` shift them.
+///
+/// # Generics
+/// - A: Alignment Axis Key
+/// - T: Action Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct AlignmentSettings<A: Hash + Eq, T: Hash + Eq> {
+    /// The axes, by key.
+    pub axes: HashMap<A, AlignmentAxis>,
+    /// The shift applied to each axis when an action is reported.
+    #[new(default)]
+    pub actions: HashMap<T, Vec<(A, f64)>>,
+}
+
+/// The alignment of a character, like how good or evil and how lawful or chaotic it is.
+/// It shifts from the actions reported to it and decays toward neutral over time.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Alignment<A: Hash + Eq> {
+    /// The value of each axis.
+    /// Axes that are not in this map are at their neutral value.
+    #[new(default)]
+    pub values: HashMap<A, f64>,
+}
+
+impl<A: Hash + Eq> Default for Alignment<A> {
+    fn default() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+}
+
+impl<A: Hash + Eq + Clone> Alignment<A> {
+    /// Returns the value of the axis.
+    pub fn value<T: Hash + Eq>(&self, axis: &A, settings: &AlignmentSettings<A, T>) -> f64 {
+        self.values.get(axis).copied().unwrap_or_else(|| {
+            settings
+                .axes
+                .get(axis)
+                .expect("Tried to get unknown alignment axis key.")
+                .neutral
+        })
+    }
+
+    /// Shifts the value of the axis, keeping it between the minimum and maximum of the axis.
+    /// Use negative amounts to shift toward the minimum.
+    pub fn shift<T: Hash + Eq>(
+        &mut self,
+        axis: &A,
+        amount: f64,
+        settings: &AlignmentSettings<A, T>,
+    ) {
+        let def = settings
+            .axes
+            .get(axis)
+            .expect("Tried to get unknown alignment axis key.");
+        let new = (self.value(axis, settings) + amount).clamp(def.min_value, def.max_value);
+        self.values.insert(axis.clone(), new);
+    }
+
+    /// Applies the shifts of the action.
+    /// Actions without shifts are ignored.
+    pub fn report<T: Hash + Eq>(&mut self, action: &T, settings: &AlignmentSettings<A, T>) {
+        if let Some(shifts) = settings.actions.get(action) {
+            for (axis, amount) in shifts {
+                self.shift(axis, *amount, settings);
+            }
+        }
+    }
+
+    /// Moves all axes toward their neutral value over `delta_time` seconds.
+    pub fn update<T: Hash + Eq>(&mut self, delta_time: f64, settings: &AlignmentSettings<A, T>) {
+        for (axis, value) in self.values.iter_mut() {
+            let def = settings
+                .axes
+                .get(axis)
+                .expect("Tried to get unknown alignment axis key.");
+            let decay = def.decay_per_second * delta_time;
+            if *value > def.neutral {
+                *value = (*value - decay).max(def.neutral);
+            } else {
+                *value = (*value + decay).min(def.neutral);
+            }
+        }
+    }
+
+    /// Writes the values of the axes into the stats selected by the hooks, so they can be used
+    /// by `StatCondition`s in skills, effectors and item transitions.
+    /// The stats that are not in the `StatSet` or in the `StatDefinitions` are ignored.
+    pub fn write_stats<K: Hash + Eq + Clone + Debug, T: Hash + Eq>(
+        &self,
+        hooks: &HashMap<A, K>,
+        stats: &mut StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        settings: &AlignmentSettings<A, T>,
+    ) {
+        for (axis, key) in hooks.iter() {
+            stats.set(key, self.value(axis, settings), stat_defs);
+        }
+    }
+}
+
+/// A condition on an `Alignment`, for example to gate dialogue options or faction interactions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AlignmentCondition<A> {
+    /// The value of the axis is greater than or equal to this value.
+    AtLeast(A, f64),
+    /// The value of the axis is lower than or equal to this value.
+    AtMost(A, f64),
+    /// The value of the axis is between these two values, included.
+    Between(A, f64, f64),
+}
+
+impl<A: Hash + Eq + Clone> AlignmentCondition<A> {
+    /// Checks if the condition is met by the alignment.
+    pub fn check<T: Hash + Eq>(
+        &self,
+        alignment: &Alignment<A>,
+        settings: &AlignmentSettings<A, T>,
+    ) -> bool {
+        match self {
+            AlignmentCondition::AtLeast(axis, min) => alignment.value(axis, settings) >= *min,
+            AlignmentCondition::AtMost(axis, max) => alignment.value(axis, settings) <= *max,
+            AlignmentCondition::Between(axis, min, max) => {
+                let value = alignment.value(axis, settings);
+                value >= *min && value <= *max
+            }
+        }
+    }
+}
+
+/// Alignment conditions combined together, like "good and not lawful".
+pub type AlignmentConditionGroup<A> = ConditionGroup<AlignmentCondition<A>>;
+
+impl<A: Hash + Eq + Clone> ConditionGroup<AlignmentCondition<A>> {
+    /// Checks if the conditions are met by the alignment.
+    pub fn check<T: Hash + Eq>(
+        &self,
+        alignment: &Alignment<A>,
+        settings: &AlignmentSettings<A, T>,
+    ) -> bool {
+        self.check_with(&|c| c.check(alignment, settings))
+    }
+}
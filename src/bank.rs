@@ -0,0 +1,415 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The maximum amounts that can be moved in a single operation with a `Bank`.
+/// None means no limit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct BankLimits {
+    /// The maximum amount of currency deposited at once.
+    pub max_deposit: Option<i64>,
+    /// The maximum amount of currency withdrawn at once.
+    pub max_withdraw: Option<i64>,
+    /// The maximum quantity of items deposited at once.
+    pub max_item_deposit: Option<usize>,
+    /// The maximum quantity of items withdrawn at once.
+    pub max_item_withdraw: Option<usize>,
+}
+
+/// A bank storing items and currencies for one or more users.
+/// Slots can be locked to prevent items from being moved in or out of them and the
+/// currencies can earn interest each game day.
+///
+/// # Generics
+/// - K: Item Key
+/// - S: Item Slot Type
+/// - U: Item Custom Data
+/// - C: Currency Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Bank<K, S: SlotType, U: Default, C: Hash + Eq> {
+    /// The items stored in the bank.
+    pub inventory: Inventory<K, S, U>,
+    /// The currencies stored in the bank.
+    #[new(value = "Wallet::new()")]
+    pub wallet: Wallet<C>,
+    /// The limits of the deposits and withdrawals.
+    #[new(default)]
+    #[serde(default)]
+    pub limits: BankLimits,
+    /// The slots of the inventory that are locked.
+    #[new(default)]
+    #[serde(default)]
+    pub locked_slots: HashSet<usize>,
+    /// What each user can do with the bank.
+    #[new(default)]
+    #[serde(default)]
+    pub user_access: HashMap<i32, InventoryAccess>,
+    /// What the users not present in `user_access` can do with the bank.
+    pub default_access: InventoryAccess,
+    /// The fraction of each currency balance earned as interest each game day.
+    /// None means that no interest is earned.
+    #[new(default)]
+    #[serde(default)]
+    pub interest_rate: Option<f64>,
+    /// The last game day for which interest was earned.
+    /// Set it to the current day of the `GameClock` when creating the bank, otherwise interest
+    /// is earned for all the days since day 0.
+    #[new(default)]
+    #[serde(default)]
+    pub last_interest_day: u64,
+}
+
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType + Clone,
+        U: Default + Clone + Debug + PartialEq,
+        C: Hash + Eq + Clone,
+    > Bank<K, S, U, C>
+{
+    /// Returns what the user can do with the bank.
+    pub fn access_of(&self, user: i32) -> InventoryAccess {
+        self.user_access
+            .get(&user)
+            .copied()
+            .unwrap_or(self.default_access)
+    }
+
+    /// Locks the slot, preventing items from being moved in or out of it.
+    pub fn lock_slot(&mut self, idx: usize) {
+        self.locked_slots.insert(idx);
+    }
+
+    /// Unlocks the slot.
+    pub fn unlock_slot(&mut self, idx: usize) {
+        self.locked_slots.remove(&idx);
+    }
+
+    /// Checks if the slot is locked.
+    pub fn is_locked(&self, idx: usize) -> bool {
+        self.locked_slots.contains(&idx)
+    }
+
+    /// Moves a quantity of items from a slot of the inventory of the user into a slot of the
+    /// bank.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * AccessDenied: The user can't deposit into this bank.
+    /// * InvalidAmount: The quantity is 0.
+    /// * AboveLimit: The quantity is over `BankLimits::max_item_deposit`.
+    /// * Item(LockedRemoteSlot): The slot of the bank is locked.
+    /// * Item: See `Inventory::delete` and `Inventory::insert_into`.
+    pub fn deposit_item<D: Default>(
+        &mut self,
+        user: i32,
+        from: &mut Inventory<K, S, U>,
+        from_idx: usize,
+        to_idx: usize,
+        quantity: usize,
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Result<(), BankError<K, U, C>> {
+        if !self.access_of(user).can_insert() {
+            return Err(BankError::AccessDenied);
+        }
+        if quantity == 0 {
+            return Err(BankError::InvalidAmount);
+        }
+        if self
+            .limits
+            .max_item_deposit
+            .map(|max| quantity > max)
+            .unwrap_or(false)
+        {
+            return Err(BankError::AboveLimit);
+        }
+        if self.is_locked(to_idx) {
+            return Err(BankError::Item(ItemError::LockedRemoteSlot));
+        }
+        move_item(
+            from,
+            from_idx,
+            &mut self.inventory,
+            to_idx,
+            quantity,
+            item_defs,
+        )
+        .map_err(BankError::Item)
+    }
+
+    /// Moves a quantity of items from a slot of the bank into a slot of the inventory of the
+    /// user.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * AccessDenied: The user can't withdraw from this bank.
+    /// * InvalidAmount: The quantity is 0.
+    /// * AboveLimit: The quantity is over `BankLimits::max_item_withdraw`.
+    /// * Item(LockedOriginSlot): The slot of the bank is locked.
+    /// * Item: See `Inventory::delete` and `Inventory::insert_into`.
+    pub fn withdraw_item<D: Default>(
+        &mut self,
+        user: i32,
+        from_idx: usize,
+        to: &mut Inventory<K, S, U>,
+        to_idx: usize,
+        quantity: usize,
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Result<(), BankError<K, U, C>> {
+        if !self.access_of(user).can_remove() {
+            return Err(BankError::AccessDenied);
+        }
+        if quantity == 0 {
+            return Err(BankError::InvalidAmount);
+        }
+        if self
+            .limits
+            .max_item_withdraw
+            .map(|max| quantity > max)
+            .unwrap_or(false)
+        {
+            return Err(BankError::AboveLimit);
+        }
+        if self.is_locked(from_idx) {
+            return Err(BankError::Item(ItemError::LockedOriginSlot));
+        }
+        move_item(
+            &mut self.inventory,
+            from_idx,
+            to,
+            to_idx,
+            quantity,
+            item_defs,
+        )
+        .map_err(BankError::Item)
+    }
+
+    /// Moves an amount of currency from the wallet of the user into the bank.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * AccessDenied: The user can't deposit into this bank.
+    /// * InvalidAmount: The amount is 0 or less.
+    /// * AboveLimit: The amount is over `BankLimits::max_deposit`.
+    /// * Wallet: See `Wallet::transfer`.
+    pub fn deposit_currency(
+        &mut self,
+        user: i32,
+        from: &mut Wallet<C>,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), BankError<K, U, C>> {
+        if !self.access_of(user).can_insert() {
+            return Err(BankError::AccessDenied);
+        }
+        if amount <= 0 {
+            return Err(BankError::InvalidAmount);
+        }
+        if self
+            .limits
+            .max_deposit
+            .map(|max| amount > max)
+            .unwrap_or(false)
+        {
+            return Err(BankError::AboveLimit);
+        }
+        from.transfer(&mut self.wallet, currency, amount, currency_defs)
+            .map_err(BankError::Wallet)
+    }
+
+    /// Moves an amount of currency from the bank into the wallet of the user.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * AccessDenied: The user can't withdraw from this bank.
+    /// * InvalidAmount: The amount is 0 or less.
+    /// * AboveLimit: The amount is over `BankLimits::max_withdraw`.
+    /// * Wallet: See `Wallet::transfer`.
+    pub fn withdraw_currency(
+        &mut self,
+        user: i32,
+        to: &mut Wallet<C>,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), BankError<K, U, C>> {
+        if !self.access_of(user).can_remove() {
+            return Err(BankError::AccessDenied);
+        }
+        if amount <= 0 {
+            return Err(BankError::InvalidAmount);
+        }
+        if self
+            .limits
+            .max_withdraw
+            .map(|max| amount > max)
+            .unwrap_or(false)
+        {
+            return Err(BankError::AboveLimit);
+        }
+        self.wallet
+            .transfer(to, currency, amount, currency_defs)
+            .map_err(BankError::Wallet)
+    }
+
+    /// Adds the interest earned by each positive balance for every game day elapsed since
+    /// `last_interest_day`. Interest is compounded daily and rounded down.
+    /// Balances stop growing at the maximum of their currency.
+    /// Returns the amount earned for each currency that earned interest.
+    pub fn accrue_interest(
+        &mut self,
+        clock: &GameClock,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Vec<(C, i64)> {
+        let today = clock.day();
+        let days = today.saturating_sub(self.last_interest_day);
+        self.last_interest_day = self.last_interest_day.max(today);
+        let rate = match self.interest_rate {
+            Some(rate) if days > 0 => rate,
+            _ => return vec![],
+        };
+        let growth = (1.0 + rate).powf(days as f64) - 1.0;
+        let mut earned = vec![];
+        for (currency, balance) in self.wallet.balances.iter_mut() {
+            if *balance <= 0 {
+                continue;
+            }
+            let mut new = balance.saturating_add((*balance as f64 * growth).floor() as i64);
+            if let Some(max) = currency_defs.defs.get(currency).and_then(|d| d.max_value) {
+                new = new.min(max.max(*balance));
+            }
+            if new > *balance {
+                earned.push((currency.clone(), new - *balance));
+                *balance = new;
+            }
+        }
+        earned
+    }
+}
+
+/// Moves items between two inventories, restoring the origin if the insertion fails.
+pub(crate) fn move_item<
+    K: PartialEq + Clone + Debug + Hash + Eq,
+    S: SlotType + Clone,
+    U: Default + Clone + Debug + PartialEq,
+    D: Default,
+>(
+    from: &mut Inventory<K, S, U>,
+    from_idx: usize,
+    to: &mut Inventory<K, S, U>,
+    to_idx: usize,
+    quantity: usize,
+    item_defs: &ItemDefinitions<K, S, D>,
+) -> Result<(), ItemError<K, U>> {
+    let backup = from.clone();
+    let item = from.delete(from_idx, quantity)?;
+    if let Err(e) = to.insert_into(to_idx, item, item_defs) {
+        *from = backup;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// The errors that can happen when using a `Bank`.
+#[derive(Debug)]
+pub enum BankError<K: PartialEq + Debug, U: Default, C> {
+    /// The user doesn't have the access required for this operation.
+    AccessDenied,
+    /// The amount or quantity is 0 or less.
+    InvalidAmount,
+    /// The amount or quantity is over the limits of the bank.
+    AboveLimit,
+    /// An error happened while moving items.
+    Item(ItemError<K, U>),
+    /// An error happened while moving currencies.
+    Wallet(WalletError<C>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn bank() -> Bank<u32, (), (), u8> {
+        let mut bank = Bank::new(Inventory::new_fixed(2), InventoryAccess::DepositOnly);
+        bank.user_access.insert(1, InventoryAccess::Full);
+        bank
+    }
+
+    #[test]
+    fn invalid_currency_amounts_are_rejected() {
+        let defs = currency_defs();
+        let mut bank = bank();
+        let mut wallet = Wallet::new();
+        wallet.add(&0, 100, &defs).unwrap();
+        bank.deposit_currency(1, &mut wallet, &0, 50, &defs)
+            .unwrap();
+        // A negative deposit would be a withdrawal by a user without the withdraw access.
+        assert!(matches!(
+            bank.deposit_currency(2, &mut wallet, &0, -50, &defs),
+            Err(BankError::InvalidAmount)
+        ));
+        assert!(matches!(
+            bank.withdraw_currency(1, &mut wallet, &0, -50, &defs),
+            Err(BankError::InvalidAmount)
+        ));
+        assert!(matches!(
+            bank.withdraw_currency(2, &mut wallet, &0, 10, &defs),
+            Err(BankError::AccessDenied)
+        ));
+        assert_eq!(bank.wallet.balance(&0), 50);
+        assert_eq!(wallet.balance(&0), 50);
+    }
+
+    #[test]
+    fn currency_limits() {
+        let defs = currency_defs();
+        let mut bank = bank();
+        bank.limits.max_withdraw = Some(10);
+        let mut wallet = Wallet::new();
+        wallet.add(&0, 100, &defs).unwrap();
+        bank.deposit_currency(2, &mut wallet, &0, 50, &defs)
+            .unwrap();
+        assert!(matches!(
+            bank.withdraw_currency(1, &mut wallet, &0, 20, &defs),
+            Err(BankError::AboveLimit)
+        ));
+        bank.withdraw_currency(1, &mut wallet, &0, 10, &defs)
+            .unwrap();
+        assert_eq!(bank.wallet.balance(&0), 40);
+        assert_eq!(wallet.balance(&0), 60);
+    }
+
+    #[test]
+    fn item_moves() {
+        let defs = item_defs();
+        let mut bank = bank();
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(2);
+        inventory.insert(ItemInstance::new(1, 5), &defs).unwrap();
+        assert!(matches!(
+            bank.deposit_item(1, &mut inventory, 0, 0, 0, &defs),
+            Err(BankError::InvalidAmount)
+        ));
+        bank.deposit_item(2, &mut inventory, 0, 0, 3, &defs)
+            .unwrap();
+        bank.lock_slot(0);
+        assert!(matches!(
+            bank.withdraw_item(1, 0, &mut inventory, 1, 1, &defs),
+            Err(BankError::Item(ItemError::LockedOriginSlot))
+        ));
+        bank.unlock_slot(0);
+        assert!(matches!(
+            bank.withdraw_item(2, 0, &mut inventory, 1, 1, &defs),
+            Err(BankError::AccessDenied)
+        ));
+        // The target slot is occupied: nothing moves.
+        assert!(bank
+            .withdraw_item(1, 0, &mut inventory, 0, 1, &defs)
+            .is_err());
+        assert_eq!(bank.inventory.get(0).as_ref().unwrap().quantity, 3);
+        bank.withdraw_item(1, 0, &mut inventory, 1, 1, &defs)
+            .unwrap();
+        assert_eq!(bank.inventory.get(0).as_ref().unwrap().quantity, 2);
+        assert_eq!(inventory.get(1).as_ref().unwrap().quantity, 1);
+    }
+}
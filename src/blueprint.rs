@@ -0,0 +1,247 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The definition of something that can be built, like a wall, a house or a crafting station.
+///
+/// # Generics
+/// - B: Blueprint Key
+/// - T: Structure Key
+/// - K: Stat Key
+/// - I: Item Key
+/// - U: Unlockable Key, for blueprints that must be learned first
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct Blueprint<B, T, K, I, U = ()> {
+    /// The key of this blueprint.
+    pub key: B,
+    /// The display name of this blueprint.
+    #[builder(setter(into))]
+    pub name: LocalizedText,
+    /// The items consumed when the construction starts.
+    pub costs: Vec<(I, usize)>,
+    /// The stat conditions required to start the construction.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub stat_conditions: Vec<StatCondition<K>>,
+    /// The unlockable that must be unlocked before building, like a learned blueprint.
+    /// None means that the blueprint is always known.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Option::default")]
+    pub required_unlock: Option<U>,
+    /// The time to complete the construction, in seconds.
+    pub build_time: f64,
+    /// The structure created when the construction completes.
+    pub structure: T,
+}
+
+/// The definitions of all known blueprints.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Blueprints<B: Hash + Eq, T, K, I, U = ()> {
+    /// The definitions.
+    pub defs: HashMap<B, Blueprint<B, T, K, I, U>>,
+}
+
+impl<B: Hash + Eq, T, K, I, U> Default for Blueprints<B, T, K, I, U> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<B: Hash + Eq + Clone, T, K, I, U> From<Vec<Blueprint<B, T, K, I, U>>>
+    for Blueprints<B, T, K, I, U>
+{
+    fn from(t: Vec<Blueprint<B, T, K, I, U>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<B: Hash + Eq, T, K, I, U> Blueprints<B, T, K, I, U> {
+    fn get(&self, key: &B) -> &Blueprint<B, T, K, I, U> {
+        self.defs
+            .get(key)
+            .expect("Tried to get unknown blueprint key.")
+    }
+}
+
+/// Decides where users can build using the claims of the factions.
+/// See `FactionRelations::can_build`.
+#[derive(new)]
+pub struct BuildPermissions<'a> {
+    /// The factions and their claims.
+    pub factions: &'a FactionRepository,
+    /// The relations between the factions.
+    pub relations: &'a FactionRelations,
+    /// The settings of the factions.
+    pub settings: &'a FactionSettings,
+}
+
+impl<'a> BuildPermissions<'a> {
+    /// Checks if the user can build on the claim.
+    pub fn can_build(&self, user: i32, claim: &(i32, i32, i32)) -> bool {
+        self.relations.can_build(
+            self.factions.faction_of_user(user),
+            self.factions.owner_of_claim(claim),
+            self.settings,
+        )
+    }
+}
+
+/// A structure being built.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Construction<B> {
+    /// The blueprint being built.
+    pub blueprint: B,
+    /// The user building it.
+    pub user: i32,
+    /// The claim where the structure is built.
+    pub claim: (i32, i32, i32),
+    /// The time remaining before the construction completes, in seconds.
+    pub remaining: f64,
+}
+
+/// The structures being built by a user or a faction.
+/// The first `max_concurrent` constructions progress at the same time, the others wait.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct BuildQueue<B> {
+    /// The maximum number of constructions progressing at the same time.
+    pub max_concurrent: usize,
+    /// What is given back when a construction is cancelled.
+    pub refund_policy: RefundPolicy,
+    /// The queued constructions, in order.
+    #[new(default)]
+    pub constructions: Vec<Construction<B>>,
+}
+
+impl<B: Hash + Eq + Clone> BuildQueue<B> {
+    /// Starts building the blueprint on the claim and consumes its costs from the inventory.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotAllowed: The user can't build on this claim.
+    /// * Locked: The required unlockable of the blueprint is not unlocked.
+    /// * ConditionsNotMet: The stat conditions are not met.
+    /// * MissingItem: The inventory doesn't have enough of this item.
+    #[allow(clippy::too_many_arguments)]
+    pub fn enqueue<
+        T,
+        K: Hash + Eq + Debug,
+        I: Hash + Eq + Clone + Debug,
+        U: Hash + Eq + Clone,
+        IT: SlotType + Clone,
+        CD: PartialEq + Default + Clone + Debug,
+        LV,
+        S2,
+        I2,
+    >(
+        &mut self,
+        blueprint: B,
+        user: i32,
+        claim: (i32, i32, i32),
+        blueprints: &Blueprints<B, T, K, I, U>,
+        inventory: &mut Inventory<I, IT, CD>,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        unlockables: &Unlockables<U, LV, S2, I2>,
+        permissions: &BuildPermissions,
+    ) -> Result<(), BuildError<I, U>> {
+        let def = blueprints.get(&blueprint);
+        if !permissions.can_build(user, &claim) {
+            return Err(BuildError::NotAllowed);
+        }
+        if let Some(unlock) = def.required_unlock.as_ref() {
+            if !unlockables.is_unlocked(unlock) {
+                return Err(BuildError::Locked(unlock.clone()));
+            }
+        }
+        if !def
+            .stat_conditions
+            .iter()
+            .all(|c| c.check(stats, stat_defs))
+        {
+            return Err(BuildError::ConditionsNotMet);
+        }
+        let mut new_inventory = inventory.clone();
+        for (key, quantity) in def.costs.iter() {
+            new_inventory
+                .use_key(key, *quantity, &UseMode::Consume)
+                .map_err(|_| BuildError::MissingItem(key.clone()))?;
+        }
+        *inventory = new_inventory;
+        self.constructions
+            .push(Construction::new(blueprint, user, claim, def.build_time));
+        Ok(())
+    }
+
+    /// Removes the construction at this index and returns the refunded items according to the
+    /// `RefundPolicy`. The items are not inserted in the inventory.
+    /// Returns None if there is no construction at this index.
+    pub fn cancel<T, K, I: Clone, U, CD: Default>(
+        &mut self,
+        idx: usize,
+        blueprints: &Blueprints<B, T, K, I, U>,
+    ) -> Option<Vec<ItemInstance<I, CD>>> {
+        if idx >= self.constructions.len() {
+            return None;
+        }
+        let construction = self.constructions.remove(idx);
+        Some(
+            blueprints
+                .get(&construction.blueprint)
+                .costs
+                .iter()
+                .map(|(key, quantity)| {
+                    ItemInstance::new(key.clone(), self.refund_policy.refunded(*quantity))
+                })
+                .filter(|ii| ii.quantity > 0)
+                .collect(),
+        )
+    }
+
+    /// Advances the active constructions by `delta_time` seconds and removes the completed
+    /// ones.
+    /// Returns the completed constructions with the structure they created.
+    pub fn update<T: Clone, K, I, U>(
+        &mut self,
+        delta_time: f64,
+        blueprints: &Blueprints<B, T, K, I, U>,
+    ) -> Vec<(Construction<B>, T)> {
+        let mut completed = vec![];
+        let mut idx = 0;
+        let mut active = 0;
+        while idx < self.constructions.len() && active < self.max_concurrent {
+            active += 1;
+            let construction = &mut self.constructions[idx];
+            construction.remaining -= delta_time;
+            if construction.remaining <= 0.0 {
+                let construction = self.constructions.remove(idx);
+                let structure = blueprints.get(&construction.blueprint).structure.clone();
+                completed.push((construction, structure));
+            } else {
+                idx += 1;
+            }
+        }
+        completed
+    }
+}
+
+/// The errors that can happen when starting a construction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError<I, U> {
+    /// The user can't build on this claim.
+    NotAllowed,
+    /// This unlockable must be unlocked before building the blueprint.
+    Locked(U),
+    /// The stat conditions of the blueprint are not met.
+    ConditionsNotMet,
+    /// The inventory doesn't have enough of this item.
+    MissingItem(I),
+}
@@ -0,0 +1,109 @@
+use crate::*;
+use core::hash::Hash;
+
+/// All the state of a character, bundled together to be saved and loaded at once.
+///
+/// # Generics
+/// - K: Stat Key
+/// - E: Effector Key
+/// - S: Skill Key
+/// - I: Item Key
+/// - IT: Item Slot Type
+/// - CD: Item Custom Data
+/// - U: Unlockable Key
+/// - L: Unlockable Inner Value
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct CharacterSheet<
+    K: Hash + Eq,
+    E,
+    S: Hash + Eq,
+    I,
+    IT: SlotType,
+    CD: Default,
+    U: Hash + Eq,
+    L,
+> {
+    /// The stats of the character.
+    pub stats: StatSet<K>,
+    /// The skills the character can use.
+    pub skills: SkillSet<S>,
+    /// The effectors active on the character.
+    pub effectors: EffectorSet<E>,
+    /// The items of the character.
+    pub inventory: Inventory<I, IT, CD>,
+    /// The things the character can unlock.
+    pub unlockables: Unlockables<U, L, K, I>,
+    /// The experience accumulated by the character.
+    #[new(default)]
+    pub experience: u32,
+}
+
+impl<
+        K: Hash + Eq + Clone,
+        E: Hash + Eq + Clone,
+        S: Hash + Eq + Clone,
+        I: Hash + Eq + Clone,
+        IT: SlotType,
+        CD: Default,
+        U: Hash + Eq,
+        L,
+    > CharacterSheet<K, E, S, I, IT, CD, U, L>
+{
+    /// Checks that all the keys used by this character exist in the definitions.
+    /// Returns the unknown keys.
+    pub fn validate<D: Default>(
+        &self,
+        stat_defs: &StatDefinitions<K>,
+        skill_defs: &SkillDefinitions<K, E, S, I>,
+        effector_defs: &EffectorDefinitions<K, E>,
+        item_defs: &ItemDefinitions<I, IT, D>,
+    ) -> Vec<UnknownKey<K, E, S, I>> {
+        let mut unknown = vec![];
+        for k in self.stats.stats.keys() {
+            if !stat_defs.defs.contains_key(k) {
+                unknown.push(UnknownKey::Stat(k.clone()));
+            }
+        }
+        for s in self.skills.skills.keys() {
+            if !skill_defs.defs.contains_key(s) {
+                unknown.push(UnknownKey::Skill(s.clone()));
+            }
+        }
+        for e in self.effectors.effectors.iter() {
+            if !effector_defs.defs.contains_key(&e.effector_key) {
+                unknown.push(UnknownKey::Effector(e.effector_key.clone()));
+            }
+        }
+        for ii in self.inventory.content.iter().flatten() {
+            if !item_defs.defs.contains_key(&ii.key) {
+                unknown.push(UnknownKey::Item(ii.key.clone()));
+            }
+        }
+        for u in self.unlockables.unlockables.values() {
+            for c in u.unlock_stat_conditions.iter() {
+                if !stat_defs.defs.contains_key(&c.stat_key) {
+                    unknown.push(UnknownKey::Stat(c.stat_key.clone()));
+                }
+            }
+            for (i, _, _) in u.unlock_item_conditions.iter() {
+                if !item_defs.defs.contains_key(i) {
+                    unknown.push(UnknownKey::Item(i.clone()));
+                }
+            }
+        }
+        unknown
+    }
+}
+
+/// A key that is not in the definitions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnknownKey<K, E, S, I> {
+    /// A stat key that is not in the `StatDefinitions`.
+    Stat(K),
+    /// An effector key that is not in the `EffectorDefinitions`.
+    Effector(E),
+    /// A skill key that is not in the `SkillDefinitions`.
+    Skill(S),
+    /// An item key that is not in the `ItemDefinitions`.
+    Item(I),
+}
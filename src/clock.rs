@@ -0,0 +1,166 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The in-game time, with days, weeks, seasons and years.
+/// Times of the day are expressed as a fraction of the day, from 0.0 (midnight) to 1.0.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct GameClock {
+    /// The duration of a day, in seconds.
+    pub day_length: f64,
+    /// The number of days in a week.
+    pub days_per_week: u32,
+    /// The number of days in a season.
+    pub days_per_season: u32,
+    /// The number of seasons in a year.
+    pub seasons_per_year: u32,
+    /// The time of the day at which the day starts.
+    pub day_start: f64,
+    /// The time of the day at which the night starts.
+    pub night_start: f64,
+    /// The time elapsed since the start of the first day, in seconds.
+    #[new(default)]
+    pub elapsed: f64,
+}
+
+impl GameClock {
+    /// Advances the clock by `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f64) {
+        self.elapsed += delta_time;
+    }
+
+    /// The number of full days elapsed. The first day is day 0.
+    pub fn day(&self) -> u64 {
+        if self.day_length <= 0.0 {
+            return 0;
+        }
+        (self.elapsed / self.day_length).floor() as u64
+    }
+
+    /// The current time of the day, between 0.0 included and 1.0 excluded.
+    pub fn time_of_day(&self) -> f64 {
+        if self.day_length <= 0.0 {
+            return 0.0;
+        }
+        (self.elapsed / self.day_length).fract()
+    }
+
+    /// The current hour, between 0.0 included and 24.0 excluded.
+    pub fn hour(&self) -> f64 {
+        self.time_of_day() * 24.0
+    }
+
+    /// The current day of the week, starting at 0.
+    pub fn day_of_week(&self) -> u32 {
+        (self.day() % u64::from(self.days_per_week.max(1))) as u32
+    }
+
+    /// The current season, starting at 0.
+    pub fn season(&self) -> u32 {
+        let season = self.day() / u64::from(self.days_per_season.max(1));
+        (season % u64::from(self.seasons_per_year.max(1))) as u32
+    }
+
+    /// The number of full years elapsed.
+    pub fn year(&self) -> u64 {
+        self.day()
+            / (u64::from(self.days_per_season.max(1)) * u64::from(self.seasons_per_year.max(1)))
+    }
+
+    /// Checks if it is currently day time.
+    pub fn is_day(&self) -> bool {
+        is_between(self.time_of_day(), self.day_start, self.night_start)
+    }
+
+    /// Checks if it is currently night time.
+    pub fn is_night(&self) -> bool {
+        !self.is_day()
+    }
+
+    /// Writes the values of the clock into the stats selected by the hooks, so they can be used
+    /// by `StatCondition`s.
+    /// The stats that are not in the `StatSet` or in the `StatDefinitions` are ignored.
+    pub fn write_stats<K: Hash + Eq + Clone + Debug>(
+        &self,
+        hooks: &ClockStats<K>,
+        stats: &mut StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+    ) {
+        let values = [
+            (&hooks.time_of_day, self.time_of_day()),
+            (&hooks.day, self.day() as f64),
+            (&hooks.day_of_week, f64::from(self.day_of_week())),
+            (&hooks.season, f64::from(self.season())),
+            (&hooks.night, if self.is_night() { 1.0 } else { 0.0 }),
+        ];
+        for (key, value) in values.iter() {
+            if let Some(key) = key {
+                stats.set(key, *value, stat_defs);
+            }
+        }
+    }
+}
+
+/// Checks if the time of the day is in the range, which wraps around midnight when `from` is
+/// greater than `to`.
+pub(crate) fn is_between(time: f64, from: f64, to: f64) -> bool {
+    if from <= to {
+        time >= from && time < to
+    } else {
+        time >= from || time < to
+    }
+}
+
+/// The stats receiving the values of a `GameClock`. See `GameClock::write_stats`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ClockStats<K> {
+    /// The stat receiving the time of the day.
+    pub time_of_day: Option<K>,
+    /// The stat receiving the number of days elapsed.
+    pub day: Option<K>,
+    /// The stat receiving the day of the week.
+    pub day_of_week: Option<K>,
+    /// The stat receiving the season.
+    pub season: Option<K>,
+    /// The stat receiving 1 during the night and 0 during the day.
+    pub night: Option<K>,
+}
+
+/// A condition on the time of a `GameClock`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ClockCondition {
+    /// It is day time.
+    Day,
+    /// It is night time.
+    Night,
+    /// The time of the day is between these two times. Wraps around midnight when the first
+    /// time is greater than the second.
+    TimeOfDay(f64, f64),
+    /// It is this day of the week.
+    DayOfWeek(u32),
+    /// It is this season.
+    Season(u32),
+}
+
+impl ClockCondition {
+    /// Checks if the condition is met at the current time of the clock.
+    pub fn check(&self, clock: &GameClock) -> bool {
+        match self {
+            ClockCondition::Day => clock.is_day(),
+            ClockCondition::Night => clock.is_night(),
+            ClockCondition::TimeOfDay(from, to) => is_between(clock.time_of_day(), *from, *to),
+            ClockCondition::DayOfWeek(day) => clock.day_of_week() == *day,
+            ClockCondition::Season(season) => clock.season() == *season,
+        }
+    }
+}
+
+/// Clock conditions combined together, like "night and not winter".
+pub type ClockConditionGroup = ConditionGroup<ClockCondition>;
+
+impl ConditionGroup<ClockCondition> {
+    /// Checks if the conditions are met at the current time of the clock.
+    pub fn check(&self, clock: &GameClock) -> bool {
+        self.check_with(&|c| c.check(clock))
+    }
+}
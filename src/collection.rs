@@ -0,0 +1,180 @@
+use crate::*;
+use core::hash::Hash;
+
+/// A reward given when a category of a `Collection` reaches a completion threshold.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct CollectionReward<R> {
+    /// The fraction of the entries of the category that must be discovered, between 0.0 and
+    /// 1.0.
+    pub threshold: f64,
+    /// The reward.
+    pub reward: R,
+}
+
+/// A category of things that can be discovered, like the items of a set, the monsters of a
+/// region or the recipes of a profession.
+///
+/// # Generics
+/// - G: Category Key
+/// - K: Entry Key, like an item key
+/// - R: Reward Type
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct CollectionCategory<G, K, R> {
+    /// The key of this category.
+    pub key: G,
+    /// The display name of this category.
+    pub name: String,
+    /// The entries of this category.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub entries: Vec<K>,
+    /// The rewards given when reaching completion thresholds.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub rewards: Vec<CollectionReward<R>>,
+}
+
+/// The definitions of all known collection categories.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct CollectionCategories<G: Hash + Eq, K, R> {
+    /// The definitions.
+    pub defs: HashMap<G, CollectionCategory<G, K, R>>,
+}
+
+impl<G: Hash + Eq, K, R> Default for CollectionCategories<G, K, R> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<G: Hash + Eq + Clone, K, R> From<Vec<CollectionCategory<G, K, R>>>
+    for CollectionCategories<G, K, R>
+{
+    fn from(t: Vec<CollectionCategory<G, K, R>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<G: Hash + Eq, K: Hash + Eq + Clone, R> CollectionCategories<G, K, R> {
+    /// Adds the items to the categories returned by `category_of`, like their slot type or a
+    /// category stored in their user data.
+    /// Items without a category or whose category is not defined are ignored.
+    pub fn add_items<S, D: Default, F: Fn(&ItemDefinition<K, S, D>) -> Option<G>>(
+        &mut self,
+        item_defs: &ItemDefinitions<K, S, D>,
+        category_of: F,
+    ) {
+        for def in item_defs.defs.values() {
+            if let Some(category) = category_of(def).and_then(|g| self.defs.get_mut(&g)) {
+                if !category.entries.contains(&def.key) {
+                    category.entries.push(def.key.clone());
+                }
+            }
+        }
+    }
+
+    fn get(&self, key: &G) -> &CollectionCategory<G, K, R> {
+        self.defs
+            .get(key)
+            .expect("Tried to get unknown collection category key.")
+    }
+}
+
+/// What a player discovered, like a collection log or a monster encyclopedia.
+///
+/// # Generics
+/// - G: Category Key
+/// - K: Entry Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Collection<G: Hash + Eq, K: Hash + Eq> {
+    /// The discovered entries.
+    #[new(default)]
+    pub discovered: HashSet<K>,
+    /// The rewards already given, as the category key and the index of the reward.
+    #[new(default)]
+    pub rewarded: HashSet<(G, usize)>,
+}
+
+impl<G: Hash + Eq + Clone, K: Hash + Eq + Clone> Collection<G, K> {
+    /// Checks if the entry was discovered.
+    pub fn is_discovered(&self, key: &K) -> bool {
+        self.discovered.contains(key)
+    }
+
+    /// Marks the entry as discovered.
+    /// Returns the discovery event, if the entry is new, and the rewards of the thresholds
+    /// reached.
+    pub fn discover<R: Clone>(
+        &mut self,
+        key: &K,
+        categories: &CollectionCategories<G, K, R>,
+    ) -> Vec<CollectionEvent<G, K, R>> {
+        let mut events = vec![];
+        if !self.discovered.insert(key.clone()) {
+            return events;
+        }
+        events.push(CollectionEvent::Discovered(key.clone()));
+        for (g, category) in categories.defs.iter() {
+            if !category.entries.contains(key) {
+                continue;
+            }
+            let completion = self.completion(g, categories);
+            for (idx, reward) in category.rewards.iter().enumerate() {
+                if completion >= reward.threshold && self.rewarded.insert((g.clone(), idx)) {
+                    events.push(CollectionEvent::Reward {
+                        category: g.clone(),
+                        reward: reward.reward.clone(),
+                    });
+                }
+            }
+        }
+        events
+    }
+
+    /// Returns the number of discovered entries of the category.
+    pub fn discovered_count<R>(
+        &self,
+        category: &G,
+        categories: &CollectionCategories<G, K, R>,
+    ) -> usize {
+        categories
+            .get(category)
+            .entries
+            .iter()
+            .filter(|k| self.is_discovered(k))
+            .count()
+    }
+
+    /// Returns the fraction of the entries of the category that were discovered, between 0.0
+    /// and 1.0. Empty categories are complete.
+    pub fn completion<R>(&self, category: &G, categories: &CollectionCategories<G, K, R>) -> f64 {
+        let total = categories.get(category).entries.len();
+        if total == 0 {
+            return 1.0;
+        }
+        self.discovered_count(category, categories) as f64 / total as f64
+    }
+}
+
+/// An event emitted by a `Collection`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollectionEvent<G, K, R> {
+    /// The entry was discovered for the first time.
+    Discovered(K),
+    /// A completion threshold of the category was reached.
+    Reward {
+        /// The key of the category.
+        category: G,
+        /// The reward given.
+        reward: R,
+    },
+}
@@ -0,0 +1,267 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// An action requested by a client, validated and applied by the server using a
+/// `CommandExecutor`.
+///
+/// # Generics
+/// - S: Skill Key
+/// - T: Item Transition Key
+/// - L: Unlockable Key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Command<S, T, L> {
+    /// Moves items from a slot of the inventory to another.
+    MoveItem {
+        /// The slot the items are taken from.
+        from: usize,
+        /// The slot the items are moved to.
+        to: usize,
+        /// The number of items to move.
+        quantity: usize,
+    },
+    /// Deletes items from a slot of the inventory, like when dropping them.
+    DeleteItem {
+        /// The slot the items are deleted from.
+        slot: usize,
+        /// The number of items to delete.
+        quantity: usize,
+    },
+    /// Activates a skill. See `SkillProcessor::try_activate`.
+    UseSkill(S),
+    /// Adds a batch of item transitions to the crafting queue.
+    /// See `CraftingQueue::enqueue`.
+    Craft {
+        /// The item transition.
+        transition: T,
+        /// The number of transitions in the batch.
+        count: u32,
+    },
+    /// Cancels the batch at this index of the crafting queue. The refunded items are inserted
+    /// in the inventory.
+    CancelCraft(usize),
+    /// Unlocks an unlockable. See `Unlockables::try_unlock`.
+    Unlock(L),
+}
+
+/// The state of an entity modified by the commands it sends.
+///
+/// # Generics
+/// - K: Stat Key
+/// - E: Effector Key
+/// - S: Skill Key
+/// - I: Item Key
+/// - IT: Item Slot Type
+/// - CD: Item Custom Data
+/// - T: Item Transition Key
+/// - L: Unlockable Key
+/// - LV: Unlockable Value
+#[derive(new)]
+pub struct CommandState<
+    'a,
+    K: Hash + Eq,
+    E,
+    S: Hash + Eq,
+    I,
+    IT: SlotType,
+    CD: Default,
+    T,
+    L: Hash + Eq,
+    LV,
+> {
+    /// The stats of the entity.
+    pub stats: &'a StatSet<K>,
+    /// The inventory of the entity.
+    pub inventory: &'a mut Inventory<I, IT, CD>,
+    /// The skills of the entity.
+    pub skills: &'a mut SkillSet<S>,
+    /// The effectors active on the entity.
+    pub effectors: &'a mut EffectorSet<E>,
+    /// The crafting queue of the entity.
+    pub crafting: &'a mut CraftingQueue<T>,
+    /// The unlockables of the entity.
+    pub unlockables: &'a mut Unlockables<L, LV, K, I>,
+}
+
+/// Validates commands and applies them to a `CommandState` using the known definitions.
+/// Invalid commands never change the state, so they can come from untrusted clients.
+#[derive(new)]
+pub struct CommandExecutor<
+    'a,
+    K: Hash + Eq,
+    E: Hash + Eq,
+    S: Hash + Eq,
+    I: Hash + Eq,
+    IT,
+    D: Default,
+    T: Hash + Eq,
+    L,
+> {
+    /// Activates the skills.
+    pub skills: SkillProcessor<'a, K, E, S, I>,
+    /// The definitions of the items.
+    pub item_defs: &'a ItemDefinitions<I, IT, D>,
+    /// The definitions of the item transitions.
+    pub transition_defs: &'a ItemTransitionDefinitions<T, I, E, K, L>,
+}
+
+impl<
+        'a,
+        K: Hash + Eq + Clone + Debug,
+        E: Hash + Eq + Clone,
+        S: Hash + Eq + Clone,
+        I: Hash + Eq + Clone + Debug,
+        IT: SlotType + Clone,
+        D: Default,
+        T: Hash + Eq + Clone,
+        L: Hash + Eq + Clone,
+    > CommandExecutor<'a, K, E, S, I, IT, D, T, L>
+{
+    /// Checks that the command is allowed and applies it.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * InvalidSlot: The command uses a slot that is not in the inventory.
+    /// * Item: The inventory operation failed.
+    /// * Skill: The skill can't be activated.
+    /// * UnknownTransition: No item transition uses this key.
+    /// * Crafting: The transition is locked or the refund doesn't fit in the inventory.
+    /// * UnknownBatch: There is no crafting batch at this index.
+    /// * Unlock: The unlockable can't be unlocked.
+    #[allow(clippy::type_complexity)]
+    pub fn validate_and_apply<CD: PartialEq + Default + Clone + Debug, LV>(
+        &self,
+        command: &Command<S, T, L>,
+        state: &mut CommandState<K, E, S, I, IT, CD, T, L, LV>,
+    ) -> Result<CommandOutcome<I, CD, L>, CommandError<K, I, CD, L>> {
+        match command {
+            Command::MoveItem { from, to, quantity } => {
+                let len = state.inventory.content.len();
+                if *from >= len || *to >= len {
+                    return Err(CommandError::InvalidSlot);
+                }
+                let mut inventory = state.inventory.clone();
+                inventory
+                    .move_item(*from, *to, *quantity, false, self.item_defs)
+                    .map_err(CommandError::Item)?;
+                *state.inventory = inventory;
+                Ok(CommandOutcome::ItemMoved)
+            }
+            Command::DeleteItem { slot, quantity } => {
+                if *slot >= state.inventory.content.len() {
+                    return Err(CommandError::InvalidSlot);
+                }
+                state
+                    .inventory
+                    .delete(*slot, *quantity)
+                    .map(CommandOutcome::ItemDeleted)
+                    .map_err(CommandError::Item)
+            }
+            Command::UseSkill(skill) => {
+                // Preview first so that a failed activation doesn't change anything.
+                self.skills
+                    .simulate_activate(
+                        skill,
+                        state.skills,
+                        state.stats,
+                        state.inventory,
+                        state.effectors,
+                    )
+                    .map_err(CommandError::Skill)?;
+                self.skills
+                    .try_activate(
+                        skill,
+                        state.skills,
+                        state.stats,
+                        state.inventory,
+                        state.effectors,
+                    )
+                    .map(CommandOutcome::SkillActivated)
+                    .map_err(CommandError::Skill)
+            }
+            Command::Craft { transition, count } => {
+                if !self.transition_defs.defs.contains_key(transition) {
+                    return Err(CommandError::UnknownTransition);
+                }
+                state
+                    .crafting
+                    .enqueue(
+                        transition.clone(),
+                        *count,
+                        self.transition_defs,
+                        state.unlockables,
+                    )
+                    .map_err(CommandError::Crafting)?;
+                Ok(CommandOutcome::CraftQueued)
+            }
+            Command::CancelCraft(idx) => {
+                if *idx >= state.crafting.batches.len() {
+                    return Err(CommandError::UnknownBatch);
+                }
+                let mut crafting = state.crafting.clone();
+                let mut inventory = state.inventory.clone();
+                let refund = crafting
+                    .cancel::<I, E, K, L, (), CD>(*idx, self.transition_defs)
+                    .unwrap_or_default();
+                for item in refund.iter() {
+                    inventory
+                        .insert(item.clone(), self.item_defs)
+                        .map_err(|_| CommandError::Crafting(CraftingError::InventoryFull))?;
+                }
+                *state.crafting = crafting;
+                *state.inventory = inventory;
+                Ok(CommandOutcome::CraftCancelled(refund))
+            }
+            Command::Unlock(id) => {
+                state
+                    .unlockables
+                    .try_unlock(
+                        id,
+                        state.stats,
+                        self.skills.stat_defs,
+                        state.inventory,
+                        self.skills.clock,
+                        self.skills.scripts,
+                    )
+                    .map_err(CommandError::Unlock)?;
+                Ok(CommandOutcome::Unlocked(id.clone()))
+            }
+        }
+    }
+}
+
+/// The result of a command applied by a `CommandExecutor`.
+#[derive(Debug, Clone)]
+pub enum CommandOutcome<I, CD: Default, L> {
+    /// The items were moved.
+    ItemMoved,
+    /// The items were deleted.
+    ItemDeleted(ItemInstance<I, CD>),
+    /// The skill was activated and gained those ranks.
+    SkillActivated(Vec<LevelUpEvent>),
+    /// The batch was added to the crafting queue.
+    CraftQueued,
+    /// The batch was cancelled and these items were refunded into the inventory.
+    CraftCancelled(Vec<ItemInstance<I, CD>>),
+    /// The unlockable was unlocked.
+    Unlocked(L),
+}
+
+/// The errors that can happen when applying a command.
+#[derive(Debug)]
+pub enum CommandError<K, I: PartialEq + Debug, CD: Default, L> {
+    /// The command uses a slot that is not in the inventory.
+    InvalidSlot,
+    /// No item transition uses this key.
+    UnknownTransition,
+    /// There is no crafting batch at this index.
+    UnknownBatch,
+    /// The inventory operation failed.
+    Item(ItemError<I, CD>),
+    /// The skill can't be activated.
+    Skill(SkillError),
+    /// The transition is locked or the refunded items don't fit in the inventory.
+    Crafting(CraftingError<L>),
+    /// The unlockable can't be unlocked.
+    Unlock(UnlockError<L, K, I>),
+}
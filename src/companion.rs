@@ -0,0 +1,175 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// A stat that changes by itself over time, like the hunger or the loyalty of a companion.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct StatDecay<K> {
+    /// The stat that decays.
+    pub stat: K,
+    /// The value removed from the base value of the stat each second.
+    /// Use a negative value for stats that grow over time.
+    pub per_second: f64,
+}
+
+/// A pet or companion owned by a user, with its own stats, skills, level and equipment.
+///
+/// # Generics
+/// - K: Stat Key
+/// - E: Effector Key
+/// - S: Skill Key
+/// - I: Item Key
+/// - IT: Item Slot Type
+/// - CD: Item Custom Data
+/// - T: Level Curve, see `LevelFor`
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Companion<K: Hash + Eq, E, S: Hash + Eq, I, IT: SlotType, CD: Default, T: LevelFor> {
+    /// The display name of the companion.
+    pub name: String,
+    /// The user owning the companion.
+    pub owner: i32,
+    /// The stats of the companion.
+    pub stats: StatSet<K>,
+    /// The skills the companion can use.
+    pub skills: SkillSet<S>,
+    /// The effectors active on the companion.
+    #[new(default)]
+    pub effectors: EffectorSet<E>,
+    /// The experience and level of the companion.
+    pub level: Leveled<T>,
+    /// The items equipped on the companion.
+    /// Use the slot restrictions of the inventory to define what can be equipped.
+    pub equipment: Inventory<I, IT, CD>,
+    /// The stats decaying over time while the companion is active, like hunger or loyalty.
+    #[new(default)]
+    #[serde(default = "Vec::new")]
+    pub decays: Vec<StatDecay<K>>,
+}
+
+impl<K: Hash + Eq + Clone + Debug, E, S: Hash + Eq, I, IT: SlotType, CD: Default, T: LevelFor>
+    Companion<K, E, S, I, IT, CD, T>
+{
+    /// Applies the decay of the stats for `delta_time` seconds.
+    /// The values stay within the limits of their `StatDefinition`.
+    pub fn update(&mut self, delta_time: f64, stat_defs: &StatDefinitions<K>) {
+        for decay in self.decays.iter() {
+            self.stats
+                .add_to(&decay.stat, -decay.per_second * delta_time, stat_defs);
+        }
+    }
+
+    /// Adds experience and returns an event for each level gained.
+    pub fn add_xp(&mut self, amount: u32) -> Vec<LevelUpEvent> {
+        self.level.add_xp(amount)
+    }
+}
+
+/// The companions of a user. Only one of them can be active at a time.
+///
+/// # Generics
+/// - Id: Companion Id
+/// - C: Companion, usually a `Companion` or a `Mount`
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct CompanionStable<Id: Hash + Eq, C> {
+    /// The companions.
+    #[new(default)]
+    pub companions: HashMap<Id, C>,
+    /// The active companion.
+    #[new(default)]
+    pub active: Option<Id>,
+    /// The maximum number of companions.
+    /// None means no limit.
+    pub capacity: Option<usize>,
+}
+
+impl<Id: Hash + Eq + Clone, C> CompanionStable<Id, C> {
+    /// Adds a companion.
+    ///
+    /// Errors:
+    /// * DuplicateCompanion: A companion with this id already exists.
+    /// * StableFull: The stable reached its capacity.
+    pub fn add(&mut self, id: Id, companion: C) -> Result<(), CompanionError<Id>> {
+        if self.companions.contains_key(&id) {
+            return Err(CompanionError::DuplicateCompanion(id));
+        }
+        if self
+            .capacity
+            .map(|c| self.companions.len() >= c)
+            .unwrap_or(false)
+        {
+            return Err(CompanionError::StableFull);
+        }
+        self.companions.insert(id, companion);
+        Ok(())
+    }
+
+    /// Removes the companion and returns it, for example when releasing or trading it.
+    /// It is deactivated if it was active.
+    pub fn remove(&mut self, id: &Id) -> Option<C> {
+        if self.active.as_ref() == Some(id) {
+            self.active = None;
+        }
+        self.companions.remove(id)
+    }
+
+    /// Makes the companion the active one. The previously active companion is deactivated.
+    ///
+    /// Errors:
+    /// * UnknownCompanion: No companion has this id.
+    pub fn activate(&mut self, id: &Id) -> Result<(), CompanionError<Id>> {
+        if !self.companions.contains_key(id) {
+            return Err(CompanionError::UnknownCompanion(id.clone()));
+        }
+        self.active = Some(id.clone());
+        Ok(())
+    }
+
+    /// Deactivates the active companion.
+    pub fn deactivate(&mut self) {
+        self.active = None;
+    }
+
+    /// Returns the active companion.
+    pub fn active(&self) -> Option<&C> {
+        self.active.as_ref().and_then(|id| self.companions.get(id))
+    }
+
+    /// Returns the active companion mutably.
+    pub fn active_mut(&mut self) -> Option<&mut C> {
+        let active = self.active.as_ref()?;
+        self.companions.get_mut(active)
+    }
+}
+
+impl<
+        Id: Hash + Eq + Clone,
+        K: Hash + Eq + Clone + Debug,
+        E,
+        S: Hash + Eq,
+        I,
+        IT: SlotType,
+        CD: Default,
+        T: LevelFor,
+    > CompanionStable<Id, Companion<K, E, S, I, IT, CD, T>>
+{
+    /// Applies the decay of the stats of the active companion for `delta_time` seconds.
+    /// Inactive companions don't decay.
+    pub fn update(&mut self, delta_time: f64, stat_defs: &StatDefinitions<K>) {
+        if let Some(companion) = self.active_mut() {
+            companion.update(delta_time, stat_defs);
+        }
+    }
+}
+
+/// The errors that can happen when using a `CompanionStable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompanionError<Id> {
+    /// No companion has this id.
+    UnknownCompanion(Id),
+    /// A companion with this id already exists.
+    DuplicateCompanion(Id),
+    /// The stable reached its capacity.
+    StableFull,
+    /// The mount doesn't have enough stamina to be ridden.
+    Exhausted,
+}
@@ -0,0 +1,94 @@
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::Vec};
+
+/// Conditions combined together, like "a and (b or not c)".
+/// The single conditions are checked by the module they belong to, for example
+/// `StatConditionGroup::check` or `ClockConditionGroup::check`.
+///
+/// # Generics
+/// - T: Single Condition
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ConditionGroup<T> {
+    /// A single condition.
+    Condition(T),
+    /// All the conditions must be met.
+    All(Vec<ConditionGroup<T>>),
+    /// At least one of the conditions must be met.
+    Any(Vec<ConditionGroup<T>>),
+    /// The condition must not be met.
+    Not(Box<ConditionGroup<T>>),
+}
+
+impl<T> ConditionGroup<T> {
+    /// Checks if the conditions are met, using `check` for the single conditions.
+    pub fn check_with<F: Fn(&T) -> bool>(&self, check: &F) -> bool {
+        match self {
+            ConditionGroup::Condition(c) => check(c),
+            ConditionGroup::All(c) => c.iter().all(|c| c.check_with(check)),
+            ConditionGroup::Any(c) => c.iter().any(|c| c.check_with(check)),
+            ConditionGroup::Not(c) => !c.check_with(check),
+        }
+    }
+
+    /// Checks if the conditions are met, using `check` for the single conditions.
+    /// Stops at the first single condition returning an error.
+    pub fn try_check_with<E, F: Fn(&T) -> Result<bool, E>>(&self, check: &F) -> Result<bool, E> {
+        match self {
+            ConditionGroup::Condition(c) => check(c),
+            ConditionGroup::All(c) => {
+                for c in c.iter() {
+                    if !c.try_check_with(check)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            ConditionGroup::Any(c) => {
+                for c in c.iter() {
+                    if c.try_check_with(check)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            ConditionGroup::Not(c) => c.try_check_with(check).map(|b| !b),
+        }
+    }
+
+    /// Returns all the single conditions of this group.
+    pub fn conditions(&self) -> Vec<&T> {
+        match self {
+            ConditionGroup::Condition(c) => vec![c],
+            ConditionGroup::All(c) | ConditionGroup::Any(c) => {
+                c.iter().flat_map(|c| c.conditions()).collect()
+            }
+            ConditionGroup::Not(c) => c.conditions(),
+        }
+    }
+}
+
+impl<T> From<T> for ConditionGroup<T> {
+    fn from(c: T) -> Self {
+        ConditionGroup::Condition(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_conditions() {
+        let group = ConditionGroup::All(vec![
+            ConditionGroup::Condition(1),
+            ConditionGroup::Not(Box::new(ConditionGroup::Any(vec![2.into(), 3.into()]))),
+        ]);
+        assert!(group.check_with(&|c: &i32| *c == 1));
+        assert!(!group.check_with(&|c: &i32| *c <= 2));
+        assert_eq!(group.conditions(), vec![&1, &2, &3]);
+        assert_eq!(
+            group.try_check_with(&|c: &i32| if *c == 2 { Err(*c) } else { Ok(true) }),
+            Err(2)
+        );
+    }
+}
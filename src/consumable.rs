@@ -0,0 +1,58 @@
+use crate::*;
+use core::hash::Hash;
+
+/// The effectors applied when consuming an item, like food or a potion.
+/// See `Inventory::consume_with_effects`.
+///
+/// # Generics
+/// - I: Item Key
+/// - E: Effector Key
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct ConsumableEffect<I, E> {
+    /// The key of the consumed item.
+    pub key: I,
+    /// The effectors added when the item is consumed.
+    pub effectors: Vec<E>,
+    /// The exclusivity group of the effect, like "well_fed" or "elixir".
+    /// Consuming an item removes the effectors of the other items of the same group.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+/// The definitions of all the consumable items.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ConsumableEffects<I: Hash + Eq, E> {
+    /// The definitions.
+    pub defs: HashMap<I, ConsumableEffect<I, E>>,
+}
+
+impl<I: Hash + Eq, E> Default for ConsumableEffects<I, E> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<I: Hash + Eq + Clone, E> From<Vec<ConsumableEffect<I, E>>> for ConsumableEffects<I, E> {
+    fn from(t: Vec<ConsumableEffect<I, E>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<I: Hash + Eq, E> ConsumableEffects<I, E> {
+    /// Returns the effectors of all the items of the group.
+    pub fn group_effectors<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a E> {
+        self.defs
+            .values()
+            .filter(move |d| d.group.as_deref() == Some(group))
+            .flat_map(|d| d.effectors.iter())
+    }
+}
@@ -0,0 +1,706 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// How much of what was spent is given back, like the input items of a cancelled transition
+/// or the skill points of a respec.
+///
+/// For transitions, only the items used with `UseMode::Consume` by a started transition using
+/// `consume_input_immediate` can be refunded, since the others were not consumed yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RefundPolicy {
+    /// Nothing is given back.
+    Nothing,
+    /// Everything is given back.
+    Full,
+    /// This fraction is given back, rounded down.
+    Partial(f64),
+}
+
+impl RefundPolicy {
+    /// Returns the quantity given back out of the spent quantity.
+    pub fn refunded(&self, consumed: usize) -> usize {
+        match self {
+            RefundPolicy::Nothing => 0,
+            RefundPolicy::Full => consumed,
+            RefundPolicy::Partial(f) => (consumed as f64 * f.clamp(0.0, 1.0)).floor() as usize,
+        }
+    }
+
+    /// Returns the amount given back out of the spent amount, like currencies.
+    /// Negative amounts give nothing back.
+    pub fn refunded_amount(&self, spent: i64) -> i64 {
+        if spent <= 0 {
+            return 0;
+        }
+        match self {
+            RefundPolicy::Nothing => 0,
+            RefundPolicy::Full => spent,
+            RefundPolicy::Partial(f) => (spent as f64 * f.clamp(0.0, 1.0)).floor() as i64,
+        }
+    }
+}
+
+/// Something that happened in a `CraftingQueue`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CraftingEvent<K, I, U: Default> {
+    /// A transition completed and produced the output items.
+    /// The output items are not inserted in the inventory.
+    Completed {
+        /// The transition id.
+        transition: K,
+        /// The output items.
+        outputs: Vec<ItemInstance<I, U>>,
+    },
+    /// A transition paused because its conditions are not met anymore.
+    Paused(K),
+    /// A paused transition resumed.
+    Resumed(K),
+    /// A batch was cancelled because its conditions are not met anymore.
+    Cancelled {
+        /// The transition id.
+        transition: K,
+        /// The number of transitions of the batch that did not complete.
+        /// See `ItemTransitionDefinition::currency_refund`.
+        remaining: u32,
+        /// The refunded input items.
+        /// They are not inserted in the inventory.
+        refund: Vec<ItemInstance<I, U>>,
+    },
+}
+
+/// The batches of item transitions queued on a crafting station.
+/// The first `max_concurrent` batches progress at the same time, the others wait.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct CraftingQueue<K> {
+    /// The maximum number of batches progressing at the same time.
+    pub max_concurrent: usize,
+    /// What is given back when a batch is cancelled.
+    pub refund_policy: RefundPolicy,
+    /// The queued batches, in order.
+    #[new(default)]
+    pub batches: Vec<ItemTransitionBatch<K>>,
+}
+
+impl<K: Hash + Eq + Clone> CraftingQueue<K> {
+    /// Adds a batch of `count` transitions at the end of the queue.
+    ///
+    /// Errors:
+    /// * Locked: The required unlockable of the transition is not unlocked.
+    /// * Unpaid: The transition has a currency cost. Use `CraftingQueue::enqueue_paid` instead.
+    pub fn enqueue<I, E, S, L: Hash + Eq + Clone, C, LV, S2, I2>(
+        &mut self,
+        transition: K,
+        count: u32,
+        transition_defs: &ItemTransitionDefinitions<K, I, E, S, L, C>,
+        unlockables: &Unlockables<L, LV, S2, I2>,
+    ) -> Result<(), CraftingError<L, C>> {
+        let def = get_def(&transition, transition_defs);
+        check_unlocked(def, unlockables)?;
+        if !def.currency_cost.is_empty() {
+            return Err(CraftingError::Unpaid);
+        }
+        self.batches.push(ItemTransitionBatch::new(
+            transition,
+            count,
+            def.time_to_complete,
+        ));
+        Ok(())
+    }
+
+    /// Same as `CraftingQueue::enqueue`, paying the currency cost of the whole batch from the
+    /// wallet. Nothing changes if an error happens.
+    /// Use `CraftingQueue::cancel_paid` to get the currencies back when cancelling the batch.
+    ///
+    /// Errors:
+    /// * Locked: The required unlockable of the transition is not unlocked.
+    /// * Wallet: The wallet can't pay the currency cost.
+    #[allow(clippy::too_many_arguments)]
+    pub fn enqueue_paid<I, E, S, L: Hash + Eq + Clone, C: Hash + Eq + Clone, LV, S2, I2>(
+        &mut self,
+        transition: K,
+        count: u32,
+        transition_defs: &ItemTransitionDefinitions<K, I, E, S, L, C>,
+        unlockables: &Unlockables<L, LV, S2, I2>,
+        wallet: &mut Wallet<C>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), CraftingError<L, C>> {
+        let def = get_def(&transition, transition_defs);
+        check_unlocked(def, unlockables)?;
+        wallet
+            .pay(&def.currency_cost_of(count), currency_defs)
+            .map_err(CraftingError::Wallet)?;
+        self.batches.push(ItemTransitionBatch::new(
+            transition,
+            count,
+            def.time_to_complete,
+        ));
+        Ok(())
+    }
+
+    /// Moves the batch at index `from` to the index `to`, shifting the batches between them.
+    /// Panics if one of the indices is out of bounds.
+    pub fn move_batch(&mut self, from: usize, to: usize) {
+        let batch = self.batches.remove(from);
+        self.batches.insert(to, batch);
+    }
+
+    /// Removes the batch at this index and returns the refunded input items according to the
+    /// `RefundPolicy`.
+    /// Returns None if there is no batch at this index.
+    pub fn cancel<I: Clone, E, S, L, C, U: Default>(
+        &mut self,
+        idx: usize,
+        transition_defs: &ItemTransitionDefinitions<K, I, E, S, L, C>,
+    ) -> Option<Vec<ItemInstance<I, U>>> {
+        if idx >= self.batches.len() {
+            return None;
+        }
+        let batch = self.batches.remove(idx);
+        Some(self.refund(&batch, transition_defs))
+    }
+
+    /// Same as `CraftingQueue::cancel`, for batches queued using `CraftingQueue::enqueue_paid`.
+    /// Also returns the currencies given back for the transitions that did not complete,
+    /// according to the `RefundPolicy`. They are not added to the wallet.
+    #[allow(clippy::type_complexity)]
+    pub fn cancel_paid<I: Clone, E, S, L, C: Clone, U: Default>(
+        &mut self,
+        idx: usize,
+        transition_defs: &ItemTransitionDefinitions<K, I, E, S, L, C>,
+    ) -> Option<(Vec<ItemInstance<I, U>>, Vec<(C, i64)>)> {
+        let remaining = self.batches.get(idx)?.remaining;
+        let transition = self.batches[idx].transition.clone();
+        let items = self.cancel(idx, transition_defs)?;
+        let currencies =
+            get_def(&transition, transition_defs).currency_refund(remaining, &self.refund_policy);
+        Some((items, currencies))
+    }
+
+    fn refund<I: Clone, E, S, L, C, U: Default>(
+        &self,
+        batch: &ItemTransitionBatch<K>,
+        transition_defs: &ItemTransitionDefinitions<K, I, E, S, L, C>,
+    ) -> Vec<ItemInstance<I, U>> {
+        let def = get_def(&batch.transition, transition_defs);
+        if !batch.started || !def.consume_input_immediate {
+            return vec![];
+        }
+        def.input_items
+            .iter()
+            .filter(|(_, _, mode)| *mode == UseMode::Consume)
+            .map(|(key, quantity, _)| {
+                ItemInstance::new(key.clone(), self.refund_policy.refunded(*quantity))
+            })
+            .filter(|ii| ii.quantity > 0)
+            .collect()
+    }
+}
+
+impl<K: Hash + Eq + Clone> CraftingQueue<K> {
+    /// Advances the active batches by `delta_time` seconds.
+    ///
+    /// A transition starts once its conditions are met, using its input items if
+    /// `consume_input_immediate` is true. Otherwise, the input items are used when it
+    /// completes.
+    /// If the conditions of a started transition are lost, the `ConditionLostReaction` of its
+    /// definition decides if it continues, pauses or if the whole batch is cancelled.
+    /// A transition whose required unlockable is locked again counts as having lost its
+    /// conditions. A `clock` of None means that the clock conditions are not met, unless there
+    /// are none. A transition whose script conditions can't be checked, like when `scripts` is
+    /// None, counts as not meeting its conditions.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update<
+        I: Hash + Eq + Clone + Debug,
+        E,
+        S: Hash + Eq + Debug,
+        L: Hash + Eq + Clone,
+        C,
+        IT: SlotType,
+        U: PartialEq + Default + Clone + Debug,
+        LV,
+        S2,
+        I2,
+    >(
+        &mut self,
+        delta_time: f64,
+        transition_defs: &ItemTransitionDefinitions<K, I, E, S, L, C>,
+        inventory: &mut Inventory<I, IT, U>,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        unlockables: &Unlockables<L, LV, S2, I2>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Vec<CraftingEvent<K, I, U>> {
+        let mut events = vec![];
+        let mut idx = 0;
+        let mut active = 0;
+        while idx < self.batches.len() && active < self.max_concurrent {
+            active += 1;
+            let keep = self.update_batch(
+                idx,
+                delta_time,
+                transition_defs,
+                inventory,
+                stats,
+                stat_defs,
+                unlockables,
+                clock,
+                scripts,
+                &mut events,
+            );
+            if keep {
+                idx += 1;
+            } else {
+                self.batches.remove(idx);
+            }
+        }
+        events
+    }
+
+    /// Advances a single batch.
+    /// Returns false if the batch is done or cancelled and should be removed.
+    #[allow(clippy::too_many_arguments)]
+    fn update_batch<
+        I: Hash + Eq + Clone + Debug,
+        E,
+        S: Hash + Eq + Debug,
+        L: Hash + Eq + Clone,
+        C,
+        IT: SlotType,
+        U: PartialEq + Default + Clone + Debug,
+        LV,
+        S2,
+        I2,
+    >(
+        &mut self,
+        idx: usize,
+        delta_time: f64,
+        transition_defs: &ItemTransitionDefinitions<K, I, E, S, L, C>,
+        inventory: &mut Inventory<I, IT, U>,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        unlockables: &Unlockables<L, LV, S2, I2>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+        events: &mut Vec<CraftingEvent<K, I, U>>,
+    ) -> bool {
+        let def = get_def(&self.batches[idx].transition, transition_defs);
+        let mut time = delta_time;
+        loop {
+            if self.batches[idx].remaining == 0 {
+                return false;
+            }
+            let batch = &mut self.batches[idx];
+            let inputs_used = batch.started && def.consume_input_immediate;
+            let met = def.is_unlocked_in(unlockables)
+                && if inputs_used {
+                    def.check_stat_conditions(stats, stat_defs, scripts)
+                        .unwrap_or(false)
+                        && def.check_clock_conditions(clock)
+                } else {
+                    def.check_conditions(inventory, stats, stat_defs, clock, scripts)
+                        .unwrap_or(false)
+                };
+            if !batch.started {
+                if !met {
+                    // Wait for the conditions before starting.
+                    return true;
+                }
+                batch.started = true;
+                if def.consume_input_immediate {
+                    use_inputs(def, inventory);
+                }
+            } else if !met {
+                match def.on_condition_lost {
+                    ConditionLostReaction::None => {}
+                    ConditionLostReaction::Pause => {
+                        if !batch.paused {
+                            batch.paused = true;
+                            events.push(CraftingEvent::Paused(batch.transition.clone()));
+                        }
+                        return true;
+                    }
+                    ConditionLostReaction::Cancel => {
+                        let refund = self.refund(&self.batches[idx], transition_defs);
+                        events.push(CraftingEvent::Cancelled {
+                            transition: self.batches[idx].transition.clone(),
+                            remaining: self.batches[idx].remaining,
+                            refund,
+                        });
+                        return false;
+                    }
+                }
+            }
+            let batch = &mut self.batches[idx];
+            if batch.paused {
+                batch.paused = false;
+                events.push(CraftingEvent::Resumed(batch.transition.clone()));
+            }
+            if time <= 0.0 && batch.next_completion_remaining > 0.0 {
+                return true;
+            }
+            batch.next_completion_remaining -= time;
+            if batch.next_completion_remaining > 0.0 {
+                return true;
+            }
+            if !def.consume_input_immediate {
+                if !def
+                    .check_conditions(inventory, stats, stat_defs, clock, scripts)
+                    .unwrap_or(false)
+                {
+                    // Wait for the input items before completing.
+                    batch.next_completion_remaining = 0.0;
+                    return true;
+                }
+                use_inputs(def, inventory);
+            }
+            // The time left after completing is used by the next transition of the batch.
+            time = -batch.next_completion_remaining;
+            batch.next_completion_remaining = def.time_to_complete;
+            batch.remaining -= 1;
+            batch.started = false;
+            events.push(CraftingEvent::Completed {
+                transition: batch.transition.clone(),
+                outputs: def
+                    .output_items
+                    .iter()
+                    .map(|(key, quantity)| ItemInstance::new(key.clone(), *quantity))
+                    .collect(),
+            });
+        }
+    }
+}
+
+fn get_def<'a, K: Hash + Eq, I, E, S, L, C>(
+    transition: &K,
+    transition_defs: &'a ItemTransitionDefinitions<K, I, E, S, L, C>,
+) -> &'a ItemTransitionDefinition<K, I, E, S, L, C> {
+    transition_defs
+        .defs
+        .get(transition)
+        .expect("Tried to get unknown item transition key.")
+}
+
+fn check_unlocked<K, I, E, S, L: Hash + Eq + Clone, C, LV, S2, I2>(
+    def: &ItemTransitionDefinition<K, I, E, S, L, C>,
+    unlockables: &Unlockables<L, LV, S2, I2>,
+) -> Result<(), CraftingError<L, C>> {
+    if def.is_unlocked_in(unlockables) {
+        Ok(())
+    } else {
+        Err(CraftingError::Locked(def.required_unlock.clone().expect(
+            "Locked transition without required unlockable. This is a bug.",
+        )))
+    }
+}
+
+fn use_inputs<
+    K,
+    I: Hash + Eq + Clone + Debug,
+    E,
+    S,
+    L,
+    C,
+    IT: SlotType,
+    U: PartialEq + Default + Clone + Debug,
+>(
+    def: &ItemTransitionDefinition<K, I, E, S, L, C>,
+    inventory: &mut Inventory<I, IT, U>,
+) {
+    for (key, quantity, mode) in def.input_items.iter() {
+        inventory
+            .use_key(key, *quantity, mode)
+            .expect("Failed to use the input items of a transition. This is a bug.");
+    }
+}
+
+/// The errors that can happen when queueing or simulating transitions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CraftingError<L, C = ()> {
+    /// This unlockable must be unlocked before using the transition.
+    Locked(L),
+    /// The transition has a currency cost, so it must be paid when queued.
+    Unpaid,
+    /// The wallet can't pay the currency cost of the transition.
+    Wallet(WalletError<C>),
+    /// The stat conditions or the input items of the transition are missing.
+    ConditionsNotMet,
+    /// The output items don't fit in the inventory.
+    InventoryFull,
+    /// A script condition of the transition can't be checked.
+    Script(ScriptError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transition_defs() -> ItemTransitionDefinitions<u32, u32, (), u32, u32> {
+        let mut transition = ItemTransitionDefinition::new(
+            1,
+            "smelt".into(),
+            "Smelt".to_string(),
+            None,
+            vec![(1, 2, UseMode::Consume)],
+            vec![],
+            vec![],
+            vec![(2, 1)],
+            ConditionLostReaction::Pause,
+            1.0,
+            true,
+            false,
+        );
+        transition.required_unlock = Some(7);
+        ItemTransitionDefinitions::from(vec![transition])
+    }
+
+    fn unlockables() -> Unlockables<u32, (), u32, u32> {
+        let mut unlockables = HashMap::new();
+        unlockables.insert(7, Unlockable::new(7, (), false));
+        Unlockables::new(unlockables)
+    }
+
+    #[test]
+    fn locked_transitions_are_not_queued() {
+        let transition_defs = transition_defs();
+        let mut unlockables = unlockables();
+        let mut queue = CraftingQueue::new(1, RefundPolicy::Full);
+        assert_eq!(
+            queue.enqueue(1, 1, &transition_defs, &unlockables),
+            Err(CraftingError::Locked(7))
+        );
+        assert!(queue.batches.is_empty());
+        unlockables.unlockables.get_mut(&7).unwrap().unlock();
+        queue.enqueue(1, 1, &transition_defs, &unlockables).unwrap();
+        assert_eq!(queue.batches.len(), 1);
+    }
+
+    #[test]
+    fn relocked_transitions_do_not_start() {
+        let item_defs = ItemDefinitions::<u32, (), ()>::from(vec![ItemDefinition::new(
+            1,
+            (),
+            "ore".into(),
+            "Ore".to_string(),
+            "".into(),
+            Some(10),
+            None,
+        )]);
+        let transition_defs = transition_defs();
+        let mut unlockables = unlockables();
+        let stat_defs = StatDefinitions::<u32>::default();
+        let stats = stat_defs.to_statset();
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(1);
+        inventory
+            .insert(ItemInstance::new(1, 4), &item_defs)
+            .unwrap();
+        unlockables.unlockables.get_mut(&7).unwrap().unlock();
+        let mut queue = CraftingQueue::new(1, RefundPolicy::Full);
+        queue.enqueue(1, 1, &transition_defs, &unlockables).unwrap();
+        unlockables.unlockables.get_mut(&7).unwrap().lock();
+        let events = queue.update(
+            1.0,
+            &transition_defs,
+            &mut inventory,
+            &stats,
+            &stat_defs,
+            &unlockables,
+            None,
+            None,
+        );
+        assert!(events.is_empty());
+        assert!(inventory.has_quantity(&1, 4));
+        unlockables.unlockables.get_mut(&7).unwrap().unlock();
+        let events = queue.update(
+            1.0,
+            &transition_defs,
+            &mut inventory,
+            &stats,
+            &stat_defs,
+            &unlockables,
+            None,
+            None,
+        );
+        assert_eq!(events.len(), 1);
+        assert!(inventory.has_quantity(&1, 2) && !inventory.has_quantity(&1, 3));
+    }
+
+    #[test]
+    fn clock_conditions_gate_transitions() {
+        let item_defs = ItemDefinitions::<u32, (), ()>::from(vec![ItemDefinition::new(
+            1,
+            (),
+            "ore".into(),
+            "Ore".to_string(),
+            "".into(),
+            Some(10),
+            None,
+        )]);
+        let mut transition_defs = transition_defs();
+        let def = transition_defs.defs.get_mut(&1).unwrap();
+        def.required_unlock = None;
+        def.clock_conditions = vec![ClockCondition::Day.into()];
+        let stat_defs = StatDefinitions::<u32>::default();
+        let stats = stat_defs.to_statset();
+        let unlockables = unlockables();
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(1);
+        inventory
+            .insert(ItemInstance::new(1, 2), &item_defs)
+            .unwrap();
+        let mut clock = GameClock::new(100.0, 7, 30, 4, 0.25, 0.75);
+        let mut queue = CraftingQueue::new(1, RefundPolicy::Full);
+        queue.enqueue(1, 1, &transition_defs, &unlockables).unwrap();
+        let events = queue.update(
+            1.0,
+            &transition_defs,
+            &mut inventory,
+            &stats,
+            &stat_defs,
+            &unlockables,
+            Some(&clock),
+            None,
+        );
+        assert!(events.is_empty() && inventory.has_quantity(&1, 2));
+        clock.update(50.0);
+        let events = queue.update(
+            1.0,
+            &transition_defs,
+            &mut inventory,
+            &stats,
+            &stat_defs,
+            &unlockables,
+            Some(&clock),
+            None,
+        );
+        assert_eq!(events.len(), 1);
+        assert!(!inventory.has(&1));
+    }
+
+    #[test]
+    fn script_conditions_gate_transitions() {
+        let item_defs = ItemDefinitions::<u32, (), ()>::from(vec![ItemDefinition::new(
+            1,
+            (),
+            "ore".into(),
+            "Ore".to_string(),
+            "".into(),
+            Some(10),
+            None,
+        )]);
+        let mut transition_defs = transition_defs();
+        let def = transition_defs.defs.get_mut(&1).unwrap();
+        def.required_unlock = None;
+        def.stat_conditions = vec![StatCondition::new(
+            0,
+            StatConditionType::Script("is_hot".to_string()),
+        )];
+        let stat_defs = StatDefinitions::from(vec![StatDefinition::new(
+            0,
+            "Heat".into(),
+            "heat".to_string(),
+            800.0,
+        )]);
+        let stats = stat_defs.to_statset();
+        let unlockables = unlockables();
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(2);
+        inventory
+            .insert(ItemInstance::new(1, 2), &item_defs)
+            .unwrap();
+        assert_eq!(
+            transition_defs.defs[&1]
+                .simulate(&inventory, &item_defs, &stats, &stat_defs, None, None),
+            Err(CraftingError::Script(ScriptError::NoScriptHost(
+                "is_hot".to_string()
+            )))
+        );
+        let mut queue = CraftingQueue::new(1, RefundPolicy::Full);
+        queue.enqueue(1, 1, &transition_defs, &unlockables).unwrap();
+        // Without the script host, the transition waits.
+        let events = queue.update(
+            1.0,
+            &transition_defs,
+            &mut inventory,
+            &stats,
+            &stat_defs,
+            &unlockables,
+            None,
+            None,
+        );
+        assert!(events.is_empty() && inventory.has_quantity(&1, 2));
+        let mut scripts = ScriptHost::default();
+        scripts.register_condition("is_hot", |value, _, _| value >= 500.0);
+        let events = queue.update(
+            1.0,
+            &transition_defs,
+            &mut inventory,
+            &stats,
+            &stat_defs,
+            &unlockables,
+            None,
+            Some(&scripts),
+        );
+        assert_eq!(events.len(), 1);
+        assert!(!inventory.has(&1));
+    }
+
+    #[test]
+    fn currency_costs_are_paid_when_queued() {
+        let mut transition = ItemTransitionDefinition::<u32, u32, (), u32, u32, u8>::new(
+            1,
+            "enchant".into(),
+            "Enchant".to_string(),
+            None,
+            vec![],
+            vec![],
+            vec![],
+            vec![(2, 1)],
+            ConditionLostReaction::Pause,
+            1.0,
+            true,
+            false,
+        );
+        transition.currency_cost = vec![(0, 10)];
+        let transition_defs = ItemTransitionDefinitions::from(vec![transition]);
+        let currency_defs =
+            CurrencyDefinitions::from(vec![CurrencyDefinition::new(0, "Gold".to_string(), 1.0)]);
+        let unlockables = unlockables();
+        let mut wallet = Wallet::new();
+        wallet.add(&0, 25, &currency_defs).unwrap();
+        let mut queue = CraftingQueue::new(1, RefundPolicy::Partial(0.5));
+        assert_eq!(
+            queue.enqueue(1, 1, &transition_defs, &unlockables),
+            Err(CraftingError::Unpaid)
+        );
+        assert_eq!(
+            queue.enqueue_paid(
+                1,
+                3,
+                &transition_defs,
+                &unlockables,
+                &mut wallet,
+                &currency_defs
+            ),
+            Err(CraftingError::Wallet(WalletError::NotEnough(0)))
+        );
+        assert_eq!(wallet.balance(&0), 25);
+        assert!(queue.batches.is_empty());
+        queue
+            .enqueue_paid(
+                1,
+                2,
+                &transition_defs,
+                &unlockables,
+                &mut wallet,
+                &currency_defs,
+            )
+            .unwrap();
+        assert_eq!(wallet.balance(&0), 5);
+        let (items, currencies) = queue
+            .cancel_paid::<u32, (), u32, u32, u8, ()>(0, &transition_defs)
+            .unwrap();
+        assert!(items.is_empty());
+        assert_eq!(currencies, vec![(0, 10)]);
+        assert!(queue.batches.is_empty());
+    }
+}
@@ -0,0 +1,418 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// The result of a damage calculation.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DamageEvent {
+    /// The damage before applying the defensive stats of the target.
+    pub raw: f64,
+    /// The damage prevented by the defensive stats of the target.
+    pub mitigated: f64,
+    /// The damage dealt to the target.
+    pub damage: f64,
+    /// Whether the hit was critical.
+    #[new(default)]
+    pub critical: bool,
+    /// The damage absorbed by shields, after mitigation.
+    /// It is not included in `damage`.
+    #[new(default)]
+    #[serde(default)]
+    pub absorbed: f64,
+}
+
+impl DamageEvent {
+    /// Lets the absorb effectors of the target protecting the stat consume the damage, before
+    /// it is removed from the stat. See `EffectorSet::absorb`.
+    pub fn absorb_with<K: PartialEq, E: Hash + Eq>(
+        &mut self,
+        stat: &K,
+        effectors: &mut EffectorSet<E>,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) {
+        let left = effectors.absorb(stat, self.damage, effector_defs);
+        self.absorbed += self.damage - left;
+        self.damage = left;
+    }
+}
+
+/// Calculates the damage dealt to a target using its defensive stats.
+/// The value of the stats with the effectors applied is used.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DamageCalculator<K> {
+    /// The stat removing a flat amount of damage.
+    #[new(default)]
+    pub armor_stat: Option<K>,
+    /// The stat removing a fraction of the damage, between 0.0 and 1.0.
+    /// It is applied after the armor.
+    #[new(default)]
+    pub resistance_stat: Option<K>,
+    /// The minimum damage dealt by a hit, after mitigation.
+    #[new(default)]
+    pub minimum_damage: f64,
+}
+
+impl<K: Hash + Eq> DamageCalculator<K> {
+    /// Calculates the damage of a projectile fired by the weapon that travelled `distance` and
+    /// went through `penetrated` objects before hitting the target.
+    pub fn weapon_hit<W>(
+        &self,
+        weapon: &WeaponDefinition<W>,
+        distance: f64,
+        penetrated: u32,
+        target: &StatSet<K>,
+    ) -> DamageEvent {
+        let raw = weapon.base_damage()
+            * weapon.distance_damage_multiplier(distance)
+            * weapon.penetration_damage_multiplier(penetrated);
+        self.mitigate(raw, target)
+    }
+
+    /// Same as `DamageCalculator::weapon_hit`, applying the damage multipliers of the ammo
+    /// loaded in the weapon and of its attachments.
+    pub fn loaded_weapon_hit<W: PartialEq + Clone + Debug + Hash + Eq>(
+        &self,
+        weapon: &WeaponDefinition<W>,
+        instance: &WeaponInstance<W>,
+        attachment_defs: &WeaponAttachments<W>,
+        distance: f64,
+        penetrated: u32,
+        target: &StatSet<K>,
+    ) -> DamageEvent {
+        let raw = weapon.base_damage()
+            * weapon.distance_damage_multiplier(distance)
+            * weapon.penetration_damage_multiplier(penetrated)
+            * instance.ammo_damage_multiplier(weapon)
+            * instance
+                .effective_stats(weapon, attachment_defs)
+                .damage_multiplier;
+        self.mitigate(raw, target)
+    }
+
+    /// Calculates the damage of a hit on a zone of the target.
+    ///
+    /// The raw damage is first multiplied by the multiplier of the zone, using the override of
+    /// the weapon if any. Then, the armor of the equipped pieces covering the zone is removed
+    /// together with the armor stat of the target, before applying the resistance.
+    pub fn zone_hit<Z: Hash + Eq, W: Hash + Eq, I, S, U>(
+        &self,
+        raw: f64,
+        zone: &Z,
+        weapon: Option<&W>,
+        table: &HitZoneTable<Z, W, I>,
+        equipment: &Inventory<I, S, U>,
+        target: &StatSet<K>,
+    ) -> DamageEvent
+    where
+        I: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType,
+        U: Default + Clone + Debug + PartialEq,
+    {
+        let raw = raw * table.multiplier(zone, weapon);
+        self.mitigate_with_armor(raw, table.zone_armor(zone, equipment), target)
+    }
+
+    /// Applies the defensive stats of the target to the damage.
+    pub fn mitigate(&self, raw: f64, target: &StatSet<K>) -> DamageEvent {
+        self.mitigate_with_armor(raw, 0.0, target)
+    }
+
+    /// Same as `DamageCalculator::mitigate`, removing `armor` in addition to the armor stat of
+    /// the target.
+    pub fn mitigate_with_armor(&self, raw: f64, armor: f64, target: &StatSet<K>) -> DamageEvent {
+        let mut damage = raw - armor;
+        if let Some(armor) = self.stat_value(&self.armor_stat, target) {
+            damage -= armor;
+        }
+        if let Some(resistance) = self.stat_value(&self.resistance_stat, target) {
+            damage *= 1.0 - resistance.clamp(0.0, 1.0);
+        }
+        damage = damage.max(self.minimum_damage.min(raw)).max(0.0);
+        DamageEvent::new(raw, raw - damage, damage)
+    }
+
+    fn stat_value(&self, key: &Option<K>, stats: &StatSet<K>) -> Option<f64> {
+        key.as_ref().and_then(|k| stat_value(k, stats))
+    }
+}
+
+/// Some damage of a specific type, like physical or fire, before mitigation.
+///
+/// # Generics
+/// - T: Damage Type, usually an enum
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct TypedDamage<T> {
+    /// The type of the damage.
+    pub damage_type: T,
+    /// The amount of damage.
+    pub amount: f64,
+    /// The flat amount of armor ignored.
+    #[new(default)]
+    #[serde(default)]
+    pub armor_penetration: f64,
+    /// The fraction of resistance ignored, between 0.0 and 1.0.
+    #[new(default)]
+    #[serde(default)]
+    pub resistance_penetration: f64,
+    /// Whether the hit was critical.
+    #[new(default)]
+    #[serde(default)]
+    pub critical: bool,
+}
+
+/// The order in which the flat armor and the percentage resistance are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MitigationOrder {
+    /// The armor is removed first, then the resistance reduces what is left.
+    /// Armor is more effective against small hits.
+    #[default]
+    ArmorFirst,
+    /// The resistance reduces the damage first, then the armor is removed.
+    /// Armor is less effective overall.
+    ResistanceFirst,
+}
+
+/// Reduces typed damage using the armor and resistance stats of the defender for each damage
+/// type. The value of the stats with the effectors applied is used. Missing stats count as 0.
+///
+/// # Generics
+/// - T: Damage Type
+/// - K: Stat Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DamageMitigation<T: Hash + Eq, K> {
+    /// The stat removing a flat amount of damage for each damage type.
+    /// The same stat can be used for multiple types, like armor for physical damage types.
+    #[new(default)]
+    #[serde(default)]
+    pub armor_stats: HashMap<T, K>,
+    /// The stat removing a fraction of the damage for each damage type.
+    /// Negative values increase the damage taken.
+    #[new(default)]
+    #[serde(default)]
+    pub resistance_stats: HashMap<T, K>,
+    /// The order in which armor and resistance are applied.
+    #[new(default)]
+    #[serde(default)]
+    pub order: MitigationOrder,
+    /// The maximum resistance, to avoid becoming immune.
+    #[new(value = "1.0")]
+    #[serde(default = "default_max_resistance")]
+    pub max_resistance: f64,
+    /// The minimum damage dealt by a hit, after mitigation.
+    /// It is never higher than the damage before mitigation.
+    #[new(default)]
+    #[serde(default)]
+    pub minimum_damage: f64,
+}
+
+impl<T: Hash + Eq, K: Hash + Eq> DamageMitigation<T, K> {
+    /// Applies the armor and resistance of the defender to the damage.
+    ///
+    /// Penetration never brings the armor or resistance below 0, but negative resistances
+    /// are kept. The damage dealt is never negative.
+    pub fn mitigate(&self, damage: &TypedDamage<T>, defender: &StatSet<K>) -> DamageEvent {
+        let raw = damage.amount.max(0.0);
+        let armor = self
+            .armor_stats
+            .get(&damage.damage_type)
+            .and_then(|k| stat_value(k, defender))
+            .unwrap_or(0.0);
+        let armor = if armor > 0.0 {
+            (armor - damage.armor_penetration.max(0.0)).max(0.0)
+        } else {
+            armor
+        };
+        let resistance = self
+            .resistance_stats
+            .get(&damage.damage_type)
+            .and_then(|k| stat_value(k, defender))
+            .unwrap_or(0.0)
+            .min(self.max_resistance);
+        let resistance = if resistance > 0.0 {
+            resistance * (1.0 - damage.resistance_penetration.clamp(0.0, 1.0))
+        } else {
+            resistance
+        };
+        let dealt = match self.order {
+            MitigationOrder::ArmorFirst => (raw - armor).max(0.0) * (1.0 - resistance),
+            MitigationOrder::ResistanceFirst => raw * (1.0 - resistance) - armor,
+        };
+        let dealt = dealt.max(self.minimum_damage.min(raw)).max(0.0);
+        let mut event = DamageEvent::new(raw, raw - dealt, dealt);
+        event.critical = damage.critical;
+        event
+    }
+}
+
+/// A part of a `DamageFormula`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DamageTerm<K> {
+    /// A constant value.
+    Flat(f64),
+    /// The value of a stat of the attacker multiplied by a factor.
+    AttackerStat(K, f64),
+    /// The value of a stat of the defender multiplied by a factor.
+    DefenderStat(K, f64),
+}
+
+/// A damage formula based on the stats of the attacker and of the defender.
+/// For example, `0.6 * strength + weapon_damage` can be expressed as
+/// `[AttackerStat(strength, 0.6), AttackerStat(weapon_damage, 1.0)]`.
+/// The value of the stats with the effectors applied is used. Missing stats count as 0.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DamageFormula<K> {
+    /// The terms that are added together to get the raw damage.
+    pub terms: Vec<DamageTerm<K>>,
+    /// The stat of the attacker holding the chance of dealing a critical hit, between 0.0 and
+    /// 1.0.
+    #[new(default)]
+    pub critical_chance_stat: Option<K>,
+    /// The multiplier applied to the raw damage of critical hits.
+    #[new(value = "1.0")]
+    pub critical_multiplier: f64,
+    /// How the defensive stats of the defender reduce the damage.
+    #[new(value = "DamageCalculator::new()")]
+    pub mitigation: DamageCalculator<K>,
+}
+
+impl<K: Hash + Eq> DamageFormula<K> {
+    /// Calculates the raw damage, without critical hits or mitigation.
+    pub fn raw_damage(&self, attacker: &StatSet<K>, defender: &StatSet<K>) -> f64 {
+        self.terms
+            .iter()
+            .map(|t| match t {
+                DamageTerm::Flat(v) => *v,
+                DamageTerm::AttackerStat(k, f) => stat_value(k, attacker).unwrap_or(0.0) * f,
+                DamageTerm::DefenderStat(k, f) => stat_value(k, defender).unwrap_or(0.0) * f,
+            })
+            .sum()
+    }
+
+    /// Calculates the damage dealt by the attacker to the defender.
+    #[cfg(feature = "std")]
+    pub fn evaluate(&self, attacker: &StatSet<K>, defender: &StatSet<K>) -> DamageEvent {
+        self.evaluate_with(attacker, defender, &mut thread_rng())
+    }
+
+    /// Same as `DamageFormula::evaluate`, using the provided random number generator to roll
+    /// critical hits.
+    pub fn evaluate_with<G: Rng>(
+        &self,
+        attacker: &StatSet<K>,
+        defender: &StatSet<K>,
+        rng: &mut G,
+    ) -> DamageEvent {
+        let mut raw = self.raw_damage(attacker, defender);
+        let chance = self
+            .critical_chance_stat
+            .as_ref()
+            .and_then(|k| stat_value(k, attacker))
+            .unwrap_or(0.0);
+        let critical = chance > 0.0 && rng.gen::<f64>() < chance;
+        if critical {
+            raw *= self.critical_multiplier;
+        }
+        let mut event = self.mitigation.mitigate(raw, defender);
+        event.critical = critical;
+        event
+    }
+}
+
+fn default_max_resistance() -> f64 {
+    1.0
+}
+
+fn stat_value<K: Hash + Eq>(key: &K, stats: &StatSet<K>) -> Option<f64> {
+    stats.stats.get(key).map(|s| s.value_with_effectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum DamageType {
+        Physical,
+        Fire,
+    }
+
+    fn defender(armor: f64, resistance: f64) -> StatSet<u32> {
+        StatDefinitions::from(vec![
+            StatDefinition::new(0, "Armor".into(), "armor".to_string(), armor),
+            StatDefinition::new(1, "Resistance".into(), "resistance".to_string(), resistance),
+        ])
+        .to_statset()
+    }
+
+    fn mitigation() -> DamageMitigation<DamageType, u32> {
+        let mut mitigation = DamageMitigation::new();
+        mitigation.armor_stats.insert(DamageType::Physical, 0);
+        mitigation.resistance_stats.insert(DamageType::Physical, 1);
+        mitigation.resistance_stats.insert(DamageType::Fire, 1);
+        mitigation
+    }
+
+    fn dealt(
+        mitigation: &DamageMitigation<DamageType, u32>,
+        damage: &TypedDamage<DamageType>,
+    ) -> f64 {
+        mitigation.mitigate(damage, &defender(10.0, 0.5)).damage
+    }
+
+    #[test]
+    fn mitigation_order() {
+        let mut mitigation = mitigation();
+        let hit = TypedDamage::new(DamageType::Physical, 30.0);
+        assert_eq!(dealt(&mitigation, &hit), 10.0);
+        assert_eq!(
+            dealt(&mitigation, &TypedDamage::new(DamageType::Fire, 30.0)),
+            15.0
+        );
+        mitigation.order = MitigationOrder::ResistanceFirst;
+        assert_eq!(dealt(&mitigation, &hit), 5.0);
+    }
+
+    #[test]
+    fn resistance_penetration_is_a_fraction() {
+        let mitigation = mitigation();
+        let mut hit = TypedDamage::new(DamageType::Fire, 40.0);
+        hit.resistance_penetration = 0.5;
+        // Half of the 50% resistance is ignored.
+        assert_eq!(dealt(&mitigation, &hit), 30.0);
+        hit.resistance_penetration = 2.0;
+        assert_eq!(dealt(&mitigation, &hit), 40.0);
+        let mut hit = TypedDamage::new(DamageType::Physical, 30.0);
+        hit.armor_penetration = 4.0;
+        assert_eq!(dealt(&mitigation, &hit), 12.0);
+    }
+
+    #[test]
+    fn negative_resistance_increases_damage() {
+        let mitigation = mitigation();
+        let mut hit = TypedDamage::new(DamageType::Fire, 10.0);
+        hit.resistance_penetration = 1.0;
+        let event = mitigation.mitigate(&hit, &defender(0.0, -0.5));
+        assert_eq!((event.mitigated, event.damage), (-5.0, 15.0));
+    }
+
+    #[test]
+    fn minimum_damage() {
+        let mut mitigation = mitigation();
+        mitigation.minimum_damage = 1.0;
+        assert_eq!(
+            dealt(&mitigation, &TypedDamage::new(DamageType::Physical, 5.0)),
+            1.0
+        );
+        assert_eq!(
+            dealt(&mitigation, &TypedDamage::new(DamageType::Physical, 0.5)),
+            0.5
+        );
+        assert_eq!(
+            dealt(&mitigation, &TypedDamage::new(DamageType::Physical, -3.0)),
+            0.0
+        );
+    }
+}
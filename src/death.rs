@@ -0,0 +1,122 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The items dropped when a character dies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeathDrop {
+    /// No item is dropped.
+    Nothing,
+    /// The items that are not equipped are dropped.
+    Unequipped,
+    /// All the items are dropped.
+    All,
+}
+
+/// What happens to a character when it dies.
+/// Equipped items are the items in the slots of the inventory that have a slot restriction.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DeathRules {
+    /// The fraction of the accumulated experience lost, between 0.0 and 1.0.
+    pub xp_loss: f64,
+    /// The durability removed from each equipped item.
+    pub durability_damage: usize,
+    /// The items dropped into a `LootContainer`.
+    pub drop: DeathDrop,
+    /// The time before the dropped items disappear, in seconds.
+    /// None means that they never disappear.
+    pub drop_expires_in: Option<f64>,
+    /// The time to wait before respawning, in seconds.
+    pub respawn_time: f64,
+    /// Whether the members of a faction lose power when dying.
+    /// See `FactionSettings::power_loss_on_death`.
+    pub faction_power_loss: bool,
+}
+
+/// The consequences of a death. See `DeathRules::apply_death`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DeathOutcome<K, S: SlotType, U: Default> {
+    /// The experience that was lost.
+    pub xp_lost: u32,
+    /// The container holding the dropped items, if any item was dropped.
+    pub dropped: Option<LootContainer<K, S, U>>,
+    /// The timer after which the character can respawn.
+    pub respawn: Timer,
+}
+
+impl DeathRules {
+    /// Applies the death penalties to the character: experience loss, durability damage to
+    /// the equipped items and item drops.
+    /// Faction power loss is applied separately using `DeathRules::apply_power_loss`.
+    pub fn apply_death<
+        K: Hash + Eq,
+        E,
+        S: Hash + Eq,
+        I: PartialEq + Clone + Debug + Hash + Eq,
+        IT: SlotType + Clone,
+        CD: Default + Clone + Debug + PartialEq,
+        U: Hash + Eq,
+        L,
+    >(
+        &self,
+        character: &mut CharacterSheet<K, E, S, I, IT, CD, U, L>,
+    ) -> DeathOutcome<I, IT, CD> {
+        let xp_lost = (f64::from(character.experience) * self.xp_loss.clamp(0.0, 1.0)).round();
+        let xp_lost = (xp_lost as u32).min(character.experience);
+        character.experience -= xp_lost;
+
+        let inventory = &mut character.inventory;
+        let mut dropped = vec![];
+        for idx in (0..inventory.content.len()).rev() {
+            let equipped = inventory
+                .slot_restriction
+                .get(idx)
+                .map(Option::is_some)
+                .unwrap_or(false);
+            if equipped {
+                if let Some(durability) =
+                    inventory.get_mut(idx).and_then(|ii| ii.durability.as_mut())
+                {
+                    *durability = durability.saturating_sub(self.durability_damage);
+                }
+            }
+            let drops = match self.drop {
+                DeathDrop::Nothing => false,
+                DeathDrop::Unequipped => !equipped,
+                DeathDrop::All => true,
+            };
+            if drops {
+                if let Ok(item) = inventory.delete_stack(idx) {
+                    dropped.push(item);
+                }
+            }
+        }
+
+        let dropped = if dropped.is_empty() {
+            None
+        } else {
+            let mut container_inventory = Inventory::new_dynamic(0, dropped.len());
+            container_inventory.content = dropped.into_iter().rev().map(Some).collect();
+            Some(LootContainer::new(
+                container_inventory,
+                None,
+                self.drop_expires_in,
+            ))
+        };
+        DeathOutcome::new(xp_lost, dropped, Timer::once(self.respawn_time))
+    }
+
+    /// Removes faction power from the user if `faction_power_loss` is true.
+    /// Deaths in claimed territory follow `FactionFlags::power_loss_in_territory`.
+    pub fn apply_power_loss(
+        &self,
+        user: i32,
+        in_claimed_territory: bool,
+        powers: &mut UserPowers,
+        settings: &FactionSettings,
+    ) {
+        if self.faction_power_loss {
+            powers.on_death(user, in_claimed_territory, settings);
+        }
+    }
+}
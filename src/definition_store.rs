@@ -0,0 +1,165 @@
+use crate::*;
+use core::hash::Hash;
+
+/// All the definitions of a game, bundled together so that they can be reloaded at once while
+/// the game is running, for example when the data files change during development.
+///
+/// # Generics
+/// - K: Stat Key
+/// - E: Effector Key
+/// - S: Skill Key
+/// - I: Item Key
+/// - IT: Item Slot Type
+/// - D: Item Definition User Data
+/// - T: Item Transition Key
+/// - U: Unlockable Key, for transitions that must be learned first
+#[derive(Clone, Serialize, Deserialize, new)]
+pub struct DefinitionStore<
+    K: Hash + Eq,
+    E: Hash + Eq,
+    S: Hash + Eq,
+    I: Hash + Eq,
+    IT,
+    D: Default,
+    T: Hash + Eq,
+    U = (),
+> {
+    /// The definitions of the stats.
+    pub stats: StatDefinitions<K>,
+    /// The definitions of the effectors.
+    pub effectors: EffectorDefinitions<K, E>,
+    /// The definitions of the items.
+    pub items: ItemDefinitions<I, IT, D>,
+    /// The definitions of the skills.
+    pub skills: SkillDefinitions<K, E, S, I>,
+    /// The definitions of the item transitions.
+    pub transitions: ItemTransitionDefinitions<T, I, E, K, U>,
+}
+
+impl<
+        K: Hash + Eq + Clone,
+        E: Hash + Eq + Clone,
+        S: Hash + Eq + Clone,
+        I: Hash + Eq + Clone,
+        IT,
+        D: Default,
+        T: Hash + Eq + Clone,
+        U,
+    > DefinitionStore<K, E, S, I, IT, D, T, U>
+{
+    /// Checks that all the definitions are consistent and only refer to known keys and to script
+    /// conditions registered in the `ScriptHost`.
+    /// Returns all the errors found.
+    pub fn validate(
+        &self,
+        scripts: Option<&ScriptHost<K>>,
+    ) -> Vec<DefinitionStoreError<K, E, S, I, T>> {
+        let mut errors = vec![];
+        errors.extend(
+            self.stats
+                .validate()
+                .into_iter()
+                .map(DefinitionStoreError::Stat),
+        );
+        errors.extend(
+            self.effectors
+                .validate(&self.stats)
+                .into_iter()
+                .map(DefinitionStoreError::Effector),
+        );
+        errors.extend(
+            self.items
+                .validate()
+                .into_iter()
+                .map(DefinitionStoreError::Item),
+        );
+        errors.extend(
+            self.skills
+                .validate(&self.stats, &self.effectors, &self.items, scripts)
+                .into_iter()
+                .map(DefinitionStoreError::Skill),
+        );
+        errors.extend(
+            self.transitions
+                .validate(&self.items, &self.stats, &self.effectors, scripts)
+                .into_iter()
+                .map(DefinitionStoreError::Transition),
+        );
+        errors
+    }
+
+    /// Replaces all the definitions by the reloaded ones, if they are valid.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// Returns all the errors found by `DefinitionStore::validate` on the reloaded definitions.
+    #[allow(clippy::type_complexity)]
+    pub fn reload(
+        &mut self,
+        reloaded: Self,
+        scripts: Option<&ScriptHost<K>>,
+    ) -> Result<(), Vec<DefinitionStoreError<K, E, S, I, T>>> {
+        let errors = reloaded.validate(scripts);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        *self = reloaded;
+        Ok(())
+    }
+
+    /// Returns the characters referring to keys that are not defined anymore, like after
+    /// removing an item from the data files, along with the unknown keys.
+    /// Characters without unknown keys are not returned.
+    #[allow(clippy::type_complexity)]
+    pub fn stale_references<'a, Id, CD: Default + 'a, UL: Hash + Eq + 'a, L: 'a>(
+        &self,
+        characters: impl IntoIterator<Item = (Id, &'a CharacterSheet<K, E, S, I, IT, CD, UL, L>)>,
+    ) -> Vec<(Id, Vec<UnknownKey<K, E, S, I>>)>
+    where
+        K: 'a,
+        E: 'a,
+        S: 'a,
+        I: 'a,
+        IT: SlotType + 'a,
+    {
+        characters
+            .into_iter()
+            .map(|(id, c)| {
+                (
+                    id,
+                    c.validate(&self.stats, &self.skills, &self.effectors, &self.items),
+                )
+            })
+            .filter(|(_, unknown)| !unknown.is_empty())
+            .collect()
+    }
+}
+
+impl<K: Hash + Eq, E: Hash + Eq, S: Hash + Eq, I: Hash + Eq, IT, D: Default, T: Hash + Eq, U>
+    Default for DefinitionStore<K, E, S, I, IT, D, T, U>
+{
+    fn default() -> Self {
+        Self {
+            stats: StatDefinitions::default(),
+            effectors: EffectorDefinitions::default(),
+            items: ItemDefinitions::default(),
+            skills: SkillDefinitions::default(),
+            transitions: ItemTransitionDefinitions::default(),
+        }
+    }
+}
+
+/// An error found when validating a `DefinitionStore`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefinitionStoreError<K, E, S, I, T> {
+    /// An error in the stat definitions.
+    Stat(StatDefinitionError<K>),
+    /// An error in the effector definitions.
+    Effector(EffectorDefinitionError<K, E>),
+    /// An error in the item definitions.
+    Item(ItemDefinitionError<I>),
+    /// An error in the skill definitions.
+    Skill(SkillDefinitionError<K, E, S, I>),
+    /// An error in the item transition definitions.
+    Transition(ItemTransitionDefinitionError<T, I, E, K>),
+}
@@ -0,0 +1,422 @@
+use crate::*;
+use core::hash::Hash;
+
+/// A definition that is registered in a `DefinitionRepository` under its own key.
+pub trait Definition {
+    /// The type of the key of the definition.
+    type Key: Hash + Eq + Clone;
+    /// The key of the definition.
+    fn key(&self) -> &Self::Key;
+}
+
+/// A collection of definitions mapped by their key, like `StatDefinitions` or
+/// `ItemDefinitions`.
+pub trait DefinitionRepository: Default {
+    /// The type of the definitions held.
+    type Definition: Definition;
+    /// The definitions mapped by their key.
+    fn definitions_mut(
+        &mut self,
+    ) -> &mut HashMap<<Self::Definition as Definition>::Key, Self::Definition>;
+
+    /// Adds the definitions to this repository.
+    ///
+    /// Errors:
+    /// * DuplicateKey: A definition with this key already exists. The definitions before it
+    ///   were added.
+    fn merge(
+        &mut self,
+        definitions: Vec<Self::Definition>,
+    ) -> Result<(), LoadError<<Self::Definition as Definition>::Key>> {
+        let defs = self.definitions_mut();
+        for d in definitions {
+            if defs.contains_key(d.key()) {
+                return Err(LoadError::DuplicateKey(d.key().clone()));
+            }
+            defs.insert(d.key().clone(), d);
+        }
+        Ok(())
+    }
+
+    /// Deserializes a list of definitions.
+    ///
+    /// Errors:
+    /// * Io: The reader failed.
+    /// * Ron/Json: The data is not a valid list of definitions.
+    /// * DuplicateKey: Two definitions use the same key.
+    #[cfg(all(feature = "std", any(feature = "ron", feature = "serde_json")))]
+    fn from_reader<R: std::io::Read>(
+        reader: R,
+        format: DefinitionFormat,
+    ) -> Result<Self, LoadError<<Self::Definition as Definition>::Key>>
+    where
+        Self::Definition: serde::de::DeserializeOwned,
+    {
+        let mut repository = Self::default();
+        repository.merge_reader(reader, format)?;
+        Ok(repository)
+    }
+
+    /// Deserializes a list of definitions from a file.
+    /// The format is guessed from the extension of the file.
+    ///
+    /// Errors:
+    /// * UnknownFormat: The extension is not one of the enabled formats.
+    /// * See `DefinitionRepository::from_reader`.
+    #[cfg(all(feature = "std", any(feature = "ron", feature = "serde_json")))]
+    fn from_path<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<Self, LoadError<<Self::Definition as Definition>::Key>>
+    where
+        Self::Definition: serde::de::DeserializeOwned,
+    {
+        Self::from_paths(&[path])
+    }
+
+    /// Deserializes the lists of definitions from multiple files and merges them together.
+    ///
+    /// Errors:
+    /// * DuplicateKey: Two definitions use the same key, in the same file or in different
+    ///   files.
+    /// * See `DefinitionRepository::from_path`.
+    #[cfg(all(feature = "std", any(feature = "ron", feature = "serde_json")))]
+    fn from_paths<P: AsRef<std::path::Path>>(
+        paths: &[P],
+    ) -> Result<Self, LoadError<<Self::Definition as Definition>::Key>>
+    where
+        Self::Definition: serde::de::DeserializeOwned,
+    {
+        let mut repository = Self::default();
+        for path in paths {
+            let format =
+                DefinitionFormat::from_path(path.as_ref()).ok_or(LoadError::UnknownFormat)?;
+            let file = std::fs::File::open(path).map_err(LoadError::Io)?;
+            repository.merge_reader(std::io::BufReader::new(file), format)?;
+        }
+        Ok(repository)
+    }
+
+    /// Deserializes a list of definitions and adds them to this repository.
+    ///
+    /// Errors:
+    /// See `DefinitionRepository::from_reader`.
+    #[cfg(all(feature = "std", any(feature = "ron", feature = "serde_json")))]
+    fn merge_reader<R: std::io::Read>(
+        &mut self,
+        reader: R,
+        format: DefinitionFormat,
+    ) -> Result<(), LoadError<<Self::Definition as Definition>::Key>>
+    where
+        Self::Definition: serde::de::DeserializeOwned,
+    {
+        let definitions = match format {
+            #[cfg(feature = "ron")]
+            DefinitionFormat::Ron => ron::de::from_reader(reader).map_err(LoadError::Ron)?,
+            #[cfg(feature = "serde_json")]
+            DefinitionFormat::Json => serde_json::from_reader(reader).map_err(LoadError::Json)?,
+        };
+        self.merge(definitions)
+    }
+}
+
+/// The formats definitions can be loaded from.
+/// Each format is enabled by the feature of the same name, along with the `std` feature.
+#[cfg(all(feature = "std", any(feature = "ron", feature = "serde_json")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionFormat {
+    /// Rusty Object Notation, using the `.ron` extension.
+    #[cfg(feature = "ron")]
+    Ron,
+    /// JSON, using the `.json` extension.
+    #[cfg(feature = "serde_json")]
+    Json,
+}
+
+#[cfg(all(feature = "std", any(feature = "ron", feature = "serde_json")))]
+impl DefinitionFormat {
+    /// Finds the format from the extension of the file.
+    /// Returns None if the extension is not one of the enabled formats.
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            #[cfg(feature = "ron")]
+            "ron" => Some(DefinitionFormat::Ron),
+            #[cfg(feature = "serde_json")]
+            "json" => Some(DefinitionFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The errors that can happen when loading definitions.
+#[derive(Debug)]
+pub enum LoadError<K> {
+    /// A definition with this key was already loaded.
+    DuplicateKey(K),
+    /// The format of the file is unknown.
+    UnknownFormat,
+    /// The file could not be read.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The data is not valid RON.
+    #[cfg(feature = "ron")]
+    Ron(ron::de::Error),
+    /// The data is not valid JSON.
+    #[cfg(feature = "serde_json")]
+    Json(serde_json::Error),
+}
+
+impl<K: Hash + Eq + Clone> Definition for StatDefinition<K> {
+    type Key = K;
+    fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: Hash + Eq + Clone> DefinitionRepository for StatDefinitions<K> {
+    type Definition = StatDefinition<K>;
+    fn definitions_mut(&mut self) -> &mut HashMap<K, StatDefinition<K>> {
+        &mut self.defs
+    }
+}
+
+impl<K, E: Hash + Eq + Clone> Definition for EffectorDefinition<K, E> {
+    type Key = E;
+    fn key(&self) -> &E {
+        &self.key
+    }
+}
+
+impl<K, E: Hash + Eq + Clone> DefinitionRepository for EffectorDefinitions<K, E> {
+    type Definition = EffectorDefinition<K, E>;
+    fn definitions_mut(&mut self) -> &mut HashMap<E, EffectorDefinition<K, E>> {
+        &mut self.defs
+    }
+}
+
+impl<K: Hash + Eq + Clone, S, D: Default> Definition for ItemDefinition<K, S, D> {
+    type Key = K;
+    fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: Hash + Eq + Clone, S, D: Default> DefinitionRepository for ItemDefinitions<K, S, D> {
+    type Definition = ItemDefinition<K, S, D>;
+    fn definitions_mut(&mut self) -> &mut HashMap<K, ItemDefinition<K, S, D>> {
+        &mut self.defs
+    }
+}
+
+impl<K, E, S: Hash + Eq + Clone, I> Definition for SkillDefinition<K, E, S, I> {
+    type Key = S;
+    fn key(&self) -> &S {
+        &self.key
+    }
+}
+
+impl<K, E, S: Hash + Eq + Clone, I> DefinitionRepository for SkillDefinitions<K, E, S, I> {
+    type Definition = SkillDefinition<K, E, S, I>;
+    fn definitions_mut(&mut self) -> &mut HashMap<S, SkillDefinition<K, E, S, I>> {
+        &mut self.defs
+    }
+}
+
+impl<K: Hash + Eq + Clone, I, E, S, U, C> Definition
+    for ItemTransitionDefinition<K, I, E, S, U, C>
+{
+    type Key = K;
+    fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: Hash + Eq + Clone, I, E, S, U, C> DefinitionRepository
+    for ItemTransitionDefinitions<K, I, E, S, U, C>
+{
+    type Definition = ItemTransitionDefinition<K, I, E, S, U, C>;
+    fn definitions_mut(&mut self) -> &mut HashMap<K, ItemTransitionDefinition<K, I, E, S, U, C>> {
+        &mut self.defs
+    }
+}
+
+impl<R: Hash + Eq + Clone> Definition for RarityDefinition<R> {
+    type Key = R;
+    fn key(&self) -> &R {
+        &self.key
+    }
+}
+
+impl<R: Hash + Eq + Clone> DefinitionRepository for RarityDefinitions<R> {
+    type Definition = RarityDefinition<R>;
+    fn definitions_mut(&mut self) -> &mut HashMap<R, RarityDefinition<R>> {
+        &mut self.defs
+    }
+}
+
+impl<K: Hash + Eq + Clone, E, S> Definition for AffixDefinition<K, E, S> {
+    type Key = K;
+    fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: Hash + Eq + Clone, E, S> DefinitionRepository for AffixDefinitions<K, E, S> {
+    type Definition = AffixDefinition<K, E, S>;
+    fn definitions_mut(&mut self) -> &mut HashMap<K, AffixDefinition<K, E, S>> {
+        &mut self.defs
+    }
+}
+
+impl<C: Hash + Eq + Clone> Definition for CurrencyDefinition<C> {
+    type Key = C;
+    fn key(&self) -> &C {
+        &self.key
+    }
+}
+
+impl<C: Hash + Eq + Clone> DefinitionRepository for CurrencyDefinitions<C> {
+    type Definition = CurrencyDefinition<C>;
+    fn definitions_mut(&mut self) -> &mut HashMap<C, CurrencyDefinition<C>> {
+        &mut self.defs
+    }
+}
+
+impl<A: Hash + Eq + Clone, K, C> Definition for AchievementDefinition<A, K, C> {
+    type Key = A;
+    fn key(&self) -> &A {
+        &self.key
+    }
+}
+
+impl<A: Hash + Eq + Clone, K, C> DefinitionRepository for AchievementDefinitions<A, K, C> {
+    type Definition = AchievementDefinition<A, K, C>;
+    fn definitions_mut(&mut self) -> &mut HashMap<A, AchievementDefinition<A, K, C>> {
+        &mut self.defs
+    }
+}
+
+impl<K: Hash + Eq + Clone> Definition for DerivedStatDefinition<K> {
+    type Key = K;
+    fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: Hash + Eq + Clone> DefinitionRepository for DerivedStatDefinitions<K> {
+    type Definition = DerivedStatDefinition<K>;
+    fn definitions_mut(&mut self) -> &mut HashMap<K, DerivedStatDefinition<K>> {
+        &mut self.defs
+    }
+}
+
+impl<K, I, C, E> Definition for TierDefinition<K, I, C, E> {
+    type Key = u32;
+    fn key(&self) -> &u32 {
+        &self.tier
+    }
+}
+
+impl<K, I, C, E> DefinitionRepository for TierDefinitions<K, I, C, E> {
+    type Definition = TierDefinition<K, I, C, E>;
+    fn definitions_mut(&mut self) -> &mut HashMap<u32, TierDefinition<K, I, C, E>> {
+        &mut self.defs
+    }
+}
+
+impl<T: Hash + Eq + Clone, K, S, I, LT> Definition for EntityTemplate<T, K, S, I, LT> {
+    type Key = T;
+    fn key(&self) -> &T {
+        &self.key
+    }
+}
+
+impl<T: Hash + Eq + Clone, K, S, I, LT> DefinitionRepository for EntityTemplates<T, K, S, I, LT> {
+    type Definition = EntityTemplate<T, K, S, I, LT>;
+    fn definitions_mut(&mut self) -> &mut HashMap<T, EntityTemplate<T, K, S, I, LT>> {
+        &mut self.defs
+    }
+}
+
+impl<G: Hash + Eq + Clone, K, R> Definition for CollectionCategory<G, K, R> {
+    type Key = G;
+    fn key(&self) -> &G {
+        &self.key
+    }
+}
+
+impl<G: Hash + Eq + Clone, K, R> DefinitionRepository for CollectionCategories<G, K, R> {
+    type Definition = CollectionCategory<G, K, R>;
+    fn definitions_mut(&mut self) -> &mut HashMap<G, CollectionCategory<G, K, R>> {
+        &mut self.defs
+    }
+}
+
+impl<I: Hash + Eq + Clone, E> Definition for ConsumableEffect<I, E> {
+    type Key = I;
+    fn key(&self) -> &I {
+        &self.key
+    }
+}
+
+impl<I: Hash + Eq + Clone, E> DefinitionRepository for ConsumableEffects<I, E> {
+    type Definition = ConsumableEffect<I, E>;
+    fn definitions_mut(&mut self) -> &mut HashMap<I, ConsumableEffect<I, E>> {
+        &mut self.defs
+    }
+}
+
+impl<K: Hash + Eq + Clone> Definition for WeaponAttachment<K> {
+    type Key = K;
+    fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: Hash + Eq + Clone> DefinitionRepository for WeaponAttachments<K> {
+    type Definition = WeaponAttachment<K>;
+    fn definitions_mut(&mut self) -> &mut HashMap<K, WeaponAttachment<K>> {
+        &mut self.defs
+    }
+}
+
+impl<N: Hash + Eq + Clone, K, S, I, L> Definition for ResourceNode<N, K, S, I, L> {
+    type Key = N;
+    fn key(&self) -> &N {
+        &self.key
+    }
+}
+
+impl<N: Hash + Eq + Clone, K, S, I, L> DefinitionRepository for ResourceNodes<N, K, S, I, L> {
+    type Definition = ResourceNode<N, K, S, I, L>;
+    fn definitions_mut(&mut self) -> &mut HashMap<N, ResourceNode<N, K, S, I, L>> {
+        &mut self.defs
+    }
+}
+
+impl<C: Hash + Eq + Clone, K, L> Definition for Crop<C, K, L> {
+    type Key = C;
+    fn key(&self) -> &C {
+        &self.key
+    }
+}
+
+impl<C: Hash + Eq + Clone, K, L> DefinitionRepository for Crops<C, K, L> {
+    type Definition = Crop<C, K, L>;
+    fn definitions_mut(&mut self) -> &mut HashMap<C, Crop<C, K, L>> {
+        &mut self.defs
+    }
+}
+
+impl<B: Hash + Eq + Clone, T, K, I, U> Definition for Blueprint<B, T, K, I, U> {
+    type Key = B;
+    fn key(&self) -> &B {
+        &self.key
+    }
+}
+
+impl<B: Hash + Eq + Clone, T, K, I, U> DefinitionRepository for Blueprints<B, T, K, I, U> {
+    type Definition = Blueprint<B, T, K, I, U>;
+    fn definitions_mut(&mut self) -> &mut HashMap<B, Blueprint<B, T, K, I, U>> {
+        &mut self.defs
+    }
+}
@@ -0,0 +1,216 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// A part of the formula of a `DerivedStatDefinition`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DerivedStatTerm<K> {
+    /// A constant value.
+    Flat(f64),
+    /// The base value of a stat multiplied by a factor.
+    Stat(K, f64),
+}
+
+/// Defines the base value of a stat as a formula over other stats.
+/// For example, `max_health = 10 * vitality + 50` can be expressed as
+/// `[Stat(vitality, 10.0), Flat(50.0)]`.
+/// The derived stat must also have a `StatDefinition`, whose bounds are applied to the result.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DerivedStatDefinition<K> {
+    /// The key of the derived stat.
+    pub key: K,
+    /// The terms that are added together to get the value of the stat.
+    pub terms: Vec<DerivedStatTerm<K>>,
+}
+
+impl<K> DerivedStatDefinition<K> {
+    /// Returns the keys of the stats used by the formula.
+    pub fn inputs(&self) -> impl Iterator<Item = &K> {
+        self.terms.iter().filter_map(|t| match t {
+            DerivedStatTerm::Stat(k, _) => Some(k),
+            DerivedStatTerm::Flat(_) => None,
+        })
+    }
+}
+
+impl<K: Hash + Eq> DerivedStatDefinition<K> {
+    /// Calculates the value of the stat from the base values of the provided `StatSet`.
+    /// Missing stats count as 0.
+    pub fn evaluate(&self, stats: &StatSet<K>) -> f64 {
+        self.terms
+            .iter()
+            .map(|t| match t {
+                DerivedStatTerm::Flat(v) => *v,
+                DerivedStatTerm::Stat(k, f) => {
+                    stats.stats.get(k).map(|s| s.value).unwrap_or(0.0) * f
+                }
+            })
+            .sum()
+    }
+}
+
+/// The definitions of all the derived stats.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DerivedStatDefinitions<K: Hash + Eq> {
+    /// The definitions.
+    pub defs: HashMap<K, DerivedStatDefinition<K>>,
+}
+
+impl<K: Hash + Eq> Default for DerivedStatDefinitions<K> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> From<Vec<DerivedStatDefinition<K>>> for DerivedStatDefinitions<K> {
+    fn from(t: Vec<DerivedStatDefinition<K>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<K: Hash + Eq + Clone> DerivedStatDefinitions<K> {
+    /// Returns the keys of the derived stats, ordered so that each stat comes after the derived
+    /// stats it depends on.
+    ///
+    /// Errors:
+    /// * Cycle: The stat depends on itself, directly or through other derived stats.
+    pub fn evaluation_order(&self) -> Result<Vec<K>, DerivedStatError<K>> {
+        let mut order = vec![];
+        let mut done = HashSet::new();
+        let mut visiting = HashSet::new();
+        for k in self.defs.keys() {
+            self.visit(k, &mut visiting, &mut done, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    fn visit(
+        &self,
+        key: &K,
+        visiting: &mut HashSet<K>,
+        done: &mut HashSet<K>,
+        order: &mut Vec<K>,
+    ) -> Result<(), DerivedStatError<K>> {
+        if done.contains(key) {
+            return Ok(());
+        }
+        let def = match self.defs.get(key) {
+            Some(def) => def,
+            // Not a derived stat.
+            None => return Ok(()),
+        };
+        if !visiting.insert(key.clone()) {
+            return Err(DerivedStatError::Cycle(key.clone()));
+        }
+        for input in def.inputs() {
+            self.visit(input, visiting, done, order)?;
+        }
+        visiting.remove(key);
+        done.insert(key.clone());
+        order.push(key.clone());
+        Ok(())
+    }
+
+    /// Checks that the definitions are consistent, only use known stats and don't depend on
+    /// themselves.
+    /// Returns all the errors found.
+    pub fn validate(&self, stat_defs: &StatDefinitions<K>) -> Vec<DerivedStatError<K>> {
+        let mut errors = vec![];
+        for (k, def) in self.defs.iter() {
+            if def.key != *k {
+                errors.push(DerivedStatError::KeyMismatch(k.clone()));
+            }
+            if !stat_defs.defs.contains_key(k) {
+                errors.push(DerivedStatError::UnknownStat(k.clone(), k.clone()));
+            }
+            for input in def.inputs() {
+                if !stat_defs.defs.contains_key(input) {
+                    errors.push(DerivedStatError::UnknownStat(k.clone(), input.clone()));
+                }
+            }
+        }
+        if let Err(e) = self.evaluation_order() {
+            errors.push(e);
+        }
+        errors
+    }
+}
+
+/// The errors related to derived stats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerivedStatError<K> {
+    /// The definition is registered under a key different from its own.
+    KeyMismatch(K),
+    /// The derived stat (first) is or uses a stat (second) that is not defined.
+    UnknownStat(K, K),
+    /// The derived stat depends on itself, directly or through other derived stats.
+    Cycle(K),
+}
+
+impl<K: Hash + Eq + Clone + Debug> StatSet<K> {
+    /// Recomputes the base value of all the derived stats, in dependency order.
+    ///
+    /// Errors:
+    /// * Cycle: A derived stat depends on itself. No stat was changed.
+    pub fn derive(
+        &mut self,
+        derived_defs: &DerivedStatDefinitions<K>,
+        stat_defs: &StatDefinitions<K>,
+    ) -> Result<(), DerivedStatError<K>> {
+        for k in derived_defs.evaluation_order()? {
+            let value = derived_defs.defs[&k].evaluate(self);
+            self.set(&k, value, stat_defs);
+        }
+        Ok(())
+    }
+
+    /// Recomputes the base value of the derived stats depending on the specified stat,
+    /// directly or through other derived stats.
+    ///
+    /// Errors:
+    /// * Cycle: A derived stat depends on itself. No stat was changed.
+    pub fn derive_from(
+        &mut self,
+        key: &K,
+        derived_defs: &DerivedStatDefinitions<K>,
+        stat_defs: &StatDefinitions<K>,
+    ) -> Result<(), DerivedStatError<K>> {
+        let mut changed = HashSet::new();
+        changed.insert(key.clone());
+        for k in derived_defs.evaluation_order()? {
+            let def = &derived_defs.defs[&k];
+            if def.inputs().any(|i| changed.contains(i)) {
+                let value = def.evaluate(self);
+                self.set(&k, value, stat_defs);
+                changed.insert(k);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the base value of the specified stat, then recomputes the derived stats depending
+    /// on it.
+    /// Returns None if the stat is not in this `StatSet`.
+    ///
+    /// Errors:
+    /// * Cycle: A derived stat depends on itself. Only the specified stat was changed.
+    pub fn set_and_derive(
+        &mut self,
+        key: &K,
+        value: f64,
+        stat_defs: &StatDefinitions<K>,
+        derived_defs: &DerivedStatDefinitions<K>,
+    ) -> Result<Option<StatChange>, DerivedStatError<K>> {
+        let change = self.set(key, value, stat_defs);
+        if change.is_some() {
+            self.derive_from(key, derived_defs, stat_defs)?;
+        }
+        Ok(change)
+    }
+}
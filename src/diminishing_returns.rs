@@ -0,0 +1,132 @@
+use crate::*;
+use core::hash::Hash;
+
+/// The diminishing returns of a category of control effectors, like stuns or roots.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DiminishingCategory {
+    /// The duration multiplier of each successive application within the window.
+    /// For example, [1.0, 0.5, 0.25] halves the duration each time, and the fourth application
+    /// is ignored because the target is immune.
+    pub multipliers: Vec<f64>,
+    /// The time after the last application at which the category resets, in seconds.
+    pub window: f64,
+}
+
+/// The diminishing returns categories, by effector tag.
+/// An effector belongs to all the categories matching its tags.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct DiminishingRules {
+    /// The categories, by effector tag.
+    #[new(default)]
+    pub categories: HashMap<String, DiminishingCategory>,
+}
+
+/// The applications of a category within its window.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct DiminishingState {
+    /// The number of applications since the category was last reset.
+    pub applications: usize,
+    /// The time remaining before the category resets, in seconds.
+    pub reset_in: f64,
+}
+
+/// The result of adding an effector through `DiminishingReturns::add`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiminishedAddResult {
+    /// The effector was added to the `EffectorSet` with its duration scaled by the multiplier.
+    Applied(EffectorAddResult, f64),
+    /// The target is immune to one of the categories of the effector. Nothing changed.
+    Immune,
+}
+
+/// Tracks the control effectors applied to an entity so that repeated applications of the same
+/// category get shorter durations, and eventually immunity.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct DiminishingReturns {
+    /// The state of each category applied recently.
+    #[new(default)]
+    pub states: HashMap<String, DiminishingState>,
+}
+
+impl DiminishingReturns {
+    /// Returns the duration multiplier the next application of the category will have.
+    /// 0.0 means that the target is immune.
+    pub fn multiplier(&self, category: &str, rules: &DiminishingRules) -> f64 {
+        let def = match rules.categories.get(category) {
+            Some(def) => def,
+            None => return 1.0,
+        };
+        let applications = self.states.get(category).map_or(0, |s| s.applications);
+        def.multipliers.get(applications).copied().unwrap_or(0.0)
+    }
+
+    /// Checks if the target is immune to the category.
+    pub fn is_immune(&self, category: &str, rules: &DiminishingRules) -> bool {
+        self.multiplier(category, rules) <= 0.0
+    }
+
+    /// Advances the windows by `delta_time` seconds and resets the categories whose window
+    /// elapsed.
+    pub fn update(&mut self, delta_time: f64) {
+        for state in self.states.values_mut() {
+            state.reset_in -= delta_time;
+        }
+        self.states.retain(|_, s| s.reset_in > 0.0);
+    }
+
+    /// Adds the effector to the `EffectorSet`, scaling its duration by the lowest multiplier
+    /// of its categories, then counts the application in each category and restarts their
+    /// windows.
+    /// Effectors without a duration or applied once are not scaled, but still count as
+    /// applications.
+    /// Nothing changes if the target is immune or if the `EffectorStacking` rule rejects the
+    /// effector.
+    pub fn add<K, E: Hash + Eq + Clone>(
+        &mut self,
+        key: &E,
+        effectors: &mut EffectorSet<E>,
+        effector_defs: &EffectorDefinitions<K, E>,
+        rules: &DiminishingRules,
+    ) -> DiminishedAddResult {
+        let def = effector_defs
+            .defs
+            .get(key)
+            .expect("Tried to get unknown effector key.");
+        let categories = def
+            .tags
+            .iter()
+            .filter(|t| rules.categories.contains_key(*t))
+            .collect::<Vec<_>>();
+        let multiplier = categories
+            .iter()
+            .map(|c| self.multiplier(c, rules))
+            .fold(1.0, f64::min);
+        if multiplier <= 0.0 {
+            return DiminishedAddResult::Immune;
+        }
+        let result = effectors.add(key, effector_defs);
+        let instance = match result {
+            EffectorAddResult::Added => effectors.effectors.last_mut(),
+            EffectorAddResult::Refreshed => effectors
+                .effectors
+                .iter_mut()
+                .find(|e| e.effector_key == *key),
+            EffectorAddResult::Rejected => return DiminishedAddResult::Applied(result, multiplier),
+        };
+        if let (Some(instance), Some(duration)) = (instance, def.duration) {
+            if duration > 0.0 {
+                instance.disable_in = Some(Timer::once(duration * multiplier));
+            }
+        }
+        for category in categories {
+            let window = rules.categories[category].window;
+            let state = self
+                .states
+                .entry(category.clone())
+                .or_insert_with(|| DiminishingState::new(0, window));
+            state.applications += 1;
+            state.reset_in = window;
+        }
+        DiminishedAddResult::Applied(result, multiplier)
+    }
+}
@@ -1,6 +1,7 @@
 use crate::*;
-use std::collections::HashMap;
-use std::hash::Hash;
+use core::fmt::Debug;
+use core::hash::Hash;
+use derivative::*;
 
 /// Holds the definitions of the stat effectors.
 #[derive(Debug, Clone, Serialize, Deserialize, new)]
@@ -29,6 +30,37 @@ impl<K: Hash + Eq + Clone, E: Hash + Eq + Clone> From<Vec<EffectorDefinition<K,
     }
 }
 
+impl<K: Hash + Eq + Clone, E: Hash + Eq + Clone> EffectorDefinitions<K, E> {
+    /// Checks that the definitions are consistent and only use known stats.
+    /// Returns all the errors found.
+    pub fn validate(&self, stat_defs: &StatDefinitions<K>) -> Vec<EffectorDefinitionError<K, E>> {
+        let mut errors = vec![];
+        for (k, def) in self.defs.iter() {
+            if def.key != *k {
+                errors.push(EffectorDefinitionError::KeyMismatch(k.clone()));
+            }
+            for (stat, _) in def.effects.iter() {
+                if !stat_defs.defs.contains_key(stat) {
+                    errors.push(EffectorDefinitionError::UnknownStat(
+                        k.clone(),
+                        stat.clone(),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// The errors found when validating `EffectorDefinitions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EffectorDefinitionError<K, E> {
+    /// The definition is registered under a key different from its own.
+    KeyMismatch(E),
+    /// The effector modifies a stat that is not defined.
+    UnknownStat(E, K),
+}
+
 /// A collection of currently active effectors.
 #[derive(Debug, Clone, Serialize, Deserialize, new)]
 pub struct EffectorSet<E> {
@@ -42,17 +74,254 @@ impl<E> Default for EffectorSet<E> {
     }
 }
 
+impl<E: Hash + Eq + Clone> EffectorSet<E> {
+    /// Adds a new instance of the specified effector, respecting the `EffectorStacking` rule
+    /// of its definition.
+    pub fn add<K>(
+        &mut self,
+        key: &E,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> EffectorAddResult {
+        self.add_with_magnitude(key, 1.0, effector_defs)
+    }
+
+    /// Same as `EffectorSet::add`, scaling the effects of the new instance by the magnitude.
+    /// See `EffectorInstance::magnitude`.
+    pub fn add_with_magnitude<K>(
+        &mut self,
+        key: &E,
+        magnitude: f64,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> EffectorAddResult {
+        let def = effector_defs
+            .defs
+            .get(key)
+            .expect("Tried to get unknown effector key.");
+        let active = self.count(key);
+        match def.stacking {
+            EffectorStacking::Unlimited => {}
+            EffectorStacking::Unique => {
+                if active > 0 {
+                    return EffectorAddResult::Rejected;
+                }
+            }
+            EffectorStacking::RefreshDuration => {
+                if let Some(e) = self.effectors.iter_mut().find(|e| e.effector_key == *key) {
+                    e.disable_in = def.duration.map(Timer::once);
+                    e.absorb_remaining = def
+                        .absorb_amounts()
+                        .into_iter()
+                        .map(|v| v * e.magnitude)
+                        .collect();
+                    return EffectorAddResult::Refreshed;
+                }
+            }
+            EffectorStacking::StackUpTo(max) => {
+                if active >= max {
+                    return EffectorAddResult::Rejected;
+                }
+            }
+        }
+        let mut instance = def.default_instance();
+        instance.magnitude = magnitude;
+        for v in instance.absorb_remaining.iter_mut() {
+            *v *= magnitude;
+        }
+        self.effectors.push(instance);
+        EffectorAddResult::Added
+    }
+
+    /// Returns the number of active instances of the specified effector.
+    pub fn count(&self, key: &E) -> usize {
+        self.effectors
+            .iter()
+            .filter(|e| e.effector_key == *key)
+            .count()
+    }
+}
+
 impl<E: Hash + Eq> EffectorSet<E> {
+    /// Returns the number of active effectors having the tag.
+    pub fn count_by_tag<K>(&self, tag: &str, effector_defs: &EffectorDefinitions<K, E>) -> usize {
+        self.effectors
+            .iter()
+            .filter(|e| Self::instance_has_tag(e, tag, effector_defs))
+            .count()
+    }
+
+    /// Checks if any active effector has the tag.
+    /// This can be used to know if an entity is silenced, rooted, etc.
+    pub fn has_tag<K>(&self, tag: &str, effector_defs: &EffectorDefinitions<K, E>) -> bool {
+        self.effectors
+            .iter()
+            .any(|e| Self::instance_has_tag(e, tag, effector_defs))
+    }
+
+    /// Removes all active effectors having the tag and returns how many were removed.
+    pub fn remove_by_tag<K>(
+        &mut self,
+        tag: &str,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> usize {
+        let before = self.effectors.len();
+        self.effectors
+            .retain(|e| !Self::instance_has_tag(e, tag, effector_defs));
+        before - self.effectors.len()
+    }
+
+    /// Consumes the absorption of the active effectors protecting the stat, oldest first, and
+    /// returns the damage left.
+    /// Effectors whose absorption is depleted for all the stats they protect are removed.
+    pub fn absorb<K: PartialEq>(
+        &mut self,
+        stat: &K,
+        damage: f64,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> f64 {
+        let mut left = damage.max(0.0);
+        for e in self.effectors.iter_mut() {
+            if left <= 0.0 {
+                break;
+            }
+            let def = match effector_defs.defs.get(&e.effector_key) {
+                Some(def) => def,
+                None => continue,
+            };
+            let protected = def
+                .effects
+                .iter()
+                .filter(|(_, ty)| matches!(ty, EffectorType::Absorb(_)))
+                .map(|(k, _)| k);
+            for (k, remaining) in protected.zip(e.absorb_remaining.iter_mut()) {
+                if k == stat {
+                    let absorbed = remaining.min(left);
+                    *remaining -= absorbed;
+                    left -= absorbed;
+                }
+            }
+        }
+        self.effectors.retain(|e| {
+            e.absorb_remaining.is_empty() || e.absorb_remaining.iter().any(|r| *r > 0.0)
+        });
+        left
+    }
+
+    fn instance_has_tag<K>(
+        instance: &EffectorInstance<E>,
+        tag: &str,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> bool {
+        effector_defs
+            .defs
+            .get(&instance.effector_key)
+            .map(|d| d.has_tag(tag))
+            .unwrap_or(false)
+    }
+}
+
+impl<E: Hash + Eq> EffectorSet<E> {
+    /// Advances the remaining duration of the active effectors by `delta_time` seconds and
+    /// removes those that expired.
+    /// Effectors with a duration of Some(0) are applied once to the base value of the stats in
+    /// the provided `StatSet`, then removed.
+    /// `AdditivePerSecond` effectors modify the base value proportionally to the elapsed time.
+    /// Base values are kept between the minimum and maximum values found in the `StatDefinitions`.
+    pub fn update<K: Eq + Hash + Clone + Debug>(
+        &mut self,
+        delta_time: f64,
+        effector_defs: &EffectorDefinitions<K, E>,
+        stat_defs: &StatDefinitions<K>,
+        stat_set: &mut StatSet<K>,
+    ) {
+        let mut rm_idx = vec![];
+        for (idx, e) in self.effectors.iter_mut().enumerate() {
+            let def = effector_defs
+                .defs
+                .get(&e.effector_key)
+                .expect("Tried to get unknown effector key.");
+
+            if def.duration == Some(0.0) {
+                for (key, ty) in def.effects.iter() {
+                    if let EffectorType::Additive(v) = ty {
+                        stat_set.add_to(key, v * e.magnitude, stat_defs);
+                    }
+                }
+                rm_idx.push(idx);
+                continue;
+            }
+
+            // Don't apply over time effectors for longer than their remaining duration.
+            let elapsed = e
+                .disable_in
+                .as_ref()
+                .map(|timer| timer.remaining.min(delta_time).max(0.0))
+                .unwrap_or(delta_time);
+            for (key, ty) in def.effects.iter() {
+                if let EffectorType::AdditivePerSecond(v) = ty {
+                    stat_set.add_to(key, v * e.magnitude * elapsed, stat_defs);
+                }
+            }
+
+            if let Some(timer) = e.disable_in.as_mut() {
+                if timer.update(delta_time) > 0 {
+                    rm_idx.push(idx);
+                }
+            }
+        }
+
+        rm_idx.reverse();
+        for idx in rm_idx {
+            self.effectors.swap_remove(idx);
+        }
+    }
+
+    /// Runs the script effects of the active effectors for `delta_time` seconds.
+    /// The factor given to the effects is the magnitude of the effector multiplied by the
+    /// elapsed time, or only the magnitude for effectors applied once.
+    /// Call it right before `update`, which removes the expired effectors.
+    ///
+    /// Errors:
+    /// * UnknownFunction: A script effect is not registered. The effects before it were run.
+    pub fn run_scripts<K: Eq + Hash>(
+        &self,
+        delta_time: f64,
+        effector_defs: &EffectorDefinitions<K, E>,
+        stat_defs: &StatDefinitions<K>,
+        stat_set: &mut StatSet<K>,
+        scripts: &ScriptHost<K>,
+    ) -> Result<(), ScriptError> {
+        for e in self.effectors.iter() {
+            let def = effector_defs
+                .defs
+                .get(&e.effector_key)
+                .expect("Tried to get unknown effector key.");
+            let factor = if def.duration == Some(0.0) {
+                e.magnitude
+            } else {
+                let elapsed = e
+                    .disable_in
+                    .as_ref()
+                    .map(|timer| timer.remaining.min(delta_time).max(0.0))
+                    .unwrap_or(delta_time);
+                e.magnitude * elapsed
+            };
+            for name in def.scripts.iter() {
+                scripts.run_effect(name, stat_set, stat_defs, factor)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Applies the effects of this effector to the provided `StatSet`.
     /// The delta time is used when using effectors that apply directly to
     /// the base stat value. (WIP)
-    pub fn apply_to<K: Eq + Hash>(
+    pub fn apply_to<K: Eq + Hash + Clone>(
         self: &Self,
         effector_defs: &EffectorDefinitions<K, E>,
         stat_set: &mut StatSet<K>,
         _delta_time: f32,
     ) {
-        for mut s in stat_set.stats.values_mut() {
+        for s in stat_set.stats.values_mut() {
             let mut new_value = s.value;
             let mut multiplicative_multiplier = 1.0;
             let mut additive_multiplier = 0.0;
@@ -64,6 +333,11 @@ impl<E: Hash + Eq> EffectorSet<E> {
                     .get(&e.effector_key)
                     .expect("Tried to get unknown stat key.");
 
+                // Effectors applied only once modify the base value in `update`.
+                if def.duration == Some(0.0) {
+                    continue;
+                }
+
                 // Algo:
                 // - Apply all multiplicative multipliers
                 // - Apply all additive multipliers
@@ -75,11 +349,17 @@ impl<E: Hash + Eq> EffectorSet<E> {
                     if *key == s.key {
                         // Apply Effector
                         match ty {
-                            EffectorType::Additive(v) => additive += v,
-                            EffectorType::AdditiveMultiplier(v) => additive_multiplier += v,
+                            EffectorType::Additive(v) => additive += v * e.magnitude,
+                            EffectorType::AdditiveMultiplier(v) => {
+                                additive_multiplier += v * e.magnitude
+                            }
                             EffectorType::MultiplicativeMultiplier(v) => {
-                                multiplicative_multiplier *= v
+                                multiplicative_multiplier *= 1.0 + (v - 1.0) * e.magnitude
                             }
+                            // Modifies the base value in `update`.
+                            EffectorType::AdditivePerSecond(_) => {}
+                            // Consumed by `absorb`.
+                            EffectorType::Absorb(_) => {}
                         }
                     }
                 }
@@ -87,6 +367,9 @@ impl<E: Hash + Eq> EffectorSet<E> {
             let multiplier = multiplicative_multiplier + additive_multiplier;
             new_value += additive;
             new_value *= multiplier;
+            if s.value_with_effectors != new_value {
+                stat_set.changed.insert(s.key.clone());
+            }
             s.value_with_effectors = new_value;
         }
     }
@@ -109,10 +392,79 @@ pub struct EffectorDefinition<K, E> {
     // TODO consider using only a single element here? It almost never happens that
     // we want to apply multiple changes to the same stat.
     pub effects: Vec<(K, EffectorType)>,
+    /// What happens when this effector is added while it is already active.
+    #[new(default)]
+    #[serde(default)]
+    pub stacking: EffectorStacking,
+    /// The tags of this effector, like "poison", "magic" or "rooted".
+    /// They are used to find and remove groups of effectors, for example when cleansing.
+    #[new(default)]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The names of the effects of the `ScriptHost` run while this effector is active.
+    /// See `EffectorSet::run_scripts`.
+    #[new(default)]
+    #[serde(default)]
+    pub scripts: Vec<String>,
+}
+
+impl<K, E> EffectorDefinition<K, E> {
+    /// Checks if this effector has the tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+impl<K, E: Clone> EffectorDefinition<K, E> {
+    /// Creates a new `EffectorInstance` lasting for the duration of this `EffectorDefinition`.
+    pub fn default_instance(&self) -> EffectorInstance<E> {
+        let mut instance = EffectorInstance::new(self.key.clone(), self.duration.map(Timer::once));
+        instance.absorb_remaining = self.absorb_amounts();
+        instance
+    }
+
+    /// Returns the damage absorbed by each `EffectorType::Absorb` effect of this effector, in
+    /// order.
+    pub fn absorb_amounts(&self) -> Vec<f64> {
+        self.effects
+            .iter()
+            .filter_map(|(_, ty)| match ty {
+                EffectorType::Absorb(v) => Some(*v),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// The way an effector behaves when it is added multiple times to the same `EffectorSet`.
+#[derive(Debug, Clone, Serialize, Deserialize, Derivative)]
+#[derivative(Default)]
+pub enum EffectorStacking {
+    /// Every added instance is active at the same time.
+    #[derivative(Default)]
+    Unlimited,
+    /// Only one instance can be active. Adding it again does nothing.
+    Unique,
+    /// Only one instance can be active. Adding it again resets its remaining duration.
+    RefreshDuration,
+    /// Up to this number of instances can be active at the same time.
+    /// Adding more does nothing.
+    StackUpTo(usize),
+}
+
+/// The result of adding an effector to an `EffectorSet`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectorAddResult {
+    /// A new instance of the effector was added.
+    Added,
+    /// The effector was already active and its duration was reset.
+    Refreshed,
+    /// The effector was not added because of its `EffectorStacking` rule.
+    Rejected,
 }
 
 /// The way this effector modifies the stat.
-#[derive(Debug, Clone, Serialize, Deserialize, new)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
 pub enum EffectorType {
     /// Adds a value to the base value of the stat.
     Additive(f64),
@@ -122,13 +474,118 @@ pub enum EffectorType {
     /// Multiplies the stat by a value.
     /// Stacks multiplicatively with other multipliers affecting this same stat.
     MultiplicativeMultiplier(f64),
+    /// Adds a value per second to the base value of the stat.
+    /// Unlike the other effector types, this permanently changes the base value.
+    /// Use negative values for damage over time and positive values for regeneration.
+    AdditivePerSecond(f64),
+    /// Absorbs this amount of damage before it reaches the stat, like a damage shield.
+    /// The effector expires when the absorption of all its `Absorb` effects is depleted. See
+    /// `EffectorSet::absorb`.
+    Absorb(f64),
 }
 
 /// An active instance of an effector.
-#[derive(Debug, Clone, Serialize, Deserialize, new)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
 pub struct EffectorInstance<E> {
     /// The key of the effector.
     pub effector_key: E,
-    /// The time before this effector expires.
-    pub disable_in: Option<f64>,
+    /// The timer after which this effector expires.
+    /// None means that it does not expire.
+    /// It is serialized as the remaining time in seconds.
+    #[serde(with = "remaining_seconds")]
+    pub disable_in: Option<Timer>,
+    /// The strength of this instance. The values of the effects are multiplied by it.
+    /// For multiplicative multipliers, the difference from 1 is multiplied instead, so a x1.2
+    /// multiplier with a magnitude of 2 becomes x1.4.
+    #[new(value = "1.0")]
+    #[serde(default = "default_magnitude")]
+    pub magnitude: f64,
+    /// The damage this instance can still absorb for each `EffectorType::Absorb` effect of its
+    /// definition, in order. Each one only protects the stat of its effect.
+    /// Empty means that it doesn't absorb damage.
+    #[new(default)]
+    #[serde(default)]
+    pub absorb_remaining: Vec<f64>,
+}
+
+fn default_magnitude() -> f64 {
+    1.0
+}
+
+/// Serializes the timer of an `EffectorInstance` as its remaining time, like before timers were
+/// used.
+mod remaining_seconds {
+    use crate::Timer;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        timer: &Option<Timer>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        timer.as_ref().map(|t| t.remaining).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Timer>, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?.map(Timer::once))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct F64Instance {
+        effector_key: u32,
+        disable_in: Option<f64>,
+        magnitude: f64,
+        absorb_remaining: Vec<f64>,
+    }
+
+    #[test]
+    fn instances_keep_the_seconds_format() {
+        let mut instance = EffectorInstance::new(1u32, Some(Timer::once(5.0)));
+        instance.disable_in.as_mut().unwrap().update(1.5);
+        let bytes = bincode::serialize(&instance).unwrap();
+        let old = F64Instance {
+            effector_key: 1,
+            disable_in: Some(3.5),
+            magnitude: 1.0,
+            absorb_remaining: vec![],
+        };
+        assert_eq!(bytes, bincode::serialize(&old).unwrap());
+        let loaded: EffectorInstance<u32> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(loaded, instance);
+        let permanent = EffectorInstance::new(1u32, None);
+        let loaded: EffectorInstance<u32> =
+            bincode::deserialize(&bincode::serialize(&permanent).unwrap()).unwrap();
+        assert_eq!(loaded.disable_in, None);
+    }
+
+    #[test]
+    fn absorb_is_tracked_per_stat_and_refreshed() {
+        let mut shield = EffectorDefinition::new(
+            1u32,
+            Some(10.0),
+            vec![
+                ("health", EffectorType::Absorb(20.0)),
+                ("mana", EffectorType::Absorb(5.0)),
+            ],
+        );
+        shield.stacking = EffectorStacking::RefreshDuration;
+        let defs = EffectorDefinitions::from(vec![shield]);
+        let mut effectors = EffectorSet::default();
+        effectors.add_with_magnitude(&1, 2.0, &defs);
+        assert_eq!(effectors.absorb(&"mana", 15.0, &defs), 5.0);
+        assert_eq!(effectors.effectors[0].absorb_remaining, vec![40.0, 0.0]);
+        assert_eq!(effectors.absorb(&"health", 30.0, &defs), 0.0);
+        assert_eq!(effectors.add(&1, &defs), EffectorAddResult::Refreshed);
+        assert_eq!(effectors.effectors[0].absorb_remaining, vec![40.0, 10.0]);
+        assert_eq!(effectors.absorb(&"health", 50.0, &defs), 10.0);
+        assert_eq!(effectors.effectors.len(), 1);
+        assert_eq!(effectors.absorb(&"mana", 50.0, &defs), 40.0);
+        assert!(effectors.effectors.is_empty());
+    }
 }
@@ -0,0 +1,186 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The definition of a kind of entity, like a monster or a non-player character.
+/// Combines the starting state found in the other definitions so entities can be created with
+/// `EntityTemplate::instantiate`.
+///
+/// # Generics
+/// - T: Template Key
+/// - K: Stat Key
+/// - S: Skill Key
+/// - I: Item Key
+/// - LT: Loot Table Key
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct EntityTemplate<T, K, S, I, LT> {
+    /// The key of this template.
+    pub key: T,
+    /// The display name of the entities created from this template.
+    pub name: String,
+    /// The starting base values of the stats.
+    /// The stats that are not listed use the default value of their `StatDefinition`.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub stats: Vec<(K, f64)>,
+    /// The skills the entities can use.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub skills: Vec<S>,
+    /// The items the entities start with.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub items: Vec<(I, usize)>,
+    /// The number of slots of the inventory of the entities.
+    pub inventory_size: usize,
+    /// The loot table rolled when the entity dies, registered in `LootTables`.
+    #[new(default)]
+    #[builder(default)]
+    pub loot_table: Option<LT>,
+    /// The id of the faction of the entities.
+    #[new(default)]
+    #[builder(default)]
+    pub faction: Option<i32>,
+}
+
+/// The runtime state of an entity created from an `EntityTemplate`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct EntityInstance<K: Hash + Eq, E, S: Hash + Eq, I, IT: SlotType, CD: Default, LT> {
+    /// The stats of the entity.
+    pub stats: StatSet<K>,
+    /// The skills the entity can use.
+    pub skills: SkillSet<S>,
+    /// The effectors active on the entity.
+    pub effectors: EffectorSet<E>,
+    /// The items of the entity.
+    pub inventory: Inventory<I, IT, CD>,
+    /// The loot table rolled when the entity dies.
+    pub loot_table: Option<LT>,
+    /// The id of the faction of the entity.
+    pub faction: Option<i32>,
+}
+
+impl<
+        T,
+        K: Hash + Eq + Clone + Debug,
+        S: Hash + Eq + Clone,
+        I: Hash + Eq + Clone + Debug,
+        LT: Clone,
+    > EntityTemplate<T, K, S, I, LT>
+{
+    /// Creates the stats, skills, effectors and inventory of a new entity from this template.
+    ///
+    /// Errors:
+    /// See `Inventory::insert`. Happens when the items don't fit in the inventory.
+    #[allow(clippy::type_complexity)]
+    pub fn instantiate<E, IT: SlotType, CD: Default + Clone + Debug + PartialEq, D: Default>(
+        &self,
+        stat_defs: &StatDefinitions<K>,
+        item_defs: &ItemDefinitions<I, IT, D>,
+    ) -> Result<EntityInstance<K, E, S, I, IT, CD, LT>, ItemError<I, CD>> {
+        let mut stats = stat_defs.to_statset();
+        for (key, value) in self.stats.iter() {
+            stats.set(key, *value, stat_defs);
+            if let Some(stat) = stats.stats.get_mut(key) {
+                stat.value_with_effectors = stat.value;
+            }
+        }
+        let mut inventory = Inventory::new_fixed(self.inventory_size);
+        for (key, quantity) in self.items.iter() {
+            inventory.insert(ItemInstance::new(key.clone(), *quantity), item_defs)?;
+        }
+        Ok(EntityInstance::new(
+            stats,
+            SkillSet::from(self.skills.clone()),
+            EffectorSet::default(),
+            inventory,
+            self.loot_table.clone(),
+            self.faction,
+        ))
+    }
+}
+
+/// The definitions of all known entity templates.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct EntityTemplates<T: Hash + Eq, K, S, I, LT> {
+    /// The definitions.
+    pub defs: HashMap<T, EntityTemplate<T, K, S, I, LT>>,
+}
+
+impl<T: Hash + Eq, K, S, I, LT> Default for EntityTemplates<T, K, S, I, LT> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<T: Hash + Eq + Clone, K, S, I, LT> From<Vec<EntityTemplate<T, K, S, I, LT>>>
+    for EntityTemplates<T, K, S, I, LT>
+{
+    fn from(t: Vec<EntityTemplate<T, K, S, I, LT>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<
+        T: Hash + Eq + Clone,
+        K: Hash + Eq + Clone,
+        S: Hash + Eq + Clone,
+        I: Hash + Eq + Clone,
+        LT,
+    > EntityTemplates<T, K, S, I, LT>
+{
+    /// Checks that the templates are consistent and only use known stats, skills and items.
+    /// Returns all the errors found.
+    pub fn validate<E, SI, IT, D: Default>(
+        &self,
+        stat_defs: &StatDefinitions<K>,
+        skill_defs: &SkillDefinitions<K, E, S, SI>,
+        item_defs: &ItemDefinitions<I, IT, D>,
+    ) -> Vec<EntityTemplateError<T, K, S, I>> {
+        let mut errors = vec![];
+        for (k, def) in self.defs.iter() {
+            if def.key != *k {
+                errors.push(EntityTemplateError::KeyMismatch(k.clone()));
+            }
+            for (stat, _) in def.stats.iter() {
+                if !stat_defs.defs.contains_key(stat) {
+                    errors.push(EntityTemplateError::UnknownStat(k.clone(), stat.clone()));
+                }
+            }
+            for skill in def.skills.iter() {
+                if !skill_defs.defs.contains_key(skill) {
+                    errors.push(EntityTemplateError::UnknownSkill(k.clone(), skill.clone()));
+                }
+            }
+            for (item, _) in def.items.iter() {
+                if !item_defs.defs.contains_key(item) {
+                    errors.push(EntityTemplateError::UnknownItem(k.clone(), item.clone()));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// The errors found when validating `EntityTemplates`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntityTemplateError<T, K, S, I> {
+    /// The template is registered under a key different from its own.
+    KeyMismatch(T),
+    /// The template sets a stat that is not defined.
+    UnknownStat(T, K),
+    /// The template has a skill that is not defined.
+    UnknownSkill(T, S),
+    /// The template has an item that is not defined.
+    UnknownItem(T, I),
+}
@@ -0,0 +1,415 @@
+use crate::*;
+use core::fmt;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A small arithmetic and logic expression, like `value < 0.1 * max && !(armor > 10)`.
+///
+/// Numbers are floats. Comparisons and logical operators return 1.0 when true and 0.0 when
+/// false, and any value other than 0.0 is considered true.
+/// Supported operators, from the lowest to the highest precedence:
+/// - `||` or `or`
+/// - `&&` or `and`
+/// - `!` or `not`
+/// - `<`, `<=`, `>`, `>=`, `==`, `!=`
+/// - `+`, `-`
+/// - `*`, `/`, `%`
+/// - unary `-`
+///
+/// Variables are made of letters, digits, `_` and `.` and are resolved when evaluating.
+///
+/// Expressions are serialized as their source text and parsed when deserialized, so invalid
+/// expressions are rejected when loading the data that contains them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A constant.
+    Number(f64),
+    /// A named value, resolved when evaluating.
+    Variable(String),
+    /// The negation of a number.
+    Negate(Box<Expr>),
+    /// The logical inverse of a value.
+    Not(Box<Expr>),
+    /// An operation between two values.
+    Binary(BinaryOperator, Box<Expr>, Box<Expr>),
+}
+
+/// An operator taking two values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryOperator {
+    /// `a + b`
+    Add,
+    /// `a - b`
+    Subtract,
+    /// `a * b`
+    Multiply,
+    /// `a / b`
+    Divide,
+    /// `a % b`
+    Remainder,
+    /// `a < b`
+    Less,
+    /// `a <= b`
+    LessOrEqual,
+    /// `a > b`
+    Greater,
+    /// `a >= b`
+    GreaterOrEqual,
+    /// `a == b`
+    Equal,
+    /// `a != b`
+    NotEqual,
+    /// `a && b`
+    And,
+    /// `a || b`
+    Or,
+}
+
+/// The errors that can happen when parsing or evaluating an `Expr`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprError {
+    /// The character at this position can't start a token.
+    UnexpectedCharacter(usize),
+    /// The token at this position is not valid here.
+    UnexpectedToken(usize),
+    /// The expression ended too early.
+    UnexpectedEnd,
+    /// The variable could not be resolved.
+    UnknownVariable(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(&'static str),
+}
+
+const OPERATORS: [&str; 16] = [
+    "||", "&&", "<=", ">=", "==", "!=", "<", ">", "!", "+", "-", "*", "/", "%", "(", ")",
+];
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let chars = input.char_indices().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let (pos, c) = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].1.is_ascii_digit() || chars[i].1 == '.') {
+                i += 1;
+            }
+            let text = chars[start..i].iter().map(|(_, c)| c).collect::<String>();
+            let n = text
+                .parse()
+                .map_err(|_| ExprError::UnexpectedCharacter(pos))?;
+            tokens.push((pos, Token::Number(n)));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].1.is_alphanumeric() || chars[i].1 == '_' || chars[i].1 == '.')
+            {
+                i += 1;
+            }
+            let text = chars[start..i].iter().map(|(_, c)| c).collect::<String>();
+            let token = match text.as_str() {
+                "and" => Token::Operator("&&"),
+                "or" => Token::Operator("||"),
+                "not" => Token::Operator("!"),
+                _ => Token::Identifier(text),
+            };
+            tokens.push((pos, token));
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| input[pos..].starts_with(**op))
+                .ok_or(ExprError::UnexpectedCharacter(pos))?;
+            tokens.push((pos, Token::Operator(op)));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek_operator(&self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some((_, Token::Operator(op))) if ops.contains(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[(&'static str, BinaryOperator)],
+        next: fn(&mut Self) -> Result<Expr, ExprError>,
+        repeat: bool,
+    ) -> Result<Expr, ExprError> {
+        let mut left = next(self)?;
+        let names = ops.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+        while let Some(op) = self.peek_operator(&names) {
+            self.position += 1;
+            let right = next(self)?;
+            let op = ops.iter().find(|(n, _)| *n == op).unwrap().1;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+            if !repeat {
+                break;
+            }
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&[("||", BinaryOperator::Or)], Self::and, true)
+    }
+
+    fn and(&mut self) -> Result<Expr, ExprError> {
+        self.binary(&[("&&", BinaryOperator::And)], Self::not, true)
+    }
+
+    fn not(&mut self) -> Result<Expr, ExprError> {
+        if self.peek_operator(&["!"]).is_some() {
+            self.position += 1;
+            Ok(Expr::Not(Box::new(self.not()?)))
+        } else {
+            self.comparison()
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ExprError> {
+        self.binary(
+            &[
+                ("<=", BinaryOperator::LessOrEqual),
+                (">=", BinaryOperator::GreaterOrEqual),
+                ("==", BinaryOperator::Equal),
+                ("!=", BinaryOperator::NotEqual),
+                ("<", BinaryOperator::Less),
+                (">", BinaryOperator::Greater),
+            ],
+            Self::sum,
+            false,
+        )
+    }
+
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        self.binary(
+            &[("+", BinaryOperator::Add), ("-", BinaryOperator::Subtract)],
+            Self::product,
+            true,
+        )
+    }
+
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        self.binary(
+            &[
+                ("*", BinaryOperator::Multiply),
+                ("/", BinaryOperator::Divide),
+                ("%", BinaryOperator::Remainder),
+            ],
+            Self::unary,
+            true,
+        )
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.peek_operator(&["-"]).is_some() {
+            self.position += 1;
+            Ok(Expr::Negate(Box::new(self.unary()?)))
+        } else {
+            self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, ExprError> {
+        let (pos, token) = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or(ExprError::UnexpectedEnd)?;
+        self.position += 1;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Identifier(name) => Ok(Expr::Variable(name)),
+            Token::Operator("(") => {
+                let inner = self.or()?;
+                match self.tokens.get(self.position) {
+                    Some((_, Token::Operator(")"))) => {
+                        self.position += 1;
+                        Ok(inner)
+                    }
+                    Some((pos, _)) => Err(ExprError::UnexpectedToken(*pos)),
+                    None => Err(ExprError::UnexpectedEnd),
+                }
+            }
+            Token::Operator(_) => Err(ExprError::UnexpectedToken(pos)),
+        }
+    }
+}
+
+impl Expr {
+    /// Parses an expression.
+    ///
+    /// Errors:
+    /// * UnexpectedCharacter: The input contains a character that is not part of the syntax.
+    /// * UnexpectedToken: The input is not a valid expression.
+    /// * UnexpectedEnd: The input ended before the expression was complete.
+    pub fn parse(input: &str) -> Result<Expr, ExprError> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            Some((pos, _)) => Err(ExprError::UnexpectedToken(*pos)),
+            None => Ok(expr),
+        }
+    }
+
+    /// Calculates the value of the expression, using `variables` to get the value of the
+    /// variables.
+    ///
+    /// Errors:
+    /// * UnknownVariable: `variables` returned None for this variable.
+    pub fn evaluate<F: Fn(&str) -> Option<f64>>(&self, variables: &F) -> Result<f64, ExprError> {
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Variable(name) => {
+                variables(name).ok_or_else(|| ExprError::UnknownVariable(name.clone()))?
+            }
+            Expr::Negate(e) => -e.evaluate(variables)?,
+            Expr::Not(e) => bool_value(e.evaluate(variables)? == 0.0),
+            Expr::Binary(op, a, b) => {
+                let a = a.evaluate(variables)?;
+                // Don't evaluate the right side if the result is already known.
+                match op {
+                    BinaryOperator::And if a == 0.0 => return Ok(0.0),
+                    BinaryOperator::Or if a != 0.0 => return Ok(1.0),
+                    _ => {}
+                }
+                let b = b.evaluate(variables)?;
+                match op {
+                    BinaryOperator::Add => a + b,
+                    BinaryOperator::Subtract => a - b,
+                    BinaryOperator::Multiply => a * b,
+                    BinaryOperator::Divide => a / b,
+                    BinaryOperator::Remainder => a % b,
+                    BinaryOperator::Less => bool_value(a < b),
+                    BinaryOperator::LessOrEqual => bool_value(a <= b),
+                    BinaryOperator::Greater => bool_value(a > b),
+                    BinaryOperator::GreaterOrEqual => bool_value(a >= b),
+                    BinaryOperator::Equal => bool_value(a == b),
+                    BinaryOperator::NotEqual => bool_value(a != b),
+                    BinaryOperator::And | BinaryOperator::Or => bool_value(b != 0.0),
+                }
+            }
+        })
+    }
+
+    /// Calculates the value of the expression and checks if it is true (not 0.0).
+    ///
+    /// Errors:
+    /// * UnknownVariable: `variables` returned None for this variable.
+    pub fn is_true<F: Fn(&str) -> Option<f64>>(&self, variables: &F) -> Result<bool, ExprError> {
+        self.evaluate(variables).map(|v| v != 0.0)
+    }
+
+    /// Returns the names of all the variables used by the expression.
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            Expr::Number(_) => vec![],
+            Expr::Variable(name) => vec![name.as_str()],
+            Expr::Negate(e) | Expr::Not(e) => e.variables(),
+            Expr::Binary(_, a, b) => {
+                let mut variables = a.variables();
+                variables.extend(b.variables());
+                variables
+            }
+        }
+    }
+}
+
+impl BinaryOperator {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Remainder => "%",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterOrEqual => ">=",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+        }
+    }
+}
+
+/// Writes the expression in a form that `Expr::parse` reads back as the same expression.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Variable(name) => write!(f, "{}", name),
+            Expr::Negate(e) => write!(f, "(-{})", e),
+            Expr::Not(e) => write!(f, "(!{})", e),
+            Expr::Binary(op, a, b) => write!(f, "({} {} {})", a, op.symbol(), b),
+        }
+    }
+}
+
+impl Serialize for Expr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Expr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Expr::parse(&source)
+            .map_err(|e| de::Error::custom(format!("invalid expression {:?}: {:?}", source, e)))
+    }
+}
+
+fn bool_value(b: bool) -> f64 {
+    if b {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_parses_back() {
+        for source in &[
+            "-(1 + 2) * a",
+            "a < 3 && not b.c == 4",
+            "!(a > 1) || -x % 2 != 0",
+            "a - (b - c) / 0.25",
+        ] {
+            let expr = Expr::parse(source).unwrap();
+            assert_eq!(Expr::parse(&expr.to_string()), Ok(expr));
+        }
+    }
+
+    #[test]
+    fn variables() {
+        let expr = Expr::parse("value < 0.1 * max || (armor > 5 && !stunned)").unwrap();
+        assert_eq!(expr.variables(), vec!["value", "max", "armor", "stunned"]);
+    }
+}
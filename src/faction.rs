@@ -1,27 +1,134 @@
+use crate::clock::is_between;
 use crate::user_group::*;
+use crate::wallet::*;
+use crate::*;
+use core::hash::Hash;
 
 /// A team with the ability to claim ownership over terrain.
 /// WIP
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
 pub struct Faction {
+    /// The id of this faction.
+    pub id: i32,
+    /// The name of this faction.
+    pub name: String,
     /// The group of users that this faction is composed of.
     pub users: UserGroup,
     /// The claiming power of this faction. Limits the number of claims it can have and maintain.
-    // TODO calculate power from users.
+    /// See `Faction::recalculate_power`.
+    #[new(default)]
     pub power: f32,
     /// All the claims owned by this faction.
+    #[new(default)]
     pub claims: Vec<(i32, i32, i32)>,
     /// A value added to the calculated power value.
+    #[new(default)]
     pub power_boost: f32,
 }
 
 impl Faction {
+    /// Sets the power of this faction to the sum of the power of its users.
+    pub fn recalculate_power(&mut self, powers: &UserPowers, settings: &FactionSettings) {
+        self.power = self
+            .users
+            .users
+            .iter()
+            .map(|u| powers.get(*u, settings))
+            .sum();
+    }
+
+    /// The power of this faction, including the power boost.
+    pub fn total_power(&self) -> f32 {
+        self.power + self.power_boost
+    }
+
+    /// The maximum number of claims that this faction can maintain with its current power.
+    pub fn max_claims(&self, settings: &FactionSettings) -> usize {
+        if settings.power_per_claim <= 0.0 {
+            return usize::MAX;
+        }
+        (self.total_power() / settings.power_per_claim).max(0.0) as usize
+    }
+
+    /// Whether this faction has more claims than its power allows it to maintain.
+    /// Claims of overclaimed factions can be taken by other factions.
+    pub fn is_overclaimed(&self, settings: &FactionSettings) -> bool {
+        self.claims.len() > self.max_claims(settings)
+    }
+
+    /// Whether the claim is next to one of the claims of this faction.
+    pub fn is_adjacent(&self, claim: &(i32, i32, i32)) -> bool {
+        self.claims.iter().any(|c| {
+            let d = (c.0 - claim.0).abs() + (c.1 - claim.1).abs() + (c.2 - claim.2).abs();
+            d == 1
+        })
+    }
+
+    /// Claim unclaimed terrain.
+    ///
+    /// Errors:
+    /// * AlreadyClaimed: This faction already owns this claim.
+    /// * NotEnoughPower: This faction can't maintain another claim.
+    /// * NotAdjacent: Claims must be adjacent and this one isn't next to an existing claim.
+    pub fn claim(&mut self, claim: (i32, i32, i32), settings: &FactionSettings) -> FactionResult {
+        self.check_can_claim(&claim, settings)?;
+        self.claims.push(claim);
+        Ok(())
+    }
+
+    /// Removes the claim from this faction.
+    /// Returns true if this faction owned the claim.
+    pub fn unclaim(&mut self, claim: &(i32, i32, i32)) -> bool {
+        if let Some(idx) = self.claims.iter().position(|c| c == claim) {
+            self.claims.swap_remove(idx);
+            true
+        } else {
+            false
+        }
+    }
+
     /// Claim terrain from another faction.
+    ///
+    /// Errors:
+    /// * NotClaimed: The other faction doesn't own this claim.
+    /// * Unclaimable: Claims can't be taken or the other faction has enough power to keep it.
+    /// * AlreadyClaimed: This faction already owns this claim.
+    /// * NotEnoughPower: This faction can't maintain another claim.
+    /// * NotAdjacent: Claims must be adjacent and this one isn't next to an existing claim.
     pub fn claim_from(
         &mut self,
-        _other: &mut Faction,
-        _settings: &FactionSettings,
+        other: &mut Faction,
+        claim: (i32, i32, i32),
+        settings: &FactionSettings,
     ) -> FactionResult {
-        // TODO
+        let idx = other
+            .claims
+            .iter()
+            .position(|c| *c == claim)
+            .ok_or(FactionError::NotClaimed)?;
+        if !settings.flags.claimable || !other.is_overclaimed(settings) {
+            return Err(FactionError::Unclaimable);
+        }
+        self.check_can_claim(&claim, settings)?;
+        other.claims.swap_remove(idx);
+        self.claims.push(claim);
+        Ok(())
+    }
+
+    fn check_can_claim(
+        &self,
+        claim: &(i32, i32, i32),
+        settings: &FactionSettings,
+    ) -> FactionResult {
+        if self.claims.contains(claim) {
+            return Err(FactionError::AlreadyClaimed);
+        }
+        if self.claims.len() >= self.max_claims(settings) {
+            return Err(FactionError::NotEnoughPower);
+        }
+        if settings.adjacent_claims_only && !self.claims.is_empty() && !self.is_adjacent(claim) {
+            return Err(FactionError::NotAdjacent);
+        }
         Ok(())
     }
 }
@@ -30,12 +137,79 @@ impl Faction {
 pub struct FactionSettings {
     /// The settings related to users.
     pub user_settings: UserGroupSettings,
+    /// The power of users that never gained or lost power.
+    pub base_player_power: f32,
+    /// The minimum player-generated claim power.
+    pub minimum_player_power: f32,
     /// The maximum player-generated claim power.
     pub maximum_player_power: f32,
+    /// The power regenerated by each user per second.
+    pub power_regeneration_per_second: f32,
+    /// The power lost by a user when dying.
+    pub power_loss_on_death: f32,
+    /// The power required to maintain a single claim.
+    pub power_per_claim: f32,
+    /// If true, new claims must be next to one of the existing claims of the faction.
+    pub adjacent_claims_only: bool,
     /// The flags that apply to claimed terrain and faction behavior.
     pub flags: FactionFlags,
 }
 
+/// The claiming power of each user, by user id.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct UserPowers {
+    /// The power of the users.
+    /// Users that are not in this map have the base player power.
+    pub powers: HashMap<i32, f32>,
+}
+
+impl UserPowers {
+    /// Returns the power of the user.
+    pub fn get(&self, user: i32, settings: &FactionSettings) -> f32 {
+        *self
+            .powers
+            .get(&user)
+            .unwrap_or(&settings.base_player_power)
+    }
+
+    /// Adds power to the user, keeping it between the minimum and maximum player power.
+    /// Use negative values to remove power.
+    pub fn add(&mut self, user: i32, power: f32, settings: &FactionSettings) {
+        let new = (self.get(user, settings) + power)
+            .max(settings.minimum_player_power)
+            .min(settings.maximum_player_power);
+        self.powers.insert(user, new);
+    }
+
+    /// Regenerates the power of the user over `delta_time` seconds.
+    /// `in_claimed_territory` indicates if the user currently is in terrain claimed by a faction.
+    pub fn regenerate(
+        &mut self,
+        user: i32,
+        delta_time: f32,
+        in_claimed_territory: bool,
+        settings: &FactionSettings,
+    ) {
+        if in_claimed_territory && !settings.flags.power_gain_in_territory {
+            return;
+        }
+        self.add(
+            user,
+            settings.power_regeneration_per_second * delta_time,
+            settings,
+        );
+    }
+
+    /// Removes power from the user because it died.
+    /// `in_claimed_territory` indicates if the user died in terrain claimed by a faction.
+    pub fn on_death(&mut self, user: i32, in_claimed_territory: bool, settings: &FactionSettings) {
+        if in_claimed_territory && !settings.flags.power_loss_in_territory {
+            return;
+        }
+        self.add(user, -settings.power_loss_on_death, settings);
+    }
+}
+
 /// Fags that modify how a faction behaves and how the claimed terrain behaves.
 pub struct FactionFlags {
     /// You can steal terrain from this faction.
@@ -48,13 +222,442 @@ pub struct FactionFlags {
     pub power_gain_in_territory: bool,
     /// If true, will not destroy the faction once all players leaved the faction.
     pub permanent: bool,
+    /// Allied factions can build in claimed terrain.
+    pub allies_can_build: bool,
+}
+
+/// The diplomatic relation between two factions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FactionRelation {
+    /// The factions are allied. Allies can't attack each other.
+    Ally,
+    /// The factions have no particular relation.
+    Neutral,
+    /// The factions are enemies.
+    Enemy,
+}
+
+/// The relations between all factions, using the ids of the factions.
+/// Relations are symmetric and factions are neutral to each other by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FactionRelations {
+    /// The relations that are not neutral, using ordered pairs of faction ids.
+    relations: HashMap<(i32, i32), FactionRelation>,
+    /// The pending alliance requests, as (from, to) pairs.
+    alliance_requests: HashSet<(i32, i32)>,
+}
+
+impl FactionRelations {
+    /// Returns the relation between two factions.
+    /// A faction is always allied with itself.
+    pub fn relation(&self, a: i32, b: i32) -> FactionRelation {
+        if a == b {
+            return FactionRelation::Ally;
+        }
+        *self
+            .relations
+            .get(&Self::pair(a, b))
+            .unwrap_or(&FactionRelation::Neutral)
+    }
+
+    /// Sets the relation between two factions.
+    /// This clears the pending alliance requests between them.
+    pub fn set_relation(&mut self, a: i32, b: i32, relation: FactionRelation) {
+        self.alliance_requests.remove(&(a, b));
+        self.alliance_requests.remove(&(b, a));
+        if relation == FactionRelation::Neutral {
+            self.relations.remove(&Self::pair(a, b));
+        } else {
+            self.relations.insert(Self::pair(a, b), relation);
+        }
+    }
+
+    /// Asks the other faction for an alliance.
+    /// If the other faction already asked for an alliance, the factions become allies and this
+    /// returns true.
+    pub fn request_alliance(&mut self, from: i32, to: i32) -> bool {
+        if self.alliance_requests.contains(&(to, from)) {
+            self.set_relation(from, to, FactionRelation::Ally);
+            true
+        } else {
+            self.alliance_requests.insert((from, to));
+            false
+        }
+    }
+
+    /// Accepts the alliance request made by the other faction.
+    ///
+    /// Errors:
+    /// * NoAllianceRequest: The other faction didn't ask for an alliance.
+    pub fn accept_alliance(&mut self, faction: i32, requester: i32) -> FactionResult {
+        if !self.alliance_requests.contains(&(requester, faction)) {
+            return Err(FactionError::NoAllianceRequest);
+        }
+        self.set_relation(faction, requester, FactionRelation::Ally);
+        Ok(())
+    }
+
+    /// Refuses the alliance request made by the other faction, or cancels an alliance request
+    /// made to it.
+    pub fn cancel_alliance_request(&mut self, a: i32, b: i32) {
+        self.alliance_requests.remove(&(a, b));
+        self.alliance_requests.remove(&(b, a));
+    }
+
+    /// Returns the factions that asked this faction for an alliance.
+    pub fn alliance_requests_for(&self, faction: i32) -> Vec<i32> {
+        self.alliance_requests
+            .iter()
+            .filter(|(_, to)| *to == faction)
+            .map(|(from, _)| *from)
+            .collect()
+    }
+
+    /// Checks if a user can attack another user using the factions they are part of, if any.
+    /// `in_claimed_territory` indicates if the attack happens in terrain claimed by a faction.
+    pub fn can_attack(
+        &self,
+        attacker_faction: Option<i32>,
+        target_faction: Option<i32>,
+        in_claimed_territory: bool,
+        settings: &FactionSettings,
+    ) -> bool {
+        if in_claimed_territory && !settings.flags.pvp_enabled {
+            return false;
+        }
+        match (attacker_faction, target_faction) {
+            (Some(a), Some(b)) => self.relation(a, b) != FactionRelation::Ally,
+            _ => true,
+        }
+    }
+
+    /// Checks if a user can build in the terrain owned by `claim_owner`, using the faction the
+    /// user is part of, if any.
+    /// Unclaimed terrain can always be built on.
+    pub fn can_build(
+        &self,
+        user_faction: Option<i32>,
+        claim_owner: Option<i32>,
+        settings: &FactionSettings,
+    ) -> bool {
+        match (user_faction, claim_owner) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(a), Some(b)) if a == b => true,
+            (Some(a), Some(b)) => {
+                settings.flags.allies_can_build && self.relation(a, b) == FactionRelation::Ally
+            }
+        }
+    }
+
+    fn pair(a: i32, b: i32) -> (i32, i32) {
+        if a < b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+}
+
+/// All known factions, indexed by id, name, claim and member.
+///
+/// Modifications that change the name, claims or users of a faction must be done through the
+/// methods of the repository to keep the indices up to date.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<Faction>", into = "Vec<Faction>")]
+pub struct FactionRepository {
+    factions: HashMap<i32, Faction>,
+    next_id: i32,
+    names: HashMap<String, i32>,
+    claims: ClaimMap,
+    members: HashMap<i32, i32>,
 }
 
-/// Alias type. List of all known factions.
-pub type FactionRepository = Vec<Faction>;
+impl FactionRepository {
+    /// Creates a new faction with an unused id and returns this id.
+    ///
+    /// Errors:
+    /// * NameTaken: Another faction has this name.
+    /// * AlreadyInFaction: One of the users is already part of a faction.
+    pub fn create(&mut self, name: String, users: UserGroup) -> Result<i32, FactionError> {
+        if self.names.contains_key(&name) {
+            return Err(FactionError::NameTaken);
+        }
+        if users.users.iter().any(|u| self.members.contains_key(u)) {
+            return Err(FactionError::AlreadyInFaction);
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let faction = Faction::new(id, name, users);
+        self.index(&faction);
+        self.factions.insert(id, faction);
+        Ok(id)
+    }
+
+    /// Removes the faction and returns it.
+    pub fn remove(&mut self, id: i32) -> Option<Faction> {
+        let faction = self.factions.remove(&id)?;
+        self.unindex(&faction);
+        Some(faction)
+    }
+
+    /// Returns the faction with this id.
+    pub fn get(&self, id: i32) -> Option<&Faction> {
+        self.factions.get(&id)
+    }
+
+    /// Returns the faction with this name.
+    pub fn get_by_name(&self, name: &str) -> Option<&Faction> {
+        self.names.get(name).and_then(|id| self.factions.get(id))
+    }
+
+    /// Returns the id of the faction owning this claim.
+    pub fn owner_of_claim(&self, claim: &(i32, i32, i32)) -> Option<i32> {
+        self.claims.owner_of_claim(claim)
+    }
+
+    /// Returns the owners of all claims.
+    pub fn claim_map(&self) -> &ClaimMap {
+        &self.claims
+    }
+
+    /// Returns the id of the faction this user is part of.
+    pub fn faction_of_user(&self, user: i32) -> Option<i32> {
+        self.members.get(&user).cloned()
+    }
+
+    /// Iterates over all factions.
+    pub fn iter(&self) -> impl Iterator<Item = &Faction> {
+        self.factions.values()
+    }
+
+    /// Modifies the faction using the provided function, then updates the indices.
+    /// The changes are rejected if they conflict with the other factions.
+    ///
+    /// Errors:
+    /// * UnknownFaction: No faction has this id.
+    /// * IdChanged: The function changed the id of the faction.
+    /// * NameTaken: Another faction has the new name.
+    /// * AlreadyClaimed: Another faction owns one of the new claims.
+    /// * AlreadyInFaction: One of the new users is part of another faction.
+    ///
+    /// Nothing changes if an error happens.
+    pub fn update<T, F: FnOnce(&mut Faction) -> T>(
+        &mut self,
+        id: i32,
+        f: F,
+    ) -> Result<T, FactionError> {
+        let mut faction = self
+            .factions
+            .get(&id)
+            .ok_or(FactionError::UnknownFaction)?
+            .clone();
+        let ret = f(&mut faction);
+        let is_other = |owner: Option<i32>| owner.is_some() && owner != Some(id);
+        if faction.id != id {
+            return Err(FactionError::IdChanged);
+        }
+        if is_other(self.names.get(&faction.name).cloned()) {
+            return Err(FactionError::NameTaken);
+        }
+        if faction
+            .claims
+            .iter()
+            .any(|c| is_other(self.owner_of_claim(c)))
+        {
+            return Err(FactionError::AlreadyClaimed);
+        }
+        if faction
+            .users
+            .users
+            .iter()
+            .any(|u| is_other(self.faction_of_user(*u)))
+        {
+            return Err(FactionError::AlreadyInFaction);
+        }
+        let old = self
+            .factions
+            .remove(&id)
+            .expect("The faction disappeared while being updated. This is a bug.");
+        self.unindex(&old);
+        self.index(&faction);
+        self.factions.insert(id, faction);
+        Ok(ret)
+    }
+
+    /// Renames the faction.
+    ///
+    /// Errors:
+    /// * UnknownFaction: No faction has this id.
+    /// * NameTaken: Another faction has this name.
+    pub fn rename(&mut self, id: i32, name: String) -> FactionResult {
+        self.update(id, |f| f.name = name)
+    }
+
+    /// Adds the user to the faction.
+    ///
+    /// Errors:
+    /// * UnknownFaction: No faction has this id.
+    /// * AlreadyInFaction: The user is already part of a faction.
+    /// * FactionFull: The faction reached the maximum number of users.
+    pub fn add_member(&mut self, id: i32, user: i32, settings: &FactionSettings) -> FactionResult {
+        if self.members.contains_key(&user) {
+            return Err(FactionError::AlreadyInFaction);
+        }
+        let faction = self
+            .factions
+            .get_mut(&id)
+            .ok_or(FactionError::UnknownFaction)?;
+        if faction.users.users.len() as i32 >= settings.user_settings.maximum_users {
+            return Err(FactionError::FactionFull);
+        }
+        faction.users.users.push(user);
+        self.members.insert(user, id);
+        Ok(())
+    }
+
+    /// Removes the user from its faction and returns the id of this faction.
+    /// Unless the faction is permanent, it is removed once it has no users left.
+    pub fn remove_member(&mut self, user: i32, settings: &FactionSettings) -> Option<i32> {
+        let id = self.members.remove(&user)?;
+        let faction = self.factions.get_mut(&id)?;
+        faction.users.users.retain(|u| *u != user);
+        if faction.users.users.is_empty() && !settings.flags.permanent {
+            self.remove(id);
+        }
+        Some(id)
+    }
+
+    /// Claims unclaimed terrain for the faction.
+    ///
+    /// Errors:
+    /// * UnknownFaction: No faction has this id.
+    /// * AlreadyClaimed: A faction already owns this claim.
+    /// * See `Faction::claim`.
+    pub fn claim(
+        &mut self,
+        id: i32,
+        claim: (i32, i32, i32),
+        settings: &FactionSettings,
+    ) -> FactionResult {
+        if self.claims.owner_of_claim(&claim).is_some() {
+            return Err(FactionError::AlreadyClaimed);
+        }
+        self.factions
+            .get_mut(&id)
+            .ok_or(FactionError::UnknownFaction)?
+            .claim(claim, settings)?;
+        self.claims.claim(claim, id)
+    }
+
+    /// Claims terrain owned by another faction.
+    ///
+    /// Errors:
+    /// * UnknownFaction: No faction has this id.
+    /// * NotClaimed: No faction owns this claim.
+    /// * AlreadyClaimed: The faction already owns this claim.
+    /// * See `Faction::claim_from`.
+    pub fn claim_from(
+        &mut self,
+        id: i32,
+        claim: (i32, i32, i32),
+        settings: &FactionSettings,
+    ) -> FactionResult {
+        let owner = self
+            .claims
+            .owner_of_claim(&claim)
+            .ok_or(FactionError::NotClaimed)?;
+        if owner == id {
+            return Err(FactionError::AlreadyClaimed);
+        }
+        let mut other = self
+            .factions
+            .remove(&owner)
+            .expect("Claim owned by an unknown faction. This is a bug.");
+        let result = match self.factions.get_mut(&id) {
+            Some(faction) => faction.claim_from(&mut other, claim, settings),
+            None => Err(FactionError::UnknownFaction),
+        };
+        self.factions.insert(owner, other);
+        result?;
+        self.claims.transfer(claim, id);
+        Ok(())
+    }
+
+    /// Removes the claim from the faction owning it and returns the id of this faction.
+    pub fn unclaim(&mut self, claim: &(i32, i32, i32)) -> Option<i32> {
+        let id = self.claims.unclaim(claim)?;
+        if let Some(faction) = self.factions.get_mut(&id) {
+            faction.unclaim(claim);
+        }
+        Some(id)
+    }
+
+    /// Gives the claim to the faction, whether another faction owns it or not, ignoring the
+    /// power and adjacency rules.
+    /// Returns the previous owner.
+    ///
+    /// Errors:
+    /// * UnknownFaction: No faction has this id.
+    pub fn transfer_claim(
+        &mut self,
+        claim: (i32, i32, i32),
+        id: i32,
+    ) -> Result<Option<i32>, FactionError> {
+        if !self.factions.contains_key(&id) {
+            return Err(FactionError::UnknownFaction);
+        }
+        let previous = self.unclaim(&claim);
+        self.factions
+            .get_mut(&id)
+            .expect("Faction disappeared while transferring a claim. This is a bug.")
+            .claims
+            .push(claim);
+        self.claims.transfer(claim, id);
+        Ok(previous)
+    }
+
+    fn index(&mut self, faction: &Faction) {
+        self.names.insert(faction.name.clone(), faction.id);
+        for c in faction.claims.iter() {
+            self.claims.transfer(*c, faction.id);
+        }
+        for u in faction.users.users.iter() {
+            self.members.insert(*u, faction.id);
+        }
+    }
+
+    fn unindex(&mut self, faction: &Faction) {
+        self.names.remove(&faction.name);
+        for c in faction.claims.iter() {
+            self.claims.unclaim(c);
+        }
+        for u in faction.users.users.iter() {
+            self.members.remove(u);
+        }
+    }
+}
+
+impl From<Vec<Faction>> for FactionRepository {
+    fn from(t: Vec<Faction>) -> Self {
+        let mut repo = Self::default();
+        for f in t {
+            repo.next_id = repo.next_id.max(f.id + 1);
+            repo.index(&f);
+            repo.factions.insert(f.id, f);
+        }
+        repo
+    }
+}
+
+impl From<FactionRepository> for Vec<Faction> {
+    fn from(t: FactionRepository) -> Self {
+        t.factions.into_values().collect()
+    }
+}
 
 /// Alias type. Result of faction methods that can fail.
-pub type FactionResult = std::result::Result<(), FactionError>;
+pub type FactionResult = core::result::Result<(), FactionError>;
 
 /// Errors that can occur while using factions.
 #[derive(Debug)]
@@ -67,9 +670,136 @@ pub enum FactionError {
     PvpDenied,
     /// You cannot use an item in this terrain.
     UseDenied,
+    /// The terrain isn't claimed by this faction.
+    NotClaimed,
+    /// The terrain isn't next to the other claims of the faction.
+    NotAdjacent,
+    /// The other faction didn't ask for an alliance.
+    NoAllianceRequest,
+    /// No faction has the specified id.
+    UnknownFaction,
+    /// Another faction already uses this name.
+    NameTaken,
+    /// The user is already part of a faction.
+    AlreadyInFaction,
+    /// The faction reached its maximum number of users.
+    FactionFull,
+    /// The terrain is already claimed.
+    AlreadyClaimed,
+    /// The id of the faction can't be changed.
+    IdChanged,
+}
+
+/// The faction owning each claimed chunk of terrain.
+/// A chunk of terrain can only be owned by a single faction.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(
+    from = "Vec<((i32, i32, i32), i32)>",
+    into = "Vec<((i32, i32, i32), i32)>"
+)]
+pub struct ClaimMap {
+    owners: HashMap<(i32, i32, i32), i32>,
+}
+
+impl ClaimMap {
+    /// Gives the unclaimed terrain to the faction.
+    ///
+    /// Errors:
+    /// * AlreadyClaimed: A faction already owns this claim.
+    pub fn claim(&mut self, claim: (i32, i32, i32), faction: i32) -> FactionResult {
+        if self.owners.contains_key(&claim) {
+            return Err(FactionError::AlreadyClaimed);
+        }
+        self.owners.insert(claim, faction);
+        Ok(())
+    }
+
+    /// Gives the terrain to the faction, whether it is claimed or not.
+    /// Returns the previous owner.
+    pub fn transfer(&mut self, claim: (i32, i32, i32), faction: i32) -> Option<i32> {
+        self.owners.insert(claim, faction)
+    }
+
+    /// Removes the owner of the terrain and returns it.
+    pub fn unclaim(&mut self, claim: &(i32, i32, i32)) -> Option<i32> {
+        self.owners.remove(claim)
+    }
+
+    /// Returns the faction owning this claim.
+    pub fn owner_of_claim(&self, claim: &(i32, i32, i32)) -> Option<i32> {
+        self.owners.get(claim).cloned()
+    }
+
+    /// Returns the faction owning the terrain at this position.
+    pub fn owner_of(&self, pos: &[f32; 3], settings: &LandClaimSettings) -> Option<i32> {
+        self.owner_of_claim(&settings.claim_id_from_position(pos))
+    }
+
+    /// Returns all the claims owned by the faction.
+    pub fn claims_of(&self, faction: i32) -> Vec<(i32, i32, i32)> {
+        self.owners
+            .iter()
+            .filter(|(_, f)| **f == faction)
+            .map(|(c, _)| *c)
+            .collect()
+    }
+
+    /// Returns the claims sharing a face with this claim.
+    pub fn neighbours(claim: &(i32, i32, i32)) -> [(i32, i32, i32); 6] {
+        let (x, y, z) = *claim;
+        [
+            (x - 1, y, z),
+            (x + 1, y, z),
+            (x, y - 1, z),
+            (x, y + 1, z),
+            (x, y, z - 1),
+            (x, y, z + 1),
+        ]
+    }
+
+    /// Returns the owners of the claims next to this claim.
+    /// A faction owning multiple neighbouring claims is only returned once.
+    pub fn adjacent_owners(&self, claim: &(i32, i32, i32)) -> Vec<i32> {
+        let mut owners = vec![];
+        for n in Self::neighbours(claim).iter() {
+            if let Some(owner) = self.owner_of_claim(n) {
+                if !owners.contains(&owner) {
+                    owners.push(owner);
+                }
+            }
+        }
+        owners
+    }
+
+    /// Checks if the faction owns a claim next to this claim.
+    pub fn is_adjacent_to(&self, claim: &(i32, i32, i32), faction: i32) -> bool {
+        Self::neighbours(claim)
+            .iter()
+            .any(|n| self.owner_of_claim(n) == Some(faction))
+    }
+
+    /// Iterates over all claims and their owner.
+    pub fn iter(&self) -> impl Iterator<Item = (&(i32, i32, i32), &i32)> {
+        self.owners.iter()
+    }
+}
+
+impl From<Vec<((i32, i32, i32), i32)>> for ClaimMap {
+    fn from(t: Vec<((i32, i32, i32), i32)>) -> Self {
+        Self {
+            owners: t.into_iter().collect(),
+        }
+    }
+}
+
+impl From<ClaimMap> for Vec<((i32, i32, i32), i32)> {
+    fn from(t: ClaimMap) -> Self {
+        t.owners.into_iter().collect()
+    }
 }
 
 /// The settings related to terrain claiming and how the world is divided into claimable chunks.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
 pub struct LandClaimSettings {
     /// The size of the claimable chunks.
     pub claim_size: [f32; 3],
@@ -78,13 +808,646 @@ pub struct LandClaimSettings {
 impl LandClaimSettings {
     /// Get the three dimensional ID of this claim area.
     pub fn claim_id_from_position(&self, pos: &[f32; 3]) -> (i32, i32, i32) {
-        let x = pos[0] / self.claim_size[0];
-        let y = pos[1] / self.claim_size[1];
+        let x = (pos[0] / self.claim_size[0]).floor();
+        let y = (pos[1] / self.claim_size[1]).floor();
         let z = if self.claim_size[2] != 0.0 {
-            pos[2] / self.claim_size[2]
+            (pos[2] / self.claim_size[2]).floor()
         } else {
             0.0
         };
         (x as i32, y as i32, z as i32)
     }
 }
+
+/// How the claims of a faction are maintained.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum UpkeepMode<C, I> {
+    /// Claims are maintained as long as the faction has enough power for them.
+    Power,
+    /// Each claim costs this price every upkeep period.
+    Currency(Vec<(C, i64)>),
+    /// Each claim consumes those items every upkeep period.
+    Items(Vec<(I, usize)>),
+}
+
+/// The rules of claim upkeep.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct UpkeepSettings<C, I> {
+    /// How the claims are paid for.
+    pub mode: UpkeepMode<C, I>,
+    /// The time between two payments, in seconds.
+    pub interval: f64,
+    /// The time a claim stays vulnerable before being unclaimed, in seconds.
+    pub decay_time: f64,
+}
+
+/// Something that happened to a claim while updating the upkeep.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpkeepEvent {
+    /// The claim of this faction wasn't paid for and is now vulnerable.
+    Vulnerable((i32, i32, i32), i32),
+    /// The vulnerable claim of this faction was paid for again.
+    Restored((i32, i32, i32), i32),
+    /// The claim of this faction stayed vulnerable for too long and was unclaimed.
+    Decayed((i32, i32, i32), i32),
+}
+
+/// The upkeep state of the claim map.
+/// Claims that aren't paid for become vulnerable and are unclaimed once they decayed.
+/// Games can use `ClaimUpkeep::is_vulnerable` to let other factions take them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimUpkeep {
+    /// The time remaining before the next payment, in seconds.
+    pub until_payment: f64,
+    /// The vulnerable claims, with the faction owning them and the time remaining before they
+    /// are unclaimed.
+    pub vulnerable: HashMap<(i32, i32, i32), (i32, f64)>,
+}
+
+impl ClaimUpkeep {
+    /// Creates the upkeep state. The first payment happens after a full interval of the
+    /// settings.
+    pub fn new<C, I>(settings: &UpkeepSettings<C, I>) -> Self {
+        Self {
+            until_payment: settings.interval,
+            vulnerable: HashMap::new(),
+        }
+    }
+
+    /// Whether the claim is vulnerable because it wasn't paid for.
+    pub fn is_vulnerable(&self, claim: &(i32, i32, i32)) -> bool {
+        self.vulnerable.contains_key(claim)
+    }
+
+    /// Advances the upkeep by `delta_time` seconds.
+    ///
+    /// When upkeep periods elapsed, `pay` is called once for each faction using currency or item
+    /// upkeep, with the id of the faction and the number of payments owed, which is its number
+    /// of claims times the number of elapsed periods. It must take the payments from the
+    /// faction (for example from its bank) and return the number of payments that were made.
+    /// With power upkeep, the claims a faction can maintain using its power are paid for.
+    /// No payments happen if the interval of the settings is 0 or less.
+    ///
+    /// The most recent claims become vulnerable first and vulnerable claims are restored first.
+    /// Returns what happened to the claims.
+    pub fn update<C, I, F: FnMut(i32, &UpkeepMode<C, I>, usize) -> usize>(
+        &mut self,
+        delta_time: f64,
+        factions: &mut FactionRepository,
+        settings: &UpkeepSettings<C, I>,
+        faction_settings: &FactionSettings,
+        mut pay: F,
+    ) -> Vec<UpkeepEvent> {
+        let mut events = vec![];
+        // Claims that changed owner or were unclaimed since they became vulnerable are no
+        // longer in debt.
+        self.vulnerable
+            .retain(|c, (f, _)| factions.owner_of_claim(c) == Some(*f));
+        let mut decayed = vec![];
+        for (claim, (faction, remaining)) in self.vulnerable.iter_mut() {
+            *remaining -= delta_time;
+            if *remaining <= 0.0 {
+                decayed.push((*claim, *faction));
+            }
+        }
+        for (claim, faction) in decayed {
+            self.vulnerable.remove(&claim);
+            factions.unclaim(&claim);
+            events.push(UpkeepEvent::Decayed(claim, faction));
+        }
+
+        if settings.interval <= 0.0 {
+            return events;
+        }
+        self.until_payment -= delta_time;
+        if self.until_payment <= 0.0 {
+            let periods = (-self.until_payment / settings.interval).floor() as usize + 1;
+            self.until_payment += periods as f64 * settings.interval;
+            let owed = factions
+                .iter()
+                .map(|f| (f.id, f.claims.clone(), f.max_claims(faction_settings)))
+                .collect::<Vec<_>>();
+            for (faction, mut claims, max_claims) in owed {
+                // A claim is only paid for if all of its elapsed periods are.
+                let paid = match settings.mode {
+                    UpkeepMode::Power => max_claims,
+                    _ => {
+                        pay(
+                            faction,
+                            &settings.mode,
+                            claims.len().saturating_mul(periods),
+                        ) / periods
+                    }
+                };
+                // Vulnerable claims are moved to the front so they are restored first.
+                claims.sort_by_key(|c| !self.vulnerable.contains_key(c));
+                for (i, claim) in claims.into_iter().enumerate() {
+                    let is_vulnerable = self.vulnerable.contains_key(&claim);
+                    if i < paid && is_vulnerable {
+                        self.vulnerable.remove(&claim);
+                        events.push(UpkeepEvent::Restored(claim, faction));
+                    } else if i >= paid && !is_vulnerable {
+                        self.vulnerable
+                            .insert(claim, (faction, settings.decay_time));
+                        events.push(UpkeepEvent::Vulnerable(claim, faction));
+                    }
+                }
+            }
+        }
+        events
+    }
+}
+
+/// The rules of wars between factions.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct WarSettings<C> {
+    /// The price paid by the attacking faction to declare a war.
+    pub declaration_cost: Vec<(C, i64)>,
+    /// The time between the declaration and the start of the fighting, in seconds.
+    pub preparation_time: f64,
+    /// The times of the day during which claims can be captured, as (start, end) pairs.
+    /// Times are between 0.0 and 1.0, see `GameClock::time_of_day`. A window whose start is
+    /// after its end wraps around midnight.
+    /// No windows means that claims can be captured at any time.
+    pub vulnerability_windows: Vec<(f64, f64)>,
+    /// The time the attackers need to outnumber the defenders on a claim to capture it, in
+    /// seconds.
+    pub capture_time: f64,
+    /// The power moved from the loser to the winner for each captured claim.
+    pub power_per_capture: f32,
+}
+
+impl<C> WarSettings<C> {
+    /// Checks if claims can be captured at this time of the day.
+    pub fn is_vulnerable(&self, time_of_day: f64) -> bool {
+        self.vulnerability_windows.is_empty()
+            || self
+                .vulnerability_windows
+                .iter()
+                .any(|(start, end)| is_between(time_of_day, *start, *end))
+    }
+}
+
+/// The conditions under which a war ends.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, new)]
+pub struct PeaceTerms {
+    /// The faction winning the war, if any.
+    pub winner: Option<i32>,
+    /// The claims given to the winner, in addition to the claims it captured.
+    #[new(default)]
+    pub claims: Vec<(i32, i32, i32)>,
+    /// The power moved from the loser to the winner, in addition to the power of the captured
+    /// claims.
+    #[new(default)]
+    pub power: f32,
+}
+
+/// The step a `War` is at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WarState {
+    /// The war was declared and starts once the remaining time elapsed, in seconds.
+    Preparing(f64),
+    /// The factions are fighting.
+    Active,
+    /// The war ended under these terms.
+    Ended(PeaceTerms),
+    /// The war ended under these terms and its result was applied. See `War::apply`.
+    Applied(PeaceTerms),
+}
+
+/// Something that happened during a war.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WarEvent {
+    /// The preparation ended and the fighting started.
+    Started,
+    /// The attacker captured this claim of the defender.
+    Captured((i32, i32, i32)),
+    /// The war ended under these terms.
+    Ended(PeaceTerms),
+}
+
+/// A war between two factions.
+/// The attacker captures the claims of the defender by outnumbering the defenders on them
+/// during the vulnerability windows. Captured claims are transferred once the war ends and its
+/// result is applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct War {
+    /// The faction that declared the war.
+    pub attacker: i32,
+    /// The faction the war was declared to.
+    pub defender: i32,
+    /// The step the war is at.
+    pub state: WarState,
+    /// The capture progress of the contested claims, in seconds.
+    #[serde(default = "Vec::new")]
+    pub captures: Vec<((i32, i32, i32), f64)>,
+    /// The claims of the defender captured by the attacker.
+    #[serde(default)]
+    pub captured: Vec<(i32, i32, i32)>,
+    /// The pending peace proposal and the faction that made it.
+    #[serde(default)]
+    pub proposal: Option<(i32, PeaceTerms)>,
+}
+
+impl War {
+    /// Declares a war, paying its cost from the wallet of the attacker.
+    /// The factions become enemies.
+    ///
+    /// Errors:
+    /// * InvalidTarget: The factions are the same or allied.
+    /// * Wallet: See `Wallet::pay`.
+    pub fn declare<C: Hash + Eq + Clone>(
+        attacker: i32,
+        defender: i32,
+        relations: &mut FactionRelations,
+        settings: &WarSettings<C>,
+        wallet: &mut Wallet<C>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<War, WarError<C>> {
+        if relations.relation(attacker, defender) == FactionRelation::Ally {
+            return Err(WarError::InvalidTarget);
+        }
+        wallet
+            .pay(&settings.declaration_cost, currency_defs)
+            .map_err(WarError::Wallet)?;
+        relations.set_relation(attacker, defender, FactionRelation::Enemy);
+        Ok(War {
+            attacker,
+            defender,
+            state: WarState::Preparing(settings.preparation_time),
+            captures: vec![],
+            captured: vec![],
+            proposal: None,
+        })
+    }
+
+    /// Checks if the faction takes part in this war.
+    pub fn involves(&self, faction: i32) -> bool {
+        self.attacker == faction || self.defender == faction
+    }
+
+    /// Returns the other faction taking part in this war.
+    pub fn opponent_of(&self, faction: i32) -> i32 {
+        if faction == self.attacker {
+            self.defender
+        } else {
+            self.attacker
+        }
+    }
+
+    /// Advances the preparation by `delta_time` seconds.
+    /// Returns Started once the fighting starts.
+    pub fn update(&mut self, delta_time: f64) -> Option<WarEvent> {
+        if let WarState::Preparing(remaining) = &mut self.state {
+            *remaining -= delta_time;
+            if *remaining <= 0.0 {
+                self.state = WarState::Active;
+                return Some(WarEvent::Started);
+            }
+        }
+        None
+    }
+
+    /// Advances the capture of a claim of the defender by `delta_time` seconds, using the
+    /// number of attackers and defenders present on it.
+    /// The progress increases while the attackers outnumber the defenders and decreases while
+    /// the defenders outnumber the attackers.
+    /// Returns Captured once the claim is captured.
+    ///
+    /// Errors:
+    /// * NotActive: The war isn't in the fighting step.
+    /// * NotVulnerable: Claims can't be captured at this time of the day.
+    /// * NotContested: The defender doesn't own this claim or it was already captured.
+    #[allow(clippy::too_many_arguments)]
+    pub fn contest<C>(
+        &mut self,
+        claim: (i32, i32, i32),
+        attackers: usize,
+        defenders: usize,
+        delta_time: f64,
+        time_of_day: f64,
+        claims: &ClaimMap,
+        settings: &WarSettings<C>,
+    ) -> Result<Option<WarEvent>, WarError<C>> {
+        if self.state != WarState::Active {
+            return Err(WarError::NotActive);
+        }
+        if !settings.is_vulnerable(time_of_day) {
+            return Err(WarError::NotVulnerable);
+        }
+        if claims.owner_of_claim(&claim) != Some(self.defender) || self.captured.contains(&claim) {
+            return Err(WarError::NotContested);
+        }
+        let idx = match self.captures.iter().position(|(c, _)| *c == claim) {
+            Some(idx) => idx,
+            None => {
+                self.captures.push((claim, 0.0));
+                self.captures.len() - 1
+            }
+        };
+        let progress = &mut self.captures[idx].1;
+        if attackers > defenders {
+            *progress += delta_time;
+        } else if defenders > attackers {
+            *progress = (*progress - delta_time).max(0.0);
+        }
+        if *progress >= settings.capture_time {
+            self.captures.swap_remove(idx);
+            self.captured.push(claim);
+            return Ok(Some(WarEvent::Captured(claim)));
+        }
+        Ok(None)
+    }
+
+    /// Proposes peace terms to the other faction, replacing the previous proposal.
+    ///
+    /// Errors:
+    /// * NotInvolved: The faction doesn't take part in this war.
+    /// * Ended: The war already ended.
+    pub fn propose_peace<C>(&mut self, faction: i32, terms: PeaceTerms) -> Result<(), WarError<C>> {
+        self.check_ongoing(faction)?;
+        self.proposal = Some((faction, terms));
+        Ok(())
+    }
+
+    /// Accepts the peace terms proposed by the other faction, ending the war.
+    ///
+    /// Errors:
+    /// * NotInvolved: The faction doesn't take part in this war.
+    /// * Ended: The war already ended.
+    /// * NoProposal: The other faction didn't propose peace terms.
+    pub fn accept_peace<C>(&mut self, faction: i32) -> Result<WarEvent, WarError<C>> {
+        self.check_ongoing(faction)?;
+        match self.proposal.take() {
+            Some((by, terms)) if by != faction => Ok(self.end(terms)),
+            other => {
+                self.proposal = other;
+                Err(WarError::NoProposal)
+            }
+        }
+    }
+
+    /// Surrenders, ending the war with the other faction as the winner.
+    ///
+    /// Errors:
+    /// * NotInvolved: The faction doesn't take part in this war.
+    /// * Ended: The war already ended.
+    pub fn surrender<C>(&mut self, faction: i32) -> Result<WarEvent, WarError<C>> {
+        self.check_ongoing(faction)?;
+        let winner = self.opponent_of(faction);
+        Ok(self.end(PeaceTerms::new(Some(winner))))
+    }
+
+    /// Applies the result of the ended war: the claims captured by the attacker, if it won, and
+    /// the claims of the terms are given to the winner, power is moved from the loser to the
+    /// winner using `Faction::power_boost` and the factions become neutral.
+    /// Claims the loser doesn't own anymore are ignored.
+    /// The result is only applied once: the war then moves to `WarState::Applied`.
+    ///
+    /// Errors:
+    /// * NotEnded: The war didn't end yet.
+    /// * AlreadyApplied: The result of the war was already applied.
+    /// * Faction(UnknownFaction): The winner doesn't exist anymore. Nothing changes.
+    pub fn apply<C>(
+        &mut self,
+        factions: &mut FactionRepository,
+        relations: &mut FactionRelations,
+        settings: &WarSettings<C>,
+    ) -> Result<(), WarError<C>> {
+        let terms = match &self.state {
+            WarState::Ended(terms) => terms.clone(),
+            WarState::Applied(_) => return Err(WarError::AlreadyApplied),
+            _ => return Err(WarError::NotEnded),
+        };
+        if let Some(winner) = terms.winner {
+            if factions.get(winner).is_none() {
+                return Err(WarError::Faction(FactionError::UnknownFaction));
+            }
+        }
+        relations.set_relation(self.attacker, self.defender, FactionRelation::Neutral);
+        self.state = WarState::Applied(terms.clone());
+        let winner = match terms.winner {
+            Some(winner) => winner,
+            None => return Ok(()),
+        };
+        let loser = self.opponent_of(winner);
+        let captured = if winner == self.attacker {
+            &self.captured[..]
+        } else {
+            &[]
+        };
+        for claim in captured.iter().chain(terms.claims.iter()) {
+            if factions.owner_of_claim(claim) == Some(loser) {
+                factions
+                    .transfer_claim(*claim, winner)
+                    .expect("The winner of the war disappeared. This is a bug.");
+            }
+        }
+        let power = terms.power + captured.len() as f32 * settings.power_per_capture;
+        // The loser may have been disbanded since the end of the war.
+        factions.update(loser, |f| f.power_boost -= power).ok();
+        factions
+            .update(winner, |f| f.power_boost += power)
+            .expect("The winner of the war disappeared. This is a bug.");
+        Ok(())
+    }
+
+    fn end(&mut self, terms: PeaceTerms) -> WarEvent {
+        self.proposal = None;
+        self.captures.clear();
+        self.state = WarState::Ended(terms.clone());
+        WarEvent::Ended(terms)
+    }
+
+    fn check_ongoing<C>(&self, faction: i32) -> Result<(), WarError<C>> {
+        if !self.involves(faction) {
+            return Err(WarError::NotInvolved);
+        }
+        if let WarState::Ended(_) | WarState::Applied(_) = self.state {
+            return Err(WarError::Ended);
+        }
+        Ok(())
+    }
+}
+
+/// The errors that can happen during a `War`.
+#[derive(Debug)]
+pub enum WarError<C> {
+    /// War can't be declared to this faction.
+    InvalidTarget,
+    /// The attacker can't pay for the declaration.
+    Wallet(WalletError<C>),
+    /// The war isn't in the fighting step.
+    NotActive,
+    /// Claims can't be captured at this time of the day.
+    NotVulnerable,
+    /// The claim isn't owned by the defender or was already captured.
+    NotContested,
+    /// The faction doesn't take part in this war.
+    NotInvolved,
+    /// The war already ended.
+    Ended,
+    /// The war didn't end yet.
+    NotEnded,
+    /// The result of the war was already applied.
+    AlreadyApplied,
+    /// The other faction didn't propose peace terms.
+    NoProposal,
+    /// An error happened while transferring claims.
+    Faction(FactionError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> FactionSettings {
+        FactionSettings {
+            user_settings: UserGroupSettings { maximum_users: 3 },
+            base_player_power: 2.0,
+            minimum_player_power: 0.0,
+            maximum_player_power: 10.0,
+            power_regeneration_per_second: 0.1,
+            power_loss_on_death: 4.0,
+            power_per_claim: 1.0,
+            adjacent_claims_only: true,
+            flags: FactionFlags {
+                claimable: true,
+                pvp_enabled: true,
+                power_loss_in_territory: true,
+                power_gain_in_territory: true,
+                permanent: false,
+                allies_can_build: false,
+            },
+        }
+    }
+
+    fn claimed(settings: &FactionSettings) -> (FactionRepository, i32) {
+        let mut repo = FactionRepository::default();
+        let faction = repo
+            .create("Guild".to_string(), UserGroup::new(0, vec![1]))
+            .unwrap();
+        repo.update(faction, |f| {
+            f.recalculate_power(&UserPowers::default(), settings)
+        })
+        .unwrap();
+        repo.claim(faction, (0, 0, 0), settings).unwrap();
+        repo.claim(faction, (1, 0, 0), settings).unwrap();
+        (repo, faction)
+    }
+
+    #[test]
+    fn upkeep_pays_all_elapsed_periods_at_once() {
+        let settings = settings();
+        let (mut repo, faction) = claimed(&settings);
+        let upkeep = UpkeepSettings::<u8, u8>::new(UpkeepMode::Currency(vec![(0, 5)]), 10.0, 1e13);
+        let mut state = ClaimUpkeep::new(&upkeep);
+        // Nothing is owed before the first interval elapsed.
+        let events = state.update(5.0, &mut repo, &upkeep, &settings, |_, _, _| 0);
+        assert!(events.is_empty());
+        let mut calls = vec![];
+        // 3 periods elapsed, but only enough for one claim.
+        let events = state.update(30.0, &mut repo, &upkeep, &settings, |_, _, owed| {
+            calls.push(owed);
+            3
+        });
+        assert_eq!(calls, vec![6]);
+        assert_eq!(events, vec![UpkeepEvent::Vulnerable((1, 0, 0), faction)]);
+        assert!((state.until_payment - 5.0).abs() < f64::EPSILON);
+        let events = state.update(1e12, &mut repo, &upkeep, &settings, |_, _, owed| owed);
+        assert_eq!(events, vec![UpkeepEvent::Restored((1, 0, 0), faction)]);
+    }
+
+    #[test]
+    fn upkeep_without_interval_does_nothing() {
+        let settings = settings();
+        let (mut repo, _) = claimed(&settings);
+        let upkeep = UpkeepSettings::<u8, u8>::new(UpkeepMode::Currency(vec![(0, 5)]), 0.0, 1.0);
+        let mut state = ClaimUpkeep::new(&upkeep);
+        let events = state.update(10.0, &mut repo, &upkeep, &settings, |_, _, _| 0);
+        assert!(events.is_empty());
+        assert!(!state.is_vulnerable(&(1, 0, 0)));
+    }
+
+    #[test]
+    fn claiming_twice_is_rejected() {
+        let settings = settings();
+        let (repo, faction) = claimed(&settings);
+        let mut faction = repo.get(faction).unwrap().clone();
+        assert!(matches!(
+            faction.claim((1, 0, 0), &settings),
+            Err(FactionError::AlreadyClaimed)
+        ));
+        assert_eq!(faction.claims.len(), 2);
+    }
+
+    #[test]
+    fn conflicting_updates_are_rejected() {
+        let settings = settings();
+        let (mut repo, guild) = claimed(&settings);
+        let other = repo
+            .create("Other".to_string(), UserGroup::new(0, vec![2]))
+            .unwrap();
+        assert!(matches!(
+            repo.update(other, |f| f.claims.push((0, 0, 0))),
+            Err(FactionError::AlreadyClaimed)
+        ));
+        assert!(matches!(
+            repo.update(other, |f| f.name = "Guild".to_string()),
+            Err(FactionError::NameTaken)
+        ));
+        assert!(matches!(
+            repo.update(other, |f| f.users.users.push(1)),
+            Err(FactionError::AlreadyInFaction)
+        ));
+        assert!(matches!(
+            repo.update(other, |f| f.id = guild),
+            Err(FactionError::IdChanged)
+        ));
+        assert_eq!(repo.get(other).unwrap().name, "Other");
+        assert_eq!(repo.owner_of_claim(&(0, 0, 0)), Some(guild));
+        repo.update(guild, |f| f.claims.retain(|c| *c != (0, 0, 0)))
+            .unwrap();
+        repo.update(other, |f| f.claims.push((0, 0, 0))).unwrap();
+        assert_eq!(repo.owner_of_claim(&(0, 0, 0)), Some(other));
+    }
+
+    #[test]
+    fn war_results_are_applied_once() {
+        let settings = settings();
+        let (mut repo, defender) = claimed(&settings);
+        let attacker = repo
+            .create("Raiders".to_string(), UserGroup::new(0, vec![2]))
+            .unwrap();
+        let war_settings = WarSettings::<u8>::new(vec![], 0.0, vec![], 1.0, 0.0);
+        let mut relations = FactionRelations::default();
+        let mut war = War::declare(
+            attacker,
+            defender,
+            &mut relations,
+            &war_settings,
+            &mut Wallet::new(),
+            &CurrencyDefinitions::default(),
+        )
+        .unwrap();
+        assert!(matches!(
+            war.apply(&mut repo, &mut relations, &war_settings),
+            Err(WarError::NotEnded)
+        ));
+        let mut terms = PeaceTerms::new(Some(attacker));
+        terms.power = 2.0;
+        war.propose_peace::<u8>(attacker, terms).unwrap();
+        war.accept_peace::<u8>(defender).unwrap();
+        war.apply(&mut repo, &mut relations, &war_settings).unwrap();
+        assert!(matches!(
+            war.apply(&mut repo, &mut relations, &war_settings),
+            Err(WarError::AlreadyApplied)
+        ));
+        assert!(matches!(
+            war.surrender::<u8>(defender),
+            Err(WarError::Ended)
+        ));
+        assert_eq!(repo.get(attacker).unwrap().power_boost, 2.0);
+        assert_eq!(repo.get(defender).unwrap().power_boost, -2.0);
+    }
+}
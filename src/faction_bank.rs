@@ -0,0 +1,485 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The permission node required to put items and currencies into a faction bank.
+pub const FACTION_BANK_DEPOSIT: &str = "faction.bank.deposit";
+/// The permission node required to take items and currencies from a faction bank.
+pub const FACTION_BANK_WITHDRAW: &str = "faction.bank.withdraw";
+
+/// The maximum amounts a user can withdraw from a `FactionBank` each game day.
+/// None or missing currencies mean no limit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct FactionBankLimits<C: Hash + Eq> {
+    /// The maximum quantity of items withdrawn per day, all items combined.
+    pub max_items: Option<usize>,
+    /// The maximum amount of each currency withdrawn per day.
+    #[new(default)]
+    pub max_currencies: HashMap<C, i64>,
+}
+
+/// What a user withdrew from a `FactionBank` during a game day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct FactionBankWithdrawals<C: Hash + Eq> {
+    /// The game day of the withdrawals.
+    pub day: u64,
+    /// The quantity of items withdrawn.
+    #[new(default)]
+    pub items: usize,
+    /// The amount of each currency withdrawn.
+    #[new(default)]
+    pub currencies: HashMap<C, i64>,
+}
+
+/// An operation done with a `FactionBank`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FactionBankAction<K, C> {
+    /// Items were put into the bank.
+    DepositItem(K, usize),
+    /// Items were taken from the bank.
+    WithdrawItem(K, usize),
+    /// Currency was put into the bank.
+    DepositCurrency(C, i64),
+    /// Currency was taken from the bank.
+    WithdrawCurrency(C, i64),
+}
+
+/// An entry of the log of a `FactionBank`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+pub struct FactionBankLogEntry<K, C> {
+    /// The user who did the operation.
+    pub user: i32,
+    /// The game day of the operation.
+    pub day: u64,
+    /// The operation.
+    pub action: FactionBankAction<K, C>,
+}
+
+/// What is needed to check if a user can use a `FactionBank`.
+#[derive(new)]
+pub struct FactionBankAccess<'a> {
+    /// The factions, used to check if the user is a member of the faction owning the bank.
+    pub factions: &'a FactionRepository,
+    /// The permissions of the users. Their groups are the ranks of the faction.
+    pub permissions: &'a PermissionTree,
+    /// The current time, used for the temporary permissions.
+    pub now: f64,
+    /// The current game day, used for the daily limits. See `GameClock::day`.
+    pub day: u64,
+}
+
+/// The items and currencies shared by the members of a faction.
+///
+/// Members need the `FACTION_BANK_DEPOSIT` and `FACTION_BANK_WITHDRAW` permissions to use it.
+/// The permission groups of the users act as ranks: each one can limit the amounts its members
+/// can withdraw each day.
+///
+/// # Generics
+/// - K: Item Key
+/// - S: Item Slot Type
+/// - U: Item Custom Data
+/// - C: Currency Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct FactionBank<K, S: SlotType, U: Default, C: Hash + Eq> {
+    /// The id of the faction owning the bank.
+    pub faction: i32,
+    /// The items stored in the bank.
+    pub inventory: Inventory<K, S, U>,
+    /// The currencies stored in the bank.
+    #[new(value = "Wallet::new()")]
+    pub wallet: Wallet<C>,
+    /// The daily withdraw limits of each permission group.
+    /// If the user is part of multiple groups with limits, the group with the highest priority is
+    /// used. Users without limits can withdraw as much as they want.
+    #[new(default)]
+    #[serde(default)]
+    pub rank_limits: HashMap<String, FactionBankLimits<C>>,
+    /// What each user withdrew during the last game day they withdrew something.
+    #[new(default)]
+    #[serde(default)]
+    pub withdrawals: HashMap<i32, FactionBankWithdrawals<C>>,
+    /// All the deposits and withdrawals, from the oldest to the most recent.
+    #[new(default)]
+    #[serde(default = "Vec::new")]
+    pub log: Vec<FactionBankLogEntry<K, C>>,
+}
+
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType + Clone,
+        U: Default + Clone + Debug + PartialEq,
+        C: Hash + Eq + Clone,
+    > FactionBank<K, S, U, C>
+{
+    /// Returns the daily withdraw limits of the user, if any.
+    pub fn limits_of(
+        &self,
+        user: i32,
+        access: &FactionBankAccess,
+    ) -> Option<&FactionBankLimits<C>> {
+        let perms = access.permissions.users.get(&user)?;
+        perms
+            .groups
+            .iter()
+            .chain(
+                perms
+                    .temporary_groups
+                    .iter()
+                    .filter(|g| g.is_active(access.now))
+                    .map(|g| &g.value),
+            )
+            .filter_map(|g| self.rank_limits.get(g).map(|l| (g, l)))
+            .max_by_key(|(g, _)| {
+                access
+                    .permissions
+                    .groups
+                    .get(*g)
+                    .map(|g| g.priority)
+                    .unwrap_or(i32::MIN)
+            })
+            .map(|(_, l)| l)
+    }
+
+    /// Returns what the user withdrew during the current game day.
+    pub fn withdrawn_today(&self, user: i32, day: u64) -> FactionBankWithdrawals<C> {
+        self.withdrawals
+            .get(&user)
+            .filter(|w| w.day == day)
+            .cloned()
+            .unwrap_or_else(|| FactionBankWithdrawals::new(day))
+    }
+
+    /// Moves a quantity of items from a slot of the inventory of the user into a slot of the
+    /// bank.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotMember: The user isn't a member of the faction owning the bank.
+    /// * MissingPermission: The user can't deposit into the bank.
+    /// * InvalidAmount: The quantity is 0.
+    /// * Item: See `Inventory::delete` and `Inventory::insert_into`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn deposit_item<D: Default>(
+        &mut self,
+        user: i32,
+        from: &mut Inventory<K, S, U>,
+        from_idx: usize,
+        to_idx: usize,
+        quantity: usize,
+        item_defs: &ItemDefinitions<K, S, D>,
+        access: &FactionBankAccess,
+    ) -> Result<(), FactionBankError<K, U, C>> {
+        self.check_access(user, FACTION_BANK_DEPOSIT, access)?;
+        if quantity == 0 {
+            return Err(FactionBankError::InvalidAmount);
+        }
+        let key = Self::key_at(from, from_idx)?;
+        move_item(
+            from,
+            from_idx,
+            &mut self.inventory,
+            to_idx,
+            quantity,
+            item_defs,
+        )
+        .map_err(FactionBankError::Item)?;
+        self.log.push(FactionBankLogEntry::new(
+            user,
+            access.day,
+            FactionBankAction::DepositItem(key, quantity),
+        ));
+        Ok(())
+    }
+
+    /// Moves a quantity of items from a slot of the bank into a slot of the inventory of the
+    /// user.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotMember: The user isn't a member of the faction owning the bank.
+    /// * MissingPermission: The user can't withdraw from the bank.
+    /// * InvalidAmount: The quantity is 0.
+    /// * DailyLimitReached: The user would withdraw more items than their rank allows today.
+    /// * Item: See `Inventory::delete` and `Inventory::insert_into`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn withdraw_item<D: Default>(
+        &mut self,
+        user: i32,
+        from_idx: usize,
+        to: &mut Inventory<K, S, U>,
+        to_idx: usize,
+        quantity: usize,
+        item_defs: &ItemDefinitions<K, S, D>,
+        access: &FactionBankAccess,
+    ) -> Result<(), FactionBankError<K, U, C>> {
+        self.check_access(user, FACTION_BANK_WITHDRAW, access)?;
+        if quantity == 0 {
+            return Err(FactionBankError::InvalidAmount);
+        }
+        let mut withdrawn = self.withdrawn_today(user, access.day);
+        withdrawn.items += quantity;
+        if let Some(max) = self.limits_of(user, access).and_then(|l| l.max_items) {
+            if withdrawn.items > max {
+                return Err(FactionBankError::DailyLimitReached);
+            }
+        }
+        let key = Self::key_at(&self.inventory, from_idx)?;
+        move_item(
+            &mut self.inventory,
+            from_idx,
+            to,
+            to_idx,
+            quantity,
+            item_defs,
+        )
+        .map_err(FactionBankError::Item)?;
+        self.withdrawals.insert(user, withdrawn);
+        self.log.push(FactionBankLogEntry::new(
+            user,
+            access.day,
+            FactionBankAction::WithdrawItem(key, quantity),
+        ));
+        Ok(())
+    }
+
+    /// Moves an amount of currency from the wallet of the user into the bank.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotMember: The user isn't a member of the faction owning the bank.
+    /// * MissingPermission: The user can't deposit into the bank.
+    /// * InvalidAmount: The amount is 0 or less.
+    /// * Wallet: See `Wallet::transfer`.
+    pub fn deposit_currency(
+        &mut self,
+        user: i32,
+        from: &mut Wallet<C>,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+        access: &FactionBankAccess,
+    ) -> Result<(), FactionBankError<K, U, C>> {
+        self.check_access(user, FACTION_BANK_DEPOSIT, access)?;
+        if amount <= 0 {
+            return Err(FactionBankError::InvalidAmount);
+        }
+        from.transfer(&mut self.wallet, currency, amount, currency_defs)
+            .map_err(FactionBankError::Wallet)?;
+        self.log.push(FactionBankLogEntry::new(
+            user,
+            access.day,
+            FactionBankAction::DepositCurrency(currency.clone(), amount),
+        ));
+        Ok(())
+    }
+
+    /// Moves an amount of currency from the bank into the wallet of the user.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotMember: The user isn't a member of the faction owning the bank.
+    /// * MissingPermission: The user can't withdraw from the bank.
+    /// * InvalidAmount: The amount is 0 or less.
+    /// * DailyLimitReached: The user would withdraw more of the currency than their rank allows
+    ///   today.
+    /// * Wallet: See `Wallet::transfer`.
+    pub fn withdraw_currency(
+        &mut self,
+        user: i32,
+        to: &mut Wallet<C>,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+        access: &FactionBankAccess,
+    ) -> Result<(), FactionBankError<K, U, C>> {
+        self.check_access(user, FACTION_BANK_WITHDRAW, access)?;
+        if amount <= 0 {
+            return Err(FactionBankError::InvalidAmount);
+        }
+        let mut withdrawn = self.withdrawn_today(user, access.day);
+        let total = withdrawn.currencies.entry(currency.clone()).or_insert(0);
+        *total += amount;
+        if let Some(max) = self
+            .limits_of(user, access)
+            .and_then(|l| l.max_currencies.get(currency))
+        {
+            if *total > *max {
+                return Err(FactionBankError::DailyLimitReached);
+            }
+        }
+        self.wallet
+            .transfer(to, currency, amount, currency_defs)
+            .map_err(FactionBankError::Wallet)?;
+        self.withdrawals.insert(user, withdrawn);
+        self.log.push(FactionBankLogEntry::new(
+            user,
+            access.day,
+            FactionBankAction::WithdrawCurrency(currency.clone(), amount),
+        ));
+        Ok(())
+    }
+
+    /// Pays the upkeep of as many claims as possible using the content of the bank and returns
+    /// the number of claims paid for.
+    /// Can be used as the payment function of `ClaimUpkeep::update`.
+    /// Power upkeep doesn't use the bank and pays for no claims.
+    pub fn pay_upkeep(
+        &mut self,
+        mode: &UpkeepMode<C, K>,
+        claims: usize,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> usize {
+        let mut paid = 0;
+        while paid < claims {
+            let ok = match mode {
+                UpkeepMode::Power => false,
+                UpkeepMode::Currency(price) => self.wallet.pay(price, currency_defs).is_ok(),
+                UpkeepMode::Items(items) => self.inventory.delete_keys(items).is_ok(),
+            };
+            if !ok {
+                break;
+            }
+            paid += 1;
+        }
+        paid
+    }
+
+    fn check_access(
+        &self,
+        user: i32,
+        node: &str,
+        access: &FactionBankAccess,
+    ) -> Result<(), FactionBankError<K, U, C>> {
+        if access.factions.faction_of_user(user) != Some(self.faction) {
+            return Err(FactionBankError::NotMember);
+        }
+        if !access.permissions.has_permission(user, node, access.now) {
+            return Err(FactionBankError::MissingPermission);
+        }
+        Ok(())
+    }
+
+    fn key_at(inventory: &Inventory<K, S, U>, idx: usize) -> Result<K, FactionBankError<K, U, C>> {
+        match inventory.content.get(idx) {
+            Some(Some(ii)) => Ok(ii.key.clone()),
+            _ => Err(FactionBankError::Item(ItemError::SlotEmpty)),
+        }
+    }
+}
+
+/// The errors that can happen when using a `FactionBank`.
+#[derive(Debug)]
+pub enum FactionBankError<K: PartialEq + Debug, U: Default, C> {
+    /// The user isn't a member of the faction owning the bank.
+    NotMember,
+    /// The user doesn't have the permission required for this operation.
+    MissingPermission,
+    /// The user reached the daily withdraw limit of their rank.
+    DailyLimitReached,
+    /// The amount or quantity is 0 or less.
+    InvalidAmount,
+    /// An error happened while moving items.
+    Item(ItemError<K, U>),
+    /// An error happened while moving currencies.
+    Wallet(WalletError<C>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn setup() -> (FactionRepository, PermissionTree, i32) {
+        let mut repo = FactionRepository::default();
+        let faction = repo
+            .create("Guild".to_string(), UserGroup::new(0, vec![1, 2]))
+            .unwrap();
+        let mut perms = PermissionTree::default();
+        let mut member = PermissionGroup::new("member".to_string(), 0);
+        member
+            .entries
+            .push(PermissionEntry::new("faction.bank.*".to_string(), true));
+        perms.add_group(member);
+        perms.user_mut(1).groups.push("member".to_string());
+        (repo, perms, faction)
+    }
+
+    #[test]
+    fn invalid_amounts_are_rejected() {
+        let (repo, perms, faction) = setup();
+        let access = FactionBankAccess::new(&repo, &perms, 0.0, 0);
+        let defs = currency_defs();
+        let mut bank = FactionBank::<u32, (), (), u8>::new(faction, Inventory::new_fixed(2));
+        bank.wallet.add(&0, 100, &defs).unwrap();
+        let mut limits = FactionBankLimits::new(None);
+        limits.max_currencies.insert(0, 10);
+        bank.rank_limits.insert("member".to_string(), limits);
+        let mut wallet = Wallet::new();
+        assert!(matches!(
+            bank.deposit_currency(1, &mut wallet, &0, -50, &defs, &access),
+            Err(FactionBankError::InvalidAmount)
+        ));
+        assert!(matches!(
+            bank.withdraw_currency(1, &mut wallet, &0, -50, &defs, &access),
+            Err(FactionBankError::InvalidAmount)
+        ));
+        let mut inventory = Inventory::new_fixed(2);
+        assert!(matches!(
+            bank.deposit_item(1, &mut inventory, 0, 0, 0, &item_defs(), &access),
+            Err(FactionBankError::InvalidAmount)
+        ));
+        assert!(bank.log.is_empty());
+        assert!(bank.withdrawals.is_empty());
+        bank.withdraw_currency(1, &mut wallet, &0, 10, &defs, &access)
+            .unwrap();
+        assert!(matches!(
+            bank.withdraw_currency(1, &mut wallet, &0, 1, &defs, &access),
+            Err(FactionBankError::DailyLimitReached)
+        ));
+        assert_eq!(bank.wallet.balance(&0), 90);
+        assert_eq!(wallet.balance(&0), 10);
+    }
+
+    #[test]
+    fn item_moves() {
+        let (repo, perms, faction) = setup();
+        let access = FactionBankAccess::new(&repo, &perms, 0.0, 0);
+        let defs = item_defs();
+        let mut bank = FactionBank::<u32, (), (), u8>::new(faction, Inventory::new_fixed(2));
+        bank.rank_limits
+            .insert("member".to_string(), FactionBankLimits::new(Some(3)));
+        let mut inventory = Inventory::new_fixed(2);
+        inventory.insert(ItemInstance::new(1, 8), &defs).unwrap();
+        assert!(matches!(
+            bank.deposit_item(2, &mut inventory, 0, 0, 8, &defs, &access),
+            Err(FactionBankError::MissingPermission)
+        ));
+        assert!(matches!(
+            bank.deposit_item(3, &mut inventory, 0, 0, 8, &defs, &access),
+            Err(FactionBankError::NotMember)
+        ));
+        bank.deposit_item(1, &mut inventory, 0, 0, 8, &defs, &access)
+            .unwrap();
+        bank.withdraw_item(1, 0, &mut inventory, 1, 3, &defs, &access)
+            .unwrap();
+        assert!(matches!(
+            bank.withdraw_item(1, 0, &mut inventory, 1, 1, &defs, &access),
+            Err(FactionBankError::DailyLimitReached)
+        ));
+        assert_eq!(bank.inventory.get(0).as_ref().unwrap().quantity, 5);
+        assert_eq!(inventory.get(1).as_ref().unwrap().quantity, 3);
+        assert_eq!(bank.log.len(), 2);
+    }
+
+    #[test]
+    fn upkeep_adds_up_duplicate_keys() {
+        let defs = item_defs();
+        let mut bank = FactionBank::<u32, (), (), u8>::new(0, Inventory::new_fixed(2));
+        bank.inventory
+            .insert(ItemInstance::new(1, 5), &defs)
+            .unwrap();
+        let upkeep = UpkeepMode::Items(vec![(1, 2), (1, 2)]);
+        assert_eq!(bank.pay_upkeep(&upkeep, 3, &currency_defs()), 1);
+        assert_eq!(bank.inventory.get(0).as_ref().unwrap().quantity, 1);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// A stage of the growth of a `Crop`, like "seed", "sprout" or "mature".
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct GrowthStage {
+    /// The display name of this stage.
+    pub name: LocalizedText,
+    /// The time spent in this stage at normal speed, in seconds.
+    /// The duration of the last stage is ignored since the crop stays mature.
+    pub duration: f64,
+}
+
+/// Changes the growth speed of a crop while the stats of its plot meet the conditions, like
+/// when it is watered or fertilized.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct GrowthModifier<K> {
+    /// The conditions on the stats of the plot.
+    pub conditions: Vec<StatCondition<K>>,
+    /// The multiplier applied to the growth speed. Multipliers of different modifiers stack
+    /// multiplicatively.
+    pub speed_multiplier: f64,
+}
+
+/// Makes a crop wither when the stats of its plot don't meet the conditions for too long, like
+/// when it is not watered.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct WitherRule<K> {
+    /// The conditions on the stats of the plot that keep the crop alive.
+    pub conditions: Vec<StatCondition<K>>,
+    /// The time the conditions can stay unmet before the crop withers, in seconds.
+    pub time: f64,
+}
+
+/// The definition of something that grows over time, like a plant or a tree.
+///
+/// # Generics
+/// - C: Crop Key
+/// - K: Stat Key
+/// - L: Loot Table Key
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct Crop<C, K, L> {
+    /// The key of this crop.
+    pub key: C,
+    /// The display name of this crop.
+    #[builder(setter(into))]
+    pub name: LocalizedText,
+    /// The growth stages. The crop is mature once it reaches the last one.
+    pub stages: Vec<GrowthStage>,
+    /// The modifiers of the growth speed.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub modifiers: Vec<GrowthModifier<K>>,
+    /// The rule making this crop wither.
+    /// None means that the crop never withers.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Option::default")]
+    pub wither: Option<WitherRule<K>>,
+    /// The loot table of the `LootTables` rolled to get the harvested items.
+    pub harvest_table: L,
+    /// The number of times the loot table is rolled when harvesting.
+    #[new(value = "1")]
+    #[builder(default = "1")]
+    pub harvest_rolls: usize,
+}
+
+impl<C, K: Hash + Eq + Debug, L> Crop<C, K, L> {
+    /// Returns the growth speed multiplier for the stats of the plot.
+    pub fn growth_speed(&self, stats: &StatSet<K>, stat_defs: &StatDefinitions<K>) -> f64 {
+        self.modifiers
+            .iter()
+            .filter(|m| m.conditions.iter().all(|c| c.check(stats, stat_defs)))
+            .map(|m| m.speed_multiplier)
+            .product()
+    }
+}
+
+/// The definitions of all known crops.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Crops<C: Hash + Eq, K, L> {
+    /// The definitions.
+    pub defs: HashMap<C, Crop<C, K, L>>,
+}
+
+impl<C: Hash + Eq, K, L> Default for Crops<C, K, L> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<C: Hash + Eq + Clone, K, L> From<Vec<Crop<C, K, L>>> for Crops<C, K, L> {
+    fn from(t: Vec<Crop<C, K, L>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<C: Hash + Eq, K, L> Crops<C, K, L> {
+    fn get(&self, key: &C) -> &Crop<C, K, L> {
+        self.defs.get(key).expect("Tried to get unknown crop key.")
+    }
+}
+
+/// A crop planted in a plot.
+///
+/// # Generics
+/// - C: Crop Key
+/// - K: Stat Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Plot<C, K: Hash + Eq> {
+    /// The planted crop.
+    pub crop: C,
+    /// The stats of the plot, like its water or fertilizer levels.
+    /// Apply effectors to them using `EffectorSet::apply_to`.
+    pub stats: StatSet<K>,
+    /// The index of the current growth stage.
+    #[new(default)]
+    pub stage: usize,
+    /// The time spent in the current stage at normal speed, in seconds.
+    #[new(default)]
+    pub progress: f64,
+    /// The time the conditions of the `WitherRule` have been unmet, in seconds.
+    #[new(default)]
+    pub neglected: f64,
+    /// Whether the crop withered. Withered crops don't grow and can't be harvested.
+    #[new(default)]
+    pub withered: bool,
+}
+
+impl<C: Hash + Eq, K: Hash + Eq> Plot<C, K> {
+    /// Checks if the crop reached its last growth stage.
+    pub fn is_mature<L>(&self, crops: &Crops<C, K, L>) -> bool {
+        self.stage + 1 >= crops.get(&self.crop).stages.len()
+    }
+}
+
+/// The plots of a farm, growing all their crops at once.
+///
+/// # Generics
+/// - P: Plot Id
+/// - C: Crop Key
+/// - K: Stat Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct GrowthTracker<P: Hash + Eq, C, K: Hash + Eq> {
+    /// The plots with a crop.
+    #[new(default)]
+    pub plots: HashMap<P, Plot<C, K>>,
+}
+
+impl<P: Hash + Eq + Clone, C: Hash + Eq, K: Hash + Eq + Debug> GrowthTracker<P, C, K> {
+    /// Plants a crop in an empty plot.
+    ///
+    /// Errors:
+    /// * Occupied: A crop is already planted in this plot.
+    pub fn plant(&mut self, plot: P, crop: C, stats: StatSet<K>) -> Result<(), FarmingError> {
+        if self.plots.contains_key(&plot) {
+            return Err(FarmingError::Occupied);
+        }
+        self.plots.insert(plot, Plot::new(crop, stats));
+        Ok(())
+    }
+
+    /// Removes the crop of the plot without harvesting it, like when clearing a withered crop.
+    pub fn clear(&mut self, plot: &P) -> Option<Plot<C, K>> {
+        self.plots.remove(plot)
+    }
+
+    /// Grows the crops for `delta_time` seconds, using the growth speed given by the stats of
+    /// their plot, and withers the neglected ones.
+    /// Mature and withered crops are skipped.
+    pub fn update<L>(
+        &mut self,
+        delta_time: f64,
+        crops: &Crops<C, K, L>,
+        stat_defs: &StatDefinitions<K>,
+    ) -> Vec<GrowthEvent<P>> {
+        let mut events = vec![];
+        for (id, plot) in self.plots.iter_mut() {
+            if plot.withered {
+                continue;
+            }
+            let crop = crops.get(&plot.crop);
+            let last = crop.stages.len().saturating_sub(1);
+            if let Some(rule) = crop.wither.as_ref() {
+                if rule
+                    .conditions
+                    .iter()
+                    .all(|c| c.check(&plot.stats, stat_defs))
+                {
+                    plot.neglected = 0.0;
+                } else {
+                    plot.neglected += delta_time;
+                    if plot.neglected >= rule.time {
+                        plot.withered = true;
+                        events.push(GrowthEvent::Withered(id.clone()));
+                        continue;
+                    }
+                }
+            }
+            if plot.stage >= last {
+                continue;
+            }
+            plot.progress += delta_time * crop.growth_speed(&plot.stats, stat_defs);
+            while plot.stage < last && plot.progress >= crop.stages[plot.stage].duration {
+                plot.progress -= crop.stages[plot.stage].duration;
+                plot.stage += 1;
+                events.push(GrowthEvent::StageReached(id.clone(), plot.stage));
+            }
+            if plot.stage >= last {
+                plot.progress = 0.0;
+                events.push(GrowthEvent::Matured(id.clone()));
+            }
+        }
+        events
+    }
+
+    /// Harvests the mature crop of the plot and empties the plot.
+    /// Returns the harvested items.
+    ///
+    /// Errors:
+    /// * EmptyPlot: No crop is planted in this plot.
+    /// * NotMature: The crop didn't reach its last growth stage.
+    /// * Withered: The crop withered.
+    #[cfg(feature = "std")]
+    pub fn harvest<L: Hash + Eq + Clone + 'static, I: Clone + 'static, U: Default>(
+        &mut self,
+        plot: &P,
+        crops: &Crops<C, K, L>,
+        loot_tables: &LootTables<L, ItemDrop<I>>,
+    ) -> Result<Vec<ItemInstance<I, U>>, FarmingError> {
+        self.harvest_with(plot, crops, loot_tables, &mut thread_rng())
+    }
+
+    /// Same as `GrowthTracker::harvest`, using the provided random number generator.
+    pub fn harvest_with<L: Hash + Eq + Clone + 'static, I: Clone + 'static, U: Default, G: Rng>(
+        &mut self,
+        plot: &P,
+        crops: &Crops<C, K, L>,
+        loot_tables: &LootTables<L, ItemDrop<I>>,
+        rng: &mut G,
+    ) -> Result<Vec<ItemInstance<I, U>>, FarmingError> {
+        let p = self.plots.get(plot).ok_or(FarmingError::EmptyPlot)?;
+        if p.withered {
+            return Err(FarmingError::Withered);
+        }
+        if !p.is_mature(crops) {
+            return Err(FarmingError::NotMature);
+        }
+        let crop = crops.get(&p.crop);
+        let harvested = (0..crop.harvest_rolls)
+            .filter_map(|_| {
+                loot_tables
+                    .roll_with(&crop.harvest_table, rng)
+                    .map(|drop| drop.roll_with(rng))
+            })
+            .collect();
+        self.plots.remove(plot);
+        Ok(harvested)
+    }
+}
+
+/// An event emitted by a `GrowthTracker`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GrowthEvent<P> {
+    /// The crop of the plot reached this growth stage.
+    StageReached(P, usize),
+    /// The crop of the plot reached its last growth stage and can be harvested.
+    Matured(P),
+    /// The crop of the plot withered.
+    Withered(P),
+}
+
+/// The errors that can happen when farming.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FarmingError {
+    /// A crop is already planted in this plot.
+    Occupied,
+    /// No crop is planted in this plot.
+    EmptyPlot,
+    /// The crop didn't reach its last growth stage.
+    NotMature,
+    /// The crop withered.
+    Withered,
+}
@@ -0,0 +1,83 @@
+//! The float functions of `std` that are missing from `core`, implemented using `libm`.
+
+/// Adds the `std` rounding, power and trigonometric functions to the floats when building
+/// without `std`.
+#[allow(dead_code)]
+pub(crate) trait Float {
+    fn floor(self) -> Self;
+    fn ceil(self) -> Self;
+    fn round(self) -> Self;
+    fn fract(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn acos(self) -> Self;
+}
+
+impl Float for f64 {
+    fn floor(self) -> f64 {
+        libm::floor(self)
+    }
+
+    fn ceil(self) -> f64 {
+        libm::ceil(self)
+    }
+
+    fn round(self) -> f64 {
+        libm::round(self)
+    }
+
+    fn fract(self) -> f64 {
+        self - libm::trunc(self)
+    }
+
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+
+    fn powf(self, n: f64) -> f64 {
+        libm::pow(self, n)
+    }
+
+    fn powi(self, n: i32) -> f64 {
+        libm::pow(self, f64::from(n))
+    }
+
+    fn acos(self) -> f64 {
+        libm::acos(self)
+    }
+}
+
+impl Float for f32 {
+    fn floor(self) -> f32 {
+        libm::floorf(self)
+    }
+
+    fn ceil(self) -> f32 {
+        libm::ceilf(self)
+    }
+
+    fn round(self) -> f32 {
+        libm::roundf(self)
+    }
+
+    fn fract(self) -> f32 {
+        self - libm::truncf(self)
+    }
+
+    fn sqrt(self) -> f32 {
+        libm::sqrtf(self)
+    }
+
+    fn powf(self, n: f32) -> f32 {
+        libm::powf(self, n)
+    }
+
+    fn powi(self, n: i32) -> f32 {
+        libm::powf(self, n as f32)
+    }
+
+    fn acos(self) -> f32 {
+        libm::acosf(self)
+    }
+}
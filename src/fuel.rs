@@ -0,0 +1,168 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The time, in seconds, during which each fuel item burns.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct FuelTable<I: Hash + Eq> {
+    /// The burn times mapped by item key.
+    pub burn_times: HashMap<I, f64>,
+}
+
+impl<I: Hash + Eq> Default for FuelTable<I> {
+    fn default() -> Self {
+        Self {
+            burn_times: HashMap::default(),
+        }
+    }
+}
+
+impl<I: Hash + Eq> FuelTable<I> {
+    /// Returns the burn time of the item, or None if it is not a fuel.
+    pub fn burn_time(&self, key: &I) -> Option<f64> {
+        self.burn_times.get(key).copied()
+    }
+}
+
+/// A crafting station that only progresses while burning fuel, like a furnace.
+/// A fuel item is taken from the fuel slot of the inventory when the previous one is burnt
+/// and transitions are queued.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct FuelStation<K> {
+    /// The transitions queued on this station.
+    pub queue: CraftingQueue<K>,
+    /// The index of the inventory slot holding the fuel.
+    pub fuel_slot: usize,
+    /// The remaining burn time of the current fuel item, in seconds.
+    #[new(default)]
+    pub burn_remaining: f64,
+}
+
+impl<K: Hash + Eq + Clone> FuelStation<K> {
+    /// Checks if fuel is currently burning.
+    pub fn is_burning(&self) -> bool {
+        self.burn_remaining > 0.0
+    }
+
+    /// Advances the station by `delta_time` seconds, using the `FuelTable` to know the burn
+    /// time of the fuel items.
+    /// See `FuelStation::update_with`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update<
+        I: Hash + Eq + Clone + Debug,
+        E,
+        S: Hash + Eq + Debug,
+        L: Hash + Eq + Clone,
+        C,
+        IT: SlotType,
+        U: PartialEq + Default + Clone + Debug,
+        LV,
+        S2,
+        I2,
+    >(
+        &mut self,
+        delta_time: f64,
+        fuel_table: &FuelTable<I>,
+        transition_defs: &ItemTransitionDefinitions<K, I, E, S, L, C>,
+        inventory: &mut Inventory<I, IT, U>,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        unlockables: &Unlockables<L, LV, S2, I2>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Vec<CraftingEvent<K, I, U>> {
+        self.update_with(
+            delta_time,
+            |ii| fuel_table.burn_time(&ii.key),
+            transition_defs,
+            inventory,
+            stats,
+            stat_defs,
+            unlockables,
+            clock,
+            scripts,
+        )
+    }
+
+    /// Advances the station by `delta_time` seconds.
+    /// The `CraftingQueue` only progresses while fuel burns, and fuel only burns while
+    /// transitions are queued.
+    /// `burn_time` returns the burn time of an item, or None if it is not a fuel. This can be
+    /// used to read it from the custom data of the items.
+    /// A `clock` of None means that the clock conditions of the transitions are not met, unless
+    /// there are none. See `CraftingQueue::update` for the script conditions.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_with<
+        I: Hash + Eq + Clone + Debug,
+        E,
+        S: Hash + Eq + Debug,
+        L: Hash + Eq + Clone,
+        C,
+        IT: SlotType,
+        U: PartialEq + Default + Clone + Debug,
+        LV,
+        S2,
+        I2,
+        F: Fn(&ItemInstance<I, U>) -> Option<f64>,
+    >(
+        &mut self,
+        delta_time: f64,
+        burn_time: F,
+        transition_defs: &ItemTransitionDefinitions<K, I, E, S, L, C>,
+        inventory: &mut Inventory<I, IT, U>,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        unlockables: &Unlockables<L, LV, S2, I2>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Vec<CraftingEvent<K, I, U>> {
+        let mut events = vec![];
+        let mut time = delta_time;
+        while time > 0.0 {
+            if !self.is_burning()
+                && (self.queue.batches.is_empty() || !self.refuel(&burn_time, inventory))
+            {
+                break;
+            }
+            let burnt = self.burn_remaining.min(time);
+            events.extend(self.queue.update(
+                burnt,
+                transition_defs,
+                inventory,
+                stats,
+                stat_defs,
+                unlockables,
+                clock,
+                scripts,
+            ));
+            self.burn_remaining -= burnt;
+            time -= burnt;
+        }
+        events
+    }
+
+    /// Takes one fuel item from the fuel slot and starts burning it.
+    /// Returns false if the fuel slot holds no fuel.
+    fn refuel<
+        I: Hash + Eq + Clone + Debug,
+        IT: SlotType,
+        U: PartialEq + Default + Clone + Debug,
+        F: Fn(&ItemInstance<I, U>) -> Option<f64>,
+    >(
+        &mut self,
+        burn_time: &F,
+        inventory: &mut Inventory<I, IT, U>,
+    ) -> bool {
+        let duration = match inventory.get(self.fuel_slot).as_ref().and_then(burn_time) {
+            Some(d) if d > 0.0 => d,
+            _ => return false,
+        };
+        match inventory.consume(self.fuel_slot) {
+            Ok(_) | Err(ItemError::StackConsumed(_)) => {
+                self.burn_remaining += duration;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The actions guild members can be allowed to do by their rank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GuildPermission {
+    /// Invite users into the guild.
+    Invite,
+    /// Kick members of lower ranks.
+    Kick,
+    /// Promote and demote members of lower ranks.
+    Promote,
+    /// Change the message of the day.
+    EditMessageOfTheDay,
+    /// Put items into the guild bank.
+    BankDeposit,
+    /// Take items from the guild bank.
+    BankWithdraw,
+}
+
+/// A rank of a `Guild`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct GuildRank {
+    /// The display name of the rank.
+    pub name: String,
+    /// The permissions of the members of this rank.
+    /// See `GuildPermission`.
+    pub permissions: PermissionBits,
+}
+
+impl GuildRank {
+    /// Checks if this rank has the permission.
+    pub fn has(&self, permission: GuildPermission) -> bool {
+        self.permissions.has(permission as u8)
+    }
+}
+
+/// A guild, or clan: a permanent group of users with ranks and a shared bank.
+///
+/// # Generics
+/// - K: Item Key
+/// - S: Item Slot Type
+/// - U: Item Custom Data
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Guild<K, S: SlotType, U: Default> {
+    /// The name of the guild.
+    pub name: String,
+    /// The members of the guild.
+    pub group: UserGroup,
+    /// The ranks of the guild, from the highest to the lowest.
+    /// The first rank is the rank of the leader and has all the permissions.
+    /// New members get the last rank.
+    pub ranks: Vec<GuildRank>,
+    /// The index of the rank of each member.
+    #[new(default)]
+    pub member_ranks: HashMap<i32, usize>,
+    /// The users invited to join the guild.
+    #[new(default)]
+    pub invitations: Vec<i32>,
+    /// The message shown to members when they connect.
+    #[new(default)]
+    pub message_of_the_day: String,
+    /// The items shared by the members.
+    pub bank: Inventory<K, S, U>,
+}
+
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType,
+        U: Default + Clone + Debug + PartialEq,
+    > Guild<K, S, U>
+{
+    /// Creates a new guild led by the user.
+    /// `ranks` must contain at least one rank.
+    pub fn with_leader(
+        id: i32,
+        name: String,
+        leader: i32,
+        ranks: Vec<GuildRank>,
+        bank: Inventory<K, S, U>,
+    ) -> Self {
+        assert!(!ranks.is_empty(), "A guild must have at least one rank.");
+        let mut guild = Guild::new(name, UserGroup::new(id, vec![leader]), ranks, bank);
+        guild.member_ranks.insert(leader, 0);
+        guild
+    }
+
+    /// Returns the index of the rank of the user, if they are a member.
+    pub fn rank_of(&self, user: i32) -> Option<usize> {
+        self.member_ranks.get(&user).copied()
+    }
+
+    /// Checks if the user is a member and has the permission.
+    /// The leader has all the permissions.
+    pub fn has_permission(&self, user: i32, permission: GuildPermission) -> bool {
+        match self.rank_of(user) {
+            Some(0) => true,
+            Some(rank) => self.ranks[rank].has(permission),
+            None => false,
+        }
+    }
+
+    /// Invites the user to join the guild.
+    ///
+    /// Errors:
+    /// * MissingPermission: The inviting user can't invite.
+    /// * AlreadyMember: The invited user is already a member.
+    pub fn invite(&mut self, by: i32, user: i32) -> Result<(), GuildError<K, U>> {
+        self.check_permission(by, GuildPermission::Invite)?;
+        if self.member_ranks.contains_key(&user) {
+            return Err(GuildError::AlreadyMember);
+        }
+        if !self.invitations.contains(&user) {
+            self.invitations.push(user);
+        }
+        Ok(())
+    }
+
+    /// Accepts the invitation of the user, who becomes a member with the lowest rank.
+    ///
+    /// Errors:
+    /// * NotInvited: The user was not invited.
+    /// * GuildFull: The guild has the maximum number of members.
+    pub fn accept_invitation(
+        &mut self,
+        user: i32,
+        settings: &UserGroupSettings,
+    ) -> Result<(), GuildError<K, U>> {
+        if !self.invitations.contains(&user) {
+            return Err(GuildError::NotInvited);
+        }
+        if self.group.users.len() as i32 >= settings.maximum_users {
+            return Err(GuildError::GuildFull);
+        }
+        self.invitations.retain(|u| *u != user);
+        self.group.users.push(user);
+        self.member_ranks.insert(user, self.ranks.len() - 1);
+        Ok(())
+    }
+
+    /// Removes the user from the guild.
+    /// If the user was the leader, the member with the highest rank becomes the leader.
+    ///
+    /// Errors:
+    /// * NotMember: The user is not a member.
+    pub fn leave(&mut self, user: i32) -> Result<(), GuildError<K, U>> {
+        let rank = self
+            .member_ranks
+            .remove(&user)
+            .ok_or(GuildError::NotMember)?;
+        self.group.users.retain(|u| *u != user);
+        if rank == 0 {
+            let next = self
+                .group
+                .users
+                .iter()
+                .min_by_key(|u| self.member_ranks[*u])
+                .copied();
+            if let Some(next) = next {
+                self.member_ranks.insert(next, 0);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes a member of a lower rank from the guild.
+    ///
+    /// Errors:
+    /// * MissingPermission: The kicking user can't kick or the kicked user doesn't have a lower
+    ///   rank.
+    /// * NotMember: The kicked user is not a member.
+    pub fn kick(&mut self, by: i32, user: i32) -> Result<(), GuildError<K, U>> {
+        self.check_permission(by, GuildPermission::Kick)?;
+        self.check_outranks(by, user)?;
+        self.leave(user)
+    }
+
+    /// Moves a member of a lower rank one rank up, up to the rank just below the promoting
+    /// user.
+    /// Returns the new rank.
+    ///
+    /// Errors:
+    /// * MissingPermission: The promoting user can't promote or the promoted user would reach
+    ///   their rank.
+    /// * NotMember: The promoted user is not a member.
+    pub fn promote(&mut self, by: i32, user: i32) -> Result<usize, GuildError<K, U>> {
+        self.check_permission(by, GuildPermission::Promote)?;
+        self.check_outranks(by, user)?;
+        let rank = self.member_ranks[&user] - 1;
+        if rank <= self.member_ranks[&by] {
+            return Err(GuildError::MissingPermission);
+        }
+        self.member_ranks.insert(user, rank);
+        Ok(rank)
+    }
+
+    /// Moves a member of a lower rank one rank down, down to the lowest rank.
+    /// Returns the new rank.
+    ///
+    /// Errors:
+    /// * MissingPermission: The demoting user can't promote or the demoted user doesn't have a
+    ///   lower rank.
+    /// * NotMember: The demoted user is not a member.
+    pub fn demote(&mut self, by: i32, user: i32) -> Result<usize, GuildError<K, U>> {
+        self.check_permission(by, GuildPermission::Promote)?;
+        self.check_outranks(by, user)?;
+        let rank = (self.member_ranks[&user] + 1).min(self.ranks.len() - 1);
+        self.member_ranks.insert(user, rank);
+        Ok(rank)
+    }
+
+    /// Makes another member the leader. The previous leader gets the second rank, if any.
+    ///
+    /// Errors:
+    /// * MissingPermission: The user is not the leader.
+    /// * NotMember: The new leader is not a member.
+    pub fn transfer_leadership(&mut self, by: i32, to: i32) -> Result<(), GuildError<K, U>> {
+        if self.rank_of(by) != Some(0) {
+            return Err(GuildError::MissingPermission);
+        }
+        if !self.member_ranks.contains_key(&to) {
+            return Err(GuildError::NotMember);
+        }
+        self.member_ranks.insert(by, 1.min(self.ranks.len() - 1));
+        self.member_ranks.insert(to, 0);
+        Ok(())
+    }
+
+    /// Changes the message of the day.
+    ///
+    /// Errors:
+    /// * MissingPermission: The user can't edit the message of the day.
+    pub fn set_message_of_the_day(
+        &mut self,
+        by: i32,
+        message: String,
+    ) -> Result<(), GuildError<K, U>> {
+        self.check_permission(by, GuildPermission::EditMessageOfTheDay)?;
+        self.message_of_the_day = message;
+        Ok(())
+    }
+
+    /// Puts items into the guild bank.
+    ///
+    /// Errors:
+    /// * MissingPermission: The user can't deposit.
+    /// * Bank: The items don't fit in the bank.
+    pub fn deposit<D: Default>(
+        &mut self,
+        by: i32,
+        item: ItemInstance<K, U>,
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Result<(), GuildError<K, U>> {
+        self.check_permission(by, GuildPermission::BankDeposit)?;
+        self.bank.insert(item, item_defs).map_err(GuildError::Bank)
+    }
+
+    /// Takes items from the guild bank.
+    ///
+    /// Errors:
+    /// * MissingPermission: The user can't withdraw.
+    /// * Bank: The items are not in the bank.
+    pub fn withdraw(
+        &mut self,
+        by: i32,
+        idx: usize,
+        quantity: usize,
+    ) -> Result<ItemInstance<K, U>, GuildError<K, U>> {
+        self.check_permission(by, GuildPermission::BankWithdraw)?;
+        self.bank.delete(idx, quantity).map_err(GuildError::Bank)
+    }
+
+    fn check_permission(
+        &self,
+        user: i32,
+        permission: GuildPermission,
+    ) -> Result<(), GuildError<K, U>> {
+        if self.has_permission(user, permission) {
+            Ok(())
+        } else {
+            Err(GuildError::MissingPermission)
+        }
+    }
+
+    /// Checks that `by` has a higher rank than `user`.
+    fn check_outranks(&self, by: i32, user: i32) -> Result<(), GuildError<K, U>> {
+        let target = self.rank_of(user).ok_or(GuildError::NotMember)?;
+        match self.rank_of(by) {
+            Some(rank) if rank < target => Ok(()),
+            _ => Err(GuildError::MissingPermission),
+        }
+    }
+}
+
+/// The errors that can happen when managing a `Guild`.
+#[derive(Debug)]
+pub enum GuildError<K: PartialEq + Debug, U: Default> {
+    /// The user doesn't have the permission or rank required for this action.
+    MissingPermission,
+    /// The user is already a member.
+    AlreadyMember,
+    /// The user is not a member.
+    NotMember,
+    /// The user was not invited.
+    NotInvited,
+    /// The guild has the maximum number of members.
+    GuildFull,
+    /// The guild bank refused the operation.
+    Bank(ItemError<K, U>),
+}
@@ -1,53 +1,425 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+use partial_function::PartialFunction;
 
+/// The way a weapon fires when the trigger is held.
 pub enum WeaponMode {
+    /// Each shot requires a manual action, like cycling a bolt.
     Manual,
+    /// One shot per trigger pull.
     Semi,
-    Burst{burst_count: u32, burst_fire_per_second: f64},
+    /// Multiple shots per trigger pull.
+    Burst {
+        /// The number of shots in a burst.
+        burst_count: u32,
+        /// The number of shots per second during a burst.
+        burst_fire_per_second: f64,
+    },
+    /// Fires continuously while the trigger is held.
     Auto,
 }
 
+/// The definition of a weapon.
 pub struct WeaponDefinition<K> {
+    /// The key of the weapon.
     pub key: K,
+    /// The way the weapon fires.
     pub weapon_mode: WeaponMode,
+    /// The number of shots per second.
     /// In case of burst, the number of times per second you can start a new burst sequence.
     pub fire_per_second: f64,
+    /// The number of shots before having to reload.
     pub clip_size: u32,
+    /// The ammo used by each shot.
     pub ammo_consume_per_shot: u32,
+    /// The types of ammo that can be loaded into the weapon.
+    pub ammo_types: Vec<AmmoType<K>>,
+    /// The slots in which attachments can be installed, like "scope" or "barrel".
+    pub attachment_slots: Vec<String>,
+    /// The multiplier applied to the fire rate depending on the number of consecutive shots.
     pub fire_speed_multiplier_ramp: PartialFunction<u32, f64>,
+    /// The time it takes to reload.
     pub reload_time: f64,
+    /// The number of projectiles fired by each shot.
     pub projectile_count: u32,
+    /// How the weapon moves when firing.
     pub recoil_pattern: RecoilPattern,
+    /// How the projectiles deviate from where the weapon is aimed.
     pub spread: SpreadPattern,
+    /// How fast the spread goes back to its initial value.
     pub spread_reduction_per_second: f64,
+    /// The fraction of damage (between 0.0 and 1.0) lost each time a projectile goes through
+    /// something.
     pub bullet_penetration_damage_loss_percent: f64,
+    /// The damage of a projectile depending on the distance it travelled.
     pub distance_damage_curve: PartialFunction<f64, f64>,
+    /// Whether the crosshair is shown when using this weapon.
     pub show_crosshair: bool,
 }
 
-impl WeaponDefinition<K> {
-
+impl<K> WeaponDefinition<K> {
+    /// The damage of a projectile at point blank.
     pub fn base_damage(&self) -> f64 {
-        self.distance_damage_curve.evaluate(0.0).unwrap()
+        self.damage_at(0.0)
+    }
+
+    /// The damage of a projectile after travelling the specified distance.
+    /// Returns 0 if the distance is outside of the damage curve.
+    pub fn damage_at(&self, distance: f64) -> f64 {
+        self.distance_damage_curve.eval(distance).unwrap_or(0.0)
+    }
+
+    /// The multiplier applied to the base damage after travelling the specified distance.
+    pub fn distance_damage_multiplier(&self, distance: f64) -> f64 {
+        let base = self.base_damage();
+        if base == 0.0 {
+            0.0
+        } else {
+            self.damage_at(distance) / base
+        }
+    }
+
+    /// The multiplier applied to the damage after going through `penetrated` objects.
+    pub fn penetration_damage_multiplier(&self, penetrated: u32) -> f64 {
+        (1.0 - self.bullet_penetration_damage_loss_percent)
+            .max(0.0)
+            .powi(penetrated as i32)
     }
+
+    /// The quantity of ammo held by a full clip.
+    pub fn clip_capacity(&self) -> u32 {
+        self.clip_size * self.ammo_consume_per_shot
+    }
+}
+
+impl<K: PartialEq> WeaponDefinition<K> {
+    /// Returns the ammo type using this item key, if the weapon can load it.
+    pub fn ammo_type(&self, ammo_key: &K) -> Option<&AmmoType<K>> {
+        self.ammo_types.iter().find(|a| a.key == *ammo_key)
+    }
+}
+
+/// A type of ammo that can be loaded into a weapon.
+pub struct AmmoType<K> {
+    /// The item key of the ammo.
+    pub key: K,
+    /// The multiplier applied to the damage of the weapon when using this ammo.
+    pub damage_multiplier: f64,
 }
 
+/// The state of a weapon.
 pub struct WeaponInstance<K> {
+    /// The key of the weapon definition.
     pub key: K,
+    /// The time at which the last shot was fired.
     pub last_shot_time: f64,
+    /// Whether the weapon is being reloaded.
     pub reloading: bool,
+    /// Whether a burst is in progress.
     pub burst_firing: bool,
+    /// The number of shots left in the current burst.
     pub burst_shots_left: u32,
+    /// The current spread.
     pub spread_value: f64,
+    /// The item key of the ammo in the clip.
+    pub loaded_ammo: Option<K>,
+    /// The quantity of ammo in the clip.
+    pub ammo_in_clip: u32,
+    /// The item keys of the installed attachments.
+    pub attachments: Vec<K>,
+}
+
+impl<K: PartialEq + Clone + Debug + Hash + Eq> WeaponInstance<K> {
+    /// The number of shots that can be fired before the clip is empty.
+    pub fn shots_left(&self, weapon: &WeaponDefinition<K>) -> u32 {
+        self.ammo_in_clip / weapon.ammo_consume_per_shot.max(1)
+    }
+
+    /// The damage multiplier of the loaded ammo. 1.0 if no ammo is loaded.
+    pub fn ammo_damage_multiplier(&self, weapon: &WeaponDefinition<K>) -> f64 {
+        self.loaded_ammo
+            .as_ref()
+            .and_then(|a| weapon.ammo_type(a))
+            .map(|a| a.damage_multiplier)
+            .unwrap_or(1.0)
+    }
+
+    /// Removes the ammo used by one shot from the clip and returns the number of shots left.
+    ///
+    /// Errors:
+    /// * EmptyClip: The clip doesn't hold enough ammo for a shot.
+    pub fn consume_shot(&mut self, weapon: &WeaponDefinition<K>) -> Result<u32, WeaponError> {
+        if self.ammo_in_clip < weapon.ammo_consume_per_shot {
+            return Err(WeaponError::EmptyClip);
+        }
+        self.ammo_in_clip -= weapon.ammo_consume_per_shot;
+        Ok(self.shots_left(weapon))
+    }
+
+    /// Fills the clip with ammo taken from the inventory and returns the quantity loaded.
+    /// If there isn't enough ammo to fill the clip, all the available ammo is loaded.
+    /// When loading a different type of ammo, the ammo left in the clip is put back into the
+    /// inventory first.
+    /// The size of the clip includes the modifiers of the attachments.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * IncompatibleAmmo: The weapon can't load this ammo.
+    /// * ClipFull: The clip is already full of this ammo.
+    /// * OutOfAmmo: The inventory doesn't contain this ammo.
+    /// * InventoryFull: The unloaded ammo doesn't fit in the inventory.
+    pub fn reload_from<S: SlotType + Clone, U: Default + Clone + Debug + PartialEq, D: Default>(
+        &mut self,
+        weapon: &WeaponDefinition<K>,
+        attachment_defs: &WeaponAttachments<K>,
+        inventory: &mut Inventory<K, S, U>,
+        ammo_key: &K,
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Result<u32, WeaponError> {
+        if weapon.ammo_type(ammo_key).is_none() {
+            return Err(WeaponError::IncompatibleAmmo);
+        }
+        let mut new_inventory = inventory.clone();
+        let mut in_clip = self.ammo_in_clip;
+        if let Some(loaded) = self.loaded_ammo.as_ref() {
+            if loaded != ammo_key && in_clip > 0 {
+                new_inventory
+                    .insert(
+                        ItemInstance::new(loaded.clone(), in_clip as usize),
+                        item_defs,
+                    )
+                    .map_err(|_| WeaponError::InventoryFull)?;
+                in_clip = 0;
+            }
+        }
+        let capacity =
+            self.effective_stats(weapon, attachment_defs).clip_size * weapon.ammo_consume_per_shot;
+        let needed = capacity.saturating_sub(in_clip);
+        if needed == 0 {
+            return Err(WeaponError::ClipFull);
+        }
+        let available = new_inventory
+            .get_key(ammo_key)
+            .map(|ii| ii.quantity)
+            .sum::<usize>();
+        if available == 0 {
+            return Err(WeaponError::OutOfAmmo);
+        }
+        let loaded = (needed as usize).min(available);
+        new_inventory
+            .delete_key(ammo_key, loaded)
+            .expect("Failed to remove the ammo from the inventory. This is a bug.");
+        *inventory = new_inventory;
+        self.loaded_ammo = Some(ammo_key.clone());
+        self.ammo_in_clip = in_clip + loaded as u32;
+        Ok(loaded as u32)
+    }
+
+    /// Installs the attachment into its slot. Returns the attachment that was previously
+    /// installed in the same slot, if any.
+    ///
+    /// Errors:
+    /// * NoAttachmentSlot: The weapon doesn't have the slot of the attachment.
+    pub fn attach(
+        &mut self,
+        weapon: &WeaponDefinition<K>,
+        key: &K,
+        attachment_defs: &WeaponAttachments<K>,
+    ) -> Result<Option<K>, WeaponError> {
+        let slot = &attachment_defs.get(key).slot;
+        if !weapon.attachment_slots.contains(slot) {
+            return Err(WeaponError::NoAttachmentSlot);
+        }
+        let previous = self.detach(slot, attachment_defs);
+        self.attachments.push(key.clone());
+        Ok(previous)
+    }
+
+    /// Removes the attachment installed in the slot and returns it.
+    pub fn detach(&mut self, slot: &str, attachment_defs: &WeaponAttachments<K>) -> Option<K> {
+        let idx = self
+            .attachments
+            .iter()
+            .position(|a| attachment_defs.get(a).slot == slot)?;
+        Some(self.attachments.remove(idx))
+    }
+
+    /// Calculates the parameters of the weapon modified by the installed attachments.
+    /// Like with effectors, additive values are applied first, then the multipliers.
+    pub fn effective_stats(
+        &self,
+        weapon: &WeaponDefinition<K>,
+        attachment_defs: &WeaponAttachments<K>,
+    ) -> EffectiveWeaponStats {
+        let modify = |stat: WeaponStat, base: f64| {
+            let mut additive = 0.0;
+            let mut additive_multiplier = 0.0;
+            let mut multiplicative_multiplier = 1.0;
+            for a in self.attachments.iter() {
+                for (s, ty) in attachment_defs.get(a).modifiers.iter() {
+                    if *s == stat {
+                        match ty {
+                            EffectorType::Additive(v) => additive += v,
+                            EffectorType::AdditiveMultiplier(v) => additive_multiplier += v,
+                            EffectorType::MultiplicativeMultiplier(v) => {
+                                multiplicative_multiplier *= v
+                            }
+                            EffectorType::AdditivePerSecond(_) | EffectorType::Absorb(_) => {}
+                        }
+                    }
+                }
+            }
+            ((base + additive) * (multiplicative_multiplier + additive_multiplier)).max(0.0)
+        };
+        EffectiveWeaponStats {
+            fire_per_second: modify(WeaponStat::FirePerSecond, weapon.fire_per_second),
+            clip_size: modify(WeaponStat::ClipSize, f64::from(weapon.clip_size)).round() as u32,
+            reload_time: modify(WeaponStat::ReloadTime, weapon.reload_time),
+            projectile_count: modify(
+                WeaponStat::ProjectileCount,
+                f64::from(weapon.projectile_count),
+            )
+            .round() as u32,
+            spread_multiplier: modify(WeaponStat::Spread, 1.0),
+            spread_reduction_per_second: modify(
+                WeaponStat::SpreadReductionPerSecond,
+                weapon.spread_reduction_per_second,
+            ),
+            damage_multiplier: modify(WeaponStat::Damage, 1.0),
+        }
+    }
+}
+
+/// A parameter of a weapon that can be modified by a `WeaponAttachment`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponStat {
+    /// The number of shots per second.
+    FirePerSecond,
+    /// The number of shots before having to reload.
+    ClipSize,
+    /// The time it takes to reload.
+    ReloadTime,
+    /// The number of projectiles fired by each shot.
+    ProjectileCount,
+    /// The spread of the projectiles. Starts at 1.0 and multiplies the spread of the weapon.
+    Spread,
+    /// How fast the spread goes back to its initial value.
+    SpreadReductionPerSecond,
+    /// The damage of the projectiles. Starts at 1.0 and multiplies the damage of the weapon.
+    Damage,
+}
+
+/// An attachment that can be installed on a weapon, like a scope, a barrel or a magazine.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct WeaponAttachment<K> {
+    /// The item key of the attachment.
+    pub key: K,
+    /// The slot of the weapon the attachment is installed in.
+    /// See `WeaponDefinition::attachment_slots`.
+    pub slot: String,
+    /// The modifications applied to the parameters of the weapon, like
+    /// `(Spread, AdditiveMultiplier(-0.2))` or `(ClipSize, Additive(5.0))`.
+    /// `EffectorType::AdditivePerSecond` and `EffectorType::Absorb` are ignored.
+    pub modifiers: Vec<(WeaponStat, EffectorType)>,
+}
+
+/// The definitions of all known weapon attachments.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct WeaponAttachments<K: Hash + Eq> {
+    /// The definitions.
+    pub defs: HashMap<K, WeaponAttachment<K>>,
+}
+
+impl<K: Hash + Eq> Default for WeaponAttachments<K> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> From<Vec<WeaponAttachment<K>>> for WeaponAttachments<K> {
+    fn from(t: Vec<WeaponAttachment<K>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<K: Hash + Eq> WeaponAttachments<K> {
+    fn get(&self, key: &K) -> &WeaponAttachment<K> {
+        self.defs
+            .get(key)
+            .expect("Tried to get unknown weapon attachment key.")
+    }
+}
+
+/// The parameters of a weapon after applying the modifiers of its attachments.
+/// See `WeaponInstance::effective_stats`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectiveWeaponStats {
+    /// The number of shots per second.
+    pub fire_per_second: f64,
+    /// The number of shots before having to reload.
+    pub clip_size: u32,
+    /// The time it takes to reload.
+    pub reload_time: f64,
+    /// The number of projectiles fired by each shot.
+    pub projectile_count: u32,
+    /// The multiplier applied to the spread of the weapon.
+    pub spread_multiplier: f64,
+    /// How fast the spread goes back to its initial value.
+    pub spread_reduction_per_second: f64,
+    /// The multiplier applied to the damage of the weapon.
+    pub damage_multiplier: f64,
 }
 
+/// The errors that can happen when using a `WeaponInstance`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeaponError {
+    /// The clip doesn't hold enough ammo to fire.
+    EmptyClip,
+    /// The clip is already full.
+    ClipFull,
+    /// The inventory doesn't contain the ammo.
+    OutOfAmmo,
+    /// The weapon can't load this type of ammo.
+    IncompatibleAmmo,
+    /// The unloaded ammo doesn't fit in the inventory.
+    InventoryFull,
+    /// The weapon doesn't have the slot of the attachment.
+    NoAttachmentSlot,
+}
+
+/// How a weapon moves when firing.
 pub enum RecoilPattern {
-    Random{hangle: f64, vangle: f64},
-    Fixed{points: Vec<(f64, f64)>},
+    /// The weapon moves randomly up to these angles.
+    Random {
+        /// The maximum horizontal angle.
+        hangle: f64,
+        /// The maximum vertical angle.
+        vangle: f64,
+    },
+    /// The weapon follows this sequence of horizontal and vertical angles.
+    Fixed {
+        /// The angles of each consecutive shot.
+        points: Vec<(f64, f64)>,
+    },
 }
 
+/// How projectiles deviate from where the weapon is aimed.
 pub enum SpreadPattern {
-    Constant{max_angle: f64},
+    /// The spread doesn't change.
+    Constant {
+        /// The maximum angle of deviation.
+        max_angle: f64,
+    },
     /// Current spread -> new spread
-    Ramped{func: PartialFunction<f64, f64>},
+    Ramped {
+        /// Calculates the new spread from the current spread after each shot.
+        func: PartialFunction<f64, f64>,
+    },
 }
-
@@ -0,0 +1,71 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// An armor piece protecting some hit zones when it is equipped.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ArmorCoverage<I, Z> {
+    /// The item key of the armor piece.
+    pub item: I,
+    /// The hit zones protected by the armor piece.
+    pub zones: Vec<Z>,
+    /// The flat amount of damage removed from hits on the protected zones.
+    pub armor: f64,
+}
+
+/// The damage multipliers of the parts of a body, like the head or the limbs, and the armor
+/// pieces protecting them.
+///
+/// # Generics
+/// - Z: Hit Zone Key
+/// - W: Weapon Key
+/// - I: Item Key
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct HitZoneTable<Z: Hash + Eq, W: Hash + Eq, I> {
+    /// The damage multiplier of each zone, like 2.0 for the head or 0.7 for the limbs.
+    /// Zones without a multiplier use 1.0.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub multipliers: HashMap<Z, f64>,
+    /// The damage multipliers used instead of the default ones for specific weapons.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub weapon_overrides: HashMap<W, HashMap<Z, f64>>,
+    /// The zones protected by each armor piece.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub coverage: Vec<ArmorCoverage<I, Z>>,
+}
+
+impl<Z: Hash + Eq, W: Hash + Eq, I> HitZoneTable<Z, W, I> {
+    /// Returns the damage multiplier of the zone when hit by the weapon.
+    /// The override of the weapon is used if it exists.
+    pub fn multiplier(&self, zone: &Z, weapon: Option<&W>) -> f64 {
+        weapon
+            .and_then(|w| self.weapon_overrides.get(w))
+            .and_then(|o| o.get(zone))
+            .or_else(|| self.multipliers.get(zone))
+            .cloned()
+            .unwrap_or(1.0)
+    }
+
+    /// Returns the sum of the armor of the equipped pieces protecting the zone.
+    pub fn zone_armor<S: SlotType, U: Default + Clone + Debug + PartialEq>(
+        &self,
+        zone: &Z,
+        equipment: &Inventory<I, S, U>,
+    ) -> f64
+    where
+        I: PartialEq + Clone + Debug + Hash + Eq,
+    {
+        self.coverage
+            .iter()
+            .filter(|c| c.zones.contains(zone) && equipment.has(&c.item))
+            .map(|c| c.armor)
+            .sum()
+    }
+}
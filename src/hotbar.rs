@@ -0,0 +1,145 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// What happens when a slot of a `Hotbar` is activated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HotbarAction<I, S> {
+    /// Uses the durability of the item in this inventory slot.
+    /// See `Inventory::use_item`.
+    UseSlot(usize),
+    /// Consumes one item from this inventory slot.
+    /// See `Inventory::consume`.
+    ConsumeSlot(usize),
+    /// Uses the durability of the first item with this key.
+    UseItem(I),
+    /// Consumes one item of the first stack with this key.
+    ConsumeItem(I),
+    /// Activates the skill.
+    /// See `SkillProcessor::try_activate`.
+    Skill(S),
+}
+
+/// An action bar whose slots reference items and skills.
+/// The number of slots is fixed when creating the hotbar.
+///
+/// # Generics
+/// - I: Item Key
+/// - S: Skill Key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hotbar<I, S> {
+    /// The slots. None values indicate empty slots.
+    pub slots: Vec<Option<HotbarAction<I, S>>>,
+}
+
+impl<I, S> Hotbar<I, S> {
+    /// Creates a hotbar with this number of empty slots.
+    pub fn new(size: usize) -> Self {
+        let mut slots = Vec::with_capacity(size);
+        (0..size).for_each(|_| slots.push(None));
+        Self { slots }
+    }
+
+    /// Returns the action of the slot, if any.
+    pub fn get(&self, slot: usize) -> Option<&HotbarAction<I, S>> {
+        self.slots.get(slot).and_then(|s| s.as_ref())
+    }
+
+    /// Assigns an action to the slot and returns the previous one.
+    /// Panics if the slot doesn't exist.
+    pub fn assign(
+        &mut self,
+        slot: usize,
+        action: HotbarAction<I, S>,
+    ) -> Option<HotbarAction<I, S>> {
+        self.slots[slot].replace(action)
+    }
+
+    /// Removes the action of the slot and returns it.
+    /// Panics if the slot doesn't exist.
+    pub fn clear(&mut self, slot: usize) -> Option<HotbarAction<I, S>> {
+        self.slots[slot].take()
+    }
+
+    /// Swaps the actions of two slots.
+    /// Panics if one of the slots doesn't exist.
+    pub fn swap(&mut self, first: usize, second: usize) {
+        self.slots.swap(first, second);
+    }
+}
+
+impl<I: Hash + Eq + Clone + Debug, S: Hash + Eq> Hotbar<I, S> {
+    /// Activates the action of the slot.
+    /// Returns the remaining durability or quantity of the item used, or the ranks gained by the
+    /// skill.
+    ///
+    /// Errors:
+    /// * EmptySlot: The hotbar slot doesn't exist or has no action.
+    /// * Item: See `Inventory::use_item` and `Inventory::consume`. `SlotEmpty` is returned when
+    ///   no item has the key.
+    /// * Skill: See `SkillProcessor::try_activate`.
+    pub fn activate<
+        K: Hash + Eq + Debug,
+        E: Hash + Eq + Clone,
+        IT: SlotType + Clone,
+        U: Default + Clone + Debug + PartialEq,
+    >(
+        &self,
+        slot: usize,
+        processor: &SkillProcessor<K, E, S, I>,
+        skills: &mut SkillSet<S>,
+        stats: &StatSet<K>,
+        inventory: &mut Inventory<I, IT, U>,
+        effectors: &mut EffectorSet<E>,
+    ) -> Result<HotbarOutcome, HotbarError<I, U>> {
+        let action = self.get(slot).ok_or(HotbarError::EmptySlot)?;
+        let first_of = |inventory: &Inventory<I, IT, U>, key: &I| {
+            inventory
+                .iter_slots()
+                .find(|(_, ii)| ii.as_ref().map(|ii| ii.key == *key).unwrap_or(false))
+                .map(|(idx, _)| idx)
+                .ok_or(HotbarError::Item(ItemError::SlotEmpty))
+        };
+        let remaining = match action {
+            HotbarAction::UseSlot(idx) => inventory.use_item(*idx),
+            HotbarAction::ConsumeSlot(idx) => inventory.consume(*idx).map(Some),
+            HotbarAction::UseItem(key) => {
+                let idx = first_of(inventory, key)?;
+                inventory.use_item(idx)
+            }
+            HotbarAction::ConsumeItem(key) => {
+                let idx = first_of(inventory, key)?;
+                inventory.consume(idx).map(Some)
+            }
+            HotbarAction::Skill(key) => {
+                return processor
+                    .try_activate(key, skills, stats, inventory, effectors)
+                    .map(HotbarOutcome::Skill)
+                    .map_err(HotbarError::Skill)
+            }
+        };
+        remaining
+            .map(HotbarOutcome::Item)
+            .map_err(HotbarError::Item)
+    }
+}
+
+/// The result of activating a `Hotbar` slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotbarOutcome {
+    /// The item was used. Contains its remaining durability or quantity, if any.
+    Item(Option<usize>),
+    /// The skill was activated and gained those ranks.
+    Skill(Vec<LevelUpEvent>),
+}
+
+/// The errors that can happen when activating a `Hotbar` slot.
+#[derive(Debug)]
+pub enum HotbarError<I: PartialEq + Debug, U: Default> {
+    /// The hotbar slot doesn't exist or has no action.
+    EmptySlot,
+    /// Using the item failed.
+    Item(ItemError<I, U>),
+    /// Activating the skill failed.
+    Skill(SkillError),
+}
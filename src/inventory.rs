@@ -1,6 +1,8 @@
 use crate::*;
-use std::fmt::Debug;
-use std::hash::Hash;
+use alloc::collections::BTreeSet;
+use core::cmp::Ordering;
+use core::fmt::Debug;
+use core::hash::Hash;
 
 /// The way the inventory size is handled.
 #[derive(new, Clone, Serialize, Deserialize, Debug)]
@@ -42,6 +44,7 @@ pub enum MoveToFrontMode {
 /// - S: Type of inventory location
 /// - U: Custom item data
 #[derive(new, Clone, Serialize, Deserialize, Debug, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
 pub struct Inventory<K, S: SlotType, U: Default> {
     /// The contents of the `Inventory`.
     /// None values indicate empty but existing inventory slots.
@@ -57,6 +60,30 @@ pub struct Inventory<K, S: SlotType, U: Default> {
     pub move_to_front: MoveToFrontMode,
     /// Configures if the inventory resizes when item are inserted/removed or not.
     pub sizing_mode: InventorySizingMode,
+    /// Whether the changes made to the `Inventory` are recorded as `InventoryEvent`s.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub record_events: bool,
+    /// The recorded events that were not drained yet.
+    /// See `Inventory::drain_events`.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(skip, default = "Vec::new")]
+    pub events: Vec<InventoryEvent<K>>,
+    /// The history of the operations run through `Inventory::journaled`, used to undo and
+    /// redo them.
+    /// None means that the history is not recorded.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Option::default")]
+    pub journal: Option<InventoryJournal<K, U>>,
+    /// The slots that changed since the last call to `Inventory::take_changes`.
+    /// Changes made by modifying `content` directly are not tracked.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(skip)]
+    pub changed_slots: BTreeSet<usize>,
 }
 
 impl<
@@ -76,6 +103,10 @@ impl<
             slot_restriction,
             move_to_front: MoveToFrontMode::None,
             sizing_mode: InventorySizingMode::new_fixed(count),
+            record_events: false,
+            events: vec![],
+            journal: None,
+            changed_slots: BTreeSet::new(),
         }
     }
 
@@ -89,6 +120,10 @@ impl<
             slot_restriction: vec![],
             move_to_front: MoveToFrontMode::None,
             sizing_mode: InventorySizingMode::new_dynamic(minimum, maximum),
+            record_events: false,
+            events: vec![],
+            journal: None,
+            changed_slots: BTreeSet::new(),
         }
     }
 
@@ -102,9 +137,16 @@ impl<
             if ii.durability.is_some() {
                 if ii.durability.unwrap() == 0 {
                     //rm item
-                    Err(ItemError::ItemDestroyed(self.delete_stack(idx)?))
+                    let quantity = ii.quantity;
+                    let destroyed = self.take(idx, quantity)?;
+                    self.push_event(InventoryEvent::Destroyed {
+                        slot: idx,
+                        key: destroyed.key.clone(),
+                    });
+                    Err(ItemError::ItemDestroyed(destroyed))
                 } else {
                     *ii.durability.as_mut().unwrap() -= 1;
+                    self.changed_slots.insert(idx);
                     Ok(Some(ii.durability.unwrap()))
                 }
             } else {
@@ -115,21 +157,141 @@ impl<
         }
     }
 
+    /// Increases the durability of the item at the specified index by `amount`, without going
+    /// over the maximum durability of its `ItemDefinition`.
+    /// Returns the new durability, or None if the item is unbreakable.
+    ///
+    /// Errors:
+    /// * SlotEmpty: There is no item at this index.
+    pub fn repair<S2, D: Default>(
+        &mut self,
+        idx: usize,
+        amount: usize,
+        item_defs: &ItemDefinitions<K, S2, D>,
+    ) -> Result<Option<usize>, ItemError<K, U>> {
+        if let Some(Some(ii)) = self.content.get_mut(idx) {
+            let max = item_defs
+                .defs
+                .get(&ii.key)
+                .expect("Tried to get unknown item key.")
+                .maximum_durability;
+            if let (Some(durability), Some(max)) = (ii.durability.as_mut(), max) {
+                let repaired = (*durability + amount).min(max);
+                if repaired != *durability {
+                    *durability = repaired;
+                    self.changed_slots.insert(idx);
+                }
+            }
+            Ok(ii.durability)
+        } else {
+            Err(ItemError::SlotEmpty)
+        }
+    }
+
+    /// Sets the durability of all the items to the maximum durability of their
+    /// `ItemDefinition`.
+    pub fn repair_all<S2, D: Default>(&mut self, item_defs: &ItemDefinitions<K, S2, D>) {
+        for (idx, ii) in self.content.iter_mut().enumerate() {
+            if let Some(ii) = ii.as_mut().filter(|ii| ii.durability.is_some()) {
+                let durability = item_defs
+                    .defs
+                    .get(&ii.key)
+                    .expect("Tried to get unknown item key.")
+                    .maximum_durability
+                    .or(ii.durability);
+                if durability != ii.durability {
+                    ii.durability = durability;
+                    self.changed_slots.insert(idx);
+                }
+            }
+        }
+    }
+
+    /// Destroys one item of the stack at the specified index and returns the materials rolled
+    /// from the salvage table: the guaranteed drops and one random drop.
+    ///
+    /// Errors:
+    /// * SlotEmpty: There is no item at this index.
+    #[cfg(feature = "std")]
+    pub fn salvage<U2: Default>(
+        &mut self,
+        idx: usize,
+        salvage_table: &LootTree<ItemDrop<K>>,
+    ) -> Result<Vec<ItemInstance<K, U2>>, ItemError<K, U>> {
+        self.salvage_with(idx, salvage_table, &mut rand::thread_rng())
+    }
+
+    /// Same as `Inventory::salvage`, using the provided random number generator.
+    pub fn salvage_with<U2: Default, G: rand::Rng>(
+        &mut self,
+        idx: usize,
+        salvage_table: &LootTree<ItemDrop<K>>,
+        rng: &mut G,
+    ) -> Result<Vec<ItemInstance<K, U2>>, ItemError<K, U>> {
+        self.delete(idx, 1)?;
+        Ok(salvage_table.roll_items_with(1, rng))
+    }
+
     /// Decreases the stack size by one and returns the current value.
     /// Once the stack size hits zero, it will return `ItemError::StackConsumed`.
     pub fn consume(&mut self, idx: usize) -> Result<usize, ItemError<K, U>> {
         if let Some(Some(ii)) = self.content.get_mut(idx) {
             ii.quantity -= 1;
-            if ii.quantity == 0 {
-                Err(ItemError::StackConsumed(self.delete_stack(idx)?))
+            self.changed_slots.insert(idx);
+            let quantity = ii.quantity;
+            let key = ii.key.clone();
+            self.push_event(InventoryEvent::Removed {
+                slot: idx,
+                key,
+                quantity: 1,
+            });
+            if quantity == 0 {
+                Err(ItemError::StackConsumed(self.take(idx, 0)?))
             } else {
-                Ok(ii.quantity)
+                Ok(quantity)
             }
         } else {
             Err(ItemError::SlotEmpty)
         }
     }
 
+    /// Consumes one item of the stack at the specified index and adds the effectors of its
+    /// `ConsumableEffect`. The effectors of the other items of the same group are removed first.
+    /// Returns the same values as `Inventory::consume`. The effectors are also added when the
+    /// last item of the stack is consumed.
+    ///
+    /// Errors:
+    /// * SlotEmpty: There is no item at this index.
+    /// * NotConsumable: The item has no `ConsumableEffect`.
+    /// * StackConsumed: See `Inventory::consume`.
+    pub fn consume_with_effects<E: Hash + Eq + Clone, SK>(
+        &mut self,
+        idx: usize,
+        effectors: &mut EffectorSet<E>,
+        consumable_defs: &ConsumableEffects<K, E>,
+        effector_defs: &EffectorDefinitions<SK, E>,
+    ) -> Result<usize, ItemError<K, U>> {
+        let key = match self.get(idx) {
+            Some(ii) => &ii.key,
+            None => return Err(ItemError::SlotEmpty),
+        };
+        let def = consumable_defs
+            .defs
+            .get(key)
+            .ok_or(ItemError::NotConsumable)?;
+        let result = self.consume(idx);
+        if let Some(group) = def.group.as_ref() {
+            let grouped = consumable_defs.group_effectors(group).collect::<Vec<_>>();
+            effectors
+                .effectors
+                .retain(|e| !grouped.contains(&&e.effector_key));
+        }
+        for e in def.effectors.iter() {
+            effectors.add(e, effector_defs);
+        }
+        result
+    }
+
     /// Looks if there is enough space to add another item stack.
     pub fn has_space(&self) -> bool {
         match self.sizing_mode {
@@ -204,10 +366,17 @@ impl<
         to_idx: usize,
         quantity: usize,
         _with_overflow: bool,
-        item_defs: &ItemDefinitions<K, S, U2>,
+        _item_defs: &ItemDefinitions<K, S, U2>,
     ) -> Result<(), ItemError<K, U>> {
-        let mv = self.delete(from_idx, quantity)?;
-        self.insert_into(to_idx, mv, item_defs)?;
+        let mv = self.take(from_idx, quantity)?;
+        let key = mv.key.clone();
+        self.place(to_idx, mv)?;
+        self.push_event(InventoryEvent::Moved {
+            from: from_idx,
+            to: to_idx,
+            key,
+            quantity,
+        });
         Ok(())
     }
 
@@ -247,9 +416,21 @@ impl<
         idx: usize,
         quantity: usize,
     ) -> Result<ItemInstance<K, U>, ItemError<K, U>> {
+        let ret = self.take(idx, quantity)?;
+        self.push_event(InventoryEvent::Removed {
+            slot: idx,
+            key: ret.key.clone(),
+            quantity,
+        });
+        Ok(ret)
+    }
+
+    /// Same as `Inventory::delete`, without recording an event.
+    fn take(&mut self, idx: usize, quantity: usize) -> Result<ItemInstance<K, U>, ItemError<K, U>> {
         if let Some(Some(ii)) = self.content.get_mut(idx) {
             if ii.quantity >= quantity {
                 ii.quantity -= quantity;
+                self.changed_slots.insert(idx);
                 let mut ret = ItemInstance::new(ii.key.clone(), quantity);
                 ret.durability = ii.durability.clone();
 
@@ -280,11 +461,13 @@ impl<
             MoveToFrontMode::TakeLast => {
                 let ret = self.content.swap_remove(idx);
                 self.content.push(None);
+                self.changed_slots.insert(self.content.len() - 1);
                 ret
             }
             MoveToFrontMode::Offset => {
                 let ret = self.content.remove(idx);
                 self.content.push(None);
+                self.changed_slots.extend(idx..self.content.len());
                 ret
             }
         }
@@ -347,6 +530,51 @@ impl<
         unreachable!();
     }
 
+    /// Uses items matching the key according to the provided `UseMode`.
+    /// * Consume: Deletes the specified quantity of items.
+    /// * UseOnce: Decreases the durability of the first matching item stack. The item stack is
+    ///   destroyed once its durability reaches 0.
+    /// * UsePerSecond: Nothing is used. Using durability over time is left to the caller.
+    ///
+    /// Errors:
+    /// * NotEnoughQuantity: Not enough items with the specified key are present in the inventory.
+    pub fn use_key(
+        &mut self,
+        key: &K,
+        quantity: usize,
+        use_mode: &UseMode,
+    ) -> Result<(), ItemError<K, U>> {
+        if !self.has_quantity(key, quantity) {
+            return Err(ItemError::NotEnoughQuantity);
+        }
+        match use_mode {
+            UseMode::Consume => self.delete_key(key, quantity).map(|_| ()),
+            UseMode::UseOnce { durability } => {
+                let idx = self
+                    .content
+                    .iter()
+                    .position(|ii| ii.as_ref().map(|ii| ii.key == *key).unwrap_or(false))
+                    .ok_or(ItemError::NotEnoughQuantity)?;
+                let used = durability.ceil() as usize;
+                let ii = self.get_mut(idx).unwrap();
+                match ii.durability {
+                    Some(left) if left <= used => {
+                        let quantity = ii.quantity;
+                        let destroyed = self.take(idx, quantity)?;
+                        self.push_event(InventoryEvent::Destroyed {
+                            slot: idx,
+                            key: destroyed.key,
+                        });
+                    }
+                    Some(left) => ii.durability = Some(left - used),
+                    None => {}
+                }
+                Ok(())
+            }
+            UseMode::UsePerSecond { rate: _ } => Ok(()),
+        }
+    }
+
     /// Checks if the total quantity of items of the specified key are present in the inventory.
     pub fn has_quantity(&self, key: &K, quantity: usize) -> bool {
         let sum: usize = self
@@ -359,6 +587,36 @@ impl<
         sum >= quantity
     }
 
+    /// Checks if the inventory contains all the specified items.
+    /// The quantities of keys present multiple times are added together.
+    pub fn has_quantities(&self, items: &[(K, usize)]) -> bool {
+        let mut totals = HashMap::new();
+        for (key, quantity) in items {
+            *totals.entry(key).or_insert(0usize) += *quantity;
+        }
+        totals
+            .into_iter()
+            .all(|(key, qty)| self.has_quantity(key, qty))
+    }
+
+    /// Deletes all the specified items by matching their keys.
+    /// The quantities of keys present multiple times are added together.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotEnoughQuantity: Not enough items of one of the keys are present in the inventory.
+    pub fn delete_keys(&mut self, items: &[(K, usize)]) -> Result<(), ItemError<K, U>> {
+        if !self.has_quantities(items) {
+            return Err(ItemError::NotEnoughQuantity);
+        }
+        for (key, quantity) in items {
+            self.delete_key(key, *quantity).expect(
+                "Failed to delete items that were checked during delete_keys. This is a bug.",
+            );
+        }
+        Ok(())
+    }
+
     /// Checks if the inventory contains at least one `ItemInstance` of the specified key.
     pub fn has(&self, key: &K) -> bool {
         self.content
@@ -372,11 +630,13 @@ impl<
     }
 
     /// Gets a mutable reference to the `ItemInstance` at the specified index.
+    /// The slot is considered changed if it holds an item. See `Inventory::take_changes`.
     pub fn get_mut(&mut self, idx: usize) -> Option<&mut ItemInstance<K, U>> {
-        self.content
-            .get_mut(idx)
-            .map(|opt| opt.as_mut())
-            .unwrap_or(None)
+        let ii = self.content.get_mut(idx).and_then(|opt| opt.as_mut());
+        if ii.is_some() {
+            self.changed_slots.insert(idx);
+        }
+        ii
     }
 
     /// Finds the item instances using the specified key. Returns an iterator of immutable
@@ -391,8 +651,14 @@ impl<
 
     /// Finds the item instances using the specified key. Returns an iterator of mutable
     /// references.
+    /// The slots of the items are considered changed. See `Inventory::take_changes`.
     pub fn get_key_mut(&mut self, key: &K) -> impl Iterator<Item = &mut ItemInstance<K, U>> {
         let key = key.clone();
+        for (idx, ii) in self.content.iter().enumerate() {
+            if ii.as_ref().map(|ii| ii.key == key).unwrap_or(false) {
+                self.changed_slots.insert(idx);
+            }
+        }
         self.content
             .iter_mut()
             .flatten()
@@ -411,6 +677,19 @@ impl<
         item: ItemInstance<K, U>,
         _item_defs: &ItemDefinitions<K, S, U2>,
     ) -> Result<(), ItemError<K, U>> {
+        let key = item.key.clone();
+        let quantity = item.quantity;
+        self.place(idx, item)?;
+        self.push_event(InventoryEvent::Inserted {
+            slot: idx,
+            key,
+            quantity,
+        });
+        Ok(())
+    }
+
+    /// Same as `Inventory::insert_into`, without recording an event.
+    fn place(&mut self, idx: usize, item: ItemInstance<K, U>) -> Result<(), ItemError<K, U>> {
         // TODO implement trying to insert whole `item` stack into current stack, otherwise give
         // up.
         let opt = self.content.get_mut(idx);
@@ -418,6 +697,7 @@ impl<
             Some(Some(_)) => Err(ItemError::SlotOccupied),
             Some(None) => {
                 *opt.unwrap() = Some(item);
+                self.changed_slots.insert(idx);
                 Ok(())
             }
             None => panic!("Out of bound inventory insertion at index {}", idx),
@@ -436,11 +716,24 @@ impl<
         mut item: ItemInstance<K, U>,
         item_defs: &ItemDefinitions<K, S, U2>,
     ) -> Result<(), ItemError<K, U>> {
-        for inst in self.get_key_mut(&item.key) {
+        for idx in 0..self.content.len() {
             if item.quantity == 0 {
                 break;
             }
-            inst.merge(&mut item, item_defs);
+            if let Some(Some(inst)) = self.content.get_mut(idx) {
+                if inst.key == item.key {
+                    let before = item.quantity;
+                    inst.merge(&mut item, item_defs);
+                    if item.quantity != before {
+                        self.changed_slots.insert(idx);
+                        self.push_event(InventoryEvent::StackMerged {
+                            slot: idx,
+                            key: item.key.clone(),
+                            quantity: before - item.quantity,
+                        });
+                    }
+                }
+            }
         }
         if item.quantity == 0 {
             return Ok(());
@@ -499,11 +792,252 @@ impl<
         }
     }
 
+    /// Returns an iterator over the slots and their index, including the empty ones.
+    pub fn iter_slots(&self) -> impl Iterator<Item = (usize, &Option<ItemInstance<K, U>>)> {
+        self.content.iter().enumerate()
+    }
+
+    /// Sorts the items using the comparison function. Items are moved to the front of the
+    /// inventory and empty slots to the back.
+    /// Items in slots having a slot restriction are not moved.
+    /// No `InventoryEvent` is recorded.
+    pub fn sort_by<F: FnMut(&ItemInstance<K, U>, &ItemInstance<K, U>) -> Ordering>(
+        &mut self,
+        mut compare: F,
+    ) {
+        let mut items = self.take_unrestricted();
+        items.sort_by(|a, b| compare(a, b));
+        self.place_unrestricted(items);
+    }
+
+    /// Sorts the items by key.
+    /// See `Inventory::sort_by`.
+    pub fn sort_by_item_key(&mut self)
+    where
+        K: Ord,
+    {
+        self.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+
+    /// Sorts the items alphabetically by the name of their `ItemDefinition`.
+    /// See `Inventory::sort_by`.
+    pub fn sort_by_name<S2, D: Default>(&mut self, item_defs: &ItemDefinitions<K, S2, D>) {
+        let name = |ii: &ItemInstance<K, U>| {
+            &item_defs
+                .defs
+                .get(&ii.key)
+                .expect("Tried to get unknown item key.")
+                .name
+        };
+        self.sort_by(|a, b| name(a).cmp(name(b)));
+    }
+
+    /// Sorts the items by quantity, from the largest stack to the smallest.
+    /// See `Inventory::sort_by`.
+    pub fn sort_by_quantity(&mut self) {
+        self.sort_by(|a, b| b.quantity.cmp(&a.quantity));
+    }
+
+    /// Sorts the items by rarity, from the highest to the lowest. `rarity_of` returns a value
+    /// that is greater for rarer items, like the tier of the rarity.
+    /// See `Inventory::sort_by`.
+    pub fn sort_by_rarity<R: Ord, F: Fn(&ItemInstance<K, U>) -> R>(&mut self, rarity_of: F) {
+        self.sort_by(|a, b| rarity_of(b).cmp(&rarity_of(a)));
+    }
+
+    /// Merges the partial stacks of the same item together, then moves the items to the front
+    /// of the inventory to close the gaps between them.
+    /// Items in slots having a slot restriction can receive items from other stacks, but are
+    /// not moved.
+    /// No `InventoryEvent` is recorded.
+    pub fn compact<S2, D: Default>(&mut self, item_defs: &ItemDefinitions<K, S2, D>) {
+        for i in 0..self.content.len() {
+            for j in i + 1..self.content.len() {
+                let (left, right) = self.content.split_at_mut(j);
+                if let (Some(into), Some(from)) = (left[i].as_mut(), right[0].as_mut()) {
+                    let before = from.quantity;
+                    into.merge(from, item_defs);
+                    if from.quantity != before {
+                        self.changed_slots.insert(i);
+                        self.changed_slots.insert(j);
+                    }
+                    if from.quantity == 0 {
+                        right[0] = None;
+                    }
+                }
+            }
+        }
+        let items = self.take_unrestricted();
+        self.place_unrestricted(items);
+    }
+
+    fn is_restricted(&self, idx: usize) -> bool {
+        self.slot_restriction
+            .get(idx)
+            .map(|r| r.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Removes the items from the slots without slot restriction.
+    fn take_unrestricted(&mut self) -> Vec<ItemInstance<K, U>> {
+        let slots = (0..self.content.len())
+            .filter(|idx| !self.is_restricted(*idx) && self.content[*idx].is_some())
+            .collect::<Vec<_>>();
+        self.changed_slots.extend(slots.iter());
+        slots
+            .into_iter()
+            .filter_map(|idx| self.content[idx].take())
+            .collect()
+    }
+
+    /// Places the items in order into the slots without slot restriction.
+    /// There must be enough of them.
+    fn place_unrestricted(&mut self, items: Vec<ItemInstance<K, U>>) {
+        let mut items = items.into_iter();
+        for idx in 0..self.content.len() {
+            if !self.is_restricted(idx) {
+                self.content[idx] = items.next();
+                if self.content[idx].is_some() {
+                    self.changed_slots.insert(idx);
+                }
+            }
+        }
+    }
+
+    /// Returns the slots that changed since the last call and forgets them, in increasing
+    /// order.
+    /// Slots that are past the end of the content were removed.
+    pub fn take_changes(&mut self) -> BTreeSet<usize> {
+        core::mem::take(&mut self.changed_slots)
+    }
+
+    /// Removes and returns the recorded events, from oldest to newest.
+    /// Events are only recorded when `record_events` is true.
+    pub fn drain_events(&mut self) -> impl Iterator<Item = InventoryEvent<K>> + '_ {
+        self.events.drain(..)
+    }
+
+    fn push_event(&mut self, event: InventoryEvent<K>) {
+        if self.record_events {
+            self.events.push(event);
+        }
+    }
+
     // TODO first insertable for key: &K
 
     //pub fn first_empty_slot_filtered(&self,
 }
 
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType + Clone,
+        U: Default + Clone + Debug + PartialEq,
+    > Inventory<K, S, U>
+{
+    /// Uses all the specified items according to their `UseMode`, in order.
+    /// See `Inventory::use_key`.
+    /// Keys present multiple times must be present in their total quantity.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotEnoughQuantity: Not enough items of one of the keys are present in the inventory.
+    pub fn use_keys<'a, I: IntoIterator<Item = (&'a K, usize, &'a UseMode)>>(
+        &mut self,
+        items: I,
+    ) -> Result<(), ItemError<K, U>>
+    where
+        K: 'a,
+    {
+        let mut copy = self.clone();
+        for (key, quantity, use_mode) in items {
+            copy.use_key(key, quantity, use_mode)?;
+        }
+        *self = copy;
+        Ok(())
+    }
+
+    /// Runs the operation on a copy of the inventory and returns the events it would record,
+    /// without changing the inventory.
+    /// This can be used to preview the result of an action or to validate it.
+    ///
+    /// Errors:
+    /// * The error returned by the operation.
+    pub fn simulate<T, E, F: FnOnce(&mut Self) -> Result<T, E>>(
+        &self,
+        operation: F,
+    ) -> Result<Vec<InventoryEvent<K>>, E> {
+        let mut copy = self.clone();
+        copy.record_events = true;
+        copy.events.clear();
+        operation(&mut copy)?;
+        Ok(copy.events)
+    }
+
+    /// Returns the events that `Inventory::insert` would record, without changing the
+    /// inventory.
+    ///
+    /// Errors:
+    /// * InventoryFull: The inventory is full and no more space can be created.
+    pub fn simulate_insert<U2: Default>(
+        &self,
+        item: ItemInstance<K, U>,
+        item_defs: &ItemDefinitions<K, S, U2>,
+    ) -> Result<Vec<InventoryEvent<K>>, ItemError<K, U>> {
+        self.simulate(|inv| inv.insert(item, item_defs))
+    }
+}
+
+/// A change that happened to the content of an `Inventory`.
+/// Slots are the indices of the `Inventory` content at the time of the change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InventoryEvent<K> {
+    /// Items were inserted into an empty slot.
+    Inserted {
+        /// The slot where the items were inserted.
+        slot: usize,
+        /// The key of the items.
+        key: K,
+        /// The number of items.
+        quantity: usize,
+    },
+    /// Items were removed from a slot.
+    Removed {
+        /// The slot from which the items were removed.
+        slot: usize,
+        /// The key of the items.
+        key: K,
+        /// The number of items.
+        quantity: usize,
+    },
+    /// Items were moved from a slot to another slot of the same `Inventory`.
+    Moved {
+        /// The slot from which the items were moved.
+        from: usize,
+        /// The slot to which the items were moved.
+        to: usize,
+        /// The key of the items.
+        key: K,
+        /// The number of items.
+        quantity: usize,
+    },
+    /// Items were added to an existing item stack.
+    StackMerged {
+        /// The slot of the item stack.
+        slot: usize,
+        /// The key of the items.
+        key: K,
+        /// The number of items added to the stack.
+        quantity: usize,
+    },
+    /// An item stack broke because its durability reached 0.
+    Destroyed {
+        /// The slot of the destroyed item stack.
+        slot: usize,
+        /// The key of the destroyed items.
+        key: K,
+    },
+}
+
 /// The different errors that can happen when interacting with the `Inventory`.
 #[derive(Debug)]
 pub enum ItemError<K: PartialEq + Debug, U: Default> {
@@ -530,4 +1064,6 @@ pub enum ItemError<K: PartialEq + Debug, U: Default> {
     SlotEmpty,
     /// There is not enough of the specified item to satisfy the query.
     NotEnoughQuantity,
+    /// The item has no `ConsumableEffect`.
+    NotConsumable,
 }
@@ -0,0 +1,335 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+use derivative::*;
+
+/// What can be done with an inventory of an `InventoryCollection`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Derivative)]
+#[derivative(Default)]
+pub enum InventoryAccess {
+    /// Items can be inserted and removed.
+    #[derivative(Default)]
+    Full,
+    /// Items can only be inserted, like a mailbox or a donation chest.
+    DepositOnly,
+    /// Items can only be removed, like a loot container.
+    WithdrawOnly,
+    /// Items can't be inserted or removed, like a bank that is too far away.
+    Locked,
+}
+
+impl InventoryAccess {
+    /// Checks if items can be inserted.
+    pub fn can_insert(self) -> bool {
+        self == InventoryAccess::Full || self == InventoryAccess::DepositOnly
+    }
+
+    /// Checks if items can be removed.
+    pub fn can_remove(self) -> bool {
+        self == InventoryAccess::Full || self == InventoryAccess::WithdrawOnly
+    }
+}
+
+/// An inventory of an `InventoryCollection`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct CollectedInventory<Id, K, S: SlotType, U: Default> {
+    /// The id of the inventory.
+    pub id: Id,
+    /// The inventory.
+    pub inventory: Inventory<K, S, U>,
+    /// What can be done with the inventory.
+    pub access: InventoryAccess,
+}
+
+/// Multiple named inventories owned together, like a backpack, a bank, stash tabs and chests.
+///
+/// The access rules are enforced by the methods of the collection, but not when accessing an
+/// inventory directly through `InventoryCollection::get_mut`.
+///
+/// # Generics
+/// - Id: Inventory Id
+/// - K: Item Key
+/// - S: Item Slot Type
+/// - U: Item Custom Data
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct InventoryCollection<Id, K, S: SlotType, U: Default> {
+    /// The inventories, in the order in which they are searched when looking for items.
+    #[new(default)]
+    pub inventories: Vec<CollectedInventory<Id, K, S, U>>,
+}
+
+impl<Id, K, S: SlotType, U: Default> Default for InventoryCollection<Id, K, S, U> {
+    fn default() -> Self {
+        Self {
+            inventories: vec![],
+        }
+    }
+}
+
+impl<
+        Id: PartialEq + Clone,
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType + Clone,
+        U: Default + Clone + Debug + PartialEq,
+    > InventoryCollection<Id, K, S, U>
+{
+    /// Adds an inventory after the existing ones.
+    ///
+    /// Errors:
+    /// * DuplicateInventory: An inventory with this id already exists.
+    pub fn add(
+        &mut self,
+        id: Id,
+        inventory: Inventory<K, S, U>,
+        access: InventoryAccess,
+    ) -> Result<(), InventoryCollectionError<Id, K, U>> {
+        if self.position(&id).is_some() {
+            return Err(InventoryCollectionError::DuplicateInventory(id));
+        }
+        self.inventories
+            .push(CollectedInventory::new(id, inventory, access));
+        Ok(())
+    }
+
+    /// Removes the inventory and returns it.
+    pub fn remove(&mut self, id: &Id) -> Option<Inventory<K, S, U>> {
+        let idx = self.position(id)?;
+        Some(self.inventories.remove(idx).inventory)
+    }
+
+    /// Returns the inventory with this id.
+    pub fn get(&self, id: &Id) -> Option<&Inventory<K, S, U>> {
+        self.position(id).map(|i| &self.inventories[i].inventory)
+    }
+
+    /// Returns the inventory with this id, ignoring its access rules.
+    pub fn get_mut(&mut self, id: &Id) -> Option<&mut Inventory<K, S, U>> {
+        let idx = self.position(id)?;
+        Some(&mut self.inventories[idx].inventory)
+    }
+
+    /// Returns the access rules of the inventory.
+    pub fn access(&self, id: &Id) -> Option<InventoryAccess> {
+        self.position(id).map(|i| self.inventories[i].access)
+    }
+
+    /// Changes the access rules of the inventory.
+    ///
+    /// Errors:
+    /// * UnknownInventory: No inventory has this id.
+    pub fn set_access(
+        &mut self,
+        id: &Id,
+        access: InventoryAccess,
+    ) -> Result<(), InventoryCollectionError<Id, K, U>> {
+        let idx = self.existing_position(id)?;
+        self.inventories[idx].access = access;
+        Ok(())
+    }
+
+    /// Returns the total quantity of the item in all the inventories.
+    pub fn quantity(&self, key: &K) -> usize {
+        self.inventories
+            .iter()
+            .flat_map(|c| c.inventory.get_key(key))
+            .map(|ii| ii.quantity)
+            .sum()
+    }
+
+    /// Checks if all the inventories together hold at least this quantity of the item.
+    pub fn has_quantity(&self, key: &K, quantity: usize) -> bool {
+        self.quantity(key) >= quantity
+    }
+
+    /// Deletes items by matching the key in the inventories allowing to remove items, in order,
+    /// until the deleted quantity reaches the specified quantity.
+    ///
+    /// Errors:
+    /// * Item(NotEnoughQuantity): The inventories allowing to remove items don't have this
+    ///   quantity of the item. Nothing was deleted.
+    pub fn delete_key(
+        &mut self,
+        key: &K,
+        quantity: usize,
+    ) -> Result<ItemInstance<K, U>, InventoryCollectionError<Id, K, U>> {
+        let removable = self
+            .inventories
+            .iter()
+            .filter(|c| c.access.can_remove())
+            .flat_map(|c| c.inventory.get_key(key))
+            .map(|ii| ii.quantity)
+            .sum::<usize>();
+        if removable < quantity {
+            return Err(InventoryCollectionError::Item(ItemError::NotEnoughQuantity));
+        }
+        let mut remaining = quantity;
+        for c in self
+            .inventories
+            .iter_mut()
+            .filter(|c| c.access.can_remove())
+        {
+            if remaining == 0 {
+                break;
+            }
+            let available = c
+                .inventory
+                .get_key(key)
+                .map(|ii| ii.quantity)
+                .sum::<usize>();
+            let rm = available.min(remaining);
+            if rm > 0 {
+                c.inventory.delete_key(key, rm).expect(
+                    "Failed to delete from inventory during delete_key call. This is a bug.",
+                );
+                remaining -= rm;
+            }
+        }
+        Ok(ItemInstance::new(key.clone(), quantity))
+    }
+
+    /// Moves a quantity of items from a slot of an inventory into another inventory, merging
+    /// them with existing stacks or using the first empty slot.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * UnknownInventory: No inventory has one of the ids.
+    /// * AccessDenied: The source inventory doesn't allow removing items or the target
+    ///   inventory doesn't allow inserting items.
+    /// * Item: See `Inventory::delete` and `Inventory::insert`.
+    pub fn transfer<D: Default>(
+        &mut self,
+        from: &Id,
+        from_idx: usize,
+        to: &Id,
+        quantity: usize,
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Result<(), InventoryCollectionError<Id, K, U>> {
+        let from_pos = self.existing_position(from)?;
+        let to_pos = self.existing_position(to)?;
+        if !self.inventories[from_pos].access.can_remove() {
+            return Err(InventoryCollectionError::AccessDenied(from.clone()));
+        }
+        if !self.inventories[to_pos].access.can_insert() {
+            return Err(InventoryCollectionError::AccessDenied(to.clone()));
+        }
+        // Both inventories are modified as copies, because `Inventory::insert` can merge part of
+        // the items into existing stacks before failing.
+        let mut source = self.inventories[from_pos].inventory.clone();
+        let mut target = self.inventories[to_pos].inventory.clone();
+        let item = source
+            .delete(from_idx, quantity)
+            .map_err(InventoryCollectionError::Item)?;
+        target
+            .insert(item, item_defs)
+            .map_err(InventoryCollectionError::Item)?;
+        self.inventories[from_pos].inventory = source;
+        self.inventories[to_pos].inventory = target;
+        Ok(())
+    }
+
+    fn position(&self, id: &Id) -> Option<usize> {
+        self.inventories.iter().position(|c| c.id == *id)
+    }
+
+    fn existing_position(&self, id: &Id) -> Result<usize, InventoryCollectionError<Id, K, U>> {
+        self.position(id)
+            .ok_or_else(|| InventoryCollectionError::UnknownInventory(id.clone()))
+    }
+}
+
+/// The errors that can happen when using an `InventoryCollection`.
+#[derive(Debug)]
+pub enum InventoryCollectionError<Id, K: PartialEq + Debug, U: Default> {
+    /// No inventory has this id.
+    UnknownInventory(Id),
+    /// An inventory with this id already exists.
+    DuplicateInventory(Id),
+    /// The access rules of the inventory don't allow this operation.
+    AccessDenied(Id),
+    /// An error happened in one of the inventories.
+    Item(ItemError<K, U>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defs() -> ItemDefinitions<u32, (), ()> {
+        ItemDefinitions::from(vec![ItemDefinition::new(
+            1,
+            (),
+            "apple".into(),
+            "Apple".to_string(),
+            "".into(),
+            Some(10),
+            None,
+        )])
+    }
+
+    #[test]
+    fn transfer_failure_changes_nothing() {
+        let defs = defs();
+        let mut source = Inventory::<u32, (), ()>::new_fixed(1);
+        source.insert(ItemInstance::new(1, 5), &defs).unwrap();
+        let mut target = Inventory::<u32, (), ()>::new_fixed(1);
+        target.insert(ItemInstance::new(1, 8), &defs).unwrap();
+        let mut collection = InventoryCollection::default();
+        collection.add(0, source, InventoryAccess::Full).unwrap();
+        collection.add(1, target, InventoryAccess::Full).unwrap();
+        assert!(collection.transfer(&0, 0, &1, 5, &defs).is_err());
+        assert_eq!(
+            collection
+                .get(&0)
+                .unwrap()
+                .get(0)
+                .as_ref()
+                .unwrap()
+                .quantity,
+            5
+        );
+        assert_eq!(
+            collection
+                .get(&1)
+                .unwrap()
+                .get(0)
+                .as_ref()
+                .unwrap()
+                .quantity,
+            8
+        );
+        assert_eq!(collection.quantity(&1), 13);
+    }
+
+    #[test]
+    fn transfer_merges_into_target() {
+        let defs = defs();
+        let mut source = Inventory::<u32, (), ()>::new_fixed(1);
+        source.insert(ItemInstance::new(1, 5), &defs).unwrap();
+        let mut collection = InventoryCollection::default();
+        collection.add(0, source, InventoryAccess::Full).unwrap();
+        collection
+            .add(1, Inventory::new_fixed(1), InventoryAccess::Full)
+            .unwrap();
+        collection.transfer(&0, 0, &1, 3, &defs).unwrap();
+        assert_eq!(
+            collection
+                .get(&0)
+                .unwrap()
+                .get(0)
+                .as_ref()
+                .unwrap()
+                .quantity,
+            2
+        );
+        assert_eq!(
+            collection
+                .get(&1)
+                .unwrap()
+                .get(0)
+                .as_ref()
+                .unwrap()
+                .quantity,
+            3
+        );
+    }
+}
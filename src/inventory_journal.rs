@@ -0,0 +1,190 @@
+use crate::*;
+use alloc::collections::VecDeque;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The content of a slot of an `Inventory` before and after an operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+pub struct SlotChange<K, U: Default> {
+    /// The index of the slot.
+    pub slot: usize,
+    /// The content of the slot before the operation.
+    pub before: Option<ItemInstance<K, U>>,
+    /// The content of the slot after the operation.
+    pub after: Option<ItemInstance<K, U>>,
+}
+
+/// The changes made to an `Inventory` by a single operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+pub struct InventoryJournalEntry<K, U: Default> {
+    /// The number of slots before the operation.
+    pub size_before: usize,
+    /// The number of slots after the operation.
+    pub size_after: usize,
+    /// The slots that changed.
+    pub changes: Vec<SlotChange<K, U>>,
+}
+
+/// A bounded history of the operations done on an `Inventory`, used to undo and redo them.
+/// This is useful for editors, or to roll back changes that failed a server-side validation.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct InventoryJournal<K, U: Default> {
+    /// The maximum number of operations that can be undone.
+    /// The oldest operations are forgotten first.
+    pub max_entries: usize,
+    /// The operations that can be undone, from oldest to newest.
+    #[new(default)]
+    pub undo: VecDeque<InventoryJournalEntry<K, U>>,
+    /// The operations that can be redone, from oldest to newest.
+    /// It is cleared when a new operation is recorded.
+    #[new(default)]
+    pub redo: Vec<InventoryJournalEntry<K, U>>,
+}
+
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType,
+        U: Default + Clone + Debug + PartialEq,
+    > Inventory<K, S, U>
+{
+    /// Runs the operation and records the changes it made in the journal, if there is one.
+    /// The changes are recorded even if the operation returns an error.
+    ///
+    /// Errors:
+    /// * The error returned by the operation.
+    pub fn journaled<T, E, F: FnOnce(&mut Self) -> Result<T, E>>(
+        &mut self,
+        operation: F,
+    ) -> Result<T, E> {
+        if self.journal.is_none() {
+            return operation(self);
+        }
+        let before = self.content.clone();
+        let result = operation(self);
+        let size = before.len().max(self.content.len());
+        let changes = (0..size)
+            .filter_map(|slot| {
+                let old = before.get(slot).cloned().unwrap_or(None);
+                let new = self.content.get(slot).cloned().unwrap_or(None);
+                if old != new {
+                    Some(SlotChange::new(slot, old, new))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if changes.is_empty() && before.len() == self.content.len() {
+            return result;
+        }
+        let entry = InventoryJournalEntry::new(before.len(), self.content.len(), changes);
+        let journal = self.journal.as_mut().unwrap();
+        journal.redo.clear();
+        journal.undo.push_back(entry);
+        while journal.undo.len() > journal.max_entries {
+            journal.undo.pop_front();
+        }
+        result
+    }
+
+    /// Reverts the last recorded operation.
+    /// Returns false if there is nothing to undo, or if the slots changed since the operation
+    /// without being journaled. Nothing changes in that case.
+    /// No `InventoryEvent` is recorded.
+    pub fn undo(&mut self) -> bool {
+        match self.journal.as_ref().and_then(|j| j.undo.back()) {
+            Some(entry) if self.is_at(entry, true) => {}
+            _ => return false,
+        }
+        let entry = self.journal.as_mut().unwrap().undo.pop_back().unwrap();
+        self.restore(&entry, false);
+        self.journal.as_mut().unwrap().redo.push(entry);
+        true
+    }
+
+    /// Applies again the last undone operation.
+    /// Returns false if there is nothing to redo, or if the slots changed since the operation
+    /// was undone without being journaled. Nothing changes in that case.
+    /// No `InventoryEvent` is recorded.
+    pub fn redo(&mut self) -> bool {
+        match self.journal.as_ref().and_then(|j| j.redo.last()) {
+            Some(entry) if self.is_at(entry, false) => {}
+            _ => return false,
+        }
+        let entry = self.journal.as_mut().unwrap().redo.pop().unwrap();
+        self.restore(&entry, true);
+        self.journal.as_mut().unwrap().undo.push_back(entry);
+        true
+    }
+
+    /// Checks if the slots are as they were after the operation if `after` is true, or before
+    /// it otherwise.
+    fn is_at(&self, entry: &InventoryJournalEntry<K, U>, after: bool) -> bool {
+        let size = if after {
+            entry.size_after
+        } else {
+            entry.size_before
+        };
+        self.content.len() == size
+            && entry.changes.iter().all(|change| {
+                let expected = if after { &change.after } else { &change.before };
+                self.content.get(change.slot).unwrap_or(&None) == expected
+            })
+    }
+
+    /// Sets the slots to their content after the operation if `forward` is true, or before it
+    /// otherwise.
+    fn restore(&mut self, entry: &InventoryJournalEntry<K, U>, forward: bool) {
+        self.content
+            .resize(entry.size_before.max(entry.size_after), None);
+        for change in entry.changes.iter() {
+            self.changed_slots.insert(change.slot);
+            self.content[change.slot] = if forward {
+                change.after.clone()
+            } else {
+                change.before.clone()
+            };
+        }
+        self.content.truncate(if forward {
+            entry.size_after
+        } else {
+            entry.size_before
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_refuses_unjournaled_changes() {
+        let item_defs = ItemDefinitions::<u32, (), ()>::from(vec![ItemDefinition::new(
+            1,
+            (),
+            "gem".into(),
+            "Gem".to_string(),
+            "".into(),
+            Some(10),
+            None,
+        )]);
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(2);
+        inventory.journal = Some(InventoryJournal::new(4));
+        inventory
+            .journaled(|inv| inv.insert(ItemInstance::new(1, 3), &item_defs))
+            .unwrap();
+        inventory.delete(0, 1).unwrap();
+        assert!(!inventory.undo());
+        assert_eq!(inventory.get(0).as_ref().unwrap().quantity, 2);
+        assert_eq!(inventory.journal.as_ref().unwrap().undo.len(), 1);
+        inventory.content[0].as_mut().unwrap().quantity = 3;
+        assert!(inventory.undo());
+        assert!(inventory.get(0).is_none());
+        inventory
+            .insert(ItemInstance::new(1, 1), &item_defs)
+            .unwrap();
+        assert!(!inventory.redo());
+        inventory.delete(0, 1).unwrap();
+        assert!(inventory.redo());
+        assert_eq!(inventory.get(0).as_ref().unwrap().quantity, 3);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// An operation staged in an `InventoryTransaction`.
+/// Inventories are referred to by their index in the inventories given to
+/// `InventoryTransaction::commit`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InventoryOperation<K, U: Default> {
+    /// Inserts the item at the first available space. See `Inventory::insert`.
+    Insert {
+        /// The index of the inventory.
+        inventory: usize,
+        /// The inserted item.
+        item: ItemInstance<K, U>,
+    },
+    /// Inserts the item into the slot. See `Inventory::insert_into`.
+    InsertInto {
+        /// The index of the inventory.
+        inventory: usize,
+        /// The slot to insert into.
+        slot: usize,
+        /// The inserted item.
+        item: ItemInstance<K, U>,
+    },
+    /// Deletes items from the slot. See `Inventory::delete`.
+    Delete {
+        /// The index of the inventory.
+        inventory: usize,
+        /// The slot to delete from.
+        slot: usize,
+        /// The number of items to delete.
+        quantity: usize,
+    },
+    /// Deletes items matching the key. See `Inventory::delete_key`.
+    DeleteKey {
+        /// The index of the inventory.
+        inventory: usize,
+        /// The key of the items.
+        key: K,
+        /// The number of items to delete.
+        quantity: usize,
+    },
+    /// Moves items from a slot to another, in the same inventory or to another one.
+    Move {
+        /// The index of the inventory the items are taken from.
+        from_inventory: usize,
+        /// The slot the items are taken from.
+        from: usize,
+        /// The index of the inventory the items are moved to.
+        to_inventory: usize,
+        /// The slot the items are moved to.
+        to: usize,
+        /// The number of items to move.
+        quantity: usize,
+    },
+}
+
+/// Multiple operations applied to one or more inventories as a whole, like the inputs and
+/// outputs of a craft or the items of a quest turn-in.
+/// If any operation fails, none of them are applied.
+///
+/// # Generics
+/// - K: Item Key
+/// - U: Item Custom Data
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct InventoryTransaction<K, U: Default> {
+    /// The staged operations, applied in order.
+    #[new(default)]
+    pub operations: Vec<InventoryOperation<K, U>>,
+}
+
+impl<K, U: Default> Default for InventoryTransaction<K, U> {
+    fn default() -> Self {
+        Self { operations: vec![] }
+    }
+}
+
+impl<K, U: Default> InventoryTransaction<K, U> {
+    /// Stages the insertion of the item at the first available space of the inventory.
+    pub fn insert(mut self, inventory: usize, item: ItemInstance<K, U>) -> Self {
+        self.operations
+            .push(InventoryOperation::Insert { inventory, item });
+        self
+    }
+
+    /// Stages the insertion of the item into the slot of the inventory.
+    pub fn insert_into(mut self, inventory: usize, slot: usize, item: ItemInstance<K, U>) -> Self {
+        self.operations.push(InventoryOperation::InsertInto {
+            inventory,
+            slot,
+            item,
+        });
+        self
+    }
+
+    /// Stages the deletion of items from the slot of the inventory.
+    pub fn delete(mut self, inventory: usize, slot: usize, quantity: usize) -> Self {
+        self.operations.push(InventoryOperation::Delete {
+            inventory,
+            slot,
+            quantity,
+        });
+        self
+    }
+
+    /// Stages the deletion of items matching the key from the inventory.
+    pub fn delete_key(mut self, inventory: usize, key: K, quantity: usize) -> Self {
+        self.operations.push(InventoryOperation::DeleteKey {
+            inventory,
+            key,
+            quantity,
+        });
+        self
+    }
+
+    /// Stages moving items from a slot to another, possibly in another inventory.
+    pub fn move_item(
+        mut self,
+        from_inventory: usize,
+        from: usize,
+        to_inventory: usize,
+        to: usize,
+        quantity: usize,
+    ) -> Self {
+        self.operations.push(InventoryOperation::Move {
+            from_inventory,
+            from,
+            to_inventory,
+            to,
+            quantity,
+        });
+        self
+    }
+}
+
+impl<K: PartialEq + Clone + Debug + Hash + Eq, U: Default + Clone + Debug + PartialEq>
+    InventoryTransaction<K, U>
+{
+    /// Applies all the staged operations, in order.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * UnknownInventory: An operation uses an inventory index that was not provided.
+    /// * Failed: An operation failed. Contains its index and the error of the `Inventory`.
+    pub fn commit<S: SlotType + Clone, D: Default>(
+        &self,
+        inventories: &mut [&mut Inventory<K, S, U>],
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Result<(), InventoryTransactionError<K, U>> {
+        let mut copies = inventories
+            .iter()
+            .map(|i| (**i).clone())
+            .collect::<Vec<_>>();
+        for (step, op) in self.operations.iter().enumerate() {
+            Self::apply(op, &mut copies, item_defs).map_err(|e| match e {
+                Some(error) => InventoryTransactionError::Failed { step, error },
+                None => InventoryTransactionError::UnknownInventory(step),
+            })?;
+        }
+        for (inventory, copy) in inventories.iter_mut().zip(copies) {
+            **inventory = copy;
+        }
+        Ok(())
+    }
+
+    /// Applies a single operation.
+    /// Returns None as the error if an inventory index is out of bounds.
+    fn apply<S: SlotType + Clone, D: Default>(
+        op: &InventoryOperation<K, U>,
+        inventories: &mut [Inventory<K, S, U>],
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Result<(), Option<ItemError<K, U>>> {
+        let count = inventories.len();
+        let check = |idx: usize| if idx < count { Ok(idx) } else { Err(None) };
+        match op {
+            InventoryOperation::Insert { inventory, item } => inventories[check(*inventory)?]
+                .insert(item.clone(), item_defs)
+                .map_err(Some),
+            InventoryOperation::InsertInto {
+                inventory,
+                slot,
+                item,
+            } => inventories[check(*inventory)?]
+                .insert_into(*slot, item.clone(), item_defs)
+                .map_err(Some),
+            InventoryOperation::Delete {
+                inventory,
+                slot,
+                quantity,
+            } => inventories[check(*inventory)?]
+                .delete(*slot, *quantity)
+                .map(|_| ())
+                .map_err(Some),
+            InventoryOperation::DeleteKey {
+                inventory,
+                key,
+                quantity,
+            } => inventories[check(*inventory)?]
+                .delete_key(key, *quantity)
+                .map(|_| ())
+                .map_err(Some),
+            InventoryOperation::Move {
+                from_inventory,
+                from,
+                to_inventory,
+                to,
+                quantity,
+            } => {
+                let (from_inventory, to_inventory) =
+                    (check(*from_inventory)?, check(*to_inventory)?);
+                if from_inventory == to_inventory {
+                    inventories[from_inventory]
+                        .move_item(*from, *to, *quantity, false, item_defs)
+                        .map_err(Some)
+                } else {
+                    let item = inventories[from_inventory]
+                        .delete(*from, *quantity)
+                        .map_err(Some)?;
+                    inventories[to_inventory]
+                        .insert_into(*to, item, item_defs)
+                        .map_err(Some)
+                }
+            }
+        }
+    }
+}
+
+/// The errors that can happen when committing an `InventoryTransaction`.
+#[derive(Debug)]
+pub enum InventoryTransactionError<K: PartialEq + Debug, U: Default> {
+    /// The operation at this index uses an inventory index that was not provided.
+    UnknownInventory(usize),
+    /// An operation failed.
+    Failed {
+        /// The index of the operation.
+        step: usize,
+        /// The error returned by the inventory.
+        error: ItemError<K, U>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn failed_operation_changes_nothing() {
+        let defs = item_defs();
+        let mut first = Inventory::<u32, (), ()>::new_fixed(1);
+        let mut second = Inventory::<u32, (), ()>::new_fixed(1);
+        first.insert(ItemInstance::new(1, 5), &defs).unwrap();
+        let transaction = InventoryTransaction::new()
+            .move_item(0, 0, 1, 0, 2)
+            .delete_key(0, 1, 4);
+        assert!(matches!(
+            transaction.commit(&mut [&mut first, &mut second], &defs),
+            Err(InventoryTransactionError::Failed { step: 1, .. })
+        ));
+        assert_eq!(first.get(0).as_ref().unwrap().quantity, 5);
+        assert!(second.get(0).is_none());
+        assert!(matches!(
+            InventoryTransaction::new()
+                .insert(2, ItemInstance::new(1, 1))
+                .commit(&mut [&mut first, &mut second], &defs),
+            Err(InventoryTransactionError::UnknownInventory(0))
+        ));
+
+        InventoryTransaction::new()
+            .move_item(0, 0, 1, 0, 2)
+            .delete_key(0, 1, 3)
+            .commit(&mut [&mut first, &mut second], &defs)
+            .unwrap();
+        assert!(first.get(0).is_none());
+        assert_eq!(second.get(0).as_ref().unwrap().quantity, 2);
+    }
+}
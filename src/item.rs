@@ -1,6 +1,6 @@
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::hash::Hash;
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
 
 /// An `ItemDefinition` stores the different properties of a type of item.
 /// It is a schema that contains the data which isn't changing between different item instances.
@@ -13,18 +13,21 @@ use std::hash::Hash;
 /// * D: The type of the custom user data. If you don't have any, use the `()` type. It can (and
 /// probably should) be different than the custom user data used on `ItemInstance`s
 #[derive(new, Clone, Serialize, Deserialize, Debug, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
 pub struct ItemDefinition<K, S, D: Default> {
     /// The key identifying this item definition.
     pub key: K,
     /// The type / item group that this item definition is part of.
     pub slot_type: S,
     /// The display name of this item definition.
-    pub name: String,
+    #[builder(setter(into))]
+    pub name: LocalizedText,
     /// The friendly name of this item definition. Mostly used to find items by name instead of by
     /// key.
     pub friendly_name: String,
     /// The display description of this item definition.
-    pub description: String,
+    #[builder(setter(into))]
+    pub description: LocalizedText,
     /// The maximum number of elements that can be in an item stack. Setting this value to 1
     /// disables the ability to stack this type of item.
     pub maximum_stack: Option<usize>,
@@ -50,7 +53,8 @@ pub struct ItemDefinition<K, S, D: Default> {
 /// * K: Type of the key. Usually an enum or a number (ie u32).
 /// * U: The type of the custom user data. If you don't have any, use the `()` type.
 /// It can (and probably should) be different than the custom user data used on `ItemInstance`s
-#[derive(new, Clone, Serialize, Deserialize, Debug, Builder)]
+#[derive(new, Clone, Serialize, Deserialize, Debug, PartialEq, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
 pub struct ItemInstance<K, U: Default> {
     /// The key specifies which `ItemDefinition` defines the properties of this item stack.
     pub key: K,
@@ -79,7 +83,7 @@ impl<K: Eq + Hash, U: Default + PartialEq> ItemInstance<K, U> {
             if let Some(def) = item_defs.defs.get(&self.key) {
                 let can_take = if def.maximum_stack.is_some() {
                     // can break if your stack is over the maximum amount allowed
-                    std::cmp::min(def.maximum_stack.unwrap() - self.quantity, other.quantity)
+                    core::cmp::min(def.maximum_stack.unwrap() - self.quantity, other.quantity)
                 } else {
                     other.quantity
                 };
@@ -117,6 +121,32 @@ impl<K: Hash + Eq + Clone, S, D: Default> From<Vec<ItemDefinition<K, S, D>>>
     }
 }
 
+impl<K: Hash + Eq + Clone, S, D: Default> ItemDefinitions<K, S, D> {
+    /// Checks that the definitions are consistent.
+    /// Returns all the errors found.
+    pub fn validate(&self) -> Vec<ItemDefinitionError<K>> {
+        let mut errors = vec![];
+        for (k, def) in self.defs.iter() {
+            if def.key != *k {
+                errors.push(ItemDefinitionError::KeyMismatch(k.clone()));
+            }
+            if def.maximum_stack == Some(0) {
+                errors.push(ItemDefinitionError::InvalidMaximumStack(k.clone()));
+            }
+        }
+        errors
+    }
+}
+
+/// The errors found when validating `ItemDefinitions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemDefinitionError<K> {
+    /// The definition is registered under a key different from its own.
+    KeyMismatch(K),
+    /// The maximum stack is 0, so the item can never be held.
+    InvalidMaximumStack(K),
+}
+
 /// A trait defining which items can be inserted into each inventory slot type.
 pub trait SlotType {
     /// Checks if the provided item type can be inserted in this slot type.
@@ -0,0 +1,177 @@
+use crate::*;
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// An item that an `ItemGenerator` can create.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ItemBase<K> {
+    /// The key of the `ItemDefinition`.
+    pub key: K,
+    /// The weight of this item when rolling. See `LootTreeNode::chances`.
+    pub weight: i32,
+    /// The minimum item level at which this item can be generated.
+    #[new(default)]
+    #[serde(default)]
+    pub min_level: u32,
+}
+
+/// A rarity that an `ItemGenerator` can give to the items, and the number of affixes rolled
+/// on items of this rarity.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct RarityRoll<R> {
+    /// The key of the `RarityDefinition`.
+    pub rarity: R,
+    /// The weight of this rarity when rolling, multiplied by the drop weight multiplier of the
+    /// `RarityDefinition`.
+    pub weight: i32,
+    /// The number of prefixes rolled on items of this rarity.
+    pub prefixes: usize,
+    /// The number of suffixes rolled on items of this rarity.
+    pub suffixes: usize,
+    /// The minimum item level at which this rarity can be rolled.
+    #[new(default)]
+    #[serde(default)]
+    pub min_level: u32,
+}
+
+/// A randomized item created by an `ItemGenerator`.
+///
+/// # Generics
+/// - K: Item Key
+/// - U: Item Custom Data
+/// - R: Rarity Key
+/// - A: Affix Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct GeneratedItem<K, U: Default, R, A> {
+    /// The item.
+    pub item: ItemInstance<K, U>,
+    /// The rarity of the item.
+    pub rarity: R,
+    /// The keys of the affixes of the item.
+    pub affixes: Vec<A>,
+}
+
+/// Creates random items from a level, like the drops of a monster: picks the item, its rarity,
+/// its affixes, its durability and its user data.
+///
+/// # Generics
+/// - K: Item Key
+/// - R: Rarity Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ItemGenerator<K, R> {
+    /// The items that can be generated.
+    pub bases: Vec<ItemBase<K>>,
+    /// The rarities that can be rolled.
+    pub rarities: Vec<RarityRoll<R>>,
+    /// The minimum durability of the generated items, as a fraction of their maximum
+    /// durability between 0.0 and 1.0.
+    #[new(value = "1.0")]
+    #[serde(default = "default_min_durability")]
+    pub min_durability: f64,
+}
+
+fn default_min_durability() -> f64 {
+    1.0
+}
+
+impl<K: Hash + Eq + Clone + 'static, R: Hash + Eq + Clone + 'static> ItemGenerator<K, R> {
+    /// Generates an item of the level.
+    /// The affixes are rolled using the level as the tier. See `AffixDefinitions::roll`.
+    /// `user_data` creates the user data of the item from its definition, rarity and level.
+    /// Returns None if no item or no rarity can be generated at this level.
+    #[cfg(feature = "std")]
+    pub fn generate<
+        S: PartialEq,
+        D: Default,
+        A: Hash + Eq + Ord + Clone + 'static,
+        E,
+        U: Default,
+        F: FnMut(&ItemDefinition<K, S, D>, &R, u32) -> U,
+    >(
+        &self,
+        level: u32,
+        item_defs: &ItemDefinitions<K, S, D>,
+        rarity_defs: &RarityDefinitions<R>,
+        affix_defs: &AffixDefinitions<A, E, S>,
+        user_data: F,
+    ) -> Option<GeneratedItem<K, U, R, A>> {
+        self.generate_with(
+            level,
+            item_defs,
+            rarity_defs,
+            affix_defs,
+            user_data,
+            &mut thread_rng(),
+        )
+    }
+
+    /// Same as `ItemGenerator::generate`, using the provided random number generator.
+    /// Use this with a seeded generator (see `seeded_rng`) to get deterministic items.
+    pub fn generate_with<
+        S: PartialEq,
+        D: Default,
+        A: Hash + Eq + Ord + Clone + 'static,
+        E,
+        U: Default,
+        F: FnMut(&ItemDefinition<K, S, D>, &R, u32) -> U,
+        G: Rng,
+    >(
+        &self,
+        level: u32,
+        item_defs: &ItemDefinitions<K, S, D>,
+        rarity_defs: &RarityDefinitions<R>,
+        affix_defs: &AffixDefinitions<A, E, S>,
+        mut user_data: F,
+        rng: &mut G,
+    ) -> Option<GeneratedItem<K, U, R, A>> {
+        let mut bases = LootTreeBuilder::new();
+        bases.nodes = self
+            .bases
+            .iter()
+            .filter(|b| b.min_level <= level)
+            .map(|b| LootTreeNode {
+                chances: b.weight,
+                result: b.key.clone(),
+                guaranteed: false,
+            })
+            .collect();
+        let key = bases.build().roll_with(rng)?;
+        let def = item_defs
+            .defs
+            .get(&key)
+            .expect("Tried to get unknown item key.");
+
+        let mut rarities = LootTreeBuilder::new();
+        rarities.nodes = self
+            .rarities
+            .iter()
+            .filter(|r| r.min_level <= level)
+            .map(|r| LootTreeNode {
+                chances: r.weight,
+                result: r.rarity.clone(),
+                guaranteed: false,
+            })
+            .collect();
+        let rarity = rarity_defs
+            .scale_loot(rarities, |r| Some(r))
+            .build()
+            .roll_with(rng)?;
+        let roll = self
+            .rarities
+            .iter()
+            .find(|r| r.rarity == rarity && r.min_level <= level)
+            .expect("Rolled a rarity that is not part of the generator.");
+
+        let affixes =
+            affix_defs.roll_with(&def.slot_type, level, roll.prefixes, roll.suffixes, rng);
+        let mut item = ItemInstance::new(key, 1);
+        item.durability = def.maximum_durability.map(|max| {
+            let min = (max as f64 * self.min_durability.clamp(0.0, 1.0)).round() as usize;
+            rng.gen_range(min, max + 1)
+        });
+        item.user_data = user_data(def, &rarity, level);
+        Some(GeneratedItem::new(item, rarity, affixes))
+    }
+}
@@ -1,17 +1,27 @@
 use crate::*;
 
-use std::collections::HashMap;
-use std::hash::Hash;
+use core::fmt::Debug;
+use core::hash::Hash;
 
 // crafting
 /// A transition from one or more items into one or more different items.
 /// Can be used for all sorts of crafting.
+///
+/// # Generics
+/// - K: Item Transition Key
+/// - I: Item Key
+/// - E: Effector Key
+/// - S: Stat Key
+/// - U: Unlockable Key, for transitions that must be learned first
+/// - C: Currency Key, for transitions that cost currency
 #[derive(new, Clone, Serialize, Deserialize, Debug, Builder)]
-pub struct ItemTransitionDefinition<K, I, E, S> {
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct ItemTransitionDefinition<K, I, E, S, U = (), C = ()> {
     /// The id of this item transition.
     pub key: K,
-    /// The name of the transition.
-    pub name: String,
+    /// The display name of the transition.
+    #[builder(setter(into))]
+    pub name: LocalizedText,
     /// The friendly name of the transition.
     pub friendly_name: String,
     /// The icon path.
@@ -20,6 +30,17 @@ pub struct ItemTransitionDefinition<K, I, E, S> {
     pub input_items: Vec<(I, usize, UseMode)>,
     /// The required stats conditions required to process the transition.
     pub stat_conditions: Vec<StatCondition<S>>,
+    /// The combined stats conditions required to process the transition, in addition to
+    /// `stat_conditions`.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub stat_condition_groups: Vec<StatConditionGroup<S>>,
+    /// The conditions on the time of the `GameClock` required to start the transition.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub clock_conditions: Vec<ClockConditionGroup>,
     /// The effectors applied during crafting.
     pub stat_effectors: Vec<E>,
     /// The different output items.
@@ -32,6 +53,180 @@ pub struct ItemTransitionDefinition<K, I, E, S> {
     pub consume_input_immediate: bool,
     /// Automatically transition when all the required conditions are met.
     pub auto_trigger: bool,
+    /// The unlockable that must be unlocked before this transition can be used, like a recipe
+    /// learned from a book.
+    /// None means that the transition is always known.
+    #[new(default)]
+    #[builder(default)]
+    pub required_unlock: Option<U>,
+    /// The names of the effects of the `ScriptHost` run on the stats of the crafter when the
+    /// transition completes. See `ItemTransitionDefinition::run_output_scripts`.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub output_scripts: Vec<String>,
+    /// The currencies paid for each transition, in addition to the input items.
+    /// See `CraftingQueue::enqueue_paid`.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub currency_cost: Vec<(C, i64)>,
+}
+
+impl<K, I: Hash + Eq + Clone + Debug, E, S: Hash + Eq + Debug, U, C>
+    ItemTransitionDefinition<K, I, E, S, U, C>
+{
+    /// Checks if all the conditions to start this transition are met.
+    /// A `clock` of None means that the clock conditions are not met, unless there are none.
+    ///
+    /// Errors:
+    /// See `ItemTransitionDefinition::check_stat_conditions`.
+    pub fn check_conditions<IT: SlotType, CD: PartialEq + Default + Clone + Debug>(
+        &self,
+        inventory: &Inventory<I, IT, CD>,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Result<bool, ScriptError> {
+        if !self.check_stat_conditions(stats, stat_defs, scripts)?
+            || !self.check_clock_conditions(clock)
+        {
+            return Ok(false);
+        }
+        for (key, quantity, _) in &self.input_items {
+            if !inventory.has_quantity(key, *quantity) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Checks if the stat conditions of this transition are met, ignoring the input items.
+    /// The script conditions are checked using the `ScriptHost`.
+    ///
+    /// Errors:
+    /// See `StatCondition::check_scripted`.
+    pub fn check_stat_conditions(
+        &self,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Result<bool, ScriptError> {
+        for c in self.stat_conditions.iter() {
+            if !c.check_scripted(stats, stat_defs, scripts)? {
+                return Ok(false);
+            }
+        }
+        for g in self.stat_condition_groups.iter() {
+            if !g.check_scripted(stats, stat_defs, scripts)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Checks if this transition uses the specified item as an input.
+    pub fn uses_input(&self, item_key: &I) -> bool {
+        self.input_items.iter().any(|(key, _, _)| key == item_key)
+    }
+
+    /// Returns the events that using the input items and inserting the output items of this
+    /// transition would record, without changing the inventory.
+    /// A `clock` of None means that the clock conditions are not met, unless there are none.
+    ///
+    /// Errors:
+    /// * ConditionsNotMet: The stat conditions, the clock conditions or the input items are
+    ///   missing.
+    /// * InventoryFull: The output items don't fit in the inventory.
+    /// * Script: A script condition of the transition can't be checked.
+    pub fn simulate<IT: SlotType + Clone, CD: PartialEq + Default + Clone + Debug, D: Default>(
+        &self,
+        inventory: &Inventory<I, IT, CD>,
+        item_defs: &ItemDefinitions<I, IT, D>,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Result<Vec<InventoryEvent<I>>, CraftingError<U>> {
+        if !self
+            .check_conditions(inventory, stats, stat_defs, clock, scripts)
+            .map_err(CraftingError::Script)?
+        {
+            return Err(CraftingError::ConditionsNotMet);
+        }
+        inventory.simulate(|inv| {
+            for (key, quantity, mode) in self.input_items.iter() {
+                inv.use_key(key, *quantity, mode)
+                    .map_err(|_| CraftingError::ConditionsNotMet)?;
+            }
+            for (key, quantity) in self.output_items.iter() {
+                inv.insert(ItemInstance::new(key.clone(), *quantity), item_defs)
+                    .map_err(|_| CraftingError::InventoryFull)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<K, I, E, S, U, C> ItemTransitionDefinition<K, I, E, S, U, C> {
+    /// Checks if the clock conditions of this transition are met.
+    /// Without a clock, they are only met if there are none.
+    pub fn check_clock_conditions(&self, clock: Option<&GameClock>) -> bool {
+        clock.map_or(self.clock_conditions.is_empty(), |clock| {
+            self.clock_conditions.iter().all(|c| c.check(clock))
+        })
+    }
+}
+
+impl<K, I, E, S, U, C: Clone> ItemTransitionDefinition<K, I, E, S, U, C> {
+    /// Returns the currencies paid for `count` transitions.
+    pub fn currency_cost_of(&self, count: u32) -> Vec<(C, i64)> {
+        self.currency_cost
+            .iter()
+            .map(|(currency, amount)| (currency.clone(), amount.saturating_mul(count as i64)))
+            .collect()
+    }
+
+    /// Returns the currencies given back for `count` transitions that were paid but not
+    /// completed, according to the `RefundPolicy`.
+    pub fn currency_refund(&self, count: u32, policy: &RefundPolicy) -> Vec<(C, i64)> {
+        self.currency_cost_of(count)
+            .into_iter()
+            .map(|(currency, amount)| (currency, policy.refunded_amount(amount)))
+            .filter(|(_, amount)| *amount > 0)
+            .collect()
+    }
+}
+
+impl<K, I, E, S: Hash + Eq, U, C> ItemTransitionDefinition<K, I, E, S, U, C> {
+    /// Runs the output scripts of this transition after it completed `count` times, like when
+    /// receiving a `CraftingEvent::Completed`. The factor given to the effects is `count`.
+    ///
+    /// Errors:
+    /// * UnknownFunction: A script effect is not registered. The effects before it were run.
+    pub fn run_output_scripts(
+        &self,
+        count: usize,
+        stats: &mut StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        scripts: &ScriptHost<S>,
+    ) -> Result<(), ScriptError> {
+        for name in self.output_scripts.iter() {
+            scripts.run_effect(name, stats, stat_defs, count as f64)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K, I, E, S, U: Hash + Eq + Clone, C> ItemTransitionDefinition<K, I, E, S, U, C> {
+    /// Checks if the required unlockable of this transition, if any, is unlocked.
+    pub fn is_unlocked_in<L, S2, I2>(&self, unlockables: &Unlockables<U, L, S2, I2>) -> bool {
+        self.required_unlock
+            .as_ref()
+            .map(|u| unlockables.is_unlocked(u))
+            .unwrap_or(true)
+    }
 }
 
 /// The way items are used in a transition.
@@ -60,7 +255,8 @@ pub enum ConditionLostReaction {
     /// The transition pauses and keeps its progress.
     Pause,
     /// The transition is cancelled and all progress is lost.
-    /// If consume_input_immediate was true, the input items are not returned.
+    /// If consume_input_immediate was true, the input items are only returned according to the
+    /// `RefundPolicy` of the `CraftingQueue`.
     Cancel,
 }
 
@@ -68,21 +264,30 @@ pub enum ConditionLostReaction {
 #[derive(new, Clone, Serialize, Deserialize, Debug)]
 pub struct ItemTransitionBatch<K> {
     /// The transition id.
-    transition: K,
+    pub transition: K,
     /// The number of transitions that are queued.
-    remaining: u32,
+    pub remaining: u32,
     /// The time until the current transition is completed.
-    next_completion_remaining: f64,
+    pub next_completion_remaining: f64,
+    /// Whether the current transition started.
+    /// Input items are consumed when starting if `consume_input_immediate` is true.
+    #[new(default)]
+    #[serde(default)]
+    pub started: bool,
+    /// Whether the current transition is paused because its conditions are not met.
+    #[new(default)]
+    #[serde(default)]
+    pub paused: bool,
 }
 
 /// The definitions of all known stats.
 #[derive(Debug, Clone, Serialize, Deserialize, new)]
-pub struct ItemTransitionDefinitions<K: Hash + Eq, I, E, S> {
+pub struct ItemTransitionDefinitions<K: Hash + Eq, I, E, S, U = (), C = ()> {
     /// The definitions.
-    pub defs: HashMap<K, ItemTransitionDefinition<K, I, E, S>>,
+    pub defs: HashMap<K, ItemTransitionDefinition<K, I, E, S, U, C>>,
 }
 
-impl<K: Hash + Eq, I, E, S> Default for ItemTransitionDefinitions<K, I, E, S> {
+impl<K: Hash + Eq, I, E, S, U, C> Default for ItemTransitionDefinitions<K, I, E, S, U, C> {
     fn default() -> Self {
         Self {
             defs: HashMap::default(),
@@ -90,10 +295,10 @@ impl<K: Hash + Eq, I, E, S> Default for ItemTransitionDefinitions<K, I, E, S> {
     }
 }
 
-impl<K: Hash + Eq + Clone, I, E, S> From<Vec<ItemTransitionDefinition<K, I, E, S>>>
-    for ItemTransitionDefinitions<K, I, E, S>
+impl<K: Hash + Eq + Clone, I, E, S, U, C> From<Vec<ItemTransitionDefinition<K, I, E, S, U, C>>>
+    for ItemTransitionDefinitions<K, I, E, S, U, C>
 {
-    fn from(t: Vec<ItemTransitionDefinition<K, I, E, S>>) -> Self {
+    fn from(t: Vec<ItemTransitionDefinition<K, I, E, S, U, C>>) -> Self {
         let defs = t
             .into_iter()
             .map(|s| (s.key.clone(), s))
@@ -101,3 +306,132 @@ impl<K: Hash + Eq + Clone, I, E, S> From<Vec<ItemTransitionDefinition<K, I, E, S
         Self::new(defs)
     }
 }
+
+impl<K: Hash + Eq, I: Hash + Eq + Clone + Debug, E, S: Hash + Eq + Debug, U, C>
+    ItemTransitionDefinitions<K, I, E, S, U, C>
+{
+    /// Returns all the transitions that can currently be started using the provided
+    /// `Inventory` and `StatSet`, excluding the transitions whose required unlockable is not
+    /// unlocked.
+    /// A `clock` of None means that the clock conditions are not met, unless there are none.
+    /// The transitions whose script conditions can't be checked are excluded. Use
+    /// `ItemTransitionDefinition::check_conditions` to get the `ScriptError`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn craftable_with<IT: SlotType, CD: PartialEq + Default + Clone + Debug, L, S2, I2>(
+        &self,
+        inventory: &Inventory<I, IT, CD>,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        unlockables: &Unlockables<U, L, S2, I2>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Vec<&ItemTransitionDefinition<K, I, E, S, U, C>>
+    where
+        U: Hash + Eq + Clone,
+    {
+        self.defs
+            .values()
+            .filter(|d| d.is_unlocked_in(unlockables))
+            .filter(|d| {
+                d.check_conditions(inventory, stats, stat_defs, clock, scripts)
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Returns all the transitions that use the specified item as an input.
+    pub fn transitions_using_input(
+        &self,
+        item_key: &I,
+    ) -> Vec<&ItemTransitionDefinition<K, I, E, S, U, C>> {
+        self.defs
+            .values()
+            .filter(|d| d.uses_input(item_key))
+            .collect()
+    }
+}
+
+impl<
+        K: Hash + Eq + Clone,
+        I: Hash + Eq + Clone,
+        E: Hash + Eq + Clone,
+        S: Hash + Eq + Clone,
+        U,
+        C,
+    > ItemTransitionDefinitions<K, I, E, S, U, C>
+{
+    /// Checks that the definitions are consistent and only use known items, stats, effectors
+    /// and script conditions.
+    /// Returns all the errors found.
+    pub fn validate<IT, D: Default>(
+        &self,
+        item_defs: &ItemDefinitions<I, IT, D>,
+        stat_defs: &StatDefinitions<S>,
+        effector_defs: &EffectorDefinitions<S, E>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Vec<ItemTransitionDefinitionError<K, I, E, S>> {
+        let mut errors = vec![];
+        for (k, def) in self.defs.iter() {
+            if def.key != *k {
+                errors.push(ItemTransitionDefinitionError::KeyMismatch(k.clone()));
+            }
+            let items = def
+                .input_items
+                .iter()
+                .map(|(i, _, _)| i)
+                .chain(def.output_items.iter().map(|(i, _)| i));
+            for item in items {
+                if !item_defs.defs.contains_key(item) {
+                    errors.push(ItemTransitionDefinitionError::UnknownItem(
+                        k.clone(),
+                        item.clone(),
+                    ));
+                }
+            }
+            let conditions = def.stat_conditions.iter().chain(
+                def.stat_condition_groups
+                    .iter()
+                    .flat_map(|g| g.conditions()),
+            );
+            for c in conditions {
+                if !stat_defs.defs.contains_key(&c.stat_key) {
+                    errors.push(ItemTransitionDefinitionError::UnknownStat(
+                        k.clone(),
+                        c.stat_key.clone(),
+                    ));
+                }
+                for e in stat_defs.validate_condition(c, scripts) {
+                    errors.push(ItemTransitionDefinitionError::InvalidCondition(
+                        k.clone(),
+                        e,
+                    ));
+                }
+            }
+            for e in def.stat_effectors.iter() {
+                if !effector_defs.defs.contains_key(e) {
+                    errors.push(ItemTransitionDefinitionError::UnknownEffector(
+                        k.clone(),
+                        e.clone(),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// The errors found when validating `ItemTransitionDefinitions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemTransitionDefinitionError<K, I, E, S> {
+    /// The definition is registered under a key different from its own.
+    KeyMismatch(K),
+    /// The transition uses an item that is not defined.
+    UnknownItem(K, I),
+    /// The transition has a condition on a stat that is not defined.
+    UnknownStat(K, S),
+    /// The transition causes an effector that is not defined.
+    UnknownEffector(K, E),
+    /// The transition has a stat condition that can't be checked. See
+    /// `StatDefinitions::validate_condition`.
+    InvalidCondition(K, StatDefinitionError<S>),
+}
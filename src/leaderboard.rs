@@ -0,0 +1,220 @@
+use crate::*;
+use core::cmp::Ordering;
+use core::hash::Hash;
+
+/// Which scores are better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeaderboardOrder {
+    /// Higher scores are better, like points.
+    HighestFirst,
+    /// Lower scores are better, like completion times.
+    LowestFirst,
+}
+
+/// How entries with the same score are ranked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TieBreak {
+    /// The entry that reached the score first is ranked higher.
+    FirstReached,
+    /// The entry that reached the score last is ranked higher.
+    LastReached,
+    /// Entries with the same score share the same rank, and the following ranks are skipped.
+    /// For example: 1, 2, 2, 4.
+    Shared,
+}
+
+/// A score of a `Leaderboard`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct LeaderboardEntry<V> {
+    /// The score.
+    pub score: V,
+    /// When the score was set, relative to the other entries.
+    pub sequence: u64,
+}
+
+/// The final ranking of a past season of a `Leaderboard`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct LeaderboardSeason<K, V> {
+    /// The name of the season.
+    pub name: String,
+    /// The ranked entries, from the best to the worst.
+    pub ranking: Vec<RankedEntry<K, V>>,
+}
+
+/// An entry with its rank.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, new)]
+pub struct RankedEntry<K, V> {
+    /// The rank, starting at 1.
+    pub rank: usize,
+    /// The key of the entry, usually a user id.
+    pub key: K,
+    /// The score.
+    pub score: V,
+}
+
+/// Ranks scores.
+///
+/// # Generics
+/// - K: Entry Key, usually a user id
+/// - V: Score
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Leaderboard<K: Hash + Eq, V: Ord> {
+    /// Which scores are better.
+    pub order: LeaderboardOrder,
+    /// How entries with the same score are ranked.
+    pub tie_break: TieBreak,
+    /// The scores of the current season.
+    #[new(default)]
+    pub entries: HashMap<K, LeaderboardEntry<V>>,
+    /// The archived past seasons, from the oldest to the newest.
+    #[new(default)]
+    pub seasons: Vec<LeaderboardSeason<K, V>>,
+    /// The sequence given to the next set score.
+    #[new(default)]
+    next_sequence: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Ord + Clone> Leaderboard<K, V> {
+    /// Sets the score of the entry, even if it is worse than the current one.
+    pub fn set(&mut self, key: K, score: V) {
+        let entry = LeaderboardEntry::new(score, self.next_sequence);
+        self.next_sequence += 1;
+        self.entries.insert(key, entry);
+    }
+
+    /// Sets the score of the entry if it is better than the current one.
+    /// Returns true if the score was set.
+    pub fn submit(&mut self, key: K, score: V) -> bool {
+        let better = self
+            .entries
+            .get(&key)
+            .map(|e| self.compare_scores(&score, &e.score) == Ordering::Less)
+            .unwrap_or(true);
+        if better {
+            self.set(key, score);
+        }
+        better
+    }
+
+    /// Removes the entry.
+    /// Returns its score, if any.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|e| e.score)
+    }
+
+    /// Returns the score of the entry.
+    pub fn score_of(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|e| &e.score)
+    }
+
+    /// Returns all the entries with their rank, from the best to the worst.
+    pub fn ranking(&self) -> Vec<RankedEntry<K, V>> {
+        let mut sorted = self.entries.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| self.compare_entries(a.1, b.1));
+        let mut ranking: Vec<RankedEntry<K, V>> = Vec::with_capacity(sorted.len());
+        for (i, (key, entry)) in sorted.into_iter().enumerate() {
+            let rank = match ranking.last() {
+                Some(prev) if self.tie_break == TieBreak::Shared && prev.score == entry.score => {
+                    prev.rank
+                }
+                _ => i + 1,
+            };
+            ranking.push(RankedEntry::new(rank, key.clone(), entry.score.clone()));
+        }
+        ranking
+    }
+
+    /// Returns the `count` best entries with their rank.
+    /// With `TieBreak::Shared`, more entries than `count` can be ranked within the top `count`
+    /// ranks, but only `count` entries are returned.
+    pub fn top(&self, count: usize) -> Vec<RankedEntry<K, V>> {
+        let mut ranking = self.ranking();
+        ranking.truncate(count);
+        ranking
+    }
+
+    /// Returns the rank of the entry, starting at 1.
+    pub fn rank_of(&self, key: &K) -> Option<usize> {
+        let entry = self.entries.get(key)?;
+        let better = self
+            .entries
+            .values()
+            .filter(|e| match self.tie_break {
+                TieBreak::Shared => self.compare_scores(&e.score, &entry.score) == Ordering::Less,
+                _ => self.compare_entries(e, entry) == Ordering::Less,
+            })
+            .count();
+        Some(better + 1)
+    }
+
+    /// Archives the current ranking as a season with this name and removes all the entries.
+    pub fn reset_season(&mut self, name: String) {
+        let ranking = self.ranking();
+        self.seasons.push(LeaderboardSeason::new(name, ranking));
+        self.entries.clear();
+    }
+
+    /// Compares two scores. The better score is `Ordering::Less`.
+    fn compare_scores(&self, a: &V, b: &V) -> Ordering {
+        match self.order {
+            LeaderboardOrder::HighestFirst => b.cmp(a),
+            LeaderboardOrder::LowestFirst => a.cmp(b),
+        }
+    }
+
+    /// Compares two entries. The better entry is `Ordering::Less`.
+    fn compare_entries(&self, a: &LeaderboardEntry<V>, b: &LeaderboardEntry<V>) -> Ordering {
+        self.compare_scores(&a.score, &b.score)
+            .then_with(|| match self.tie_break {
+                TieBreak::LastReached => b.sequence.cmp(&a.sequence),
+                _ => a.sequence.cmp(&b.sequence),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(ranking: &[RankedEntry<u32, u32>]) -> Vec<(usize, u32)> {
+        ranking.iter().map(|e| (e.rank, e.key)).collect()
+    }
+
+    #[test]
+    fn ties_follow_the_tie_break() {
+        let mut board = Leaderboard::new(LeaderboardOrder::HighestFirst, TieBreak::FirstReached);
+        board.set(1, 10);
+        board.set(2, 20);
+        board.set(3, 10);
+        assert_eq!(keys(&board.ranking()), vec![(1, 2), (2, 1), (3, 3)]);
+        assert_eq!(board.rank_of(&3), Some(3));
+        board.tie_break = TieBreak::LastReached;
+        assert_eq!(keys(&board.ranking()), vec![(1, 2), (2, 3), (3, 1)]);
+        board.tie_break = TieBreak::Shared;
+        assert_eq!(keys(&board.ranking()), vec![(1, 2), (2, 1), (2, 3)]);
+        assert_eq!(board.rank_of(&3), Some(2));
+        assert_eq!(keys(&board.top(2)), vec![(1, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn only_better_scores_are_submitted() {
+        let mut board = Leaderboard::new(LeaderboardOrder::LowestFirst, TieBreak::FirstReached);
+        assert!(board.submit(1, 30));
+        assert!(!board.submit(1, 40));
+        assert!(board.submit(1, 25));
+        assert_eq!(board.score_of(&1), Some(&25));
+        assert_eq!(board.remove(&1), Some(25));
+        assert_eq!(board.rank_of(&1), None);
+    }
+
+    #[test]
+    fn seasons_archive_the_ranking() {
+        let mut board = Leaderboard::new(LeaderboardOrder::HighestFirst, TieBreak::FirstReached);
+        board.set(1, 5);
+        board.set(2, 7);
+        board.reset_season("Spring".to_string());
+        assert!(board.entries.is_empty());
+        assert_eq!(board.seasons[0].name, "Spring");
+        assert_eq!(keys(&board.seasons[0].ranking), vec![(1, 2), (2, 1)]);
+    }
+}
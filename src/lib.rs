@@ -1,8 +1,16 @@
 //! A crate providing advanced and general features for games.
 //! It can be used just as much for simple ascii games than for full distributed mmorpg games.
+//!
+//! The `std` feature is enabled by default. Without it, the crate is `no_std` and uses `alloc`
+//! and `hashbrown` instead. The functions using `thread_rng` and the functions loading
+//! definition files are then unavailable: use the `_with` variants taking a random number
+//! generator instead.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_docs)]
 
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
 #[macro_use]
 extern crate serde;
 #[macro_use]
@@ -10,34 +18,169 @@ extern crate derive_new;
 #[macro_use]
 extern crate derive_builder;
 
+mod achievement;
+mod affix;
+mod alignment;
+mod bank;
+mod blueprint;
+mod character;
+mod clock;
+mod collection;
+mod command;
+mod companion;
+mod condition;
+mod consumable;
+mod crafting;
+mod damage;
+mod death;
+mod definition_store;
+mod definitions;
+mod derived_stat;
+mod diminishing_returns;
 mod effector;
+mod entity_template;
+mod expr;
 mod faction;
+mod faction_bank;
+mod farming;
+#[cfg(not(feature = "std"))]
+mod float;
+mod fuel;
+mod guild;
+mod gun;
+mod hit_zone;
+mod hotbar;
 mod inventory;
+mod inventory_collection;
+mod inventory_journal;
+mod inventory_transaction;
 mod item;
+mod item_generator;
 mod item_transition;
+mod leaderboard;
+mod localization;
+mod loot_container;
 mod loot_tree;
+mod mail;
+mod market;
+mod mount;
+mod name_generator;
+mod opposed_check;
+mod party;
+mod patch;
 mod permissions;
+mod prestige;
+mod rarity;
+mod resource_node;
+mod save;
+mod scheduler;
+mod script;
+mod session;
+mod shop;
 mod skill;
+mod skill_tree;
+mod spawn;
 mod stat;
 mod statistics;
+mod status;
+mod threat;
 mod tier;
+mod tooltip;
+mod trade;
 mod unlock;
 mod user;
 mod user_group;
 mod user_management;
+mod wallet;
 
+#[cfg(test)]
+mod test_util;
+
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+// Unused when a dependency, like `serde_json`, links `std` and its float methods.
+#[cfg(not(feature = "std"))]
+#[allow(unused_imports)]
+use float::Float;
+#[cfg(not(feature = "std"))]
+use hashbrown::{HashMap, HashSet};
+
+pub use self::achievement::*;
+pub use self::affix::*;
+pub use self::alignment::*;
+pub use self::bank::*;
+pub use self::blueprint::*;
+pub use self::character::*;
+pub use self::clock::*;
+pub use self::collection::*;
+pub use self::command::*;
+pub use self::companion::*;
+pub use self::condition::*;
+pub use self::consumable::*;
+pub use self::crafting::*;
+pub use self::damage::*;
+pub use self::death::*;
+pub use self::definition_store::*;
+pub use self::definitions::*;
+pub use self::derived_stat::*;
+pub use self::diminishing_returns::*;
 pub use self::effector::*;
+pub use self::entity_template::*;
+pub use self::expr::*;
 pub use self::faction::*;
+pub use self::faction_bank::*;
+pub use self::farming::*;
+pub use self::fuel::*;
+pub use self::guild::*;
+pub use self::gun::*;
+pub use self::hit_zone::*;
+pub use self::hotbar::*;
 pub use self::inventory::*;
+pub use self::inventory_collection::*;
+pub use self::inventory_journal::*;
+pub use self::inventory_transaction::*;
 pub use self::item::*;
+pub use self::item_generator::*;
 pub use self::item_transition::*;
+pub use self::leaderboard::*;
+pub use self::localization::*;
+pub use self::loot_container::*;
 pub use self::loot_tree::*;
+pub use self::mail::*;
+pub use self::market::*;
+pub use self::mount::*;
+pub use self::name_generator::*;
+pub use self::opposed_check::*;
+pub use self::party::*;
+pub use self::patch::*;
 pub use self::permissions::*;
+pub use self::prestige::*;
+pub use self::rarity::*;
+pub use self::resource_node::*;
+pub use self::save::*;
+pub use self::scheduler::*;
+pub use self::script::*;
+pub use self::session::*;
+pub use self::shop::*;
 pub use self::skill::*;
+pub use self::skill_tree::*;
+pub use self::spawn::*;
 pub use self::stat::*;
 pub use self::statistics::*;
+pub use self::status::*;
+pub use self::threat::*;
 pub use self::tier::*;
+pub use self::tooltip::*;
+pub use self::trade::*;
 pub use self::unlock::*;
 pub use self::user::*;
 pub use self::user_group::*;
 pub use self::user_management::*;
+pub use self::wallet::*;
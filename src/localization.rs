@@ -0,0 +1,163 @@
+use crate::*;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A text displayed to the user, translated using a `Localization`.
+///
+/// In human readable formats like json and ron, a text without fallback is written as a plain
+/// string containing its key, so the display strings written before localization was added
+/// still load.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, new)]
+pub struct LocalizedText {
+    /// The key of the text in the `Localization`, like "item.apple.name".
+    pub key: String,
+    /// The text displayed when no language has a translation for the key.
+    /// None means that the key itself is displayed.
+    #[new(default)]
+    pub fallback: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "LocalizedText")]
+struct LocalizedTextFields {
+    key: String,
+    #[serde(default)]
+    fallback: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LocalizedTextRepr {
+    Key(String),
+    Fields(LocalizedTextFields),
+}
+
+impl LocalizedText {
+    /// Returns the text displayed when no translation is found.
+    pub fn fallback_text(&self) -> &str {
+        self.fallback.as_deref().unwrap_or(&self.key)
+    }
+}
+
+impl From<LocalizedTextFields> for LocalizedText {
+    fn from(fields: LocalizedTextFields) -> Self {
+        LocalizedText {
+            key: fields.key,
+            fallback: fields.fallback,
+        }
+    }
+}
+
+impl Serialize for LocalizedText {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() && self.fallback.is_none() {
+            return serializer.serialize_str(&self.key);
+        }
+        let mut s = serializer.serialize_struct("LocalizedText", 2)?;
+        s.serialize_field("key", &self.key)?;
+        s.serialize_field("fallback", &self.fallback)?;
+        s.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for LocalizedText {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Binary formats can't guess which representation was used.
+        if !deserializer.is_human_readable() {
+            return LocalizedTextFields::deserialize(deserializer).map(LocalizedText::from);
+        }
+        Ok(match LocalizedTextRepr::deserialize(deserializer)? {
+            LocalizedTextRepr::Key(key) => LocalizedText::new(key),
+            LocalizedTextRepr::Fields(fields) => fields.into(),
+        })
+    }
+}
+
+impl From<String> for LocalizedText {
+    fn from(key: String) -> Self {
+        LocalizedText::new(key)
+    }
+}
+
+impl From<&str> for LocalizedText {
+    fn from(key: &str) -> Self {
+        LocalizedText::new(key.to_string())
+    }
+}
+
+/// The translations of the texts of a game in each language.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct Localization {
+    /// The translated texts, by language then by key.
+    #[new(default)]
+    pub texts: HashMap<String, HashMap<String, String>>,
+    /// The language to look into when a language has no translation for a key, like "fr" for
+    /// "fr-CA" and "en" for "fr".
+    #[new(default)]
+    pub fallbacks: HashMap<String, String>,
+}
+
+impl Localization {
+    /// Adds or replaces the translation of a key in a language.
+    pub fn insert(&mut self, language: &str, key: &str, text: String) {
+        self.texts
+            .entry(language.to_string())
+            .or_default()
+            .insert(key.to_string(), text);
+    }
+
+    /// Returns the translation of the key in the language, following the fallback chain of
+    /// the language when it doesn't have one.
+    pub fn get(&self, language: &str, key: &str) -> Option<&str> {
+        let mut visited = vec![];
+        let mut current = Some(language);
+        while let Some(language) = current {
+            if visited.contains(&language) {
+                break;
+            }
+            if let Some(text) = self.texts.get(language).and_then(|t| t.get(key)) {
+                return Some(text);
+            }
+            visited.push(language);
+            current = self.fallbacks.get(language).map(|l| l.as_str());
+        }
+        None
+    }
+
+    /// Returns the text to display in the language.
+    /// Uses the fallback text of the `LocalizedText` when no translation is found.
+    pub fn text<'a>(&'a self, language: &str, text: &'a LocalizedText) -> &'a str {
+        self.get(language, &text.key)
+            .unwrap_or_else(|| text.fallback_text())
+    }
+}
+
+#[cfg(all(test, feature = "serde_json"))]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn plain_strings_are_keys() {
+        let text: LocalizedText = serde_json::from_str("\"Apple\"").unwrap();
+        assert_eq!(text, LocalizedText::from("Apple"));
+        assert_eq!(serde_json::to_string(&text).unwrap(), "\"Apple\"");
+        let mut text = LocalizedText::from("item.apple.name");
+        text.fallback = Some("Apple".to_string());
+        let json = serde_json::to_string(&text).unwrap();
+        assert_eq!(serde_json::from_str::<LocalizedText>(&json).unwrap(), text);
+        let text: LocalizedText = serde_json::from_str("{\"key\": \"item.pear.name\"}").unwrap();
+        assert_eq!(text, LocalizedText::from("item.pear.name"));
+    }
+
+    #[test]
+    fn definitions_with_string_names_load() {
+        let def: ItemDefinition<u32, (), ()> = serde_json::from_str(
+            r#"{"key": 1, "slot_type": null, "name": "Apple", "friendly_name": "apple",
+            "description": "A fruit.", "maximum_stack": 10, "maximum_durability": null,
+            "user_data": null}"#,
+        )
+        .unwrap();
+        assert_eq!(def.name.key, "Apple");
+        assert_eq!(def.description.fallback_text(), "A fruit.");
+    }
+}
@@ -0,0 +1,247 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// Items lying on the ground or in a corpse that can be looted, like a dropped loot bag.
+/// Items can be taken out of the container, but not put back into it.
+///
+/// # Generics
+/// - K: Item Key
+/// - S: Item Slot Type
+/// - U: Item Custom Data
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct LootContainer<K, S: SlotType, U: Default> {
+    /// The items of the container.
+    pub inventory: Inventory<K, S, U>,
+    /// The users allowed to take items from the container.
+    /// None means that everyone can.
+    pub allowed_users: Option<Vec<i32>>,
+    /// The time left before the container disappears, in seconds.
+    /// None means that it never expires.
+    pub expires_in: Option<f64>,
+}
+
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType + Clone,
+        U: Default + Clone + Debug + PartialEq,
+    > LootContainer<K, S, U>
+{
+    /// Creates a container holding the results of rolling the guaranteed nodes and `count`
+    /// random nodes of the loot tree.
+    #[cfg(feature = "std")]
+    pub fn from_loot<D: Default>(
+        loot: &LootTree<ItemDrop<K>>,
+        count: usize,
+        item_defs: &ItemDefinitions<K, S, D>,
+        allowed_users: Option<Vec<i32>>,
+        expires_in: Option<f64>,
+    ) -> Self {
+        Self::from_loot_with(
+            loot,
+            count,
+            item_defs,
+            allowed_users,
+            expires_in,
+            &mut thread_rng(),
+        )
+    }
+
+    /// Same as `LootContainer::from_loot`, using the provided random number generator.
+    pub fn from_loot_with<D: Default, G: Rng>(
+        loot: &LootTree<ItemDrop<K>>,
+        count: usize,
+        item_defs: &ItemDefinitions<K, S, D>,
+        allowed_users: Option<Vec<i32>>,
+        expires_in: Option<f64>,
+        rng: &mut G,
+    ) -> Self {
+        let items = loot.roll_items_with::<U, _>(count, rng);
+        let mut inventory = Inventory::new_dynamic(0, items.len());
+        for item in items.into_iter().filter(|ii| ii.quantity > 0) {
+            inventory
+                .insert(item, item_defs)
+                .expect("Failed to insert loot into the container. This is a bug.");
+        }
+        Self::new(inventory, allowed_users, expires_in)
+    }
+
+    /// Advances the expiry timer by `delta_time` seconds.
+    /// Returns true if the container expired and should be removed.
+    pub fn update(&mut self, delta_time: f64) -> bool {
+        if let Some(left) = self.expires_in.as_mut() {
+            *left -= delta_time;
+        }
+        self.is_expired()
+    }
+
+    /// Checks if the expiry timer reached 0.
+    pub fn is_expired(&self) -> bool {
+        self.expires_in.map(|left| left <= 0.0).unwrap_or(false)
+    }
+
+    /// Checks if the container has no items left.
+    pub fn is_empty(&self) -> bool {
+        self.inventory.content.iter().all(|ii| ii.is_none())
+    }
+
+    /// Checks if the user is allowed to take items from the container.
+    pub fn can_access(&self, user: i32) -> bool {
+        self.allowed_users
+            .as_ref()
+            .map(|users| users.contains(&user))
+            .unwrap_or(true)
+    }
+
+    /// Moves a quantity of items from a slot of the container into the target inventory.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * AccessDenied: The user is not allowed to take items from the container.
+    /// * Expired: The container expired.
+    /// * Item: See `Inventory::delete` and `Inventory::insert`.
+    pub fn take<D: Default>(
+        &mut self,
+        user: i32,
+        idx: usize,
+        quantity: usize,
+        target: &mut Inventory<K, S, U>,
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Result<(), LootContainerError<K, U>> {
+        self.check_access(user)?;
+        // Both inventories are modified as copies, because `Inventory::insert` can merge part of
+        // the items into existing stacks before failing.
+        let mut inventory = self.inventory.clone();
+        let mut new_target = target.clone();
+        let item = inventory
+            .delete(idx, quantity)
+            .map_err(LootContainerError::Item)?;
+        new_target
+            .insert(item, item_defs)
+            .map_err(LootContainerError::Item)?;
+        self.inventory = inventory;
+        *target = new_target;
+        Ok(())
+    }
+
+    /// Moves all the items of the container into the target inventory.
+    /// Item stacks that don't fit stay in the container.
+    ///
+    /// Errors:
+    /// * AccessDenied: The user is not allowed to take items from the container.
+    /// * Expired: The container expired.
+    /// * Item(InventoryFull): Some items didn't fit in the target inventory. The other items
+    ///   were taken.
+    pub fn take_all<D: Default>(
+        &mut self,
+        user: i32,
+        target: &mut Inventory<K, S, U>,
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Result<(), LootContainerError<K, U>> {
+        self.check_access(user)?;
+        let mut full = false;
+        // In reverse, so that removing a slot doesn't move the slots left to visit.
+        for idx in (0..self.inventory.content.len()).rev() {
+            let quantity = match self.inventory.get(idx) {
+                Some(ii) => ii.quantity,
+                None => continue,
+            };
+            if self.take(user, idx, quantity, target, item_defs).is_err() {
+                full = true;
+            }
+        }
+        if full {
+            Err(LootContainerError::Item(ItemError::InventoryFull))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_access(&self, user: i32) -> Result<(), LootContainerError<K, U>> {
+        if self.is_expired() {
+            Err(LootContainerError::Expired)
+        } else if !self.can_access(user) {
+            Err(LootContainerError::AccessDenied)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The errors that can happen when taking items from a `LootContainer`.
+#[derive(Debug)]
+pub enum LootContainerError<K: PartialEq + Debug, U: Default> {
+    /// The user is not allowed to take items from the container.
+    AccessDenied,
+    /// The container expired.
+    Expired,
+    /// Moving the items failed.
+    Item(ItemError<K, U>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defs() -> ItemDefinitions<u32, (), ()> {
+        ItemDefinitions::from(vec![ItemDefinition::new(
+            1,
+            (),
+            "apple".into(),
+            "Apple".to_string(),
+            "".into(),
+            Some(10),
+            None,
+        )])
+    }
+
+    fn container(defs: &ItemDefinitions<u32, (), ()>) -> LootContainer<u32, (), ()> {
+        let mut inventory = Inventory::new_dynamic(0, 2);
+        inventory.insert(ItemInstance::new(1, 10), defs).unwrap();
+        inventory.insert(ItemInstance::new(1, 5), defs).unwrap();
+        LootContainer::new(inventory, Some(vec![1]), None)
+    }
+
+    #[test]
+    fn take_failure_changes_nothing() {
+        let defs = defs();
+        let mut loot = container(&defs);
+        let mut target = Inventory::<u32, (), ()>::new_fixed(1);
+        target.insert(ItemInstance::new(1, 8), &defs).unwrap();
+        assert!(loot.take(1, 1, 5, &mut target, &defs).is_err());
+        assert_eq!(target.get(0).as_ref().unwrap().quantity, 8);
+        assert_eq!(loot.inventory.get(1).as_ref().unwrap().quantity, 5);
+    }
+
+    #[test]
+    fn take_all_leaves_stacks_that_dont_fit() {
+        let defs = defs();
+        let mut loot = container(&defs);
+        let mut target = Inventory::<u32, (), ()>::new_fixed(1);
+        target.insert(ItemInstance::new(1, 2), &defs).unwrap();
+        assert!(loot.take_all(1, &mut target, &defs).is_err());
+        assert_eq!(target.get(0).as_ref().unwrap().quantity, 7);
+        assert_eq!(loot.inventory.get(0).as_ref().unwrap().quantity, 10);
+        assert!(loot.inventory.get(1).is_none());
+    }
+
+    #[test]
+    fn take_checks_access() {
+        let defs = defs();
+        let mut loot = container(&defs);
+        let mut target = Inventory::<u32, (), ()>::new_fixed(1);
+        assert!(matches!(
+            loot.take(2, 0, 1, &mut target, &defs),
+            Err(LootContainerError::AccessDenied)
+        ));
+        loot.expires_in = Some(1.0);
+        assert!(loot.update(1.0));
+        assert!(matches!(
+            loot.take(1, 0, 1, &mut target, &defs),
+            Err(LootContainerError::Expired)
+        ));
+    }
+}
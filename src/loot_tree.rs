@@ -1,5 +1,11 @@
+use crate::*;
+use core::hash::Hash;
 use partial_function::LowerPartialFunction;
-use rand::{thread_rng, Rng};
+use rand::distributions::Uniform;
+use rand::rngs::StdRng;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{Rng, SeedableRng};
 
 /// A weighted node of a loot tree with the corresponding result.
 #[derive(Deserialize)]
@@ -8,6 +14,10 @@ pub struct LootTreeNode<R> {
     pub chances: i32,
     /// The result of this node.
     pub result: R,
+    /// Whether this node is always part of the results of `LootTree::roll_many`.
+    /// Guaranteed nodes are not part of the weighted rolls and their chances are ignored.
+    #[serde(default)]
+    pub guaranteed: bool,
 }
 
 /// A builder for the `LootTree`.
@@ -23,11 +33,25 @@ impl<R: Clone + 'static> LootTreeBuilder<R> {
         LootTreeBuilder { nodes: vec![] }
     }
 
+    /// Multiplies the chances of each node by the factor returned for its result.
+    /// The chances are rounded to the nearest integer.
+    pub fn scale_chances<F: Fn(&R) -> f64>(mut self, factor: F) -> Self {
+        for n in self.nodes.iter_mut() {
+            n.chances = (f64::from(n.chances) * factor(&n.result)).round() as i32;
+        }
+        self
+    }
+
     /// Builds the loot tree.
     pub fn build(self) -> LootTree<R> {
         let mut f = LowerPartialFunction::new();
         let mut accum = 0;
+        let mut guaranteed = vec![];
         for n in self.nodes.into_iter() {
+            if n.guaranteed {
+                guaranteed.push(n.result);
+                continue;
+            }
             let tmp = n.chances;
             f = f.with(accum, move |_| n.result.clone());
             accum = accum + tmp;
@@ -35,6 +59,7 @@ impl<R: Clone + 'static> LootTreeBuilder<R> {
         LootTree {
             partial_func: f.build(),
             max: accum,
+            guaranteed,
         }
     }
 }
@@ -57,12 +82,217 @@ impl<R: Clone + 'static> LootTreeBuilder<R> {
 pub struct LootTree<R> {
     partial_func: LowerPartialFunction<i32, R>,
     max: i32,
+    guaranteed: Vec<R>,
 }
 
 impl<R> LootTree<R> {
     /// Returns a random item from the loot tree.
+    #[cfg(feature = "std")]
     pub fn roll(&self) -> Option<R> {
-        let rng = thread_rng().gen_range(0, self.max);
+        self.roll_with(&mut thread_rng())
+    }
+
+    /// Returns a random item from the loot tree using the provided random number generator.
+    /// Use this with a seeded generator (see `seeded_rng`) to get deterministic results.
+    pub fn roll_with<G: Rng>(&self, rng: &mut G) -> Option<R> {
+        if self.max <= 0 {
+            return None;
+        }
+        let rng = rng.gen_range(0, self.max);
         self.partial_func.eval(rng)
     }
 }
+
+impl<R: Clone> LootTree<R> {
+    /// Returns the results of the guaranteed nodes, followed by `count` random results.
+    #[cfg(feature = "std")]
+    pub fn roll_many(&self, count: usize) -> Vec<R> {
+        self.roll_many_with(count, &mut thread_rng())
+    }
+
+    /// Same as `LootTree::roll_many`, using the provided random number generator.
+    pub fn roll_many_with<G: Rng>(&self, count: usize, rng: &mut G) -> Vec<R> {
+        let mut results = self.guaranteed.clone();
+        results.extend((0..count).filter_map(|_| self.roll_with(rng)));
+        results
+    }
+}
+
+impl<K: Clone> LootTree<ItemDrop<K>> {
+    /// Rolls the guaranteed nodes and `count` random nodes into a list of `ItemInstance`s, each
+    /// with a random quantity.
+    #[cfg(feature = "std")]
+    pub fn roll_items<U: Default>(&self, count: usize) -> Vec<ItemInstance<K, U>> {
+        self.roll_items_with(count, &mut thread_rng())
+    }
+
+    /// Same as `LootTree::roll_items`, using the provided random number generator.
+    pub fn roll_items_with<U: Default, G: Rng>(
+        &self,
+        count: usize,
+        rng: &mut G,
+    ) -> Vec<ItemInstance<K, U>> {
+        self.roll_many_with(count, rng)
+            .iter()
+            .map(|d| d.roll_with(rng))
+            .collect()
+    }
+}
+
+/// An item dropped with a random quantity.
+/// Used as the result type of a `LootTree` to produce `ItemInstance`s.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ItemDrop<K> {
+    /// The key of the dropped item.
+    pub key: K,
+    /// The minimum quantity dropped, inclusive.
+    pub min_quantity: usize,
+    /// The maximum quantity dropped, inclusive.
+    pub max_quantity: usize,
+}
+
+impl<K: Clone> ItemDrop<K> {
+    /// Creates an `ItemInstance` with a random quantity between the minimum and maximum.
+    /// If the maximum is lower than the minimum, the quantity is always the minimum.
+    pub fn roll_with<U: Default, G: Rng>(&self, rng: &mut G) -> ItemInstance<K, U> {
+        let max = self.max_quantity.max(self.min_quantity);
+        let quantity = rng.sample(Uniform::new_inclusive(self.min_quantity, max));
+        ItemInstance::new(self.key.clone(), quantity)
+    }
+}
+
+/// Creates a random number generator from a seed.
+/// Generators created with the same seed will give the same sequence of rolls.
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// The result of a node of a loot tree registered in `LootTables`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LootResult<K, R> {
+    /// A final result.
+    Loot(R),
+    /// Roll again on the loot tree registered with this key.
+    Table(K),
+}
+
+/// A registry of loot trees whose results can refer to other registered loot trees.
+/// This allows expressing things like "roll on the rare table 10% of the time".
+pub struct LootTables<K, R> {
+    tables: HashMap<K, LootTree<LootResult<K, R>>>,
+    references: HashMap<K, Vec<K>>,
+}
+
+impl<K: Hash + Eq, R> Default for LootTables<K, R> {
+    fn default() -> Self {
+        Self {
+            tables: HashMap::default(),
+            references: HashMap::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone + 'static, R: Clone + 'static> LootTables<K, R> {
+    /// Builds and registers a loot tree, replacing the one previously registered with the same
+    /// key.
+    ///
+    /// Errors:
+    /// * Cycle: Rolling on this loot tree could lead back to itself.
+    pub fn insert(
+        &mut self,
+        key: K,
+        builder: LootTreeBuilder<LootResult<K, R>>,
+    ) -> Result<(), LootError<K>> {
+        let references = builder
+            .nodes
+            .iter()
+            .filter_map(|n| match &n.result {
+                LootResult::Table(k) => Some(k.clone()),
+                LootResult::Loot(_) => None,
+            })
+            .collect::<Vec<_>>();
+        if self.reaches(&references, &key) {
+            return Err(LootError::Cycle(key));
+        }
+        self.references.insert(key.clone(), references);
+        self.tables.insert(key, builder.build());
+        Ok(())
+    }
+
+    /// Removes the loot tree registered with this key.
+    /// Returns true if it was registered.
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.references.remove(key);
+        self.tables.remove(key).is_some()
+    }
+
+    /// Checks if a loot tree is registered with this key.
+    pub fn contains(&self, key: &K) -> bool {
+        self.tables.contains_key(key)
+    }
+
+    /// Returns a random result from the loot tree registered with this key, following the
+    /// references to other loot trees.
+    /// Returns None if the loot tree or one of the referenced loot trees is not registered.
+    #[cfg(feature = "std")]
+    pub fn roll(&self, key: &K) -> Option<R> {
+        self.roll_with(key, &mut thread_rng())
+    }
+
+    /// Same as `LootTables::roll`, using the provided random number generator.
+    pub fn roll_with<G: Rng>(&self, key: &K, rng: &mut G) -> Option<R> {
+        let mut current = self.tables.get(key)?.roll_with(rng)?;
+        loop {
+            match current {
+                LootResult::Loot(r) => return Some(r),
+                LootResult::Table(k) => current = self.tables.get(&k)?.roll_with(rng)?,
+            }
+        }
+    }
+
+    /// Checks if `target` can be reached by following the references of the provided keys.
+    fn reaches(&self, from: &[K], target: &K) -> bool {
+        let mut visited = vec![];
+        let mut to_visit = from.to_vec();
+        while let Some(k) = to_visit.pop() {
+            if k == *target {
+                return true;
+            }
+            if visited.contains(&k) {
+                continue;
+            }
+            if let Some(refs) = self.references.get(&k) {
+                to_visit.extend(refs.iter().cloned());
+            }
+            visited.push(k);
+        }
+        false
+    }
+}
+
+/// The errors that can happen when using `LootTables`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LootError<K> {
+    /// Registering the loot tree with this key would create a reference cycle.
+    Cycle(K),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_drop_quantities_stay_in_bounds() {
+        let mut rng = seeded_rng(3);
+        let inverted = ItemDrop::new(1u32, 4, 2).roll_with::<(), _>(&mut rng);
+        assert_eq!(inverted.quantity, 4);
+        let max = ItemDrop::new(1u32, usize::MAX, usize::MAX).roll_with::<(), _>(&mut rng);
+        assert_eq!(max.quantity, usize::MAX);
+        for _ in 0..20 {
+            let quantity = ItemDrop::new(1u32, 1, 3)
+                .roll_with::<(), _>(&mut rng)
+                .quantity;
+            assert!((1..=3).contains(&quantity));
+        }
+    }
+}
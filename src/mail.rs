@@ -0,0 +1,420 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// A message sent between users, optionally carrying items and currencies.
+/// The attachments are taken from the sender when attaching them and are held by the mail
+/// until the recipient claims them.
+///
+/// # Generics
+/// - K: Item Key
+/// - U: Item Custom Data
+/// - C: Currency Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Mail<K, U: Default, C> {
+    /// The id of the mail. Assigned by `PostOffice::send`.
+    #[new(default)]
+    pub id: u32,
+    /// The user who sent the mail.
+    pub sender: i32,
+    /// The user receiving the mail.
+    pub recipient: i32,
+    /// The subject of the mail.
+    pub subject: String,
+    /// The content of the mail.
+    pub body: String,
+    /// The attached items.
+    #[new(default)]
+    #[serde(default = "Vec::new")]
+    pub items: Vec<ItemInstance<K, U>>,
+    /// The attached currencies.
+    #[new(default)]
+    #[serde(default = "Vec::new")]
+    pub currencies: Vec<(C, i64)>,
+    /// The amount the recipient has to pay to the sender to claim the attachments.
+    #[new(default)]
+    pub cash_on_delivery: Option<(C, i64)>,
+    /// Whether the recipient read the mail.
+    #[new(default)]
+    #[serde(default)]
+    pub read: bool,
+    /// Whether the mail was returned to its sender.
+    #[new(default)]
+    #[serde(default)]
+    pub returned: bool,
+    /// The timer after which the mail expires.
+    /// None means that the mail never expires.
+    #[new(default)]
+    pub expires: Option<Timer>,
+}
+
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        U: Default + Clone + Debug + PartialEq,
+        C: Hash + Eq + Clone,
+    > Mail<K, U, C>
+{
+    /// Checks if items or currencies are attached to the mail.
+    pub fn has_attachments(&self) -> bool {
+        !self.items.is_empty() || !self.currencies.is_empty()
+    }
+
+    /// Moves the quantity of the item in the slot of the sender inventory into the mail.
+    ///
+    /// Errors:
+    /// See `Inventory::delete`.
+    pub fn attach_item<S: SlotType>(
+        &mut self,
+        from: &mut Inventory<K, S, U>,
+        idx: usize,
+        quantity: usize,
+    ) -> Result<(), MailError<C>> {
+        let item = from
+            .delete(idx, quantity)
+            .map_err(|_| MailError::NotEnoughItems)?;
+        self.items.push(item);
+        Ok(())
+    }
+
+    /// Moves the amount of currency from the sender wallet into the mail.
+    ///
+    /// Errors:
+    /// See `Wallet::remove`.
+    pub fn attach_currency(
+        &mut self,
+        from: &mut Wallet<C>,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), MailError<C>> {
+        from.remove(currency, amount, currency_defs)
+            .map_err(MailError::Wallet)?;
+        self.currencies.push((currency.clone(), amount));
+        Ok(())
+    }
+}
+
+/// The mails received by a user.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Mailbox<K, U: Default, C> {
+    /// The mails, from the oldest to the newest.
+    #[new(default)]
+    pub mails: Vec<Mail<K, U, C>>,
+}
+
+impl<K, U: Default, C> Mailbox<K, U, C> {
+    /// Returns the mail with this id.
+    pub fn get(&self, id: u32) -> Option<&Mail<K, U, C>> {
+        self.mails.iter().find(|m| m.id == id)
+    }
+
+    /// Returns the number of mails that were not read.
+    pub fn unread_count(&self) -> usize {
+        self.mails.iter().filter(|m| !m.read).count()
+    }
+
+    /// Marks the mail as read.
+    ///
+    /// Errors:
+    /// * UnknownMail: No mail has this id in this mailbox.
+    pub fn mark_read(&mut self, id: u32) -> Result<(), MailError<C>> {
+        let mail = self
+            .mails
+            .iter_mut()
+            .find(|m| m.id == id)
+            .ok_or(MailError::UnknownMail)?;
+        mail.read = true;
+        Ok(())
+    }
+
+    fn position(&self, id: u32) -> Result<usize, MailError<C>> {
+        self.mails
+            .iter()
+            .position(|m| m.id == id)
+            .ok_or(MailError::UnknownMail)
+    }
+}
+
+/// Something that happened to a mail while updating a `PostOffice`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MailEvent {
+    /// The mail expired and was returned to its sender with its attachments.
+    Returned(u32),
+    /// The mail expired and was deleted, along with its attachments.
+    Deleted(u32),
+}
+
+/// Delivers the mails between the mailboxes of the users.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct PostOffice<K, U: Default, C> {
+    /// The time mails stay in a mailbox before expiring, in seconds.
+    /// Expired mails are returned to their sender if they have attachments, otherwise they are
+    /// deleted. Expired returned mails are deleted.
+    /// None means that mails never expire.
+    pub mail_duration: Option<f64>,
+    /// The mailbox of each user.
+    #[new(default)]
+    pub mailboxes: HashMap<i32, Mailbox<K, U, C>>,
+    /// The id given to the next mail.
+    #[new(default)]
+    pub next_id: u32,
+}
+
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        U: Default + Clone + Debug + PartialEq,
+        C: Hash + Eq + Clone,
+    > PostOffice<K, U, C>
+{
+    /// Returns the mailbox of the user, if they ever received a mail.
+    pub fn mailbox(&self, user: i32) -> Option<&Mailbox<K, U, C>> {
+        self.mailboxes.get(&user)
+    }
+
+    /// Returns the mailbox of the user, creating it if needed.
+    pub fn mailbox_mut(&mut self, user: i32) -> &mut Mailbox<K, U, C> {
+        self.mailboxes.entry(user).or_insert_with(Mailbox::new)
+    }
+
+    /// Delivers the mail to its recipient. Returns the id of the mail.
+    pub fn send(&mut self, mut mail: Mail<K, U, C>) -> u32 {
+        mail.id = self.next_id;
+        self.next_id += 1;
+        mail.expires = self.mail_duration.map(Timer::once);
+        let id = mail.id;
+        self.mailbox_mut(mail.recipient).mails.push(mail);
+        id
+    }
+
+    /// Moves the attachments of the mail into the inventory and wallet of the user, paying
+    /// the cash on delivery to the sender. The mail is marked as read and stays in the mailbox.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * UnknownMail: No mail has this id in the mailbox of the user.
+    /// * InventoryFull: The items don't fit in the inventory.
+    /// * Wallet: The wallet can't pay the cash on delivery or hold the currencies.
+    pub fn claim<S: SlotType + Clone, D: Default>(
+        &mut self,
+        user: i32,
+        id: u32,
+        to: (&mut Inventory<K, S, U>, &mut Wallet<C>),
+        item_defs: &ItemDefinitions<K, S, D>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), MailError<C>> {
+        let mailbox = self.mailbox_mut(user);
+        let pos = mailbox.position(id)?;
+        let mail = &mailbox.mails[pos];
+        let (inventory, wallet) = to;
+        let mut new_inventory = inventory.clone();
+        let mut new_wallet = wallet.clone();
+        if let Some((currency, amount)) = mail.cash_on_delivery.as_ref() {
+            new_wallet
+                .remove(currency, *amount, currency_defs)
+                .map_err(MailError::Wallet)?;
+        }
+        for item in mail.items.iter() {
+            new_inventory
+                .insert(item.clone(), item_defs)
+                .map_err(|_| MailError::InventoryFull)?;
+        }
+        for (currency, amount) in mail.currencies.iter() {
+            new_wallet
+                .add(currency, *amount, currency_defs)
+                .map_err(MailError::Wallet)?;
+        }
+        *inventory = new_inventory;
+        *wallet = new_wallet;
+        let mail = &mut mailbox.mails[pos];
+        mail.items.clear();
+        mail.currencies.clear();
+        mail.read = true;
+        if let Some((currency, amount)) = mail.cash_on_delivery.take() {
+            let mut payment = Mail::new(
+                user,
+                mail.sender,
+                format!("Payment: {}", mail.subject),
+                String::new(),
+            );
+            payment.currencies.push((currency, amount));
+            self.send(payment);
+        }
+        Ok(())
+    }
+
+    /// Sends the mail back to its sender with its attachments, for example to refuse a cash on
+    /// delivery.
+    ///
+    /// Errors:
+    /// * UnknownMail: No mail has this id in the mailbox of the user.
+    /// * AlreadyReturned: The mail was already returned once.
+    pub fn return_to_sender(&mut self, user: i32, id: u32) -> Result<(), MailError<C>> {
+        let mailbox = self.mailbox_mut(user);
+        let pos = mailbox.position(id)?;
+        if mailbox.mails[pos].returned {
+            return Err(MailError::AlreadyReturned);
+        }
+        let mail = mailbox.mails.remove(pos);
+        self.bounce(mail);
+        Ok(())
+    }
+
+    /// Deletes the mail.
+    ///
+    /// Errors:
+    /// * UnknownMail: No mail has this id in the mailbox of the user.
+    /// * HasAttachments: The attachments of the mail were not claimed.
+    pub fn delete(&mut self, user: i32, id: u32) -> Result<(), MailError<C>> {
+        let mailbox = self.mailbox_mut(user);
+        let pos = mailbox.position(id)?;
+        if mailbox.mails[pos].has_attachments() {
+            return Err(MailError::HasAttachments);
+        }
+        mailbox.mails.remove(pos);
+        Ok(())
+    }
+
+    /// Advances the expiration of the mails by `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f64) -> Vec<MailEvent> {
+        let mut expired = vec![];
+        for mailbox in self.mailboxes.values_mut() {
+            let mut i = 0;
+            while i < mailbox.mails.len() {
+                let elapsed = mailbox.mails[i]
+                    .expires
+                    .as_mut()
+                    .map(|t| t.update(delta_time) > 0)
+                    .unwrap_or(false);
+                if elapsed {
+                    expired.push(mailbox.mails.remove(i));
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        let mut events = vec![];
+        for mail in expired {
+            if mail.has_attachments() && !mail.returned {
+                events.push(MailEvent::Returned(mail.id));
+                self.bounce(mail);
+            } else {
+                events.push(MailEvent::Deleted(mail.id));
+            }
+        }
+        events
+    }
+
+    fn bounce(&mut self, mut mail: Mail<K, U, C>) {
+        core::mem::swap(&mut mail.sender, &mut mail.recipient);
+        mail.cash_on_delivery = None;
+        mail.read = false;
+        mail.returned = true;
+        mail.expires = self.mail_duration.map(Timer::once);
+        self.mailbox_mut(mail.recipient).mails.push(mail);
+    }
+}
+
+/// The errors that can happen when using mails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MailError<C> {
+    /// No mail has this id in the mailbox.
+    UnknownMail,
+    /// The sender doesn't have enough of the items.
+    NotEnoughItems,
+    /// The attachments of the mail were not claimed.
+    HasAttachments,
+    /// The mail was already returned to its sender.
+    AlreadyReturned,
+    /// The items don't fit in the inventory.
+    InventoryFull,
+    /// The wallet can't pay or receive the currency.
+    Wallet(WalletError<C>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn claim_with_cash_on_delivery() {
+        let (item_defs, currency_defs) = (item_defs(), currency_defs());
+        let mut post = PostOffice::<u32, (), u8>::new(None);
+        let mut sender = player(0);
+        let mut recipient = player(0);
+        sender
+            .0
+            .insert(ItemInstance::new(1, 5), &item_defs)
+            .unwrap();
+        let mut mail = Mail::new(1, 2, "Apples".to_string(), String::new());
+        mail.attach_item(&mut sender.0, 0, 3).unwrap();
+        mail.cash_on_delivery = Some((0, 30));
+        assert_eq!(
+            mail.attach_item(&mut sender.0, 0, 3),
+            Err(MailError::NotEnoughItems)
+        );
+        let id = post.send(mail);
+        assert_eq!(sender.0.get(0).as_ref().unwrap().quantity, 2);
+
+        assert_eq!(
+            post.claim(
+                2,
+                id,
+                (&mut recipient.0, &mut recipient.1),
+                &item_defs,
+                &currency_defs
+            ),
+            Err(MailError::Wallet(WalletError::NotEnough(0)))
+        );
+        assert!(recipient.0.get(0).is_none());
+        recipient.1.add(&0, 50, &currency_defs).unwrap();
+        post.claim(
+            2,
+            id,
+            (&mut recipient.0, &mut recipient.1),
+            &item_defs,
+            &currency_defs,
+        )
+        .unwrap();
+        assert_eq!(recipient.0.get(0).as_ref().unwrap().quantity, 3);
+        assert_eq!(recipient.1.balance(&0), 20);
+        assert!(!post.mailbox(2).unwrap().get(id).unwrap().has_attachments());
+
+        // The payment is mailed to the sender.
+        let payment = &post.mailbox(1).unwrap().mails[0];
+        assert_eq!(payment.currencies, vec![(0, 30)]);
+        let payment = payment.id;
+        post.claim(
+            1,
+            payment,
+            (&mut sender.0, &mut sender.1),
+            &item_defs,
+            &currency_defs,
+        )
+        .unwrap();
+        assert_eq!(sender.1.balance(&0), 30);
+        post.delete(1, payment).unwrap();
+    }
+
+    #[test]
+    fn expired_mails_are_returned_once() {
+        let currency_defs = currency_defs();
+        let mut post = PostOffice::<u32, (), u8>::new(Some(10.0));
+        let mut wallet = Wallet::new();
+        wallet.add(&0, 10, &currency_defs).unwrap();
+        let mut mail = Mail::new(1, 2, "Gold".to_string(), String::new());
+        mail.attach_currency(&mut wallet, &0, 10, &currency_defs)
+            .unwrap();
+        let id = post.send(mail);
+        assert_eq!(post.delete(2, id), Err(MailError::HasAttachments));
+        assert_eq!(post.update(11.0), vec![MailEvent::Returned(id)]);
+        assert!(post.mailbox(2).unwrap().mails.is_empty());
+        assert_eq!(post.mailbox(1).unwrap().unread_count(), 1);
+        assert_eq!(
+            post.return_to_sender(1, id),
+            Err(MailError::AlreadyReturned)
+        );
+        assert_eq!(post.update(11.0), vec![MailEvent::Deleted(id)]);
+        assert!(post.mailbox(1).unwrap().mails.is_empty());
+    }
+}
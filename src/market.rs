@@ -0,0 +1,570 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The fees taken by a `Market`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct MarketFees {
+    /// The amount paid by the seller when creating a listing, in the currency of the listing.
+    /// It is not refunded.
+    pub listing_fee: i64,
+    /// The fraction of the sale price kept by the market, between 0.0 and 1.0.
+    /// The result is rounded down.
+    pub sale_fee: f64,
+}
+
+impl MarketFees {
+    /// The amount received by the seller for a sale at this price.
+    pub fn proceeds(&self, price: i64) -> i64 {
+        price - (price as f64 * self.sale_fee.clamp(0.0, 1.0)).floor() as i64
+    }
+}
+
+/// The conditions of a sale, chosen by the seller when creating a listing.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ListingTerms<C> {
+    /// The currency of the bids and of the buyout price.
+    pub currency: C,
+    /// The minimum amount of the first bid.
+    /// None means that the listing doesn't accept bids.
+    pub starting_bid: Option<i64>,
+    /// The price to buy the listing immediately.
+    /// None means that the listing can only be bought by bidding.
+    pub buyout: Option<i64>,
+    /// The time the listing stays on the market, in seconds.
+    pub duration: f64,
+}
+
+/// The highest bid of a `Listing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, new)]
+pub struct Bid {
+    /// The user who bid.
+    pub bidder: i32,
+    /// The amount bid. It is held by the market until the listing ends.
+    pub amount: i64,
+}
+
+/// Items put up for sale on a `Market`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Listing<K, U: Default, C> {
+    /// The id of the listing.
+    pub id: u32,
+    /// The user selling the items.
+    pub seller: i32,
+    /// The items sold. They are held by the market until the listing ends.
+    pub item: ItemInstance<K, U>,
+    /// The conditions of the sale.
+    pub terms: ListingTerms<C>,
+    /// The highest bid, if any.
+    #[new(default)]
+    pub highest_bid: Option<Bid>,
+    /// The timer after which the listing ends.
+    pub expires: Timer,
+}
+
+impl<K, U: Default, C> Listing<K, U, C> {
+    /// The minimum amount of the next bid, if the listing accepts bids.
+    pub fn minimum_bid(&self) -> Option<i64> {
+        self.terms.starting_bid.map(|start| {
+            self.highest_bid
+                .map(|b| b.amount + 1)
+                .unwrap_or(start)
+                .max(start)
+        })
+    }
+}
+
+/// Something a user receives from a `Market`, waiting to be claimed using `Market::claim`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MarketDelivery<K, U: Default, C> {
+    /// Items bought, or returned to the seller.
+    Item(ItemInstance<K, U>),
+    /// Currency from a sale, or a refunded bid.
+    Currency(C, i64),
+}
+
+/// What happened to a listing that ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarketEvent<C> {
+    /// The listing was sold to the buyer at this price.
+    Sold {
+        /// The id of the listing.
+        listing: u32,
+        /// The user who bought the listing.
+        buyer: i32,
+        /// The currency of the price.
+        currency: C,
+        /// The price paid.
+        price: i64,
+    },
+    /// The listing expired without bids. The items are returned to the seller.
+    Expired {
+        /// The id of the listing.
+        listing: u32,
+    },
+}
+
+/// An auction house where users sell items to each other.
+/// The items and the bids are held by the market while the listing is active.
+/// When a listing ends, the items, the sale proceeds and the refunded bids are delivered to
+/// the users, who receive them using `Market::claim`.
+///
+/// # Generics
+/// - K: Item Key
+/// - U: Item Custom Data
+/// - C: Currency Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Market<K, U: Default, C> {
+    /// The fees taken by the market.
+    pub fees: MarketFees,
+    /// The active listings.
+    #[new(default)]
+    pub listings: Vec<Listing<K, U, C>>,
+    /// What each user can claim.
+    #[new(default)]
+    pub deliveries: HashMap<i32, Vec<MarketDelivery<K, U, C>>>,
+    /// The id given to the next listing.
+    #[new(default)]
+    pub next_id: u32,
+}
+
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        U: Default + Clone + Debug + PartialEq,
+        C: Hash + Eq + Clone,
+    > Market<K, U, C>
+{
+    /// Creates a listing for the quantity of the item in the slot of the seller inventory.
+    /// The items are moved to the market and the listing fee is paid.
+    /// Returns the id of the listing.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * InvalidTerms: The listing has neither a starting bid nor a buyout price.
+    /// * NotEnoughItems: The slot doesn't contain this quantity of items.
+    /// * Wallet: The wallet can't pay the listing fee.
+    pub fn create_listing<S: SlotType + Clone>(
+        &mut self,
+        seller: i32,
+        from: (&mut Inventory<K, S, U>, &mut Wallet<C>),
+        idx: usize,
+        quantity: usize,
+        terms: ListingTerms<C>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<u32, MarketError<C>> {
+        if terms.starting_bid.is_none() && terms.buyout.is_none() {
+            return Err(MarketError::InvalidTerms);
+        }
+        let (inventory, wallet) = from;
+        let mut new_inventory = inventory.clone();
+        let item = new_inventory
+            .delete(idx, quantity)
+            .map_err(|_| MarketError::NotEnoughItems)?;
+        if self.fees.listing_fee > 0 {
+            wallet
+                .remove(&terms.currency, self.fees.listing_fee, currency_defs)
+                .map_err(MarketError::Wallet)?;
+        }
+        *inventory = new_inventory;
+        let id = self.next_id;
+        self.next_id += 1;
+        let expires = Timer::once(terms.duration);
+        self.listings
+            .push(Listing::new(id, seller, item, terms, expires));
+        Ok(id)
+    }
+
+    /// Returns the listing with this id.
+    pub fn get(&self, id: u32) -> Option<&Listing<K, U, C>> {
+        self.listings.iter().find(|l| l.id == id)
+    }
+
+    /// Returns the listings selling this item.
+    pub fn search_key(&self, key: &K) -> Vec<&Listing<K, U, C>> {
+        self.listings
+            .iter()
+            .filter(|l| l.item.key == *key)
+            .collect()
+    }
+
+    /// Returns the listings selling items whose name or friendly name contains the text,
+    /// ignoring the case.
+    pub fn search_name<S, D: Default>(
+        &self,
+        text: &str,
+        item_defs: &ItemDefinitions<K, S, D>,
+    ) -> Vec<&Listing<K, U, C>> {
+        let text = text.to_lowercase();
+        self.listings
+            .iter()
+            .filter(|l| {
+                item_defs
+                    .defs
+                    .get(&l.item.key)
+                    .map(|d| {
+                        d.name.fallback_text().to_lowercase().contains(&text)
+                            || d.friendly_name.to_lowercase().contains(&text)
+                    })
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Returns the listings of the seller.
+    pub fn listings_of(&self, seller: i32) -> Vec<&Listing<K, U, C>> {
+        self.listings
+            .iter()
+            .filter(|l| l.seller == seller)
+            .collect()
+    }
+
+    /// Bids on a listing. The amount is removed from the wallet and held by the market.
+    /// The previous highest bid is refunded to its bidder.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * UnknownListing: No active listing has this id.
+    /// * OwnListing: The bidder is the seller.
+    /// * NoBids: The listing doesn't accept bids.
+    /// * BidTooLow: The amount is lower than `Listing::minimum_bid`.
+    /// * Wallet: The wallet can't pay the amount.
+    pub fn bid(
+        &mut self,
+        id: u32,
+        bidder: i32,
+        wallet: &mut Wallet<C>,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), MarketError<C>> {
+        let pos = self.position(id)?;
+        let listing = &self.listings[pos];
+        if listing.seller == bidder {
+            return Err(MarketError::OwnListing);
+        }
+        let minimum = listing.minimum_bid().ok_or(MarketError::NoBids)?;
+        if amount < minimum {
+            return Err(MarketError::BidTooLow);
+        }
+        wallet
+            .remove(&listing.terms.currency, amount, currency_defs)
+            .map_err(MarketError::Wallet)?;
+        let currency = listing.terms.currency.clone();
+        if let Some(previous) = self.listings[pos].highest_bid.take() {
+            self.deliver(
+                previous.bidder,
+                MarketDelivery::Currency(currency, previous.amount),
+            );
+        }
+        self.listings[pos].highest_bid = Some(Bid::new(bidder, amount));
+        Ok(())
+    }
+
+    /// Buys a listing at its buyout price. The items are moved into the buyer inventory and
+    /// the price is removed from the wallet. The listing ends.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * UnknownListing: No active listing has this id.
+    /// * OwnListing: The buyer is the seller.
+    /// * NoBuyout: The listing has no buyout price.
+    /// * Wallet: The wallet can't pay the price.
+    /// * InventoryFull: The items don't fit in the buyer inventory.
+    pub fn buyout<S: SlotType + Clone, D: Default>(
+        &mut self,
+        id: u32,
+        buyer: i32,
+        to: (&mut Inventory<K, S, U>, &mut Wallet<C>),
+        item_defs: &ItemDefinitions<K, S, D>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<MarketEvent<C>, MarketError<C>> {
+        let pos = self.position(id)?;
+        let listing = &self.listings[pos];
+        if listing.seller == buyer {
+            return Err(MarketError::OwnListing);
+        }
+        let price = listing.terms.buyout.ok_or(MarketError::NoBuyout)?;
+        let (inventory, wallet) = to;
+        let mut new_wallet = wallet.clone();
+        new_wallet
+            .remove(&listing.terms.currency, price, currency_defs)
+            .map_err(MarketError::Wallet)?;
+        let mut new_inventory = inventory.clone();
+        new_inventory
+            .insert(listing.item.clone(), item_defs)
+            .map_err(|_| MarketError::InventoryFull)?;
+        *inventory = new_inventory;
+        *wallet = new_wallet;
+        let listing = self.listings.remove(pos);
+        if let Some(previous) = listing.highest_bid {
+            self.deliver(
+                previous.bidder,
+                MarketDelivery::Currency(listing.terms.currency.clone(), previous.amount),
+            );
+        }
+        self.deliver(
+            listing.seller,
+            MarketDelivery::Currency(listing.terms.currency.clone(), self.fees.proceeds(price)),
+        );
+        Ok(MarketEvent::Sold {
+            listing: listing.id,
+            buyer,
+            currency: listing.terms.currency,
+            price,
+        })
+    }
+
+    /// Cancels a listing that has no bid. The items are delivered back to the seller.
+    ///
+    /// Errors:
+    /// * UnknownListing: No active listing has this id.
+    /// * NotSeller: The user is not the seller of the listing.
+    /// * HasBids: Someone already bid on the listing.
+    pub fn cancel(&mut self, id: u32, seller: i32) -> Result<(), MarketError<C>> {
+        let pos = self.position(id)?;
+        let listing = &self.listings[pos];
+        if listing.seller != seller {
+            return Err(MarketError::NotSeller);
+        }
+        if listing.highest_bid.is_some() {
+            return Err(MarketError::HasBids);
+        }
+        let listing = self.listings.remove(pos);
+        self.deliver(listing.seller, MarketDelivery::Item(listing.item));
+        Ok(())
+    }
+
+    /// Advances the listings by `delta_time` seconds and ends the expired ones.
+    /// Listings with a bid are sold to the highest bidder, the others are returned to their
+    /// seller.
+    pub fn update(&mut self, delta_time: f64) -> Vec<MarketEvent<C>> {
+        let mut ended = vec![];
+        let mut i = 0;
+        while i < self.listings.len() {
+            if self.listings[i].expires.update(delta_time) > 0 {
+                ended.push(self.listings.remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        let mut events = vec![];
+        for listing in ended {
+            if let Some(bid) = listing.highest_bid {
+                self.deliver(bid.bidder, MarketDelivery::Item(listing.item));
+                self.deliver(
+                    listing.seller,
+                    MarketDelivery::Currency(
+                        listing.terms.currency.clone(),
+                        self.fees.proceeds(bid.amount),
+                    ),
+                );
+                events.push(MarketEvent::Sold {
+                    listing: listing.id,
+                    buyer: bid.bidder,
+                    currency: listing.terms.currency,
+                    price: bid.amount,
+                });
+            } else {
+                self.deliver(listing.seller, MarketDelivery::Item(listing.item));
+                events.push(MarketEvent::Expired {
+                    listing: listing.id,
+                });
+            }
+        }
+        events
+    }
+
+    /// Returns what the user can claim.
+    pub fn deliveries_of(&self, user: i32) -> &[MarketDelivery<K, U, C>] {
+        self.deliveries
+            .get(&user)
+            .map(|d| d.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Moves everything the user can claim into their inventory and wallet.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * InventoryFull: The items don't fit in the inventory.
+    /// * Wallet: The wallet can't hold the currencies.
+    pub fn claim<S: SlotType + Clone, D: Default>(
+        &mut self,
+        user: i32,
+        to: (&mut Inventory<K, S, U>, &mut Wallet<C>),
+        item_defs: &ItemDefinitions<K, S, D>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), MarketError<C>> {
+        let (inventory, wallet) = to;
+        let mut new_inventory = inventory.clone();
+        let mut new_wallet = wallet.clone();
+        for delivery in self.deliveries_of(user) {
+            match delivery {
+                MarketDelivery::Item(item) => {
+                    new_inventory
+                        .insert(item.clone(), item_defs)
+                        .map_err(|_| MarketError::InventoryFull)?;
+                }
+                MarketDelivery::Currency(currency, amount) => {
+                    new_wallet
+                        .add(currency, *amount, currency_defs)
+                        .map_err(MarketError::Wallet)?;
+                }
+            }
+        }
+        *inventory = new_inventory;
+        *wallet = new_wallet;
+        self.deliveries.remove(&user);
+        Ok(())
+    }
+
+    fn deliver(&mut self, user: i32, delivery: MarketDelivery<K, U, C>) {
+        self.deliveries.entry(user).or_default().push(delivery);
+    }
+
+    fn position(&self, id: u32) -> Result<usize, MarketError<C>> {
+        self.listings
+            .iter()
+            .position(|l| l.id == id)
+            .ok_or(MarketError::UnknownListing)
+    }
+}
+
+/// The errors that can happen when using a `Market`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarketError<C> {
+    /// No active listing has this id.
+    UnknownListing,
+    /// The listing has neither a starting bid nor a buyout price.
+    InvalidTerms,
+    /// The seller doesn't have enough of the items.
+    NotEnoughItems,
+    /// Users can't bid on or buy their own listing.
+    OwnListing,
+    /// Only the seller can do this.
+    NotSeller,
+    /// The listing already has a bid.
+    HasBids,
+    /// The listing doesn't accept bids.
+    NoBids,
+    /// The bid is lower than the minimum bid of the listing.
+    BidTooLow,
+    /// The listing has no buyout price.
+    NoBuyout,
+    /// The items don't fit in the inventory.
+    InventoryFull,
+    /// The wallet can't pay or receive the currency.
+    Wallet(WalletError<C>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn outbid_and_sold_on_expiration() {
+        let (item_defs, currency_defs) = (item_defs(), currency_defs());
+        let mut market = Market::<u32, (), u8>::new(MarketFees::new(5, 0.1));
+        let mut seller = player(10);
+        let mut first = player(100);
+        let mut second = player(100);
+        seller
+            .0
+            .insert(ItemInstance::new(1, 4), &item_defs)
+            .unwrap();
+        let terms = ListingTerms::new(0, Some(20), None, 60.0);
+        let id = market
+            .create_listing(
+                1,
+                (&mut seller.0, &mut seller.1),
+                0,
+                3,
+                terms,
+                &currency_defs,
+            )
+            .unwrap();
+        assert_eq!(seller.0.get(0).as_ref().unwrap().quantity, 1);
+        assert_eq!(seller.1.balance(&0), 5);
+
+        assert_eq!(
+            market.bid(id, 2, &mut first.1, 10, &currency_defs),
+            Err(MarketError::BidTooLow)
+        );
+        market.bid(id, 2, &mut first.1, 20, &currency_defs).unwrap();
+        market
+            .bid(id, 3, &mut second.1, 50, &currency_defs)
+            .unwrap();
+        assert_eq!(market.cancel(id, 1), Err(MarketError::HasBids));
+        assert_eq!(first.1.balance(&0), 80);
+        assert_eq!(second.1.balance(&0), 50);
+
+        assert_eq!(
+            market.update(61.0),
+            vec![MarketEvent::Sold {
+                listing: id,
+                buyer: 3,
+                currency: 0,
+                price: 50,
+            }]
+        );
+        for (user, p) in [(1, &mut seller), (2, &mut first), (3, &mut second)].iter_mut() {
+            market
+                .claim(*user, (&mut p.0, &mut p.1), &item_defs, &currency_defs)
+                .unwrap();
+        }
+        // The refunded bid, the sold items and the proceeds minus the sale fee.
+        assert_eq!(first.1.balance(&0), 100);
+        assert_eq!(second.0.get(0).as_ref().unwrap().quantity, 3);
+        assert_eq!(seller.1.balance(&0), 50);
+        assert!(market.deliveries.is_empty());
+    }
+
+    #[test]
+    fn failed_buyout_changes_nothing() {
+        let (item_defs, currency_defs) = (item_defs(), currency_defs());
+        let mut market = Market::<u32, (), u8>::new(MarketFees::default());
+        let mut seller = player(0);
+        let mut buyer = player(100);
+        seller
+            .0
+            .insert(ItemInstance::new(1, 3), &item_defs)
+            .unwrap();
+        buyer.0.insert(ItemInstance::new(1, 9), &item_defs).unwrap();
+        let terms = ListingTerms::new(0, None, Some(30), 60.0);
+        let id = market
+            .create_listing(
+                1,
+                (&mut seller.0, &mut seller.1),
+                0,
+                3,
+                terms,
+                &currency_defs,
+            )
+            .unwrap();
+        assert_eq!(
+            market.buyout(
+                id,
+                2,
+                (&mut buyer.0, &mut buyer.1),
+                &item_defs,
+                &currency_defs
+            ),
+            Err(MarketError::InventoryFull)
+        );
+        assert_eq!(buyer.1.balance(&0), 100);
+        assert!(market.get(id).is_some());
+        buyer.0.delete(0, 9).unwrap();
+        market
+            .buyout(
+                id,
+                2,
+                (&mut buyer.0, &mut buyer.1),
+                &item_defs,
+                &currency_defs,
+            )
+            .unwrap();
+        assert_eq!(buyer.1.balance(&0), 70);
+        assert_eq!(buyer.0.get(0).as_ref().unwrap().quantity, 3);
+        assert_eq!(market.get(id).map(|l| l.id), None);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The stamina of a `Mount`, drained while it is ridden.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct MountStamina<K> {
+    /// The stat of the mount holding its stamina.
+    pub stat: K,
+    /// The stamina removed each second while the mount is ridden.
+    pub drain_per_second: f64,
+    /// The stamina recovered each second while the mount is not ridden.
+    pub regen_per_second: f64,
+    /// The minimum stamina required to ride the mount.
+    /// The rider is dismounted when the stamina reaches 0.
+    pub min_to_mount: f64,
+}
+
+/// A creature or vehicle that can be ridden, granting effectors like movement speed to its
+/// rider. Store mounts in a `CompanionStable` so that only one of them is ridden at a time.
+///
+/// # Generics
+/// - K: Stat Key
+/// - E: Effector Key
+/// - I: Item Key
+/// - IT: Item Slot Type
+/// - CD: Item Custom Data
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Mount<K: Hash + Eq, E, I, IT: SlotType, CD: Default> {
+    /// The display name of the mount.
+    pub name: String,
+    /// The stats of the mount.
+    pub stats: StatSet<K>,
+    /// The effectors added to the rider while mounted, like a movement speed multiplier.
+    pub effectors: Vec<E>,
+    /// The bags carried by the mount, if any.
+    #[new(default)]
+    pub saddlebag: Option<Inventory<I, IT, CD>>,
+    /// The stamina of the mount.
+    /// None means that the mount never gets tired.
+    #[new(default)]
+    pub stamina: Option<MountStamina<K>>,
+}
+
+impl<K: Hash + Eq + Debug, E, I, IT: SlotType, CD: Default> Mount<K, E, I, IT, CD> {
+    /// Returns the current stamina, if the mount uses stamina.
+    pub fn current_stamina(&self) -> Option<f64> {
+        self.stamina.as_ref().map(|s| {
+            self.stats
+                .stats
+                .get(&s.stat)
+                .map(|i| i.value)
+                .unwrap_or(0.0)
+        })
+    }
+
+    /// Checks if the mount has enough stamina to be ridden.
+    pub fn can_mount(&self) -> bool {
+        match (self.stamina.as_ref(), self.current_stamina()) {
+            (Some(stamina), Some(current)) => current >= stamina.min_to_mount,
+            _ => true,
+        }
+    }
+}
+
+impl<
+        Id: Hash + Eq + Clone,
+        K: Hash + Eq + Clone + Debug,
+        E: Hash + Eq + Clone,
+        I,
+        IT: SlotType,
+        CD: Default,
+    > CompanionStable<Id, Mount<K, E, I, IT, CD>>
+{
+    /// Rides the mount, adding its effectors to the rider.
+    /// The mount currently ridden is dismounted first.
+    ///
+    /// Errors:
+    /// * UnknownCompanion: No mount has this id.
+    /// * Exhausted: The mount doesn't have enough stamina.
+    pub fn mount<SK>(
+        &mut self,
+        id: &Id,
+        rider_effectors: &mut EffectorSet<E>,
+        effector_defs: &EffectorDefinitions<SK, E>,
+    ) -> Result<(), CompanionError<Id>> {
+        let mount = self
+            .companions
+            .get(id)
+            .ok_or_else(|| CompanionError::UnknownCompanion(id.clone()))?;
+        if !mount.can_mount() {
+            return Err(CompanionError::Exhausted);
+        }
+        self.dismount(rider_effectors);
+        for e in self.companions[id].effectors.iter() {
+            rider_effectors.add(e, effector_defs);
+        }
+        self.active = Some(id.clone());
+        Ok(())
+    }
+
+    /// Stops riding the current mount, removing its effectors from the rider.
+    pub fn dismount(&mut self, rider_effectors: &mut EffectorSet<E>) {
+        if let Some(mount) = self.active() {
+            for e in mount.effectors.iter() {
+                if let Some(idx) = rider_effectors
+                    .effectors
+                    .iter()
+                    .position(|i| i.effector_key == *e)
+                {
+                    rider_effectors.effectors.swap_remove(idx);
+                }
+            }
+        }
+        self.active = None;
+    }
+
+    /// Drains the stamina of the ridden mount and regenerates the stamina of the others for
+    /// `delta_time` seconds.
+    /// The rider is dismounted when the stamina of the ridden mount reaches 0.
+    /// Returns true if the rider was dismounted.
+    pub fn update(
+        &mut self,
+        delta_time: f64,
+        rider_effectors: &mut EffectorSet<E>,
+        stat_defs: &StatDefinitions<K>,
+    ) -> bool {
+        for (id, mount) in self.companions.iter_mut() {
+            if let Some(stamina) = mount.stamina.as_ref() {
+                let delta = if self.active.as_ref() == Some(id) {
+                    -stamina.drain_per_second
+                } else {
+                    stamina.regen_per_second
+                };
+                mount
+                    .stats
+                    .add_to(&stamina.stat, delta * delta_time, stat_defs);
+            }
+        }
+        let exhausted = self
+            .active()
+            .and_then(|m| m.current_stamina())
+            .map(|s| s <= 0.0)
+            .unwrap_or(false);
+        if exhausted {
+            self.dismount(rider_effectors);
+        }
+        exhausted
+    }
+}
@@ -0,0 +1,169 @@
+use crate::*;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// Marks the start of a name in the states of a `NameModel::Markov`.
+const START: char = '^';
+/// Marks the end of a name in the states of a `NameModel::Markov`.
+const END: char = '$';
+
+/// The way a `NameGenerator` creates names.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NameModel {
+    /// Names made of syllables picked at random, like "Ka" + "ro" + "th".
+    Syllables {
+        /// The syllables.
+        syllables: Vec<String>,
+        /// The minimum number of syllables of a name.
+        min_syllables: usize,
+        /// The maximum number of syllables of a name.
+        max_syllables: usize,
+    },
+    /// Names where each letter is picked based on the letters before it in example names.
+    /// See `NameGenerator::markov`.
+    Markov {
+        /// The number of previous letters used to pick the next one.
+        order: usize,
+        /// The letters found after each sequence of `order` letters in the example names.
+        /// A letter appears once per time it was found, so that common sequences are more
+        /// likely.
+        transitions: HashMap<String, Vec<char>>,
+        /// The minimum number of letters of a name.
+        min_length: usize,
+        /// The maximum number of letters of a name.
+        max_length: usize,
+    },
+}
+
+/// Creates random names for NPCs, affixes or factions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameGenerator {
+    /// The way names are created.
+    pub model: NameModel,
+    /// The names containing one of these words are never generated, ignoring the case.
+    pub banned_words: Vec<String>,
+    /// The number of names generated before giving up when they all contain banned words.
+    pub max_attempts: usize,
+}
+
+impl NameGenerator {
+    /// Creates a generator joining between `min_syllables` and `max_syllables` syllables.
+    pub fn syllables(syllables: Vec<String>, min_syllables: usize, max_syllables: usize) -> Self {
+        Self::with_model(NameModel::Syllables {
+            syllables,
+            min_syllables,
+            max_syllables,
+        })
+    }
+
+    /// Creates a generator making names similar to the examples, between `min_length` and
+    /// `max_length` letters long.
+    /// A higher `order` gives names closer to the examples, usually 2 or 3.
+    pub fn markov(examples: &[&str], order: usize, min_length: usize, max_length: usize) -> Self {
+        let mut transitions: HashMap<String, Vec<char>> = HashMap::new();
+        for example in examples {
+            let letters = vec![START; order]
+                .into_iter()
+                .chain(example.to_lowercase().chars())
+                .chain(core::iter::once(END))
+                .collect::<Vec<_>>();
+            for window in letters.windows(order + 1) {
+                transitions
+                    .entry(window[..order].iter().collect())
+                    .or_default()
+                    .push(window[order]);
+            }
+        }
+        Self::with_model(NameModel::Markov {
+            order,
+            transitions,
+            min_length,
+            max_length,
+        })
+    }
+
+    fn with_model(model: NameModel) -> Self {
+        Self {
+            model,
+            banned_words: vec![],
+            max_attempts: 100,
+        }
+    }
+
+    /// Generates a name, starting with an uppercase letter.
+    /// Returns None if no name without banned words was found in `max_attempts` attempts.
+    #[cfg(feature = "std")]
+    pub fn generate(&self) -> Option<String> {
+        self.generate_with(&mut thread_rng())
+    }
+
+    /// Same as `NameGenerator::generate`, using the provided random number generator.
+    /// Use this with a seeded generator (see `seeded_rng`) to get deterministic names.
+    pub fn generate_with<G: Rng>(&self, rng: &mut G) -> Option<String> {
+        (0..self.max_attempts)
+            .filter_map(|_| self.attempt(rng))
+            .map(|name| capitalize(&name))
+            .find(|name| self.is_allowed(name))
+    }
+
+    /// Checks if the name doesn't contain any banned word.
+    pub fn is_allowed(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        !self
+            .banned_words
+            .iter()
+            .any(|w| name.contains(&w.to_lowercase()))
+    }
+
+    fn attempt<G: Rng>(&self, rng: &mut G) -> Option<String> {
+        match &self.model {
+            NameModel::Syllables {
+                syllables,
+                min_syllables,
+                max_syllables,
+            } => {
+                if syllables.is_empty() {
+                    return None;
+                }
+                let count = rng.gen_range(*min_syllables, max_syllables.max(min_syllables) + 1);
+                Some(
+                    (0..count)
+                        .map(|_| syllables[rng.gen_range(0, syllables.len())].as_str())
+                        .collect(),
+                )
+            }
+            NameModel::Markov {
+                order,
+                transitions,
+                min_length,
+                max_length,
+            } => {
+                let mut state = START.to_string().repeat(*order);
+                let mut name = String::new();
+                while name.chars().count() < *max_length {
+                    let next = transitions.get(&state)?;
+                    let c = next[rng.gen_range(0, next.len())];
+                    if c == END {
+                        break;
+                    }
+                    name.push(c);
+                    state.push(c);
+                    state.remove(0);
+                }
+                if name.chars().count() < *min_length {
+                    return None;
+                }
+                Some(name)
+            }
+        }
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,266 @@
+use crate::*;
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// A number of dice with the same number of sides, rolled and summed together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, new)]
+pub struct Dice {
+    /// The number of dice.
+    pub count: u32,
+    /// The number of sides of each die.
+    pub sides: u32,
+}
+
+impl Dice {
+    /// A single 20 sided die, giving an uniform result.
+    pub fn d20() -> Self {
+        Self::new(1, 20)
+    }
+
+    /// Three 6 sided dice, giving results close to the average more often.
+    pub fn three_d6() -> Self {
+        Self::new(3, 6)
+    }
+
+    /// A single 100 sided die.
+    pub fn percentile() -> Self {
+        Self::new(1, 100)
+    }
+
+    /// The lowest possible result.
+    pub fn min(&self) -> u32 {
+        self.count
+    }
+
+    /// The highest possible result.
+    pub fn max(&self) -> u32 {
+        self.count * self.sides
+    }
+
+    /// Rolls the dice and returns the sum of the results.
+    #[cfg(feature = "std")]
+    pub fn roll(&self) -> u32 {
+        self.roll_with(&mut thread_rng())
+    }
+
+    /// Same as `Dice::roll`, using the provided random number generator.
+    pub fn roll_with<G: Rng>(&self, rng: &mut G) -> u32 {
+        (0..self.count)
+            .map(|_| rng.gen_range(1, self.sides.max(1) + 1))
+            .sum()
+    }
+}
+
+/// The result of an `OpposedCheck`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckResult {
+    /// The roll reached the critical success threshold. Always a success.
+    CriticalSuccess,
+    /// The total of the roll beat the opposition.
+    Success,
+    /// The total of the roll didn't beat the opposition.
+    Failure,
+    /// The roll reached the critical failure threshold. Always a failure.
+    CriticalFailure,
+}
+
+impl CheckResult {
+    /// Checks if this is a success or a critical success.
+    pub fn is_success(&self) -> bool {
+        match self {
+            CheckResult::CriticalSuccess | CheckResult::Success => true,
+            CheckResult::Failure | CheckResult::CriticalFailure => false,
+        }
+    }
+}
+
+/// The detailed outcome of an `OpposedCheck`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+pub struct CheckOutcome {
+    /// The result of the dice of the attacker.
+    pub attacker_roll: u32,
+    /// The result of the dice of the attacker added to its stat.
+    pub attacker_total: f64,
+    /// The result of the dice of the defender, if the defender rolled.
+    pub defender_roll: Option<u32>,
+    /// The total the attacker had to beat.
+    pub defender_total: f64,
+    /// The result of the check.
+    pub result: CheckResult,
+}
+
+impl CheckOutcome {
+    /// The difference between the totals of the attacker and the defender.
+    pub fn margin(&self) -> f64 {
+        self.attacker_total - self.defender_total
+    }
+}
+
+/// A check rolling the stat of an attacker against the stat of a defender, or against a
+/// fixed difficulty for saving throws.
+/// The values of the stats include their effectors, so bonuses and penalties are applied
+/// through `EffectorSet::apply_to`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct OpposedCheck<K> {
+    /// The dice rolled by each side.
+    pub dice: Dice,
+    /// The stat added to the roll of the attacker.
+    pub attacker_stat: K,
+    /// The stat added to the roll of the defender.
+    pub defender_stat: K,
+    /// The minimum roll of the attacker resulting in a critical success.
+    #[new(default)]
+    #[serde(default)]
+    pub critical_success: Option<u32>,
+    /// The maximum roll of the attacker resulting in a critical failure.
+    #[new(default)]
+    #[serde(default)]
+    pub critical_failure: Option<u32>,
+    /// Whether the attacker succeeds when both totals are equal.
+    #[new(default)]
+    #[serde(default)]
+    pub attacker_wins_ties: bool,
+}
+
+impl<K: Hash + Eq> OpposedCheck<K> {
+    /// Rolls the dice for the attacker and the defender, adds their stat to their roll and
+    /// compares the totals.
+    #[cfg(feature = "std")]
+    pub fn roll(&self, attacker: &StatSet<K>, defender: &StatSet<K>) -> CheckOutcome {
+        self.roll_with(attacker, defender, &mut thread_rng())
+    }
+
+    /// Same as `OpposedCheck::roll`, using the provided random number generator.
+    pub fn roll_with<G: Rng>(
+        &self,
+        attacker: &StatSet<K>,
+        defender: &StatSet<K>,
+        rng: &mut G,
+    ) -> CheckOutcome {
+        let attacker_roll = self.dice.roll_with(rng);
+        let defender_roll = self.dice.roll_with(rng);
+        let defender_total = f64::from(defender_roll) + stat_value(defender, &self.defender_stat);
+        self.outcome(attacker, attacker_roll, Some(defender_roll), defender_total)
+    }
+
+    /// Rolls the dice for the attacker only and compares the total against the difficulty.
+    /// Use this for saving throws and skill checks.
+    #[cfg(feature = "std")]
+    pub fn roll_against(&self, attacker: &StatSet<K>, difficulty: f64) -> CheckOutcome {
+        self.roll_against_with(attacker, difficulty, &mut thread_rng())
+    }
+
+    /// Same as `OpposedCheck::roll_against`, using the provided random number generator.
+    pub fn roll_against_with<G: Rng>(
+        &self,
+        attacker: &StatSet<K>,
+        difficulty: f64,
+        rng: &mut G,
+    ) -> CheckOutcome {
+        let attacker_roll = self.dice.roll_with(rng);
+        self.outcome(attacker, attacker_roll, None, difficulty)
+    }
+
+    fn outcome(
+        &self,
+        attacker: &StatSet<K>,
+        attacker_roll: u32,
+        defender_roll: Option<u32>,
+        defender_total: f64,
+    ) -> CheckOutcome {
+        let attacker_total = f64::from(attacker_roll) + stat_value(attacker, &self.attacker_stat);
+        let result = if self
+            .critical_success
+            .map(|t| attacker_roll >= t)
+            .unwrap_or(false)
+        {
+            CheckResult::CriticalSuccess
+        } else if self
+            .critical_failure
+            .map(|t| attacker_roll <= t)
+            .unwrap_or(false)
+        {
+            CheckResult::CriticalFailure
+        } else if attacker_total > defender_total
+            || (self.attacker_wins_ties && attacker_total == defender_total)
+        {
+            CheckResult::Success
+        } else {
+            CheckResult::Failure
+        };
+        CheckOutcome::new(
+            attacker_roll,
+            attacker_total,
+            defender_roll,
+            defender_total,
+            result,
+        )
+    }
+}
+
+fn stat_value<K: Hash + Eq>(stats: &StatSet<K>, key: &K) -> f64 {
+    stats
+        .stats
+        .get(key)
+        .expect("Tried to get unknown stat key.")
+        .value_with_effectors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeded_rng;
+
+    fn stats(strength: f64) -> StatSet<u32> {
+        StatDefinitions::from(vec![StatDefinition::new(
+            0,
+            "Strength".into(),
+            "strength".to_string(),
+            strength,
+        )])
+        .to_statset()
+    }
+
+    #[test]
+    fn dice_stay_in_bounds() {
+        let dice = Dice::three_d6();
+        let mut rng = seeded_rng(4);
+        for _ in 0..100 {
+            let roll = dice.roll_with(&mut rng);
+            assert!(roll >= dice.min() && roll <= dice.max());
+        }
+    }
+
+    #[test]
+    fn totals_are_compared() {
+        // A single die with one side always rolls 1.
+        let mut check = OpposedCheck::new(Dice::new(1, 1), 0, 0);
+        let mut rng = seeded_rng(0);
+        let outcome = check.roll_with(&stats(5.0), &stats(3.0), &mut rng);
+        assert_eq!(outcome.result, CheckResult::Success);
+        assert_eq!(outcome.defender_roll, Some(1));
+        assert_eq!(outcome.margin(), 2.0);
+        let outcome = check.roll_against_with(&stats(5.0), 6.0, &mut rng);
+        assert_eq!(outcome.result, CheckResult::Failure);
+        assert_eq!(outcome.defender_roll, None);
+        check.attacker_wins_ties = true;
+        assert!(check
+            .roll_against_with(&stats(5.0), 6.0, &mut rng)
+            .result
+            .is_success());
+    }
+
+    #[test]
+    fn criticals_ignore_the_totals() {
+        let mut check = OpposedCheck::new(Dice::new(1, 1), 0, 0);
+        let mut rng = seeded_rng(0);
+        check.critical_failure = Some(1);
+        let outcome = check.roll_against_with(&stats(50.0), 0.0, &mut rng);
+        assert_eq!(outcome.result, CheckResult::CriticalFailure);
+        check.critical_success = Some(1);
+        let outcome = check.roll_against_with(&stats(0.0), 50.0, &mut rng);
+        assert_eq!(outcome.result, CheckResult::CriticalSuccess);
+    }
+}
@@ -0,0 +1,459 @@
+use crate::*;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// The role of a member of a `Party`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PartyRole {
+    /// Manages the party. There is exactly one leader.
+    Leader,
+    /// Can invite and kick members.
+    Assistant,
+    /// A regular member.
+    Member,
+}
+
+/// A member of a `Party`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct PartyMember {
+    /// The id of the user.
+    pub user: i32,
+    /// The role of the user.
+    pub role: PartyRole,
+}
+
+/// An invitation to join a `Party`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct PartyInvitation {
+    /// The id of the invited user.
+    pub user: i32,
+    /// The id of the member who sent the invitation.
+    pub invited_by: i32,
+    /// The time in seconds before the invitation expires.
+    pub expires_in: f64,
+}
+
+/// A ready check, asking all members if they are ready.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ReadyCheck {
+    /// The answers of the members.
+    #[new(default)]
+    pub answers: HashMap<i32, bool>,
+    /// The time in seconds before the ready check ends.
+    pub remaining: f64,
+}
+
+/// The result of a `ReadyCheck`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReadyCheckState {
+    /// Some members didn't answer yet.
+    Pending,
+    /// All members are ready.
+    Ready,
+    /// A member is not ready, or the ready check ended before all members answered.
+    NotReady,
+}
+
+/// The way loot is distributed between the members of a `Party`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LootDistributionMode {
+    /// Anyone can take the loot.
+    FreeForAll,
+    /// Each loot goes to the next member in turn.
+    RoundRobin,
+    /// Members choose need, greed or pass. Members choosing need roll first, then members
+    /// choosing greed.
+    NeedGreed,
+}
+
+/// The choice of a member for a `LootRoll`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LootChoice {
+    /// The member needs the loot.
+    Need,
+    /// The member wants the loot.
+    Greed,
+    /// The member doesn't want the loot.
+    Pass,
+}
+
+/// A loot to distribute to the members of a `Party`, usually a result of a `LootTree`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct LootRoll<R> {
+    /// The loot.
+    pub loot: R,
+    /// The choice of each member. Used with `LootDistributionMode::NeedGreed`.
+    #[new(default)]
+    pub choices: HashMap<i32, LootChoice>,
+    /// The member who won the loot, once distributed.
+    #[new(default)]
+    pub winner: Option<i32>,
+}
+
+/// A temporary group of users playing together.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Party {
+    /// The id of the party.
+    pub id: i32,
+    /// The members of the party, in the order they joined.
+    #[new(default)]
+    pub members: Vec<PartyMember>,
+    /// The pending invitations.
+    #[new(default)]
+    pub invitations: Vec<PartyInvitation>,
+    /// The ready check in progress, if any.
+    #[new(default)]
+    pub ready_check: Option<ReadyCheck>,
+    /// The way loot is distributed.
+    #[new(value = "LootDistributionMode::FreeForAll")]
+    pub loot_mode: LootDistributionMode,
+    /// The index of the member receiving the next loot with `LootDistributionMode::RoundRobin`.
+    #[new(default)]
+    pub round_robin_index: usize,
+    /// The maximum number of members.
+    pub maximum_members: usize,
+}
+
+impl Party {
+    /// Creates a new party with a leader.
+    pub fn with_leader(id: i32, leader: i32, maximum_members: usize) -> Self {
+        let mut party = Party::new(id, maximum_members);
+        party
+            .members
+            .push(PartyMember::new(leader, PartyRole::Leader));
+        party
+    }
+
+    /// Returns the id of the leader.
+    pub fn leader(&self) -> Option<i32> {
+        self.members
+            .iter()
+            .find(|m| m.role == PartyRole::Leader)
+            .map(|m| m.user)
+    }
+
+    /// Returns the role of the user, if they are a member.
+    pub fn role_of(&self, user: i32) -> Option<PartyRole> {
+        self.members.iter().find(|m| m.user == user).map(|m| m.role)
+    }
+
+    /// Checks if the user is a member.
+    pub fn is_member(&self, user: i32) -> bool {
+        self.role_of(user).is_some()
+    }
+
+    /// Checks if the party has the maximum number of members.
+    pub fn is_full(&self) -> bool {
+        self.members.len() >= self.maximum_members
+    }
+
+    /// Decreases the timers of the invitations and of the ready check by `delta_time` seconds.
+    /// Expired invitations are removed.
+    pub fn update(&mut self, delta_time: f64) {
+        for i in self.invitations.iter_mut() {
+            i.expires_in -= delta_time;
+        }
+        self.invitations.retain(|i| i.expires_in > 0.0);
+        if let Some(check) = self.ready_check.as_mut() {
+            check.remaining = (check.remaining - delta_time).max(0.0);
+        }
+    }
+
+    /// Invites the user to join the party.
+    /// Inviting an already invited user resets the expiration of the invitation.
+    ///
+    /// Errors:
+    /// * MissingPermission: The inviting user is not the leader or an assistant.
+    /// * AlreadyMember: The invited user is already a member.
+    /// * PartyFull: The party has the maximum number of members.
+    pub fn invite(&mut self, by: i32, user: i32, expires_in: f64) -> Result<(), PartyError> {
+        self.check_can_manage(by)?;
+        if self.is_member(user) {
+            return Err(PartyError::AlreadyMember);
+        }
+        if self.is_full() {
+            return Err(PartyError::PartyFull);
+        }
+        self.invitations.retain(|i| i.user != user);
+        self.invitations
+            .push(PartyInvitation::new(user, by, expires_in));
+        Ok(())
+    }
+
+    /// Accepts the invitation of the user, who becomes a member.
+    ///
+    /// Errors:
+    /// * NotInvited: The user has no pending invitation.
+    /// * PartyFull: The party has the maximum number of members.
+    pub fn accept_invitation(&mut self, user: i32) -> Result<(), PartyError> {
+        if !self.invitations.iter().any(|i| i.user == user) {
+            return Err(PartyError::NotInvited);
+        }
+        if self.is_full() {
+            return Err(PartyError::PartyFull);
+        }
+        self.invitations.retain(|i| i.user != user);
+        self.members.push(PartyMember::new(user, PartyRole::Member));
+        Ok(())
+    }
+
+    /// Declines the invitation of the user.
+    /// Returns true if there was an invitation.
+    pub fn decline_invitation(&mut self, user: i32) -> bool {
+        let before = self.invitations.len();
+        self.invitations.retain(|i| i.user != user);
+        before != self.invitations.len()
+    }
+
+    /// Removes the user from the party.
+    /// If the user was the leader, the oldest remaining member becomes the leader.
+    ///
+    /// Errors:
+    /// * NotMember: The user is not a member.
+    pub fn leave(&mut self, user: i32) -> Result<(), PartyError> {
+        let idx = self
+            .members
+            .iter()
+            .position(|m| m.user == user)
+            .ok_or(PartyError::NotMember)?;
+        let removed = self.members.remove(idx);
+        if removed.role == PartyRole::Leader {
+            if let Some(m) = self.members.first_mut() {
+                m.role = PartyRole::Leader;
+            }
+        }
+        if let Some(check) = self.ready_check.as_mut() {
+            check.answers.remove(&user);
+        }
+        Ok(())
+    }
+
+    /// Removes a member from the party.
+    ///
+    /// Errors:
+    /// * MissingPermission: The kicking user is not the leader or an assistant, or tries to kick
+    ///   the leader.
+    /// * NotMember: The kicked user is not a member.
+    pub fn kick(&mut self, by: i32, user: i32) -> Result<(), PartyError> {
+        self.check_can_manage(by)?;
+        match self.role_of(user) {
+            None => Err(PartyError::NotMember),
+            Some(PartyRole::Leader) => Err(PartyError::MissingPermission),
+            Some(_) => self.leave(user),
+        }
+    }
+
+    /// Changes the role of a member.
+    /// Setting the role of a member to leader makes the previous leader an assistant.
+    ///
+    /// Errors:
+    /// * MissingPermission: The user changing the role is not the leader.
+    /// * NotMember: The user is not a member.
+    pub fn set_role(&mut self, by: i32, user: i32, role: PartyRole) -> Result<(), PartyError> {
+        if self.role_of(by) != Some(PartyRole::Leader) {
+            return Err(PartyError::MissingPermission);
+        }
+        if !self.is_member(user) {
+            return Err(PartyError::NotMember);
+        }
+        if by == user {
+            return Ok(());
+        }
+        for m in self.members.iter_mut() {
+            if m.user == user {
+                m.role = role;
+            } else if m.user == by && role == PartyRole::Leader {
+                m.role = PartyRole::Assistant;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts a ready check lasting `duration` seconds, replacing the current one.
+    ///
+    /// Errors:
+    /// * MissingPermission: The user is not the leader or an assistant.
+    pub fn start_ready_check(&mut self, by: i32, duration: f64) -> Result<(), PartyError> {
+        self.check_can_manage(by)?;
+        self.ready_check = Some(ReadyCheck::new(duration));
+        Ok(())
+    }
+
+    /// Answers the ready check in progress.
+    ///
+    /// Errors:
+    /// * NotMember: The user is not a member.
+    /// * NoReadyCheck: There is no ready check in progress.
+    pub fn answer_ready_check(&mut self, user: i32, ready: bool) -> Result<(), PartyError> {
+        if !self.is_member(user) {
+            return Err(PartyError::NotMember);
+        }
+        let check = self.ready_check.as_mut().ok_or(PartyError::NoReadyCheck)?;
+        check.answers.insert(user, ready);
+        Ok(())
+    }
+
+    /// Returns the state of the ready check in progress, if any.
+    pub fn ready_check_state(&self) -> Option<ReadyCheckState> {
+        self.ready_check.as_ref().map(|check| {
+            if check.answers.values().any(|r| !r) {
+                ReadyCheckState::NotReady
+            } else if self
+                .members
+                .iter()
+                .all(|m| check.answers.get(&m.user) == Some(&true))
+            {
+                ReadyCheckState::Ready
+            } else if check.remaining <= 0.0 {
+                ReadyCheckState::NotReady
+            } else {
+                ReadyCheckState::Pending
+            }
+        })
+    }
+
+    /// Chooses the member receiving the loot according to the `LootDistributionMode`.
+    /// The winner is also stored in the `LootRoll`.
+    /// Returns None with `LootDistributionMode::FreeForAll`, or if every member passed.
+    #[cfg(feature = "std")]
+    pub fn distribute_loot<R>(&mut self, roll: &mut LootRoll<R>) -> Option<i32> {
+        self.distribute_loot_with(roll, &mut thread_rng())
+    }
+
+    /// Same as `Party::distribute_loot`, using the provided random number generator.
+    pub fn distribute_loot_with<R, G: Rng>(
+        &mut self,
+        roll: &mut LootRoll<R>,
+        rng: &mut G,
+    ) -> Option<i32> {
+        roll.winner = match self.loot_mode {
+            LootDistributionMode::FreeForAll => None,
+            LootDistributionMode::RoundRobin => {
+                if self.members.is_empty() {
+                    None
+                } else {
+                    let idx = self.round_robin_index % self.members.len();
+                    self.round_robin_index = idx + 1;
+                    Some(self.members[idx].user)
+                }
+            }
+            LootDistributionMode::NeedGreed => [LootChoice::Need, LootChoice::Greed]
+                .iter()
+                .filter_map(|choice| {
+                    self.members
+                        .iter()
+                        .filter(|m| roll.choices.get(&m.user) == Some(choice))
+                        .map(|m| (rng.gen_range(1, 101), m.user))
+                        .max()
+                        .map(|(_, user)| user)
+                })
+                .next(),
+        };
+        roll.winner
+    }
+
+    fn check_can_manage(&self, user: i32) -> Result<(), PartyError> {
+        match self.role_of(user) {
+            Some(PartyRole::Leader) | Some(PartyRole::Assistant) => Ok(()),
+            _ => Err(PartyError::MissingPermission),
+        }
+    }
+}
+
+/// The errors that can happen when managing a `Party`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartyError {
+    /// The user doesn't have the role required for this action.
+    MissingPermission,
+    /// The user is already a member.
+    AlreadyMember,
+    /// The user is not a member.
+    NotMember,
+    /// The user has no pending invitation.
+    NotInvited,
+    /// The party has the maximum number of members.
+    PartyFull,
+    /// There is no ready check in progress.
+    NoReadyCheck,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeded_rng;
+
+    fn party() -> Party {
+        let mut party = Party::with_leader(1, 10, 3);
+        party.invite(10, 20, 5.0).unwrap();
+        party.accept_invitation(20).unwrap();
+        party
+    }
+
+    #[test]
+    fn invitations_expire_and_respect_the_size() {
+        let mut party = party();
+        assert_eq!(
+            party.invite(20, 30, 5.0),
+            Err(PartyError::MissingPermission)
+        );
+        party.invite(10, 30, 5.0).unwrap();
+        party.invite(10, 40, 5.0).unwrap();
+        party.update(5.0);
+        assert_eq!(party.accept_invitation(30), Err(PartyError::NotInvited));
+        party.invite(10, 30, 5.0).unwrap();
+        party.accept_invitation(30).unwrap();
+        assert!(party.is_full());
+        assert_eq!(party.invite(10, 40, 5.0), Err(PartyError::PartyFull));
+    }
+
+    #[test]
+    fn leadership_moves_to_the_oldest_member() {
+        let mut party = party();
+        assert_eq!(party.kick(10, 10), Err(PartyError::MissingPermission));
+        party.set_role(10, 20, PartyRole::Leader).unwrap();
+        assert_eq!(party.leader(), Some(20));
+        assert_eq!(party.role_of(10), Some(PartyRole::Assistant));
+        party.leave(20).unwrap();
+        assert_eq!(party.leader(), Some(10));
+    }
+
+    #[test]
+    fn ready_checks_need_every_member() {
+        let mut party = party();
+        assert_eq!(
+            party.answer_ready_check(10, true),
+            Err(PartyError::NoReadyCheck)
+        );
+        party.start_ready_check(10, 10.0).unwrap();
+        party.answer_ready_check(10, true).unwrap();
+        assert_eq!(party.ready_check_state(), Some(ReadyCheckState::Pending));
+        party.answer_ready_check(20, true).unwrap();
+        assert_eq!(party.ready_check_state(), Some(ReadyCheckState::Ready));
+        party.start_ready_check(10, 10.0).unwrap();
+        party.answer_ready_check(10, true).unwrap();
+        party.update(10.0);
+        assert_eq!(party.ready_check_state(), Some(ReadyCheckState::NotReady));
+    }
+
+    #[test]
+    fn loot_is_distributed_by_mode() {
+        let mut party = party();
+        let mut rng = seeded_rng(1);
+        let mut roll = LootRoll::new("sword");
+        assert_eq!(party.distribute_loot_with(&mut roll, &mut rng), None);
+        party.loot_mode = LootDistributionMode::RoundRobin;
+        let winners = (0..3)
+            .map(|_| party.distribute_loot_with(&mut roll, &mut rng))
+            .collect::<Vec<_>>();
+        assert_eq!(winners, vec![Some(10), Some(20), Some(10)]);
+        party.loot_mode = LootDistributionMode::NeedGreed;
+        roll.choices.insert(10, LootChoice::Greed);
+        roll.choices.insert(20, LootChoice::Need);
+        assert_eq!(party.distribute_loot_with(&mut roll, &mut rng), Some(20));
+        assert_eq!(roll.winner, Some(20));
+        roll.choices.insert(20, LootChoice::Pass);
+        roll.choices.insert(10, LootChoice::Pass);
+        assert_eq!(party.distribute_loot_with(&mut roll, &mut rng), None);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// A state that can be replicated by sending the differences between two of its versions
+/// instead of the whole state, like when sending updates over the network.
+pub trait Diff {
+    /// The differences between two versions of the state.
+    type Patch;
+    /// Returns the changes turning `old` into `new`.
+    fn diff(old: &Self, new: &Self) -> Self::Patch;
+    /// Applies the changes returned by `Diff::diff`.
+    /// The patch must be applied to the same state as the `old` state used to create it.
+    ///
+    /// Errors:
+    /// * MissingElement: The state doesn't match the one used to create the patch.
+    ///
+    /// Nothing changes if an error happens.
+    fn apply_patch(&mut self, patch: &Self::Patch) -> Result<(), PatchError>;
+}
+
+/// The errors that can happen when applying a patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// The element at this index is neither in the list nor in the patch, so the list is
+    /// shorter than the one used to create the patch.
+    MissingElement(usize),
+}
+
+/// The changes made to the elements of a list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VecPatch<T> {
+    /// The new length of the list.
+    pub len: usize,
+    /// The elements that changed or were added, with their index.
+    pub changed: Vec<(usize, T)>,
+}
+
+impl<T: PartialEq + Clone> VecPatch<T> {
+    /// Returns the changes turning `old` into `new`.
+    pub fn diff(old: &[T], new: &[T]) -> Self {
+        let changed = new
+            .iter()
+            .enumerate()
+            .filter(|(idx, v)| old.get(*idx) != Some(v))
+            .map(|(idx, v)| (idx, v.clone()))
+            .collect();
+        Self {
+            len: new.len(),
+            changed,
+        }
+    }
+
+    /// Applies the changes to the list.
+    ///
+    /// Errors:
+    /// * MissingElement: The list is shorter than the one used to create the patch.
+    ///
+    /// Nothing changes if an error happens.
+    pub fn apply(&self, list: &mut Vec<T>) -> Result<(), PatchError> {
+        // The elements after the end of the list must all be in the patch, in order.
+        let mut len = list.len().min(self.len);
+        for (idx, _) in self.changed.iter() {
+            if *idx == len {
+                len += 1;
+            } else if *idx > len {
+                return Err(PatchError::MissingElement(len));
+            }
+        }
+        if len != self.len {
+            return Err(PatchError::MissingElement(len.min(self.len)));
+        }
+        list.truncate(self.len);
+        for (idx, v) in self.changed.iter() {
+            if *idx < list.len() {
+                list[*idx] = v.clone();
+            } else {
+                list.push(v.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if there are no changes.
+    pub fn is_empty(&self, old_len: usize) -> bool {
+        self.len == old_len && self.changed.is_empty()
+    }
+}
+
+/// The changes made to the values of a map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapPatch<K, V> {
+    /// The values that changed or were added.
+    pub changed: Vec<(K, V)>,
+    /// The keys that were removed.
+    pub removed: Vec<K>,
+}
+
+impl<K: Hash + Eq + Clone, V: PartialEq + Clone> MapPatch<K, V> {
+    /// Returns the changes turning `old` into `new`.
+    pub fn diff(old: &HashMap<K, V>, new: &HashMap<K, V>) -> Self {
+        let changed = new
+            .iter()
+            .filter(|(k, v)| old.get(*k) != Some(v))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let removed = old
+            .keys()
+            .filter(|k| !new.contains_key(*k))
+            .cloned()
+            .collect();
+        Self { changed, removed }
+    }
+
+    /// Applies the changes to the map.
+    pub fn apply(&self, map: &mut HashMap<K, V>) {
+        for k in self.removed.iter() {
+            map.remove(k);
+        }
+        for (k, v) in self.changed.iter() {
+            map.insert(k.clone(), v.clone());
+        }
+    }
+
+    /// Checks if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Only the content of the inventory is replicated.
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType,
+        U: Default + Clone + Debug + PartialEq,
+    > Diff for Inventory<K, S, U>
+{
+    type Patch = VecPatch<Option<ItemInstance<K, U>>>;
+    fn diff(old: &Self, new: &Self) -> Self::Patch {
+        VecPatch::diff(&old.content, &new.content)
+    }
+    fn apply_patch(&mut self, patch: &Self::Patch) -> Result<(), PatchError> {
+        let old_len = self.content.len();
+        patch.apply(&mut self.content)?;
+        self.changed_slots
+            .extend(patch.changed.iter().map(|(idx, _)| *idx));
+        self.changed_slots.extend(patch.len..old_len);
+        Ok(())
+    }
+}
+
+impl<K: Hash + Eq + Clone> Diff for StatSet<K> {
+    type Patch = MapPatch<K, StatInstance<K>>;
+    fn diff(old: &Self, new: &Self) -> Self::Patch {
+        MapPatch::diff(&old.stats, &new.stats)
+    }
+    fn apply_patch(&mut self, patch: &Self::Patch) -> Result<(), PatchError> {
+        self.changed
+            .extend(patch.changed.iter().map(|(k, _)| k.clone()));
+        self.changed.extend(patch.removed.iter().cloned());
+        patch.apply(&mut self.stats);
+        Ok(())
+    }
+}
+
+impl<E: PartialEq + Clone> Diff for EffectorSet<E> {
+    type Patch = VecPatch<EffectorInstance<E>>;
+    fn diff(old: &Self, new: &Self) -> Self::Patch {
+        VecPatch::diff(&old.effectors, &new.effectors)
+    }
+    fn apply_patch(&mut self, patch: &Self::Patch) -> Result<(), PatchError> {
+        patch.apply(&mut self.effectors)
+    }
+}
+
+impl<S: Hash + Eq + Clone> Diff for SkillSet<S> {
+    type Patch = MapPatch<S, SkillInstance<S>>;
+    fn diff(old: &Self, new: &Self) -> Self::Patch {
+        MapPatch::diff(&old.skills, &new.skills)
+    }
+    fn apply_patch(&mut self, patch: &Self::Patch) -> Result<(), PatchError> {
+        patch.apply(&mut self.skills);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vec_patch_round_trip() {
+        let old = vec![1, 2, 3];
+        let new = vec![1, 5, 3, 4, 6];
+        let patch = VecPatch::diff(&old, &new);
+        let mut list = old.clone();
+        assert_eq!(patch.apply(&mut list), Ok(()));
+        assert_eq!(list, new);
+        let patch = VecPatch::diff(&new, &old);
+        assert_eq!(patch.apply(&mut list), Ok(()));
+        assert_eq!(list, old);
+    }
+
+    #[test]
+    fn mismatched_vec_patch_is_rejected() {
+        let patch = VecPatch::diff(&[1, 2, 3], &[1, 2, 3, 4]);
+        let mut list = vec![1];
+        assert_eq!(patch.apply(&mut list), Err(PatchError::MissingElement(1)));
+        assert_eq!(list, vec![1]);
+        let patch = VecPatch::diff(&[1, 2, 3], &[1, 5, 3]);
+        assert_eq!(patch.apply(&mut list), Err(PatchError::MissingElement(2)));
+        assert_eq!(list, vec![1]);
+    }
+}
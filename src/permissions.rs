@@ -1 +1,382 @@
+use crate::*;
 
+/// A set of up to 64 permissions, stored as bits.
+/// The meaning of each bit is chosen by the user of the set, usually with an enum cast to `u8`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, new)]
+pub struct PermissionBits {
+    /// The bits of the permissions. Bit n is set if permission n is granted.
+    pub bits: u64,
+}
+
+impl PermissionBits {
+    /// A set containing all the permissions.
+    pub fn all() -> Self {
+        PermissionBits::new(u64::MAX)
+    }
+
+    /// Checks if the permission is granted.
+    pub fn has(&self, permission: u8) -> bool {
+        permission < 64 && self.bits & (1 << permission) != 0
+    }
+
+    /// Grants the permission.
+    /// Permissions of 64 and more don't fit and are ignored.
+    pub fn grant(&mut self, permission: u8) {
+        if permission < 64 {
+            self.bits |= 1 << permission;
+        }
+    }
+
+    /// Revokes the permission.
+    /// Permissions of 64 and more don't fit and are ignored.
+    pub fn revoke(&mut self, permission: u8) {
+        if permission < 64 {
+            self.bits &= !(1 << permission);
+        }
+    }
+}
+
+/// Checks if a permission node matches a pattern.
+/// Nodes are made of segments separated by dots, like "faction.claim.create".
+/// A pattern ending with "*" matches all the nodes starting with the segments before it:
+/// "faction.claim.*" matches "faction.claim.create" and "faction.claim", and "*" matches every
+/// node.
+pub fn permission_matches(pattern: &str, node: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    if let Some(prefix) = pattern.strip_suffix(".*") {
+        node == prefix || (node.starts_with(prefix) && node[prefix.len()..].starts_with('.'))
+    } else {
+        pattern == node
+    }
+}
+
+/// How specific a pattern is. Exact patterns are more specific than wildcards with the same
+/// number of segments.
+fn specificity(pattern: &str) -> usize {
+    let segments = pattern.split('.').count() * 2;
+    if pattern.ends_with('*') {
+        segments - 1
+    } else {
+        segments
+    }
+}
+
+/// Allows or denies the permission nodes matching a pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, new)]
+pub struct PermissionEntry {
+    /// The pattern of the permission nodes. See `permission_matches`.
+    pub node: String,
+    /// Whether the nodes are allowed or denied.
+    pub allow: bool,
+}
+
+/// Resolves a node using the entries.
+/// The most specific matching entry wins. Deny wins over allow when they are as specific.
+/// Returns None if no entry matches.
+fn resolve<'a, I: Iterator<Item = &'a PermissionEntry>>(entries: I, node: &str) -> Option<bool> {
+    entries
+        .filter(|e| permission_matches(&e.node, node))
+        .map(|e| (specificity(&e.node), !e.allow))
+        .max()
+        .map(|(_, deny)| !deny)
+}
+
+/// A named group of permission entries that users can belong to.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct PermissionGroup {
+    /// The name of the group.
+    pub name: String,
+    /// Groups with a higher priority take precedence over groups with a lower priority.
+    pub priority: i32,
+    /// The permissions of this group.
+    #[new(default)]
+    pub entries: Vec<PermissionEntry>,
+    /// The groups whose permissions are inherited.
+    /// The entries of this group take precedence over the inherited entries.
+    #[new(default)]
+    pub parents: Vec<String>,
+}
+
+/// A value that stops applying at a point in time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+pub struct Expiring<T> {
+    /// The value.
+    pub value: T,
+    /// The time at which the value stops applying, in seconds.
+    /// It uses the same clock as the `now` values passed to the methods using it.
+    pub expires_at: f64,
+}
+
+impl<T> Expiring<T> {
+    /// Checks if the value still applies.
+    pub fn is_active(&self, now: f64) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// The permissions of a user.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct UserPermissions {
+    /// The names of the groups the user belongs to.
+    #[new(default)]
+    pub groups: Vec<String>,
+    /// The permissions of the user. They take precedence over the permissions of the groups.
+    #[new(default)]
+    pub entries: Vec<PermissionEntry>,
+    /// The names of the groups the user belongs to until a point in time.
+    #[new(default)]
+    #[serde(default)]
+    pub temporary_groups: Vec<Expiring<String>>,
+    /// The permissions of the user that apply until a point in time.
+    /// They are as important as the permanent entries of the user.
+    #[new(default)]
+    #[serde(default)]
+    pub temporary_entries: Vec<Expiring<PermissionEntry>>,
+}
+
+impl UserPermissions {
+    /// Adds the user to the group until `expires_at`.
+    pub fn add_temporary_group(&mut self, group: String, expires_at: f64) {
+        self.temporary_groups.push(Expiring::new(group, expires_at));
+    }
+
+    /// Grants or denies permissions to the user until `expires_at`.
+    pub fn add_temporary_entry(&mut self, entry: PermissionEntry, expires_at: f64) {
+        self.temporary_entries
+            .push(Expiring::new(entry, expires_at));
+    }
+
+    /// Removes the temporary groups and entries that expired.
+    pub fn remove_expired(&mut self, now: f64) {
+        self.temporary_groups.retain(|g| g.is_active(now));
+        self.temporary_entries.retain(|e| e.is_active(now));
+    }
+
+    /// Collects all the permissions applying to the user at this time, including the ones of
+    /// the groups and of the groups they inherit from.
+    pub fn effective_permissions(
+        &self,
+        groups: &HashMap<String, PermissionGroup>,
+        now: f64,
+    ) -> EffectivePermissions {
+        let mut layers = vec![self.active_entries(now).cloned().collect::<Vec<_>>()];
+        layers.extend(
+            self.active_groups(groups, now)
+                .into_iter()
+                .map(|g| g.entries.clone()),
+        );
+        EffectivePermissions::new(layers)
+    }
+
+    /// Checks if the user has the permission at this time.
+    /// Same as checking the `EffectivePermissions`, without copying the entries. The groups are
+    /// only looked up if no entry of the user matches the node.
+    pub fn has_permission(
+        &self,
+        groups: &HashMap<String, PermissionGroup>,
+        node: &str,
+        now: f64,
+    ) -> bool {
+        resolve(self.active_entries(now), node)
+            .or_else(|| {
+                self.active_groups(groups, now)
+                    .into_iter()
+                    .find_map(|g| resolve(g.entries.iter(), node))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns the permanent and active temporary entries of the user.
+    fn active_entries(&self, now: f64) -> impl Iterator<Item = &PermissionEntry> {
+        self.entries.iter().chain(
+            self.temporary_entries
+                .iter()
+                .filter(move |e| e.is_active(now))
+                .map(|e| &e.value),
+        )
+    }
+
+    /// Returns the groups applying to the user at this time, in the order they are checked.
+    fn active_groups<'a>(
+        &'a self,
+        groups: &'a HashMap<String, PermissionGroup>,
+        now: f64,
+    ) -> Vec<&'a PermissionGroup> {
+        let names = self.groups.iter().chain(
+            self.temporary_groups
+                .iter()
+                .filter(move |g| g.is_active(now))
+                .map(|g| &g.value),
+        );
+        let mut visited = vec![];
+        let mut ordered = vec![];
+        for group in sorted_groups(names, groups) {
+            collect_groups(group, groups, &mut visited, &mut ordered);
+        }
+        ordered
+    }
+}
+
+/// Returns the existing groups with these names, from the highest priority to the lowest.
+fn sorted_groups<'a, I: Iterator<Item = &'a String>>(
+    names: I,
+    groups: &'a HashMap<String, PermissionGroup>,
+) -> Vec<&'a PermissionGroup> {
+    let mut sorted = names.filter_map(|g| groups.get(g)).collect::<Vec<_>>();
+    sorted.sort_by_key(|g| -g.priority);
+    sorted
+}
+
+/// Adds the group, then the groups it inherits from.
+fn collect_groups<'a>(
+    group: &'a PermissionGroup,
+    groups: &'a HashMap<String, PermissionGroup>,
+    visited: &mut Vec<&'a str>,
+    ordered: &mut Vec<&'a PermissionGroup>,
+) {
+    if visited.contains(&group.name.as_str()) {
+        return;
+    }
+    visited.push(&group.name);
+    ordered.push(group);
+    for parent in sorted_groups(group.parents.iter(), groups) {
+        collect_groups(parent, groups, visited, ordered);
+    }
+}
+
+/// A snapshot of all the permissions applying to a user.
+/// See `UserPermissions::effective_permissions`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct EffectivePermissions {
+    /// The permission entries, grouped by importance.
+    /// The first layer contains the entries of the user, followed by the entries of each
+    /// group from the most important to the least important.
+    pub layers: Vec<Vec<PermissionEntry>>,
+}
+
+impl EffectivePermissions {
+    /// Checks if the permission is granted.
+    ///
+    /// The layers are checked in order. The first one with an entry matching the node decides.
+    /// Among the matching entries of a layer, the most specific one wins, and deny wins over
+    /// allow.
+    /// Returns false if nothing matches.
+    pub fn has_permission(&self, node: &str) -> bool {
+        self.layers
+            .iter()
+            .find_map(|layer| resolve(layer.iter(), node))
+            .unwrap_or(false)
+    }
+}
+
+/// Holds the permission groups and the permissions of the users.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct PermissionTree {
+    /// The groups mapped by name.
+    #[new(default)]
+    pub groups: HashMap<String, PermissionGroup>,
+    /// The permissions of the users mapped by user id.
+    #[new(default)]
+    pub users: HashMap<i32, UserPermissions>,
+}
+
+impl PermissionTree {
+    /// Adds a group, replacing the group with the same name.
+    pub fn add_group(&mut self, group: PermissionGroup) {
+        self.groups.insert(group.name.clone(), group);
+    }
+
+    /// Returns the permissions of the user, creating them if needed.
+    pub fn user_mut(&mut self, user: i32) -> &mut UserPermissions {
+        self.users.entry(user).or_default()
+    }
+
+    /// Returns all the permissions applying to the user at this time.
+    /// See `UserPermissions::effective_permissions`.
+    pub fn effective_permissions(&self, user: i32, now: f64) -> EffectivePermissions {
+        self.users
+            .get(&user)
+            .map(|u| u.effective_permissions(&self.groups, now))
+            .unwrap_or_default()
+    }
+
+    /// Checks if the user has the permission at this time.
+    ///
+    /// The entries of the user are checked first. Then, the groups of the user are checked
+    /// from the highest priority to the lowest. A group is checked before the groups it
+    /// inherits from. The first one with an entry matching the node decides.
+    /// Among the matching entries of the user or of a group, the most specific one wins, and deny
+    /// wins over allow.
+    /// Returns false if nothing matches.
+    pub fn has_permission(&self, user: i32, node: &str, now: f64) -> bool {
+        self.users
+            .get(&user)
+            .map(|u| u.has_permission(&self.groups, node, now))
+            .unwrap_or(false)
+    }
+
+    /// Removes the temporary groups and entries that expired for all users.
+    pub fn remove_expired(&mut self, now: f64) {
+        for user in self.users.values_mut() {
+            user.remove_expired(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_bits_are_ignored() {
+        let mut bits = PermissionBits::default();
+        bits.grant(3);
+        bits.grant(64);
+        bits.grant(255);
+        assert_eq!(bits.bits, 1 << 3);
+        let mut all = PermissionBits::all();
+        all.revoke(200);
+        all.revoke(63);
+        assert!(!all.has(63) && all.has(62) && !all.has(200));
+    }
+
+    #[test]
+    fn has_permission_matches_effective_permissions() {
+        let mut tree = PermissionTree::new();
+        let mut member = PermissionGroup::new("member".to_string(), 0);
+        member.entries = vec![
+            PermissionEntry::new("chat.*".to_string(), true),
+            PermissionEntry::new("build".to_string(), true),
+        ];
+        let mut muted = PermissionGroup::new("muted".to_string(), 10);
+        muted.entries = vec![PermissionEntry::new("chat.send".to_string(), false)];
+        muted.parents = vec!["member".to_string()];
+        tree.add_group(member);
+        tree.add_group(muted);
+        let user = tree.user_mut(1);
+        user.groups.push("member".to_string());
+        user.add_temporary_group("muted".to_string(), 10.0);
+        user.entries
+            .push(PermissionEntry::new("build".to_string(), false));
+        for (node, now) in [
+            ("chat.send", 0.0),
+            ("chat.send", 20.0),
+            ("chat.read", 0.0),
+            ("build", 0.0),
+            ("trade", 0.0),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                tree.has_permission(1, node, *now),
+                tree.effective_permissions(1, *now).has_permission(node)
+            );
+        }
+        assert!(!tree.has_permission(1, "chat.send", 0.0));
+        assert!(tree.has_permission(1, "chat.send", 20.0));
+        assert!(!tree.has_permission(1, "build", 0.0));
+        assert!(!tree.has_permission(2, "chat.read", 0.0));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The rules used when prestiging, or rebirthing, a `Leveled` element.
+///
+/// # Generics
+/// - C: Currency Key
+/// - E: Effector Key
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct PrestigeRules<C, E> {
+    /// The minimum level required to prestige.
+    pub min_level: u32,
+    /// The currencies given each time the element prestiges.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub currency_rewards: Vec<(C, i64)>,
+    /// The permanent effectors granted by prestiging.
+    /// Their magnitude is the number of times the element prestiged.
+    /// See `EffectorInstance::magnitude`.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub stat_effectors: Vec<E>,
+    /// The value added to the multiplier returned by `Prestige::multiplier` for each prestige.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub multiplier_per_prestige: f64,
+    /// Whether the unlockables stay unlocked when prestiging.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub keep_unlocks: bool,
+    /// Whether the items stay in the inventory when prestiging.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub keep_items: bool,
+}
+
+impl<C, E> PrestigeRules<C, E> {
+    /// Locks all the unlockables, unless `keep_unlocks` is true.
+    /// Use this after `Prestige::prestige`.
+    pub fn reset_unlockables<U: Hash + Eq, K, S, I>(
+        &self,
+        unlockables: &mut Unlockables<U, K, S, I>,
+    ) {
+        if self.keep_unlocks {
+            return;
+        }
+        for unlockable in unlockables.unlockables.values_mut() {
+            unlockable.lock();
+        }
+    }
+
+    /// Deletes all the items of the inventory, unless `keep_items` is true.
+    /// Use this after `Prestige::prestige`.
+    pub fn reset_inventory<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType,
+        U: Default + Clone + Debug + PartialEq,
+    >(
+        &self,
+        inventory: &mut Inventory<K, S, U>,
+    ) {
+        if self.keep_items {
+            return;
+        }
+        for idx in (0..inventory.content.len()).rev() {
+            let _ = inventory.delete_stack(idx);
+        }
+    }
+}
+
+/// The number of times an element prestiged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct Prestige {
+    /// The number of times the element prestiged.
+    pub count: u32,
+}
+
+impl Prestige {
+    /// Checks if the element has a high enough level to prestige.
+    pub fn can_prestige<T: LevelFor, C, E>(
+        &self,
+        leveled: &Leveled<T>,
+        rules: &PrestigeRules<C, E>,
+    ) -> bool {
+        leveled.level() >= rules.min_level
+    }
+
+    /// The multiplier to apply to the stats affected by prestiging.
+    /// Starts at 1 and increases by `multiplier_per_prestige` for each prestige.
+    pub fn multiplier<C, E>(&self, rules: &PrestigeRules<C, E>) -> f64 {
+        1.0 + f64::from(self.count) * rules.multiplier_per_prestige
+    }
+
+    /// Resets the element back to level 1 in exchange for the rewards of the rules.
+    /// The currency rewards are added to the wallet and the effectors of the previous
+    /// prestige are replaced by effectors of a higher magnitude.
+    /// Returns the new prestige count.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * LevelTooLow: The element didn't reach the minimum level.
+    /// * Wallet: The wallet can't hold the currency rewards.
+    pub fn prestige<T: LevelFor, C: Hash + Eq + Clone, E: Hash + Eq + Clone, K>(
+        &mut self,
+        leveled: &mut Leveled<T>,
+        rules: &PrestigeRules<C, E>,
+        wallet: &mut Wallet<C>,
+        currency_defs: &CurrencyDefinitions<C>,
+        effectors: &mut EffectorSet<E>,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> Result<u32, PrestigeError<C>> {
+        if !self.can_prestige(leveled, rules) {
+            return Err(PrestigeError::LevelTooLow);
+        }
+        let mut new_wallet = wallet.clone();
+        for (currency, amount) in rules.currency_rewards.iter() {
+            new_wallet
+                .add(currency, *amount, currency_defs)
+                .map_err(PrestigeError::Wallet)?;
+        }
+        *wallet = new_wallet;
+        leveled.accumulated_xp = 0;
+        effectors
+            .effectors
+            .retain(|e| !rules.stat_effectors.contains(&e.effector_key));
+        self.count += 1;
+        for e in rules.stat_effectors.iter() {
+            effectors.add_with_magnitude(e, f64::from(self.count), effector_defs);
+        }
+        Ok(self.count)
+    }
+}
+
+/// The errors that can happen when prestiging.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrestigeError<C> {
+    /// The element didn't reach the minimum level required to prestige.
+    LevelTooLow,
+    /// The wallet can't hold the currency rewards.
+    Wallet(WalletError<C>),
+}
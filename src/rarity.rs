@@ -0,0 +1,84 @@
+use crate::*;
+use core::hash::Hash;
+
+/// The definition of a rarity, or quality, that items can have.
+/// For example: common, rare, legendary.
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct RarityDefinition<R> {
+    /// The key of this rarity.
+    pub key: R,
+    /// The display name of this rarity.
+    pub name: String,
+    /// The color used to display the items of this rarity, in RGB.
+    pub color: (u8, u8, u8),
+    /// The multiplier applied to the stats granted by items of this rarity.
+    pub stat_multiplier: f64,
+    /// The multiplier applied to the chances of dropping items of this rarity from a loot tree.
+    pub drop_weight_multiplier: f64,
+}
+
+/// The definitions of all known rarities.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct RarityDefinitions<R: Hash + Eq> {
+    /// The definitions.
+    pub defs: HashMap<R, RarityDefinition<R>>,
+}
+
+impl<R: Hash + Eq> Default for RarityDefinitions<R> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<R: Hash + Eq + Clone> From<Vec<RarityDefinition<R>>> for RarityDefinitions<R> {
+    fn from(t: Vec<RarityDefinition<R>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<R: Hash + Eq> RarityDefinitions<R> {
+    /// Applies the stat multiplier of the rarity to the value.
+    pub fn scale_stat(&self, rarity: &R, value: f64) -> f64 {
+        value
+            * self
+                .defs
+                .get(rarity)
+                .expect("Tried to get unknown rarity key.")
+                .stat_multiplier
+    }
+
+    /// Multiplies the chances of the nodes of the builder by the drop weight multiplier of their
+    /// rarity. `rarity_of` returns the rarity of the result of a node, if it has one.
+    pub fn scale_loot<T: Clone + 'static, F: Fn(&T) -> Option<&R>>(
+        &self,
+        builder: LootTreeBuilder<T>,
+        rarity_of: F,
+    ) -> LootTreeBuilder<T> {
+        builder.scale_chances(|t| {
+            rarity_of(t)
+                .map(|r| {
+                    self.defs
+                        .get(r)
+                        .expect("Tried to get unknown rarity key.")
+                        .drop_weight_multiplier
+                })
+                .unwrap_or(1.0)
+        })
+    }
+}
+
+/// An `ItemInstance` with a rarity.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ItemWithRarity<K, U: Default, R> {
+    /// The item.
+    pub item: ItemInstance<K, U>,
+    /// The rarity of the item.
+    pub rarity: R,
+}
@@ -0,0 +1,212 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+use rand::Rng;
+
+/// The definition of something that can be harvested, like an ore vein, a tree or a fishing
+/// spot.
+///
+/// # Generics
+/// - N: Resource Node Key
+/// - K: Stat Key
+/// - S: Skill Key
+/// - I: Item Key
+/// - L: Loot Table Key
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct ResourceNode<N, K, S, I, L> {
+    /// The key of this node.
+    pub key: N,
+    /// The display name of this node.
+    #[builder(setter(into))]
+    pub name: LocalizedText,
+    /// The tools required to harvest, like a pickaxe, with their quantity and `UseMode`.
+    /// `UseMode::UseOnce` consumes the durability of the tool on each harvest.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub tools: Vec<(I, usize, UseMode)>,
+    /// The stat conditions required to harvest.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub stat_conditions: Vec<StatCondition<K>>,
+    /// The skill that must be known to harvest, with the minimum experience of this skill.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Option::default")]
+    pub required_skill: Option<(S, u32)>,
+    /// The loot table of the `LootTables` rolled to get the harvested items.
+    pub loot_table: L,
+    /// The number of times the loot table is rolled on each harvest.
+    #[new(value = "1")]
+    #[builder(default = "1")]
+    pub rolls: usize,
+    /// The number of harvests before the node is depleted.
+    /// None means that the node is never depleted.
+    #[new(default)]
+    #[builder(default)]
+    pub charges: Option<u32>,
+    /// The time it takes a depleted node to be available again, in seconds.
+    #[new(default)]
+    #[builder(default)]
+    pub respawn_time: f64,
+}
+
+/// The definitions of all known resource nodes.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ResourceNodes<N: Hash + Eq, K, S, I, L> {
+    /// The definitions.
+    pub defs: HashMap<N, ResourceNode<N, K, S, I, L>>,
+}
+
+impl<N: Hash + Eq, K, S, I, L> Default for ResourceNodes<N, K, S, I, L> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<N: Hash + Eq + Clone, K, S, I, L> From<Vec<ResourceNode<N, K, S, I, L>>>
+    for ResourceNodes<N, K, S, I, L>
+{
+    fn from(t: Vec<ResourceNode<N, K, S, I, L>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+/// The runtime state of a placed resource node.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct NodeState {
+    /// The remaining harvests before the node is depleted.
+    /// None means that the node is never depleted.
+    pub charges: Option<u32>,
+    /// The timer after which a depleted node is available again.
+    #[new(default)]
+    pub respawn: Option<Timer>,
+}
+
+impl NodeState {
+    /// Creates the state of a node that was just placed, with all its charges.
+    pub fn from_definition<N, K, S, I, L>(def: &ResourceNode<N, K, S, I, L>) -> Self {
+        Self::new(def.charges)
+    }
+
+    /// Checks if the node can be harvested, ignoring the requirements.
+    pub fn is_available(&self) -> bool {
+        self.respawn.is_none() && self.charges != Some(0)
+    }
+
+    /// Advances the respawn timer of a depleted node by `delta_time` seconds.
+    /// Returns true if the node respawned with all its charges.
+    pub fn update<N, K, S, I, L>(
+        &mut self,
+        delta_time: f64,
+        def: &ResourceNode<N, K, S, I, L>,
+    ) -> bool {
+        if let Some(timer) = self.respawn.as_mut() {
+            if timer.update(delta_time) > 0 {
+                self.respawn = None;
+                self.charges = def.charges;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Harvests the node, uses the tools and inserts the harvested items into the inventory.
+    /// Returns the harvested items.
+    /// The node starts respawning when its last charge is used.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * Depleted: The node has no charges left and is respawning.
+    /// * ConditionsNotMet: The stat conditions are not met.
+    /// * MissingSkill: The required skill is not known or doesn't have enough experience.
+    /// * MissingTool: A required tool is not in the inventory or is broken.
+    /// * InventoryFull: The harvested items don't fit in the inventory.
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_harvest<
+        N,
+        K: Hash + Eq + Debug,
+        S: Hash + Eq + Clone,
+        I: Hash + Eq + Clone + Debug + 'static,
+        L: Hash + Eq + Clone + 'static,
+        IT: SlotType + Clone,
+        CD: PartialEq + Default + Clone + Debug,
+        D: Default,
+        G: Rng,
+    >(
+        &mut self,
+        def: &ResourceNode<N, K, S, I, L>,
+        inventory: &mut Inventory<I, IT, CD>,
+        item_defs: &ItemDefinitions<I, IT, D>,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        skills: &SkillSet<S>,
+        loot_tables: &LootTables<L, ItemDrop<I>>,
+        rng: &mut G,
+    ) -> Result<Vec<ItemInstance<I, CD>>, HarvestError<S, I>> {
+        if !self.is_available() {
+            return Err(HarvestError::Depleted);
+        }
+        if !def
+            .stat_conditions
+            .iter()
+            .all(|c| c.check(stats, stat_defs))
+        {
+            return Err(HarvestError::ConditionsNotMet);
+        }
+        if let Some((skill, xp)) = def.required_skill.as_ref() {
+            if skills.skills.get(skill).map(|s| s.xp < *xp).unwrap_or(true) {
+                return Err(HarvestError::MissingSkill(skill.clone()));
+            }
+        }
+        let mut new_inventory = inventory.clone();
+        for (key, quantity, mode) in def.tools.iter() {
+            new_inventory
+                .use_key(key, *quantity, mode)
+                .map_err(|_| HarvestError::MissingTool(key.clone()))?;
+        }
+        let harvested = (0..def.rolls)
+            .filter_map(|_| {
+                loot_tables
+                    .roll_with(&def.loot_table, rng)
+                    .map(|drop| drop.roll_with(rng))
+            })
+            .collect::<Vec<ItemInstance<I, CD>>>();
+        for item in harvested.iter() {
+            new_inventory
+                .insert(item.clone(), item_defs)
+                .map_err(|_| HarvestError::InventoryFull)?;
+        }
+        *inventory = new_inventory;
+        if let Some(charges) = self.charges.as_mut() {
+            *charges = charges.saturating_sub(1);
+            if *charges == 0 {
+                self.respawn = Some(Timer::once(def.respawn_time));
+            }
+        }
+        Ok(harvested)
+    }
+}
+
+/// The errors that can happen when harvesting a resource node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HarvestError<S, I> {
+    /// The node has no charges left and is respawning.
+    Depleted,
+    /// The stat conditions of the node are not met.
+    ConditionsNotMet,
+    /// The skill is not known or doesn't have enough experience.
+    MissingSkill(S),
+    /// The tool is not in the inventory or is broken.
+    MissingTool(I),
+    /// The harvested items don't fit in the inventory.
+    InventoryFull,
+}
@@ -0,0 +1,208 @@
+use crate::*;
+
+/// Serialized state tagged with the version of its schema, like a `CharacterSheet`, an
+/// `Inventory` or the relations of a `FactionMap`.
+///
+/// # Generics
+/// - V: Value, a self-describing representation of the data like `serde_json::Value`
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct SaveData<V> {
+    /// The version of the schema used by the data.
+    pub version: u32,
+    /// The data.
+    pub data: V,
+}
+
+/// Upgrades saved data from a version of its schema to the next one, like when a field was
+/// renamed or added.
+pub trait Migration<V> {
+    /// Converts the data to the next version.
+    /// Returns the reason of the failure if the data can't be converted.
+    fn migrate(&self, data: V) -> Result<V, String>;
+}
+
+impl<V, F: Fn(V) -> Result<V, String>> Migration<V> for F {
+    fn migrate(&self, data: V) -> Result<V, String> {
+        self(data)
+    }
+}
+
+/// The migrations upgrading saved data to the current version of its schema.
+pub struct Migrations<V> {
+    /// The version of the schema used when saving.
+    pub current_version: u32,
+    /// The migrations mapped by the version they upgrade from.
+    pub migrations: HashMap<u32, Box<dyn Migration<V>>>,
+}
+
+impl<V> Migrations<V> {
+    /// Creates a registry without migrations.
+    pub fn new(current_version: u32) -> Self {
+        Self {
+            current_version,
+            migrations: HashMap::new(),
+        }
+    }
+
+    /// Adds the migration upgrading data from `from_version` to `from_version + 1`.
+    /// It replaces the previous migration of this version, if any.
+    pub fn add<M: Migration<V> + 'static>(&mut self, from_version: u32, migration: M) {
+        self.migrations.insert(from_version, Box::new(migration));
+    }
+
+    /// Wraps the data using the current version.
+    pub fn wrap(&self, data: V) -> SaveData<V> {
+        SaveData::new(self.current_version, data)
+    }
+
+    /// Applies the migrations, one version at a time, until the data reaches the current
+    /// version.
+    ///
+    /// Errors:
+    /// * NewerVersion: The data uses a version more recent than the current one.
+    /// * MissingMigration: No migration upgrades from this version.
+    /// * Failed: The migration from this version failed.
+    pub fn upgrade(&self, save: SaveData<V>) -> Result<V, MigrationError> {
+        if save.version > self.current_version {
+            return Err(MigrationError::NewerVersion(save.version));
+        }
+        let mut data = save.data;
+        for version in save.version..self.current_version {
+            let migration = self
+                .migrations
+                .get(&version)
+                .ok_or(MigrationError::MissingMigration(version))?;
+            data = migration
+                .migrate(data)
+                .map_err(|reason| MigrationError::Failed { version, reason })?;
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl Migrations<serde_json::Value> {
+    /// Serializes the data and wraps it using the current version.
+    ///
+    /// Errors:
+    /// * Json: The data can't be serialized.
+    pub fn save<T: serde::Serialize>(
+        &self,
+        data: &T,
+    ) -> Result<SaveData<serde_json::Value>, MigrationError> {
+        Ok(self.wrap(serde_json::to_value(data).map_err(MigrationError::Json)?))
+    }
+
+    /// Upgrades the data to the current version, then deserializes it.
+    ///
+    /// Errors:
+    /// * Json: The upgraded data is not valid.
+    /// * See `Migrations::upgrade`.
+    pub fn load<T: serde::de::DeserializeOwned>(
+        &self,
+        save: SaveData<serde_json::Value>,
+    ) -> Result<T, MigrationError> {
+        serde_json::from_value(self.upgrade(save)?).map_err(MigrationError::Json)
+    }
+}
+
+/// The errors that can happen when upgrading saved data.
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The data uses a version more recent than the current one.
+    NewerVersion(u32),
+    /// No migration upgrades from this version.
+    MissingMigration(u32),
+    /// The migration from this version failed.
+    Failed {
+        /// The version the migration upgrades from.
+        version: u32,
+        /// The reason of the failure.
+        reason: String,
+    },
+    /// The data is not valid JSON for the requested type.
+    #[cfg(feature = "serde_json")]
+    Json(serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use core::fmt::Debug;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    // Maps are kept to a single entry so that their debug output doesn't depend on the
+    // iteration order.
+    fn round_trip<T: Serialize + DeserializeOwned + Debug>(value: &T) {
+        let expected = format!("{:?}", value);
+        let bytes = bincode::serialize(value).unwrap();
+        let decoded: T = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(format!("{:?}", decoded), expected);
+        let bytes = postcard::to_allocvec(value).unwrap();
+        let decoded: T = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(format!("{:?}", decoded), expected);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let item_defs = ItemDefinitions::<u32, (), ()>::from(vec![ItemDefinition::new(
+            1,
+            (),
+            "apple".into(),
+            "Apple".to_string(),
+            "".into(),
+            Some(10),
+            Some(5),
+        )]);
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(3);
+        inventory
+            .insert_into(1, ItemInstance::new(1, 2), &item_defs)
+            .unwrap();
+        inventory.take_changes();
+        round_trip(&inventory);
+        round_trip(&item_defs.defs[&1]);
+
+        let mut health = StatDefinition::new(0u32, "Health".into(), "health".to_string(), 5.0);
+        health.min_value = Some(0.0);
+        let stat_defs = StatDefinitions::from(vec![health]);
+        let stats = stat_defs.to_statset();
+        round_trip(&stats);
+        round_trip(&StatConditionType::Expr(Expr::parse("value > 1").unwrap()));
+        round_trip(&StatConditionType::BetweenPercent(0.1, 0.2));
+
+        let effector_defs = EffectorDefinitions::from(vec![EffectorDefinition::new(
+            2u32,
+            Some(10.0),
+            vec![(0, EffectorType::Additive(1.0))],
+        )]);
+        let mut effectors = EffectorSet::default();
+        effectors.add(&2, &effector_defs);
+        round_trip(&effectors);
+
+        let mut skills = SkillSet::from(vec![3u32]);
+        skills.skills.get_mut(&3).unwrap().state = SkillState::Casting { remaining: 1.5 };
+        round_trip(&skills);
+
+        let sheet = CharacterSheet::<u32, u32, u32, u32, (), (), u32, ()>::new(
+            stats,
+            skills,
+            effectors,
+            inventory,
+            Unlockables::default(),
+        );
+        round_trip(&sheet);
+
+        let mut factions = FactionRepository::default();
+        let guild = factions
+            .create("Guild".to_string(), UserGroup::new(0, vec![1]))
+            .unwrap();
+        round_trip(&factions);
+        let mut claims = ClaimMap::default();
+        claims.claim((1, 2, 3), guild).unwrap();
+        round_trip(&claims);
+        let mut relations = FactionRelations::default();
+        relations.set_relation(guild, guild + 1, FactionRelation::Enemy);
+        round_trip(&relations);
+    }
+}
@@ -0,0 +1,186 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// A timer counting down to zero, optionally restarting each time it elapses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+pub struct Timer {
+    /// The time remaining before the timer elapses, in seconds.
+    pub remaining: f64,
+    /// The time between two repetitions of the timer.
+    /// None means that the timer elapses only once, as does an interval of 0 or less.
+    pub interval: Option<f64>,
+    /// Whether the timer is paused. Paused timers don't advance.
+    #[new(default)]
+    #[serde(default)]
+    pub paused: bool,
+    /// Whether the timer elapsed and won't repeat.
+    #[new(default)]
+    #[serde(default)]
+    pub finished: bool,
+}
+
+impl Timer {
+    /// Creates a timer elapsing once after the delay.
+    pub fn once(delay: f64) -> Self {
+        Self::new(delay, None)
+    }
+
+    /// Creates a timer elapsing every `interval` seconds, starting after the first interval.
+    pub fn repeating(interval: f64) -> Self {
+        Self::new(interval, Some(interval))
+    }
+
+    /// Advances the timer by `delta_time` seconds.
+    /// Returns the number of times the timer elapsed.
+    /// Timers that elapse only once return 1 the first time they elapse and 0 afterward.
+    pub fn update(&mut self, delta_time: f64) -> u32 {
+        if self.paused || self.finished {
+            return 0;
+        }
+        self.remaining -= delta_time;
+        if self.remaining > 0.0 {
+            return 0;
+        }
+        match self.interval {
+            Some(interval) if interval > 0.0 => {
+                let mut count = 0;
+                while self.remaining <= 0.0 {
+                    self.remaining += interval;
+                    count += 1;
+                }
+                count
+            }
+            _ => {
+                self.finished = true;
+                1
+            }
+        }
+    }
+
+    /// Checks if the timer elapsed and won't repeat.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Pauses the timer.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes the timer.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+}
+
+/// An event waiting in a `Scheduler`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ScheduledEvent<Id, T> {
+    /// The id of the scheduled event.
+    pub id: Id,
+    /// The timer after which the event happens.
+    pub timer: Timer,
+    /// The event emitted when the timer elapses.
+    pub event: T,
+}
+
+/// Emits events after a delay or at regular intervals.
+/// The events are plain data, so the scheduler can be saved and loaded with the rest of the game
+/// state.
+///
+/// # Generics
+/// - Id: Scheduled Event Id
+/// - T: Event Type
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Scheduler<Id, T> {
+    /// The scheduled events, in the order they were scheduled.
+    pub events: Vec<ScheduledEvent<Id, T>>,
+}
+
+impl<Id, T> Default for Scheduler<Id, T> {
+    fn default() -> Self {
+        Self { events: vec![] }
+    }
+}
+
+impl<Id: PartialEq + Clone, T: Clone> Scheduler<Id, T> {
+    /// Schedules an event happening once after the delay, in seconds.
+    /// Replaces the event previously scheduled with the same id.
+    pub fn schedule(&mut self, id: Id, delay: f64, event: T) {
+        self.schedule_timer(id, Timer::once(delay), event);
+    }
+
+    /// Schedules an event happening every `interval` seconds.
+    /// Replaces the event previously scheduled with the same id.
+    pub fn schedule_repeating(&mut self, id: Id, interval: f64, event: T) {
+        self.schedule_timer(id, Timer::repeating(interval), event);
+    }
+
+    /// Schedules an event happening when the timer elapses.
+    /// Replaces the event previously scheduled with the same id.
+    pub fn schedule_timer(&mut self, id: Id, timer: Timer, event: T) {
+        self.cancel(&id);
+        self.events.push(ScheduledEvent::new(id, timer, event));
+    }
+
+    /// Removes the scheduled event and returns it.
+    pub fn cancel(&mut self, id: &Id) -> Option<T> {
+        let idx = self.events.iter().position(|e| e.id == *id)?;
+        Some(self.events.remove(idx).event)
+    }
+
+    /// Checks if an event is scheduled with this id.
+    pub fn contains(&self, id: &Id) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Returns the timer of the scheduled event.
+    pub fn get(&self, id: &Id) -> Option<&Timer> {
+        self.events.iter().find(|e| e.id == *id).map(|e| &e.timer)
+    }
+
+    /// Returns the timer of the scheduled event mutably.
+    pub fn get_mut(&mut self, id: &Id) -> Option<&mut Timer> {
+        self.events
+            .iter_mut()
+            .find(|e| e.id == *id)
+            .map(|e| &mut e.timer)
+    }
+
+    /// Pauses the scheduled event.
+    /// Returns false if no event is scheduled with this id.
+    pub fn pause(&mut self, id: &Id) -> bool {
+        self.get_mut(id).map(Timer::pause).is_some()
+    }
+
+    /// Resumes the scheduled event.
+    /// Returns false if no event is scheduled with this id.
+    pub fn resume(&mut self, id: &Id) -> bool {
+        self.get_mut(id).map(Timer::resume).is_some()
+    }
+
+    /// Pauses all the scheduled events.
+    pub fn pause_all(&mut self) {
+        self.events.iter_mut().for_each(|e| e.timer.pause());
+    }
+
+    /// Resumes all the scheduled events.
+    pub fn resume_all(&mut self) {
+        self.events.iter_mut().for_each(|e| e.timer.resume());
+    }
+
+    /// Advances all the timers by `delta_time` seconds and returns the events that happened,
+    /// in the order they were scheduled.
+    /// Repeating events are returned once for each time they elapsed. Events happening only
+    /// once are removed from the scheduler.
+    pub fn update(&mut self, delta_time: f64) -> Vec<(Id, T)> {
+        let mut fired = vec![];
+        for e in self.events.iter_mut() {
+            for _ in 0..e.timer.update(delta_time) {
+                fired.push((e.id.clone(), e.event.clone()));
+            }
+        }
+        self.events.retain(|e| !e.timer.is_finished());
+        fired
+    }
+}
@@ -0,0 +1,107 @@
+use crate::*;
+use core::hash::Hash;
+
+/// A named script function checking the value of a stat, along with its minimum and maximum
+/// values.
+pub type ScriptCondition = Box<dyn Fn(f64, Option<f64>, Option<f64>) -> bool>;
+
+/// A named script function changing stats. The last parameter is a factor, like the magnitude
+/// of an effector multiplied by the elapsed time or the number of completed transitions.
+pub type ScriptEffect<K> = Box<dyn Fn(&mut StatSet<K>, &StatDefinitions<K>, f64)>;
+
+/// The script functions that data can refer to by name, letting modders extend the behavior
+/// of conditions, effectors and item transitions without recompiling the game.
+///
+/// The functions are plain closures, so they can call into any scripting engine, like rhai or
+/// lua.
+///
+/// Used by:
+/// * `StatConditionType::Script`, see `StatConditionGroup::check_scripted`. Skills,
+///   unlockables and item transitions check those conditions using the `ScriptHost` they are
+///   given, like `SkillProcessor::scripts`.
+/// * `EffectorDefinition::scripts`, see `EffectorSet::run_scripts`.
+/// * `ItemTransitionDefinition::output_scripts`, see `run_output_scripts`.
+///
+/// # Generics
+/// - K: Stat Key
+pub struct ScriptHost<K: Hash + Eq> {
+    /// The conditions, by name.
+    pub conditions: HashMap<String, ScriptCondition>,
+    /// The effects, by name.
+    pub effects: HashMap<String, ScriptEffect<K>>,
+}
+
+impl<K: Hash + Eq> Default for ScriptHost<K> {
+    fn default() -> Self {
+        Self {
+            conditions: HashMap::new(),
+            effects: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq> ScriptHost<K> {
+    /// Registers a condition, replacing the previous one using this name.
+    pub fn register_condition<F: Fn(f64, Option<f64>, Option<f64>) -> bool + 'static>(
+        &mut self,
+        name: impl Into<String>,
+        condition: F,
+    ) {
+        self.conditions.insert(name.into(), Box::new(condition));
+    }
+
+    /// Registers an effect, replacing the previous one using this name.
+    pub fn register_effect<F: Fn(&mut StatSet<K>, &StatDefinitions<K>, f64) + 'static>(
+        &mut self,
+        name: impl Into<String>,
+        effect: F,
+    ) {
+        self.effects.insert(name.into(), Box::new(effect));
+    }
+
+    /// Calls the condition with the value of a stat and its minimum and maximum values.
+    ///
+    /// Errors:
+    /// * UnknownFunction: No condition uses this name.
+    pub fn check_condition(
+        &self,
+        name: &str,
+        value: f64,
+        min_value: Option<f64>,
+        max_value: Option<f64>,
+    ) -> Result<bool, ScriptError> {
+        let condition = self
+            .conditions
+            .get(name)
+            .ok_or_else(|| ScriptError::UnknownFunction(name.to_string()))?;
+        Ok(condition(value, min_value, max_value))
+    }
+
+    /// Calls the effect on the stats.
+    ///
+    /// Errors:
+    /// * UnknownFunction: No effect uses this name.
+    pub fn run_effect(
+        &self,
+        name: &str,
+        stats: &mut StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        factor: f64,
+    ) -> Result<(), ScriptError> {
+        let effect = self
+            .effects
+            .get(name)
+            .ok_or_else(|| ScriptError::UnknownFunction(name.to_string()))?;
+        effect(stats, stat_defs, factor);
+        Ok(())
+    }
+}
+
+/// The errors that can happen when calling script functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    /// No script function of this kind uses this name.
+    UnknownFunction(String),
+    /// The script condition with this name was checked without a `ScriptHost`.
+    NoScriptHost(String),
+}
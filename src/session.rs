@@ -0,0 +1,203 @@
+use crate::*;
+
+/// The connection of a user.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Session {
+    /// The id of the user.
+    pub user: i32,
+    /// The time at which the user logged in, in seconds.
+    pub login_at: f64,
+    /// The time of the last activity of the user, in seconds.
+    pub last_activity: f64,
+    /// Whether the user is idle.
+    #[new(default)]
+    pub idle: bool,
+}
+
+/// A change in the sessions of a `SessionManager`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SessionEvent {
+    /// The user logged in.
+    Joined(i32),
+    /// The user logged out.
+    Left {
+        /// The id of the user.
+        user: i32,
+        /// The duration of the session, in seconds.
+        duration: f64,
+    },
+    /// The user didn't do anything for the idle timeout.
+    Idle(i32),
+    /// The user was idle and did something.
+    Active(i32),
+}
+
+/// Tracks which users are connected and for how long they played.
+/// Times are in seconds and must all come from the same clock.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct SessionManager {
+    /// The time without activity after which a user is considered idle, in seconds.
+    pub idle_timeout: f64,
+    /// The sessions of the connected users.
+    #[new(default)]
+    pub sessions: HashMap<i32, Session>,
+    /// The play time of the previous sessions of each user, in seconds.
+    #[new(default)]
+    pub playtime: HashMap<i32, f64>,
+    /// The time at which each user last logged out, in seconds.
+    #[new(default)]
+    pub last_logout: HashMap<i32, f64>,
+    /// The events that were not drained yet.
+    /// See `SessionManager::drain_events`.
+    #[new(default)]
+    #[serde(skip)]
+    pub events: Vec<SessionEvent>,
+}
+
+impl SessionManager {
+    /// Starts a session for the user.
+    ///
+    /// Errors:
+    /// * AlreadyConnected: The user already has a session.
+    pub fn login(&mut self, user: i32, now: f64) -> Result<(), SessionError> {
+        if self.sessions.contains_key(&user) {
+            return Err(SessionError::AlreadyConnected);
+        }
+        self.sessions.insert(user, Session::new(user, now, now));
+        self.events.push(SessionEvent::Joined(user));
+        Ok(())
+    }
+
+    /// Ends the session of the user and returns its duration.
+    ///
+    /// Errors:
+    /// * NotConnected: The user has no session.
+    pub fn logout(&mut self, user: i32, now: f64) -> Result<f64, SessionError> {
+        let session = self
+            .sessions
+            .remove(&user)
+            .ok_or(SessionError::NotConnected)?;
+        let duration = (now - session.login_at).max(0.0);
+        *self.playtime.entry(user).or_insert(0.0) += duration;
+        self.last_logout.insert(user, now);
+        self.events.push(SessionEvent::Left { user, duration });
+        Ok(duration)
+    }
+
+    /// Records an activity of the user, so that it is not idle.
+    ///
+    /// Errors:
+    /// * NotConnected: The user has no session.
+    pub fn activity(&mut self, user: i32, now: f64) -> Result<(), SessionError> {
+        let session = self
+            .sessions
+            .get_mut(&user)
+            .ok_or(SessionError::NotConnected)?;
+        session.last_activity = now;
+        if session.idle {
+            session.idle = false;
+            self.events.push(SessionEvent::Active(user));
+        }
+        Ok(())
+    }
+
+    /// Marks the users without activity for the idle timeout as idle.
+    pub fn update(&mut self, now: f64) {
+        for session in self.sessions.values_mut() {
+            if !session.idle && now - session.last_activity >= self.idle_timeout {
+                session.idle = true;
+                self.events.push(SessionEvent::Idle(session.user));
+            }
+        }
+    }
+
+    /// Checks if the user has a session.
+    pub fn is_online(&self, user: i32) -> bool {
+        self.sessions.contains_key(&user)
+    }
+
+    /// Checks if the user has a session and is idle.
+    pub fn is_idle(&self, user: i32) -> bool {
+        self.sessions.get(&user).map(|s| s.idle).unwrap_or(false)
+    }
+
+    /// Returns the ids of the users having a session.
+    pub fn online_users(&self) -> Vec<i32> {
+        self.sessions.keys().copied().collect()
+    }
+
+    /// Returns the duration of the current session of the user, if any.
+    pub fn session_time(&self, user: i32, now: f64) -> Option<f64> {
+        self.sessions
+            .get(&user)
+            .map(|s| (now - s.login_at).max(0.0))
+    }
+
+    /// Returns the play time of the user over all its sessions, including the current one.
+    pub fn total_playtime(&self, user: i32, now: f64) -> f64 {
+        self.playtime.get(&user).copied().unwrap_or(0.0)
+            + self.session_time(user, now).unwrap_or(0.0)
+    }
+
+    /// Removes and returns the events, from oldest to newest.
+    pub fn drain_events(&mut self) -> impl Iterator<Item = SessionEvent> + '_ {
+        self.events.drain(..)
+    }
+}
+
+/// The errors that can happen when using the `SessionManager`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionError {
+    /// The user already has a session.
+    AlreadyConnected,
+    /// The user has no session.
+    NotConnected,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playtime_adds_up_over_sessions() {
+        let mut manager = SessionManager::new(60.0);
+        manager.login(1, 0.0).unwrap();
+        assert_eq!(manager.login(1, 5.0), Err(SessionError::AlreadyConnected));
+        assert_eq!(manager.logout(1, 100.0), Ok(100.0));
+        assert_eq!(manager.logout(1, 110.0), Err(SessionError::NotConnected));
+        manager.login(1, 200.0).unwrap();
+        assert_eq!(manager.session_time(1, 250.0), Some(50.0));
+        assert_eq!(manager.total_playtime(1, 250.0), 150.0);
+        assert_eq!(manager.last_logout[&1], 100.0);
+        assert_eq!(
+            manager.drain_events().collect::<Vec<_>>(),
+            vec![
+                SessionEvent::Joined(1),
+                SessionEvent::Left {
+                    user: 1,
+                    duration: 100.0
+                },
+                SessionEvent::Joined(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn inactive_users_become_idle() {
+        let mut manager = SessionManager::new(60.0);
+        manager.login(1, 0.0).unwrap();
+        manager.activity(1, 30.0).unwrap();
+        manager.update(60.0);
+        assert!(!manager.is_idle(1));
+        manager.update(90.0);
+        manager.update(100.0);
+        assert!(manager.is_idle(1));
+        manager.activity(1, 110.0).unwrap();
+        assert!(!manager.is_idle(1));
+        assert_eq!(
+            manager.drain_events().skip(1).collect::<Vec<_>>(),
+            vec![SessionEvent::Idle(1), SessionEvent::Active(1)]
+        );
+        assert_eq!(manager.activity(2, 110.0), Err(SessionError::NotConnected));
+    }
+}
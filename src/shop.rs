@@ -0,0 +1,339 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// The prices of an item in a `Shop`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ItemPrice<C> {
+    /// The price paid by the player to buy one item.
+    /// None means that the item can't be bought.
+    pub buy: Option<Vec<(C, i64)>>,
+    /// The price paid to the player when selling one item.
+    /// None means that the shop doesn't buy this item.
+    pub sell: Option<Vec<(C, i64)>>,
+}
+
+/// The prices of the items of a `Shop`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct PriceTable<K: Hash + Eq, C> {
+    /// The prices mapped by item key.
+    pub prices: HashMap<K, ItemPrice<C>>,
+}
+
+impl<K: Hash + Eq, C> Default for PriceTable<K, C> {
+    fn default() -> Self {
+        Self {
+            prices: HashMap::default(),
+        }
+    }
+}
+
+/// Periodically adds items to the stock of a `Shop`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Restock<K> {
+    /// The key of the restocked item.
+    pub key: K,
+    /// The quantity added at each restock.
+    pub quantity: usize,
+    /// The stock won't go over this quantity when restocking.
+    pub max_quantity: usize,
+    /// The time between restocks, in seconds.
+    pub interval: f64,
+    /// The time elapsed since the last restock.
+    #[new(default)]
+    pub elapsed: f64,
+}
+
+/// A vendor selling the items of its `Inventory` and buying items from players.
+///
+/// # Generics
+/// - K: Item Key
+/// - S: Item Slot Type
+/// - U: Item Custom Data
+/// - C: Currency Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Shop<K: Hash + Eq, S: SlotType, U: Default, C> {
+    /// The stock of the shop.
+    pub inventory: Inventory<K, S, U>,
+    /// The prices of the items.
+    pub prices: PriceTable<K, C>,
+    /// The items that are periodically restocked.
+    #[new(default)]
+    pub restocks: Vec<Restock<K>>,
+}
+
+impl<
+        K: PartialEq + Clone + Debug + Hash + Eq,
+        S: SlotType + Clone,
+        U: Default + Clone + Debug + PartialEq,
+        C: Hash + Eq + Clone,
+    > Shop<K, S, U, C>
+{
+    /// Advances the restock timers by `delta_time` seconds and restocks the items whose timer
+    /// elapsed.
+    /// Restocked items that don't fit in the inventory are lost.
+    pub fn update<D: Default>(&mut self, delta_time: f64, item_defs: &ItemDefinitions<K, S, D>) {
+        for restock in self.restocks.iter_mut() {
+            if restock.interval <= 0.0 {
+                continue;
+            }
+            restock.elapsed += delta_time;
+            while restock.elapsed >= restock.interval {
+                restock.elapsed -= restock.interval;
+                let current = self
+                    .inventory
+                    .get_key(&restock.key)
+                    .map(|ii| ii.quantity)
+                    .sum::<usize>();
+                let quantity = restock
+                    .quantity
+                    .min(restock.max_quantity.saturating_sub(current));
+                if quantity > 0 {
+                    let _ = self
+                        .inventory
+                        .insert(ItemInstance::new(restock.key.clone(), quantity), item_defs);
+                }
+            }
+        }
+    }
+
+    /// The price to buy this quantity of the item, if it is for sale.
+    pub fn buy_price(&self, key: &K, quantity: usize) -> Option<Vec<(C, i64)>> {
+        self.prices
+            .prices
+            .get(key)
+            .and_then(|p| p.buy.as_ref())
+            .map(|p| total_price(p, quantity))
+    }
+
+    /// The price received when selling this quantity of the item, if the shop buys it.
+    pub fn sell_price(&self, key: &K, quantity: usize) -> Option<Vec<(C, i64)>> {
+        self.prices
+            .prices
+            .get(key)
+            .and_then(|p| p.sell.as_ref())
+            .map(|p| total_price(p, quantity))
+    }
+
+    /// Buys items from the shop. The items are moved into the player inventory and the price is
+    /// removed from the wallet.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotForSale: The item has no buy price.
+    /// * OutOfStock: The shop doesn't have this quantity of the item.
+    /// * Wallet: The wallet can't pay the price.
+    /// * InventoryFull: The items don't fit in the player inventory.
+    pub fn buy<U2: Default + Clone + Debug + PartialEq, D: Default>(
+        &mut self,
+        player_inventory: &mut Inventory<K, S, U2>,
+        wallet: &mut Wallet<C>,
+        key: &K,
+        quantity: usize,
+        item_defs: &ItemDefinitions<K, S, D>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), ShopError<C>> {
+        let price = self.buy_price(key, quantity).ok_or(ShopError::NotForSale)?;
+        if !self.inventory.has_quantity(key, quantity) {
+            return Err(ShopError::OutOfStock);
+        }
+        if let Err(e) = wallet.clone().pay(&price, currency_defs) {
+            return Err(ShopError::Wallet(e));
+        }
+        let mut new_inventory = player_inventory.clone();
+        new_inventory
+            .insert(ItemInstance::new(key.clone(), quantity), item_defs)
+            .map_err(|_| ShopError::InventoryFull)?;
+        *player_inventory = new_inventory;
+        self.inventory
+            .delete_key(key, quantity)
+            .expect("Failed to remove items from the shop stock. This is a bug.");
+        wallet.pay(&price, currency_defs).map_err(ShopError::Wallet)
+    }
+
+    /// Sells items to the shop. The items are removed from the player inventory and the price
+    /// is added to the wallet.
+    /// The sold items are added to the stock of the shop if they fit.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotBuying: The item has no sell price.
+    /// * NotEnoughItems: The player inventory doesn't have this quantity of the item.
+    /// * Wallet: The wallet can't hold the price.
+    pub fn sell<U2: Default + Clone + Debug + PartialEq, D: Default>(
+        &mut self,
+        player_inventory: &mut Inventory<K, S, U2>,
+        wallet: &mut Wallet<C>,
+        key: &K,
+        quantity: usize,
+        item_defs: &ItemDefinitions<K, S, D>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), ShopError<C>> {
+        let price = self.sell_price(key, quantity).ok_or(ShopError::NotBuying)?;
+        if !player_inventory.has_quantity(key, quantity) {
+            return Err(ShopError::NotEnoughItems);
+        }
+        let mut new_wallet = wallet.clone();
+        for (currency, amount) in price.iter() {
+            new_wallet
+                .add(currency, *amount, currency_defs)
+                .map_err(ShopError::Wallet)?;
+        }
+        *wallet = new_wallet;
+        player_inventory
+            .delete_key(key, quantity)
+            .expect("Failed to remove items from the player inventory. This is a bug.");
+        let _ = self
+            .inventory
+            .insert(ItemInstance::new(key.clone(), quantity), item_defs);
+        Ok(())
+    }
+}
+
+fn total_price<C: Clone>(price: &[(C, i64)], quantity: usize) -> Vec<(C, i64)> {
+    price
+        .iter()
+        .map(|(c, a)| (c.clone(), a * quantity as i64))
+        .collect()
+}
+
+/// The errors that can happen when buying from or selling to a `Shop`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShopError<C> {
+    /// The shop doesn't sell this item.
+    NotForSale,
+    /// The shop doesn't buy this item.
+    NotBuying,
+    /// The shop doesn't have enough of this item in stock.
+    OutOfStock,
+    /// The player doesn't have enough of this item.
+    NotEnoughItems,
+    /// The bought items don't fit in the player inventory.
+    InventoryFull,
+    /// The wallet can't pay or receive the price.
+    Wallet(WalletError<C>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn shop() -> Shop<u32, (), (), u8> {
+        let mut prices = PriceTable::default();
+        prices
+            .prices
+            .insert(1, ItemPrice::new(Some(vec![(0, 10)]), Some(vec![(0, 4)])));
+        Shop::new(Inventory::new_fixed(2), prices)
+    }
+
+    #[test]
+    fn buy_and_sell() {
+        let (item_defs, currency_defs) = (item_defs(), currency_defs());
+        let mut shop = shop();
+        shop.inventory
+            .insert(ItemInstance::new(1, 5), &item_defs)
+            .unwrap();
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(1);
+        let mut wallet = Wallet::new();
+        wallet.add(&0, 25, &currency_defs).unwrap();
+        assert_eq!(
+            shop.buy(
+                &mut inventory,
+                &mut wallet,
+                &1,
+                3,
+                &item_defs,
+                &currency_defs
+            ),
+            Err(ShopError::Wallet(WalletError::NotEnough(0)))
+        );
+        assert_eq!(
+            shop.buy(
+                &mut inventory,
+                &mut wallet,
+                &1,
+                6,
+                &item_defs,
+                &currency_defs
+            ),
+            Err(ShopError::OutOfStock)
+        );
+        shop.buy(
+            &mut inventory,
+            &mut wallet,
+            &1,
+            2,
+            &item_defs,
+            &currency_defs,
+        )
+        .unwrap();
+        assert_eq!(wallet.balance(&0), 5);
+        assert_eq!(inventory.get(0).as_ref().unwrap().quantity, 2);
+        assert!(shop.inventory.has_quantity(&1, 3));
+        assert!(!shop.inventory.has_quantity(&1, 4));
+
+        assert_eq!(
+            shop.sell(
+                &mut inventory,
+                &mut wallet,
+                &1,
+                3,
+                &item_defs,
+                &currency_defs
+            ),
+            Err(ShopError::NotEnoughItems)
+        );
+        shop.sell(
+            &mut inventory,
+            &mut wallet,
+            &1,
+            1,
+            &item_defs,
+            &currency_defs,
+        )
+        .unwrap();
+        assert_eq!(wallet.balance(&0), 9);
+        assert_eq!(inventory.get(0).as_ref().unwrap().quantity, 1);
+        assert!(shop.inventory.has_quantity(&1, 4));
+    }
+
+    #[test]
+    fn full_inventory_changes_nothing() {
+        let (item_defs, currency_defs) = (item_defs(), currency_defs());
+        let mut shop = shop();
+        shop.inventory
+            .insert(ItemInstance::new(1, 5), &item_defs)
+            .unwrap();
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(1);
+        inventory
+            .insert(ItemInstance::new(1, 9), &item_defs)
+            .unwrap();
+        let mut wallet = Wallet::new();
+        wallet.add(&0, 100, &currency_defs).unwrap();
+        assert_eq!(
+            shop.buy(
+                &mut inventory,
+                &mut wallet,
+                &1,
+                2,
+                &item_defs,
+                &currency_defs
+            ),
+            Err(ShopError::InventoryFull)
+        );
+        assert_eq!(wallet.balance(&0), 100);
+        assert_eq!(inventory.get(0).as_ref().unwrap().quantity, 9);
+        assert!(shop.inventory.has_quantity(&1, 5));
+    }
+
+    #[test]
+    fn restock_stops_at_max_quantity() {
+        let item_defs = item_defs();
+        let mut shop = shop();
+        shop.restocks.push(Restock::new(1, 2, 5, 1.0));
+        shop.update(10.0, &item_defs);
+        assert!(shop.inventory.has_quantity(&1, 5));
+        assert!(!shop.inventory.has_quantity(&1, 6));
+    }
+}
@@ -1,22 +1,25 @@
 use crate::*;
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::hash::Hash;
+use core::fmt::Debug;
+use core::hash::Hash;
+use derivative::*;
 
 // World interaction
 // or
 // Stat buff
 /// The definition of an usable skill.
 #[derive(new, Clone, Serialize, Deserialize, Debug, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
 pub struct SkillDefinition<K, E, S, I> {
     /// The id of this skill.
     pub key: S,
-    /// The name.
-    pub name: String,
+    /// The display name.
+    #[builder(setter(into))]
+    pub name: LocalizedText,
     /// The computer friendly name.
     pub friendly_name: String,
     /// The complete description of this skill.
-    pub description: String,
+    #[builder(setter(into))]
+    pub description: LocalizedText,
     /// The cooldown between usages of this skill.
     pub cooldown: f64,
     /// Whether this skill is will activate automatically once all conditions are met or
@@ -24,10 +27,222 @@ pub struct SkillDefinition<K, E, S, I> {
     pub passive: bool,
     /// The stats conditions required to activate this skill.
     pub conditions: Vec<StatCondition<K>>,
+    /// The combined stats conditions required to activate this skill, in addition to
+    /// `conditions`.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub condition_groups: Vec<StatConditionGroup<K>>,
+    /// The conditions on the time of the `GameClock` required to activate this skill.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub clock_conditions: Vec<ClockConditionGroup>,
     /// The item conditions required to activate this skill.
     pub item_conditions: Vec<(I, usize, UseMode)>,
     /// The caused stat effectors.
     pub stat_effectors: Vec<E>,
+    /// How the skill is activated.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "SkillActivation::default")]
+    pub activation: SkillActivation<K>,
+    /// The causes interrupting the cast or the channel of this skill.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub interrupted_by: Vec<InterruptCause>,
+    /// What the skill can target.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub targeting: TargetingMode,
+    /// How the skill improves when it gains experience.
+    /// None means that the skill always stays at rank 1.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub progression: Option<SkillProgression>,
+}
+
+/// How a skill gains experience and ranks, and how its values scale with its rank.
+/// The scaling tables contain the multiplier applied at each rank, starting with rank 1.
+/// Ranks higher than the size of a table use its last multiplier and empty tables mean no
+/// scaling.
+#[derive(Clone, Serialize, Deserialize, Debug, new)]
+pub struct SkillProgression {
+    /// The rank reached for the accumulated experience of the skill.
+    pub curve: LevelCurve,
+    /// The experience gained each time the skill is activated.
+    pub xp_per_use: u32,
+    /// The multipliers of the cooldown.
+    #[new(default)]
+    #[serde(default)]
+    pub cooldown: Vec<f64>,
+    /// The multipliers of the quantities of the item conditions and of the stat drained by
+    /// toggled skills.
+    #[new(default)]
+    #[serde(default)]
+    pub cost: Vec<f64>,
+    /// The multipliers of the magnitude of the stat effectors.
+    /// See `EffectorInstance::magnitude`.
+    #[new(default)]
+    #[serde(default)]
+    pub magnitude: Vec<f64>,
+}
+
+impl SkillProgression {
+    fn scale(table: &[f64], rank: u32) -> f64 {
+        let idx = (rank.max(1) - 1) as usize;
+        table
+            .get(idx)
+            .or_else(|| table.last())
+            .copied()
+            .unwrap_or(1.0)
+    }
+}
+
+/// How a skill is activated.
+#[derive(Clone, Serialize, Deserialize, Debug, Derivative)]
+#[derivative(Default(bound = ""))]
+pub enum SkillActivation<K> {
+    /// The stat effectors are added immediately.
+    #[derivative(Default)]
+    Instant,
+    /// The stat effectors are added once the cast time is over.
+    Cast {
+        /// The cast time, in seconds.
+        duration: f64,
+    },
+    /// The stat effectors are active during the channel and removed when it ends.
+    Channel {
+        /// The duration of the channel, in seconds.
+        duration: f64,
+    },
+    /// The stat effectors are active until the skill is activated again or the stat runs out.
+    Toggle {
+        /// The stat drained while the skill is active.
+        stat: K,
+        /// The value removed from the stat each second.
+        drain_per_second: f64,
+    },
+}
+
+/// Something interrupting a skill being cast or channeled.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum InterruptCause {
+    /// The caster took damage.
+    Damage,
+    /// The caster moved.
+    Movement,
+    /// A game specific cause.
+    Custom(String),
+}
+
+/// What a skill can target.
+/// Positions and directions are `[x, y, z]` coordinates. 2D games can leave z at 0.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Derivative)]
+#[derivative(Default)]
+pub enum TargetingMode {
+    /// The skill only affects the caster. The target position is ignored.
+    #[derivative(Default)]
+    Caster,
+    /// The skill affects a single target within range.
+    Single {
+        /// The maximum distance between the caster and the target.
+        range: f64,
+    },
+    /// The skill affects the targets in a cone in front of the caster.
+    Cone {
+        /// The length of the cone.
+        range: f64,
+        /// The total opening angle of the cone, in degrees.
+        angle: f64,
+    },
+    /// The skill affects the targets around the caster.
+    Radius {
+        /// The radius of the area.
+        radius: f64,
+    },
+    /// The skill launches a projectile towards the target.
+    Projectile {
+        /// The maximum distance travelled by the projectile.
+        range: f64,
+        /// The speed of the projectile, in distance per second.
+        speed: f64,
+    },
+}
+
+impl TargetingMode {
+    /// Checks if the target position can be targeted by a caster at this position.
+    /// `facing` is the direction the caster is looking at and is only used by cones.
+    ///
+    /// Errors:
+    /// * OutOfRange: The target is too far from the caster.
+    /// * OutsideAngle: The target is not inside of the cone.
+    pub fn validate_target(
+        &self,
+        caster_pos: [f64; 3],
+        facing: [f64; 3],
+        target_pos: [f64; 3],
+    ) -> Result<(), TargetingError> {
+        let offset = [
+            target_pos[0] - caster_pos[0],
+            target_pos[1] - caster_pos[1],
+            target_pos[2] - caster_pos[2],
+        ];
+        let distance = length(offset);
+        match *self {
+            TargetingMode::Caster => Ok(()),
+            TargetingMode::Single { range }
+            | TargetingMode::Radius { radius: range }
+            | TargetingMode::Projectile { range, .. } => {
+                if distance > range {
+                    Err(TargetingError::OutOfRange)
+                } else {
+                    Ok(())
+                }
+            }
+            TargetingMode::Cone { range, angle } => {
+                if distance > range {
+                    return Err(TargetingError::OutOfRange);
+                }
+                let facing_length = length(facing);
+                if distance == 0.0 || facing_length == 0.0 {
+                    return Ok(());
+                }
+                let dot = offset[0] * facing[0] + offset[1] * facing[1] + offset[2] * facing[2];
+                let cos = (dot / (distance * facing_length)).clamp(-1.0, 1.0);
+                if cos.acos().to_degrees() > angle / 2.0 {
+                    Err(TargetingError::OutsideAngle)
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// The time taken by the projectile to reach a target at this distance.
+    /// Returns 0 for the modes that are not projectiles.
+    pub fn travel_time(&self, distance: f64) -> f64 {
+        match *self {
+            TargetingMode::Projectile { speed, .. } if speed > 0.0 => distance / speed,
+            _ => 0.0,
+        }
+    }
+}
+
+fn length(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+/// The errors that can happen when validating the target of a skill.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetingError {
+    /// The target is too far from the caster.
+    OutOfRange,
+    /// The target is not inside of the cone of the skill.
+    OutsideAngle,
 }
 
 /// # Generics
@@ -41,35 +256,177 @@ impl<K: Hash + Eq + Debug, E, S, I: Hash + Eq + Clone + PartialEq + Debug>
     SkillDefinition<K, E, S, I>
 {
     /// Checks if all the conditions to use this skill are met.
+    /// The item conditions are checked using their unscaled quantities. See
+    /// `SkillDefinition::item_costs` for the quantities used at a given rank.
+    ///
+    /// Errors:
+    /// See `SkillDefinition::check_stat_conditions`.
     pub fn check_conditions<IT: SlotType, CD: PartialEq + Default + Clone + Debug>(
         &self,
         stats: &StatSet<K>,
         inventory: &Inventory<I, IT, CD>,
         stat_defs: &StatDefinitions<K>,
-    ) -> bool {
-        for c in &self.conditions {
-            if !c.check(stats, stat_defs) {
-                return false;
-            }
+        scripts: Option<&ScriptHost<K>>,
+    ) -> Result<bool, ScriptError> {
+        if !self.check_stat_conditions(stats, stat_defs, scripts)? {
+            return Ok(false);
         }
         for ic in &self.item_conditions {
             if !inventory.has_quantity(&ic.0, ic.1) {
-                return false;
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Checks if the stat conditions and condition groups of this skill are met.
+    /// The script conditions are checked using the `ScriptHost`.
+    ///
+    /// Errors:
+    /// See `StatCondition::check_scripted`.
+    pub fn check_stat_conditions(
+        &self,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        scripts: Option<&ScriptHost<K>>,
+    ) -> Result<bool, ScriptError> {
+        for c in self.conditions.iter() {
+            if !c.check_scripted(stats, stat_defs, scripts)? {
+                return Ok(false);
             }
         }
-        true
+        for g in self.condition_groups.iter() {
+            if !g.check_scripted(stats, stat_defs, scripts)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the item conditions of this skill with the quantities scaled for this rank.
+    /// Scaled quantities are rounded to the nearest integer.
+    pub fn item_costs(&self, rank: u32) -> Vec<(&I, usize, &UseMode)> {
+        let multiplier = self.cost_multiplier(rank);
+        self.item_conditions
+            .iter()
+            .map(|(key, quantity, use_mode)| {
+                let scaled = (*quantity as f64 * multiplier).round() as usize;
+                (key, scaled, use_mode)
+            })
+            .collect()
+    }
+
+    /// Checks if the target position can be targeted by this skill.
+    /// See `TargetingMode::validate_target`.
+    pub fn validate_target(
+        &self,
+        caster_pos: [f64; 3],
+        facing: [f64; 3],
+        target_pos: [f64; 3],
+    ) -> Result<(), TargetingError> {
+        self.targeting
+            .validate_target(caster_pos, facing, target_pos)
+    }
+}
+
+impl<K, E, S, I> SkillDefinition<K, E, S, I> {
+    /// Checks if the clock conditions of this skill are met.
+    /// Without a clock, they are only met if there are none.
+    pub fn check_clock_conditions(&self, clock: Option<&GameClock>) -> bool {
+        clock.map_or(self.clock_conditions.is_empty(), |clock| {
+            self.clock_conditions.iter().all(|c| c.check(clock))
+        })
+    }
+
+    /// Returns the rank of this skill for the accumulated experience.
+    pub fn rank(&self, xp: u32) -> u32 {
+        self.progression
+            .as_ref()
+            .map(|p| p.curve.level_for_xp(xp))
+            .unwrap_or(1)
+    }
+
+    /// Returns the cooldown of this skill at this rank.
+    pub fn cooldown_at(&self, rank: u32) -> f64 {
+        self.cooldown * self.multiplier(rank, |p| &p.cooldown)
+    }
+
+    /// Returns the multiplier of the item and stat costs of this skill at this rank.
+    pub fn cost_multiplier(&self, rank: u32) -> f64 {
+        self.multiplier(rank, |p| &p.cost)
+    }
+
+    /// Returns the magnitude of the stat effectors of this skill at this rank.
+    pub fn magnitude_at(&self, rank: u32) -> f64 {
+        self.multiplier(rank, |p| &p.magnitude)
+    }
+
+    fn multiplier<F: Fn(&SkillProgression) -> &Vec<f64>>(&self, rank: u32, table: F) -> f64 {
+        self.progression
+            .as_ref()
+            .map(|p| SkillProgression::scale(table(p), rank))
+            .unwrap_or(1.0)
     }
 }
 
 /// An instance of a skill.
 /// There is one per skill per entity that can use it.
 /// Holds the cooldown for each skill.
-#[derive(Debug, Clone, Serialize, Deserialize, new)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
 pub struct SkillInstance<S> {
     /// The skill key.
     pub skill_key: S,
     /// The remaining time to wait before we can use this skill again.
     pub current_cooldown: f64,
+    /// Whether the skill is being cast, channeled or is toggled on.
+    #[new(default)]
+    #[serde(default)]
+    pub state: SkillState,
+    /// The experience accumulated by this skill.
+    #[new(default)]
+    #[serde(default)]
+    pub xp: u32,
+}
+
+impl<S> SkillInstance<S> {
+    /// Returns the current rank of this skill.
+    pub fn rank<K, E, I>(&self, def: &SkillDefinition<K, E, S, I>) -> u32 {
+        def.rank(self.xp)
+    }
+
+    /// Adds experience to this skill and returns an event for each rank gained.
+    pub fn add_xp<K, E, I>(
+        &mut self,
+        amount: u32,
+        def: &SkillDefinition<K, E, S, I>,
+    ) -> Vec<LevelUpEvent> {
+        let before = self.rank(def);
+        self.xp = self.xp.saturating_add(amount);
+        (before + 1..=self.rank(def))
+            .map(LevelUpEvent::new)
+            .collect()
+    }
+}
+
+/// What a skill is currently doing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Derivative)]
+#[derivative(Default)]
+pub enum SkillState {
+    /// The skill is not active.
+    #[derivative(Default)]
+    Idle,
+    /// The skill is being cast.
+    Casting {
+        /// The remaining cast time, in seconds.
+        remaining: f64,
+    },
+    /// The skill is being channeled.
+    Channeling {
+        /// The remaining channel time, in seconds.
+        remaining: f64,
+    },
+    /// The skill is toggled on.
+    Toggled,
 }
 
 /// The set of skill that can be used by an entity.
@@ -79,6 +436,15 @@ pub struct SkillSet<S: Hash + Eq> {
     pub skills: HashMap<S, SkillInstance<S>>,
 }
 
+impl<S: Hash + Eq> SkillSet<S> {
+    /// Decreases the cooldown of all skills by `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f64) {
+        for s in self.skills.values_mut() {
+            s.current_cooldown = (s.current_cooldown - delta_time).max(0.0);
+        }
+    }
+}
+
 impl<S: Hash + Eq + Clone> From<Vec<S>> for SkillSet<S> {
     fn from(t: Vec<S>) -> Self {
         let mut h = HashMap::new();
@@ -115,3 +481,583 @@ impl<K, E, S: Hash + Eq + Clone, I> From<Vec<SkillDefinition<K, E, S, I>>>
         Self::new(defs)
     }
 }
+
+impl<K: Hash + Eq + Clone, E: Hash + Eq + Clone, S: Hash + Eq + Clone, I: Hash + Eq + Clone>
+    SkillDefinitions<K, E, S, I>
+{
+    /// Checks that the definitions are consistent and only use known stats, effectors, items
+    /// and script conditions.
+    /// Returns all the errors found.
+    pub fn validate<IT, D: Default>(
+        &self,
+        stat_defs: &StatDefinitions<K>,
+        effector_defs: &EffectorDefinitions<K, E>,
+        item_defs: &ItemDefinitions<I, IT, D>,
+        scripts: Option<&ScriptHost<K>>,
+    ) -> Vec<SkillDefinitionError<K, E, S, I>> {
+        let mut errors = vec![];
+        for (k, def) in self.defs.iter() {
+            if def.key != *k {
+                errors.push(SkillDefinitionError::KeyMismatch(k.clone()));
+            }
+            let conditions = def
+                .conditions
+                .iter()
+                .chain(def.condition_groups.iter().flat_map(|g| g.conditions()));
+            for c in conditions {
+                if !stat_defs.defs.contains_key(&c.stat_key) {
+                    errors.push(SkillDefinitionError::UnknownStat(
+                        k.clone(),
+                        c.stat_key.clone(),
+                    ));
+                }
+                for e in stat_defs.validate_condition(c, scripts) {
+                    errors.push(SkillDefinitionError::InvalidCondition(k.clone(), e));
+                }
+            }
+            if let SkillActivation::Toggle { stat, .. } = &def.activation {
+                if !stat_defs.defs.contains_key(stat) {
+                    errors.push(SkillDefinitionError::UnknownStat(k.clone(), stat.clone()));
+                }
+            }
+            for (item, _, _) in def.item_conditions.iter() {
+                if !item_defs.defs.contains_key(item) {
+                    errors.push(SkillDefinitionError::UnknownItem(k.clone(), item.clone()));
+                }
+            }
+            for e in def.stat_effectors.iter() {
+                if !effector_defs.defs.contains_key(e) {
+                    errors.push(SkillDefinitionError::UnknownEffector(k.clone(), e.clone()));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// The errors found when validating `SkillDefinitions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkillDefinitionError<K, E, S, I> {
+    /// The definition is registered under a key different from its own.
+    KeyMismatch(S),
+    /// The skill has a condition on a stat that is not defined.
+    UnknownStat(S, K),
+    /// The skill causes an effector that is not defined.
+    UnknownEffector(S, E),
+    /// The skill has a condition on an item that is not defined.
+    UnknownItem(S, I),
+    /// The skill has a stat condition that can't be checked. See
+    /// `StatDefinitions::validate_condition`.
+    InvalidCondition(S, StatDefinitionError<K>),
+}
+
+/// Activates skills using the known definitions.
+#[derive(new)]
+pub struct SkillProcessor<'a, K: Hash + Eq, E: Hash + Eq, S: Hash + Eq, I> {
+    /// The definitions of the skills.
+    pub skill_defs: &'a SkillDefinitions<K, E, S, I>,
+    /// The definitions of the stats used in skill conditions.
+    pub stat_defs: &'a StatDefinitions<K>,
+    /// The definitions of the effectors caused by skills.
+    pub effector_defs: &'a EffectorDefinitions<K, E>,
+    /// The clock used to check the clock conditions of the skills.
+    /// Without a clock, the skills having clock conditions can't be activated.
+    #[new(default)]
+    pub clock: Option<&'a GameClock>,
+    /// The script functions used to check the script conditions of the skills.
+    /// Without it, activating a skill having script conditions fails.
+    #[new(default)]
+    pub scripts: Option<&'a ScriptHost<K>>,
+}
+
+impl<
+        'a,
+        K: Hash + Eq + Debug,
+        E: Hash + Eq + Clone,
+        S: Hash + Eq,
+        I: Hash + Eq + Clone + PartialEq + Debug,
+    > SkillProcessor<'a, K, E, S, I>
+{
+    /// Attempts to activate the skill.
+    /// If the skill is off cooldown and its conditions are met, the item conditions are used
+    /// according to their `UseMode`, then the skill starts according to its
+    /// `SkillActivation`. Instant skills add their stat effectors to the `EffectorSet` and
+    /// start their cooldown immediately, other skills do so when they end.
+    /// Activating a toggled skill again toggles it off.
+    /// The cooldown, item costs and magnitude of the stat effectors are scaled using the rank
+    /// of the skill, and the skill gains the experience of its `SkillProgression`.
+    /// Returns an event for each rank gained.
+    ///
+    /// Errors:
+    /// * NotInSkillSet: The skill is not part of the provided `SkillSet`.
+    /// * AlreadyActive: The skill is being cast or channeled.
+    /// * OnCooldown: The skill was used too recently.
+    /// * ConditionsNotMet: The stat conditions of the skill are not met, or the inventory doesn't
+    ///   contain the item costs of the skill at its rank.
+    /// * Script: A script condition of the skill can't be checked.
+    pub fn try_activate<IT: SlotType + Clone, CD: PartialEq + Default + Clone + Debug>(
+        &self,
+        skill_key: &S,
+        skills: &mut SkillSet<S>,
+        stats: &StatSet<K>,
+        inventory: &mut Inventory<I, IT, CD>,
+        effectors: &mut EffectorSet<E>,
+    ) -> Result<Vec<LevelUpEvent>, SkillError> {
+        let instance = skills
+            .skills
+            .get_mut(skill_key)
+            .ok_or(SkillError::NotInSkillSet)?;
+        let def = self
+            .skill_defs
+            .defs
+            .get(skill_key)
+            .expect("Tried to get unknown skill key.");
+        let rank = instance.rank(def);
+        match instance.state {
+            SkillState::Idle => {}
+            SkillState::Toggled => {
+                instance.state = SkillState::Idle;
+                instance.current_cooldown = def.cooldown_at(rank);
+                remove_effectors(def, effectors);
+                return Ok(vec![]);
+            }
+            SkillState::Casting { .. } | SkillState::Channeling { .. } => {
+                return Err(SkillError::AlreadyActive);
+            }
+        }
+        if instance.current_cooldown > 0.0 {
+            return Err(SkillError::OnCooldown);
+        }
+        if !def
+            .check_stat_conditions(stats, self.stat_defs, self.scripts)
+            .map_err(SkillError::Script)?
+            || !def.check_clock_conditions(self.clock)
+        {
+            return Err(SkillError::ConditionsNotMet);
+        }
+        inventory
+            .use_keys(def.item_costs(rank))
+            .map_err(|_| SkillError::ConditionsNotMet)?;
+        let level_ups = def
+            .progression
+            .as_ref()
+            .map(|p| instance.add_xp(p.xp_per_use, def))
+            .unwrap_or_default();
+        match def.activation {
+            SkillActivation::Instant => {
+                self.add_effectors(def, rank, effectors);
+                instance.current_cooldown = def.cooldown_at(rank);
+            }
+            SkillActivation::Cast { duration } => {
+                instance.state = SkillState::Casting {
+                    remaining: duration,
+                }
+            }
+            SkillActivation::Channel { duration } => {
+                self.add_effectors(def, rank, effectors);
+                instance.state = SkillState::Channeling {
+                    remaining: duration,
+                };
+            }
+            SkillActivation::Toggle { .. } => {
+                self.add_effectors(def, rank, effectors);
+                instance.state = SkillState::Toggled;
+            }
+        }
+        Ok(level_ups)
+    }
+
+    fn add_effectors(
+        &self,
+        def: &SkillDefinition<K, E, S, I>,
+        rank: u32,
+        effectors: &mut EffectorSet<E>,
+    ) {
+        let magnitude = def.magnitude_at(rank);
+        for e in &def.stat_effectors {
+            effectors.add_with_magnitude(e, magnitude, self.effector_defs);
+        }
+    }
+}
+
+/// Removes one instance of each stat effector of the skill.
+fn remove_effectors<K, E: PartialEq, S, I>(
+    def: &SkillDefinition<K, E, S, I>,
+    effectors: &mut EffectorSet<E>,
+) {
+    for e in &def.stat_effectors {
+        if let Some(idx) = effectors
+            .effectors
+            .iter()
+            .position(|instance| instance.effector_key == *e)
+        {
+            effectors.effectors.swap_remove(idx);
+        }
+    }
+}
+
+impl<
+        'a,
+        K: Hash + Eq + Clone + Debug,
+        E: Hash + Eq + Clone,
+        S: Hash + Eq + Clone,
+        I: Hash + Eq + Clone + PartialEq + Debug,
+    > SkillProcessor<'a, K, E, S, I>
+{
+    /// Activates all the passive skills of the `SkillSet` that are off cooldown and for which
+    /// the conditions are met.
+    /// Returns the events caused by the activations.
+    pub fn update_passives<IT: SlotType + Clone, CD: PartialEq + Default + Clone + Debug>(
+        &self,
+        skills: &mut SkillSet<S>,
+        stats: &StatSet<K>,
+        inventory: &mut Inventory<I, IT, CD>,
+        effectors: &mut EffectorSet<E>,
+    ) -> Vec<SkillEvent<S>> {
+        let passives = skills
+            .skills
+            .values()
+            .filter(|s| s.current_cooldown <= 0.0 && s.state == SkillState::Idle)
+            .filter(|s| {
+                self.skill_defs
+                    .defs
+                    .get(&s.skill_key)
+                    .map(|d| d.passive)
+                    .unwrap_or(false)
+            })
+            .map(|s| s.skill_key.clone())
+            .collect::<Vec<_>>();
+        let mut events = vec![];
+        for key in passives {
+            if let Ok(level_ups) = self.try_activate(&key, skills, stats, inventory, effectors) {
+                events.push(SkillEvent::Activated(key.clone()));
+                events.extend(
+                    level_ups
+                        .into_iter()
+                        .map(|e| SkillEvent::RankedUp(key.clone(), e)),
+                );
+            }
+        }
+        events
+    }
+
+    /// Returns what `SkillProcessor::try_activate` would change, without changing anything.
+    ///
+    /// Errors:
+    /// * Same as `SkillProcessor::try_activate`.
+    pub fn simulate_activate<IT: SlotType + Clone, CD: PartialEq + Default + Clone + Debug>(
+        &self,
+        skill_key: &S,
+        skills: &SkillSet<S>,
+        stats: &StatSet<K>,
+        inventory: &Inventory<I, IT, CD>,
+        effectors: &EffectorSet<E>,
+    ) -> Result<SkillPreview<E, I>, SkillError> {
+        let instance = skills
+            .skills
+            .get(skill_key)
+            .ok_or(SkillError::NotInSkillSet)?;
+        let mut skills_copy = SkillSet::new(HashMap::new());
+        skills_copy
+            .skills
+            .insert(skill_key.clone(), instance.clone());
+        let mut effectors_copy = effectors.clone();
+        let mut level_ups = vec![];
+        let inventory_events = inventory.simulate(|inv| {
+            self.try_activate(skill_key, &mut skills_copy, stats, inv, &mut effectors_copy)
+                .map(|events| level_ups = events)
+        })?;
+        let def = self
+            .skill_defs
+            .defs
+            .get(skill_key)
+            .expect("Tried to get unknown skill key.");
+        let mut effectors_added = vec![];
+        let mut effectors_removed = vec![];
+        let mut checked = vec![];
+        for e in def.stat_effectors.iter() {
+            if checked.contains(&e) {
+                continue;
+            }
+            checked.push(e);
+            let before = effectors.count(e);
+            let after = effectors_copy.count(e);
+            (before..after).for_each(|_| effectors_added.push(e.clone()));
+            (after..before).for_each(|_| effectors_removed.push(e.clone()));
+        }
+        let instance = &skills_copy.skills[skill_key];
+        Ok(SkillPreview {
+            inventory_events,
+            effectors_added,
+            effectors_removed,
+            state: instance.state.clone(),
+            cooldown: instance.current_cooldown,
+            level_ups,
+        })
+    }
+
+    /// Advances the skills being cast, channeled or toggled on by `delta_time` seconds.
+    /// Casts add their stat effectors when they complete. Channels remove their stat effectors
+    /// when they end. Toggled skills drain their stat and are toggled off when the stat doesn't
+    /// have enough value left.
+    /// Returns the events caused by the skills ending.
+    pub fn update_active(
+        &self,
+        delta_time: f64,
+        skills: &mut SkillSet<S>,
+        stats: &mut StatSet<K>,
+        effectors: &mut EffectorSet<E>,
+    ) -> Vec<SkillEvent<S>> {
+        let mut events = vec![];
+        for instance in skills.skills.values_mut() {
+            let def = self
+                .skill_defs
+                .defs
+                .get(&instance.skill_key)
+                .expect("Tried to get unknown skill key.");
+            let rank = def.rank(instance.xp);
+            match &mut instance.state {
+                SkillState::Idle => {}
+                SkillState::Casting { remaining } => {
+                    *remaining -= delta_time;
+                    if *remaining <= 0.0 {
+                        self.add_effectors(def, rank, effectors);
+                        instance.state = SkillState::Idle;
+                        instance.current_cooldown = def.cooldown_at(rank);
+                        events.push(SkillEvent::Completed(instance.skill_key.clone()));
+                    }
+                }
+                SkillState::Channeling { remaining } => {
+                    *remaining -= delta_time;
+                    if *remaining <= 0.0 {
+                        remove_effectors(def, effectors);
+                        instance.state = SkillState::Idle;
+                        instance.current_cooldown = def.cooldown_at(rank);
+                        events.push(SkillEvent::Completed(instance.skill_key.clone()));
+                    }
+                }
+                SkillState::Toggled => {
+                    if let SkillActivation::Toggle {
+                        stat,
+                        drain_per_second,
+                    } = &def.activation
+                    {
+                        let cost = drain_per_second * def.cost_multiplier(rank) * delta_time;
+                        let available = stats.stats.get(stat).map(|s| s.value).unwrap_or(0.0);
+                        if available < cost {
+                            remove_effectors(def, effectors);
+                            instance.state = SkillState::Idle;
+                            instance.current_cooldown = def.cooldown_at(rank);
+                            events.push(SkillEvent::ToggledOff(instance.skill_key.clone()));
+                        } else {
+                            stats.add_to(stat, -cost, self.stat_defs);
+                        }
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// Interrupts the skills being cast or channeled that can be interrupted by this cause.
+    /// Interrupted casts don't add their stat effectors and interrupted channels remove them.
+    /// The cooldown doesn't start and the used items are not given back.
+    /// Returns the events of the interrupted skills.
+    pub fn interrupt(
+        &self,
+        cause: &InterruptCause,
+        skills: &mut SkillSet<S>,
+        effectors: &mut EffectorSet<E>,
+    ) -> Vec<SkillEvent<S>> {
+        let mut events = vec![];
+        for instance in skills.skills.values_mut() {
+            let def = self
+                .skill_defs
+                .defs
+                .get(&instance.skill_key)
+                .expect("Tried to get unknown skill key.");
+            if !def.interrupted_by.contains(cause) {
+                continue;
+            }
+            match instance.state {
+                SkillState::Casting { .. } => {}
+                SkillState::Channeling { .. } => remove_effectors(def, effectors),
+                SkillState::Idle | SkillState::Toggled => continue,
+            }
+            instance.state = SkillState::Idle;
+            events.push(SkillEvent::Interrupted(instance.skill_key.clone()));
+        }
+        events
+    }
+}
+
+/// What activating a skill would change. See `SkillProcessor::simulate_activate`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkillPreview<E, I> {
+    /// The changes made to the inventory by the item conditions.
+    pub inventory_events: Vec<InventoryEvent<I>>,
+    /// The stat effectors that would be added.
+    pub effectors_added: Vec<E>,
+    /// The stat effectors that would be removed, when toggling a skill off.
+    pub effectors_removed: Vec<E>,
+    /// The state of the skill after the activation.
+    pub state: SkillState,
+    /// The cooldown of the skill after the activation.
+    pub cooldown: f64,
+    /// The ranks the skill would gain.
+    pub level_ups: Vec<LevelUpEvent>,
+}
+
+/// Something that happened to a skill.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum SkillEvent<S> {
+    /// The skill was activated.
+    Activated(S),
+    /// The cast or the channel of the skill completed.
+    Completed(S),
+    /// The cast or the channel of the skill was interrupted.
+    Interrupted(S),
+    /// The toggled skill was toggled off because its stat ran out.
+    ToggledOff(S),
+    /// The skill reached a new rank.
+    RankedUp(S, LevelUpEvent),
+}
+
+/// The errors that can happen when activating a skill.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkillError {
+    /// The skill is not part of the `SkillSet`.
+    NotInSkillSet,
+    /// The skill cooldown is not over yet.
+    OnCooldown,
+    /// The skill is being cast or channeled.
+    AlreadyActive,
+    /// The stat or item conditions required to activate the skill are not met.
+    ConditionsNotMet,
+    /// A script condition of the skill can't be checked.
+    Script(ScriptError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn skill(item_conditions: Vec<(u32, usize, UseMode)>) -> SkillDefinition<u32, u32, u32, u32> {
+        let mut def = SkillDefinition::new(
+            1,
+            "Volley".into(),
+            "volley".to_string(),
+            "".into(),
+            0.0,
+            false,
+            vec![],
+            item_conditions,
+            vec![],
+        );
+        let mut progression = SkillProgression::new(LevelCurve::Linear { xp_per_level: 10 }, 10);
+        progression.cost = vec![1.0, 0.5];
+        def.progression = Some(progression);
+        def
+    }
+
+    #[test]
+    fn item_costs_are_scaled_by_rank() {
+        let stat_defs = StatDefinitions::default();
+        let stats = stat_defs.to_statset();
+        let effector_defs = EffectorDefinitions::default();
+        let skill_defs = SkillDefinitions::from(vec![skill(vec![(1, 4, UseMode::Consume)])]);
+        let processor = SkillProcessor::new(&skill_defs, &stat_defs, &effector_defs);
+        let mut skills = SkillSet::from(vec![1]);
+        skills.skills.get_mut(&1).unwrap().xp = 10;
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(2);
+        inventory
+            .insert(ItemInstance::new(1, 3), &item_defs())
+            .unwrap();
+        let mut effectors = EffectorSet::default();
+        // Rank 2 only costs 2 of the 4 apples of the item condition.
+        assert_eq!(
+            processor.try_activate(&1, &mut skills, &stats, &mut inventory, &mut effectors),
+            Ok(vec![LevelUpEvent::new(3)])
+        );
+        assert!(inventory.has_quantity(&1, 1) && !inventory.has_quantity(&1, 2));
+        assert_eq!(
+            processor.try_activate(&1, &mut skills, &stats, &mut inventory, &mut effectors),
+            Err(SkillError::ConditionsNotMet)
+        );
+    }
+
+    #[test]
+    fn failed_activation_uses_nothing() {
+        let stat_defs = StatDefinitions::default();
+        let stats = stat_defs.to_statset();
+        let effector_defs = EffectorDefinitions::default();
+        let skill_defs = SkillDefinitions::from(vec![skill(vec![
+            (1, 2, UseMode::Consume),
+            (1, 2, UseMode::Consume),
+        ])]);
+        let processor = SkillProcessor::new(&skill_defs, &stat_defs, &effector_defs);
+        let mut skills = SkillSet::from(vec![1]);
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(2);
+        inventory
+            .insert(ItemInstance::new(1, 3), &item_defs())
+            .unwrap();
+        let mut effectors = EffectorSet::default();
+        assert_eq!(
+            processor.try_activate(&1, &mut skills, &stats, &mut inventory, &mut effectors),
+            Err(SkillError::ConditionsNotMet)
+        );
+        assert_eq!(inventory.get(0).as_ref().unwrap().quantity, 3);
+        assert_eq!(skills.skills[&1].xp, 0);
+    }
+
+    #[test]
+    fn script_conditions_use_the_script_host() {
+        let stat_defs = StatDefinitions::from(vec![StatDefinition::new(
+            0,
+            "Rage".into(),
+            "rage".to_string(),
+            30.0,
+        )]);
+        let stats = stat_defs.to_statset();
+        let effector_defs = EffectorDefinitions::default();
+        let mut def = skill(vec![]);
+        def.conditions = vec![StatCondition::new(
+            0,
+            StatConditionType::Script("enraged".to_string()),
+        )];
+        let skill_defs = SkillDefinitions::from(vec![def]);
+        let mut processor = SkillProcessor::new(&skill_defs, &stat_defs, &effector_defs);
+        let mut skills = SkillSet::from(vec![1]);
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(1);
+        let mut effectors = EffectorSet::default();
+        assert_eq!(
+            skill_defs.validate(&stat_defs, &effector_defs, &item_defs(), None),
+            vec![SkillDefinitionError::InvalidCondition(
+                1,
+                StatDefinitionError::UnknownScript(0, "enraged".to_string())
+            )]
+        );
+        assert_eq!(
+            processor.try_activate(&1, &mut skills, &stats, &mut inventory, &mut effectors),
+            Err(SkillError::Script(ScriptError::NoScriptHost(
+                "enraged".to_string()
+            )))
+        );
+        let mut scripts = ScriptHost::default();
+        scripts.register_condition("enraged", |value, _, _| value >= 50.0);
+        processor.scripts = Some(&scripts);
+        assert!(skill_defs
+            .validate(&stat_defs, &effector_defs, &item_defs(), Some(&scripts))
+            .is_empty());
+        assert_eq!(
+            processor.try_activate(&1, &mut skills, &stats, &mut inventory, &mut effectors),
+            Err(SkillError::ConditionsNotMet)
+        );
+        let mut stats = stats;
+        stats.stats.get_mut(&0).unwrap().value = 60.0;
+        assert!(processor
+            .try_activate(&1, &mut skills, &stats, &mut inventory, &mut effectors)
+            .is_ok());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// What a rank of a `SkillTreeNode` gives.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SkillTreeReward<K, S> {
+    /// Adds the skill to the `SkillSet` when the first rank is allocated.
+    Skill(S),
+    /// Adds this value to the base value of the stat for each allocated rank.
+    StatBonus(K, f64),
+}
+
+/// A node of a `SkillTree`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct SkillTreeNode<N, K, S> {
+    /// The key of the node.
+    pub key: N,
+    /// What each rank gives.
+    pub reward: SkillTreeReward<K, S>,
+    /// The skill points needed for each rank.
+    pub cost: u32,
+    /// The maximum number of ranks that can be allocated.
+    pub max_rank: u32,
+    /// The nodes that must have at least these ranks before allocating this node.
+    #[new(default)]
+    pub prerequisites: Vec<(N, u32)>,
+}
+
+/// A tree of nodes in which skill points are allocated to get skills and stat bonuses.
+///
+/// # Generics
+/// - N: Node Key
+/// - K: Stat Key
+/// - S: Skill Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct SkillTree<N: Hash + Eq, K, S> {
+    /// The nodes mapped by their key.
+    pub nodes: HashMap<N, SkillTreeNode<N, K, S>>,
+}
+
+impl<N: Hash + Eq, K, S> Default for SkillTree<N, K, S> {
+    fn default() -> Self {
+        Self {
+            nodes: HashMap::default(),
+        }
+    }
+}
+
+impl<N: Hash + Eq + Clone, K, S> From<Vec<SkillTreeNode<N, K, S>>> for SkillTree<N, K, S> {
+    fn from(t: Vec<SkillTreeNode<N, K, S>>) -> Self {
+        let nodes = t
+            .into_iter()
+            .map(|n| (n.key.clone(), n))
+            .collect::<HashMap<_, _>>();
+        Self::new(nodes)
+    }
+}
+
+/// The skill points and allocated ranks of an entity in a `SkillTree`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct SkillTreeProgress<N: Hash + Eq> {
+    /// The skill points that can be allocated.
+    pub available_points: u32,
+    /// The skill points allocated in the tree.
+    #[new(default)]
+    pub spent_points: u32,
+    /// The allocated ranks mapped by node.
+    #[new(default)]
+    pub ranks: HashMap<N, u32>,
+    /// The change the stat bonus of each node actually made to the stat, which can be lower
+    /// than the bonus when the stat reached its maximum value. It is removed when respecing.
+    #[new(default)]
+    #[serde(default)]
+    pub applied_bonuses: HashMap<N, f64>,
+}
+
+impl<N: Hash + Eq + Clone> SkillTreeProgress<N> {
+    /// Returns the allocated ranks of the node.
+    pub fn rank(&self, node: &N) -> u32 {
+        self.ranks.get(node).copied().unwrap_or(0)
+    }
+
+    /// Checks if one more rank can be allocated to the node.
+    ///
+    /// Errors:
+    /// * UnknownNode: The node is not in the tree.
+    /// * MaxRank: The node already has its maximum rank.
+    /// * NotEnoughPoints: Not enough skill points are available.
+    /// * MissingPrerequisite: This prerequisite doesn't have the required rank.
+    pub fn can_allocate<K, S>(
+        &self,
+        node: &N,
+        tree: &SkillTree<N, K, S>,
+    ) -> Result<(), SkillTreeError<N>> {
+        let def = tree.nodes.get(node).ok_or(SkillTreeError::UnknownNode)?;
+        if self.rank(node) >= def.max_rank {
+            return Err(SkillTreeError::MaxRank);
+        }
+        if self.available_points < def.cost {
+            return Err(SkillTreeError::NotEnoughPoints);
+        }
+        if let Some((n, _)) = def
+            .prerequisites
+            .iter()
+            .find(|(n, rank)| self.rank(n) < *rank)
+        {
+            return Err(SkillTreeError::MissingPrerequisite(n.clone()));
+        }
+        Ok(())
+    }
+
+    /// Allocates one rank to the node and applies its reward.
+    /// Returns the new rank of the node.
+    ///
+    /// Errors:
+    /// See `SkillTreeProgress::can_allocate`.
+    pub fn allocate<K: Hash + Eq + Clone + Debug, S: Hash + Eq + Clone>(
+        &mut self,
+        node: &N,
+        tree: &SkillTree<N, K, S>,
+        skills: &mut SkillSet<S>,
+        stats: &mut StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+    ) -> Result<u32, SkillTreeError<N>> {
+        self.can_allocate(node, tree)?;
+        let def = &tree.nodes[node];
+        self.available_points -= def.cost;
+        self.spent_points += def.cost;
+        let rank = self.ranks.entry(node.clone()).or_insert(0);
+        *rank += 1;
+        match &def.reward {
+            SkillTreeReward::Skill(s) => {
+                skills
+                    .skills
+                    .entry(s.clone())
+                    .or_insert_with(|| SkillInstance::new(s.clone(), 0.0));
+            }
+            SkillTreeReward::StatBonus(k, v) => {
+                if let Some(change) = stats.add_to(k, *v, stat_defs) {
+                    *self.applied_bonuses.entry(node.clone()).or_insert(0.0) +=
+                        change.current - change.previous;
+                }
+            }
+        }
+        Ok(*rank)
+    }
+
+    /// Removes all the allocated ranks and their rewards.
+    /// Stat bonuses are removed using the change they actually made to the stats.
+    /// The spent skill points are given back according to the `RefundPolicy`.
+    /// Returns the number of skill points given back.
+    pub fn respec<K: Hash + Eq + Clone + Debug, S: Hash + Eq>(
+        &mut self,
+        tree: &SkillTree<N, K, S>,
+        skills: &mut SkillSet<S>,
+        stats: &mut StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        refund_policy: &RefundPolicy,
+    ) -> u32 {
+        for (node, _) in self.ranks.drain() {
+            let def = tree
+                .nodes
+                .get(&node)
+                .expect("Tried to get unknown skill tree node key.");
+            match &def.reward {
+                SkillTreeReward::Skill(s) => {
+                    skills.skills.remove(s);
+                }
+                SkillTreeReward::StatBonus(k, _) => {
+                    let applied = self.applied_bonuses.remove(&node).unwrap_or(0.0);
+                    stats.add_to(k, -applied, stat_defs);
+                }
+            }
+        }
+        let refunded = refund_policy.refunded(self.spent_points as usize) as u32;
+        self.available_points += refunded;
+        self.spent_points = 0;
+        refunded
+    }
+}
+
+/// The errors that can happen when allocating skill points in a `SkillTree`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkillTreeError<N> {
+    /// The node is not in the tree.
+    UnknownNode,
+    /// The node already has its maximum rank.
+    MaxRank,
+    /// Not enough skill points are available.
+    NotEnoughPoints,
+    /// This prerequisite node doesn't have the required rank.
+    MissingPrerequisite(N),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn respec_removes_the_applied_bonus() {
+        let mut strength =
+            StatDefinition::new(0u32, "Strength".into(), "strength".to_string(), 8.0);
+        strength.max_value = Some(10.0);
+        let stat_defs = StatDefinitions::from(vec![strength]);
+        let mut stats = stat_defs.to_statset();
+        let mut skills = SkillSet::<u32>::new(HashMap::new());
+        let tree = SkillTree::from(vec![SkillTreeNode::new(
+            1u32,
+            SkillTreeReward::StatBonus(0, 3.0),
+            1,
+            2,
+        )]);
+        let mut progress = SkillTreeProgress::new(2);
+        progress
+            .allocate(&1, &tree, &mut skills, &mut stats, &stat_defs)
+            .unwrap();
+        progress
+            .allocate(&1, &tree, &mut skills, &mut stats, &stat_defs)
+            .unwrap();
+        assert_eq!(stats.stats[&0].value, 10.0);
+        // Only 2 of the 6 points of the bonus were applied because of the maximum value.
+        progress.respec(
+            &tree,
+            &mut skills,
+            &mut stats,
+            &stat_defs,
+            &RefundPolicy::Full,
+        );
+        assert_eq!(stats.stats[&0].value, 8.0);
+        assert_eq!(progress.available_points, 2);
+        assert!(progress.applied_bonuses.is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+use rand::Rng;
+
+/// An entry of a `SpawnTable`.
+///
+/// # Generics
+/// - R: Spawned Type, like a monster key
+/// - K: Stat Key
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct SpawnEntry<R, K> {
+    /// What is spawned.
+    pub result: R,
+    /// The weight of this entry when rolling. See `LootTreeNode::chances`.
+    pub weight: i32,
+    /// The minimum number spawned at once, inclusive.
+    pub min_group: usize,
+    /// The maximum number spawned at once, inclusive.
+    pub max_group: usize,
+    /// The stat conditions required for this entry to spawn.
+    /// They are checked against the stats of the `SpawnContext`.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub stat_conditions: Vec<StatCondition<K>>,
+    /// The conditions on the time of the `GameClock` required for this entry to spawn.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub clock_conditions: Vec<ClockConditionGroup>,
+    /// The minimum tier of the region for this entry to spawn, inclusive.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub min_tier: u32,
+    /// The maximum tier of the region for this entry to spawn, inclusive.
+    /// None means no maximum.
+    #[new(default)]
+    #[builder(default)]
+    pub max_tier: Option<u32>,
+    /// The time to wait after this entry spawned before it can spawn again, in seconds.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub cooldown: f64,
+    /// The remaining time before this entry can spawn again.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub current_cooldown: f64,
+}
+
+impl<R, K: Hash + Eq + Debug> SpawnEntry<R, K> {
+    /// Checks if this entry can spawn in the provided context.
+    pub fn can_spawn(&self, context: &SpawnContext<K>) -> bool {
+        self.current_cooldown <= 0.0
+            && context.region_tier >= self.min_tier
+            && self
+                .max_tier
+                .map(|t| context.region_tier <= t)
+                .unwrap_or(true)
+            && self
+                .stat_conditions
+                .iter()
+                .all(|c| c.check(context.stats, context.stat_defs))
+            && context
+                .clock
+                .map_or(self.clock_conditions.is_empty(), |clock| {
+                    self.clock_conditions.iter().all(|c| c.check(clock))
+                })
+    }
+}
+
+/// The circumstances in which a spawn is rolled.
+#[derive(new)]
+pub struct SpawnContext<'a, K: Hash + Eq> {
+    /// The stats checked by the stat conditions, like the stats of the region or of the player.
+    pub stats: &'a StatSet<K>,
+    /// The definitions of the stats.
+    pub stat_defs: &'a StatDefinitions<K>,
+    /// The clock used to check the clock conditions.
+    /// None means that the clock conditions are not met, unless there are none.
+    pub clock: Option<&'a GameClock>,
+    /// The tier of the region where the spawn happens.
+    pub region_tier: u32,
+}
+
+/// A group spawned by a `SpawnTable`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, new)]
+pub struct Spawn<R> {
+    /// What is spawned.
+    pub result: R,
+    /// The number spawned.
+    pub count: usize,
+}
+
+/// A weighted table of what can spawn, like the monsters of a region.
+/// Works like a `LootTree` where the entries also have a group size, conditions and a
+/// cooldown.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct SpawnTable<R, K> {
+    /// The entries of the table.
+    pub entries: Vec<SpawnEntry<R, K>>,
+}
+
+impl<R, K> Default for SpawnTable<R, K> {
+    fn default() -> Self {
+        Self { entries: vec![] }
+    }
+}
+
+impl<R: Clone, K: Hash + Eq + Debug> SpawnTable<R, K> {
+    /// Decreases the cooldown of all entries by `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f64) {
+        for e in self.entries.iter_mut() {
+            e.current_cooldown = (e.current_cooldown - delta_time).max(0.0);
+        }
+    }
+
+    /// Rolls an entry among those that can spawn in the context, using their weight, and a
+    /// random group size for it. The cooldown of the rolled entry starts.
+    /// Returns None if no entry can spawn.
+    pub fn roll_spawn<G: Rng>(
+        &mut self,
+        rng: &mut G,
+        context: &SpawnContext<K>,
+    ) -> Option<Spawn<R>> {
+        let mut builder = LootTreeBuilder::new();
+        builder.nodes = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.can_spawn(context))
+            .map(|(idx, e)| LootTreeNode {
+                chances: e.weight,
+                result: idx,
+                guaranteed: false,
+            })
+            .collect();
+        let idx = builder.build().roll_with(rng)?;
+        let entry = &mut self.entries[idx];
+        entry.current_cooldown = entry.cooldown;
+        let count = rng.gen_range(entry.min_group, entry.max_group.max(entry.min_group) + 1);
+        Some(Spawn::new(entry.result.clone(), count))
+    }
+}
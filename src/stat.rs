@@ -1,7 +1,7 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
 use derivative::*;
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::hash::Hash;
 // Different properties of a player/item/entity
 
 /// The definition of a stat.
@@ -11,11 +11,13 @@ use std::hash::Hash;
 /// For example, it can be used to contain the health or mana of an entity just as well as it
 /// can be used to keep track of the number of enemies positioned around an entity.
 #[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
 pub struct StatDefinition<K> {
     /// The key.
     pub key: K,
-    /// The name.
-    pub name: String,
+    /// The display name.
+    #[builder(setter(into))]
+    pub name: LocalizedText,
     /// The computer friendly name.
     pub friendly_name: String,
     /// The default value.
@@ -38,9 +40,28 @@ impl<K: Clone> StatDefinition<K> {
     }
 }
 
+impl<K> StatDefinition<K> {
+    /// Constrains the value between the minimum and maximum values of this stat, if any.
+    pub fn clamp(&self, value: f64) -> f64 {
+        let mut value = value;
+        if let Some(min) = self.min_value {
+            if value < min {
+                value = min;
+            }
+        }
+        if let Some(max) = self.max_value {
+            if value > max {
+                value = max;
+            }
+        }
+        value
+    }
+}
+
 /// An instance of a stat.
 /// Contains a base value as well as a value after applying the stat effectors.
-#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
 pub struct StatInstance<K> {
     /// The key of the stat.
     pub key: K,
@@ -76,6 +97,64 @@ impl<K: Hash + Eq + Clone> StatDefinitions<K> {
             .collect::<HashMap<_, _>>();
         StatSet::new(instances)
     }
+
+    /// Checks that the definitions are consistent.
+    /// Returns all the errors found.
+    pub fn validate(&self) -> Vec<StatDefinitionError<K>> {
+        let mut errors = vec![];
+        for (k, def) in self.defs.iter() {
+            if def.key != *k {
+                errors.push(StatDefinitionError::KeyMismatch(k.clone()));
+            }
+            let min = def.min_value.unwrap_or(f64::NEG_INFINITY);
+            let max = def.max_value.unwrap_or(f64::INFINITY);
+            if min > max || def.default_value < min || def.default_value > max {
+                errors.push(StatDefinitionError::InvalidBounds(k.clone()));
+            }
+        }
+        errors
+    }
+
+    /// Checks that the condition can be checked using `StatCondition::check_scripted` with
+    /// this `ScriptHost`.
+    /// The expression of a `StatConditionType::Expr` condition must only use variables that can
+    /// be resolved, and the function of a `StatConditionType::Script` condition must be
+    /// registered in the `ScriptHost`.
+    /// Returns all the errors found. Conditions of other types have none.
+    pub fn validate_condition(
+        &self,
+        condition: &StatCondition<K>,
+        scripts: Option<&ScriptHost<K>>,
+    ) -> Vec<StatDefinitionError<K>> {
+        let expr = match &condition.condition {
+            StatConditionType::Expr(expr) => expr,
+            StatConditionType::Script(name) => {
+                if scripts
+                    .filter(|s| s.conditions.contains_key(name))
+                    .is_some()
+                {
+                    return vec![];
+                }
+                return vec![StatDefinitionError::UnknownScript(
+                    condition.stat_key.clone(),
+                    name.clone(),
+                )];
+            }
+            _ => return vec![],
+        };
+        let def = self.defs.get(&condition.stat_key);
+        let (min, max) = def.map_or((None, None), |d| (d.min_value, d.max_value));
+        expr.variables()
+            .into_iter()
+            .filter(|name| {
+                expr_variable(name, 0.0, min, max).is_none()
+                    && !self.defs.values().any(|d| d.friendly_name == *name)
+            })
+            .map(|name| {
+                StatDefinitionError::UnknownVariable(condition.stat_key.clone(), name.to_string())
+            })
+            .collect()
+    }
 }
 
 impl<K: Hash + Eq + Clone> From<Vec<StatDefinition<K>>> for StatDefinitions<K> {
@@ -93,28 +172,84 @@ impl<K: Hash + Eq + Clone> From<Vec<StatDefinition<K>>> for StatDefinitions<K> {
 pub struct StatSet<K: Hash + Eq> {
     /// The stats.
     pub stats: HashMap<K, StatInstance<K>>,
+    /// The stats whose base value or value with effectors changed since the last call to
+    /// `StatSet::take_changes`.
+    /// Changes made by modifying `stats` directly are not tracked.
+    #[new(default)]
+    #[serde(skip, default = "HashSet::new")]
+    pub changed: HashSet<K>,
+}
+
+impl<K: Hash + Eq> StatSet<K> {
+    /// Returns the stats that changed since the last call and forgets them.
+    pub fn take_changes(&mut self) -> HashSet<K> {
+        core::mem::take(&mut self.changed)
+    }
+}
+
+impl<K: Hash + Eq + Clone + Debug> StatSet<K> {
+    /// Adds `delta` to the base value of the specified stat.
+    /// The new value is kept between the minimum and maximum values of the `StatDefinition`.
+    /// Returns None and changes nothing if the stat is not in this `StatSet` or in the
+    /// `StatDefinitions`.
+    pub fn add_to(
+        &mut self,
+        key: &K,
+        delta: f64,
+        stat_defs: &StatDefinitions<K>,
+    ) -> Option<StatChange> {
+        let current = self.stats.get(key)?.value;
+        self.set(key, current + delta, stat_defs)
+    }
+
+    /// Sets the base value of the specified stat.
+    /// The new value is kept between the minimum and maximum values of the `StatDefinition`.
+    /// Returns None and changes nothing if the stat is not in this `StatSet` or in the
+    /// `StatDefinitions`.
+    pub fn set(
+        &mut self,
+        key: &K,
+        value: f64,
+        stat_defs: &StatDefinitions<K>,
+    ) -> Option<StatChange> {
+        let def = stat_defs.defs.get(key)?;
+        let stat = self.stats.get_mut(key)?;
+        let previous = stat.value;
+        stat.value = def.clamp(value);
+        if stat.value != previous {
+            self.changed.insert(key.clone());
+        }
+        let reached_bound = if Some(stat.value) == def.min_value {
+            Some(StatBound::Min)
+        } else if Some(stat.value) == def.max_value {
+            Some(StatBound::Max)
+        } else {
+            None
+        };
+        Some(StatChange::new(previous, stat.value, reached_bound))
+    }
+}
+
+/// The result of a change to the base value of a stat.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct StatChange {
+    /// The value before the change.
+    pub previous: f64,
+    /// The value after the change.
+    pub current: f64,
+    /// Which bound the value is at after the change, if any.
+    /// For example, this is `Some(StatBound::Min)` when health reaches 0.
+    pub reached_bound: Option<StatBound>,
 }
 
-//impl<K: Hash+Eq> StatSet<K> {
-//    pub fn update(&mut self, delta_time: f64, stat_set: &mut StatSet<K>) {
-//        let mut rm_idx = vec![];
-//        for (idx, stat) in self.effectors.iter_mut().enumerate() {
-//            // TODO: apply modifier rules and ordering.
-//
-//            if let Some(left) = stat.disable_in.as_mut() {
-//                *left -= delta_time;
-//                if *left <= 0.0 {
-//                    rm_idx.push(idx);
-//                }
-//            }
-//        }
-//
-//        rm_idx.reverse();
-//        for idx in rm_idx {
-//            self.effectors.swap_remove(idx);
-//        }
-//    }
-//}
+/// One of the bounds of a stat's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatBound {
+    /// The minimum value of the stat.
+    Min,
+    /// The maximum value of the stat.
+    Max,
+}
 
 /// Condition based on a stat to activate something.
 #[derive(Clone, Debug, Serialize, Deserialize, new)]
@@ -137,9 +272,77 @@ impl<K: Hash + Eq + Debug> StatCondition<K> {
             "Requested stat key {:?} is not in provided StatDefinitions.",
             self.stat_key
         ));
+        if let StatConditionType::Expr(expr) = &self.condition {
+            return expr
+                .is_true(&|name| {
+                    expr_variable(name, v.value, def.min_value, def.max_value).or_else(|| {
+                        stat_defs
+                            .defs
+                            .values()
+                            .find(|d| d.friendly_name == name)
+                            .and_then(|d| stats.stats.get(&d.key))
+                            .map(|s| s.value)
+                    })
+                })
+                .unwrap_or(false);
+        }
         self.condition
             .is_true(v.value, def.min_value, def.max_value)
     }
+
+    /// Checks if this stat condition is met, calling the script functions of the `ScriptHost`
+    /// for `StatConditionType::Script` conditions.
+    ///
+    /// Errors:
+    /// * UnknownFunction: The script condition is not registered.
+    /// * NoScriptHost: This is a script condition and there is no `ScriptHost`.
+    pub fn check_scripted(
+        &self,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        scripts: Option<&ScriptHost<K>>,
+    ) -> Result<bool, ScriptError> {
+        if let StatConditionType::Script(name) = &self.condition {
+            let scripts = scripts.ok_or_else(|| ScriptError::NoScriptHost(name.clone()))?;
+            let value = stats
+                .stats
+                .get(&self.stat_key)
+                .expect("Tried to get unknown stat key.")
+                .value;
+            let def = stat_defs
+                .defs
+                .get(&self.stat_key)
+                .expect("Tried to get unknown stat key.");
+            return scripts.check_condition(name, value, def.min_value, def.max_value);
+        }
+        Ok(self.check(stats, stat_defs))
+    }
+}
+
+/// Stat conditions combined together, like "mana > 20 and (stealth > 0 or behind_target > 0)".
+pub type StatConditionGroup<K> = ConditionGroup<StatCondition<K>>;
+
+impl<K: Hash + Eq + Debug> ConditionGroup<StatCondition<K>> {
+    /// Checks if the conditions are met for the provided `StatSet` using the known
+    /// `StatDefinitions`.
+    pub fn check(&self, stats: &StatSet<K>, stat_defs: &StatDefinitions<K>) -> bool {
+        self.check_with(&|c| c.check(stats, stat_defs))
+    }
+
+    /// Checks if the conditions are met, calling the script functions of the `ScriptHost` for
+    /// `StatConditionType::Script` conditions.
+    ///
+    /// Errors:
+    /// * UnknownFunction: A script condition is not registered.
+    /// * NoScriptHost: A script condition is checked and there is no `ScriptHost`.
+    pub fn check_scripted(
+        &self,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        scripts: Option<&ScriptHost<K>>,
+    ) -> Result<bool, ScriptError> {
+        self.try_check_with(&|c| c.check_scripted(stats, stat_defs, scripts))
+    }
 }
 
 /// A condition based on a stat's value.
@@ -173,7 +376,23 @@ pub enum StatConditionType {
     /// The value is divisible by this value.
     /// DivisibleBy(2) is equivalent to (value % 2 == 0).
     DivisibleBy(i32),
+    /// An expression that must be true, like `value < 0.1 * max || (armor > 5 && !stunned)`.
+    /// See `Expr` for the syntax.
+    /// `value`, `min`, `max` and `percent` refer to the stat of the condition. When the
+    /// condition is checked using a `StatCondition`, the base value of the other stats can be
+    /// used through their `friendly_name`.
+    /// The condition is not met if a variable can't be resolved. Use
+    /// `StatDefinitions::validate_condition` to find those variables ahead of time.
+    Expr(Expr),
+    /// A condition registered in a `ScriptHost` under this name.
+    /// It is checked by `StatConditionGroup::check_scripted` and
+    /// `StatCondition::check_scripted`, which skills, unlockables and item transitions use with
+    /// the `ScriptHost` they are given. It is never met by `StatCondition::check`.
+    /// `StatDefinitions::validate_condition` reports the conditions that are not registered.
+    Script(String),
     /// A custom function that takes the value and returns whether the condition passed or not.
+    /// It can't be serialized. It must stay the last variant so that the index of the other
+    /// variants is the same when serialized and deserialized by binary formats like bincode.
     #[serde(skip)]
     //Custom(#[derivative(Debug = "ignore")] std::sync::Arc<Box<dyn Fn(f64) -> bool>>),
     Custom(#[derivative(Debug = "ignore")] fn(f64) -> bool),
@@ -204,6 +423,142 @@ impl StatConditionType {
             }
             StatConditionType::DivisibleBy(p) => value as i32 % p == 0,
             StatConditionType::Custom(e) => e(value),
+            StatConditionType::Script(_) => false,
+            StatConditionType::Expr(expr) => expr
+                .is_true(&|name| expr_variable(name, value, min_value, max_value))
+                .unwrap_or(false),
         }
     }
 }
+
+/// Resolves the variables of a `StatConditionType::Expr` referring to the stat itself.
+/// `percent` can't be resolved when the maximum value isn't greater than the minimum value.
+fn expr_variable(
+    name: &str,
+    value: f64,
+    min_value: Option<f64>,
+    max_value: Option<f64>,
+) -> Option<f64> {
+    match name {
+        "value" => Some(value),
+        "min" => min_value,
+        "max" => max_value,
+        "percent" => match (min_value, max_value) {
+            (Some(min), Some(max)) if max > min => Some((value - min) / (max - min)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The errors found when validating `StatDefinitions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatDefinitionError<K> {
+    /// The definition is registered under a key different from its own.
+    KeyMismatch(K),
+    /// The minimum value is greater than the maximum value, or the default value is outside of
+    /// them.
+    InvalidBounds(K),
+    /// The expression of a condition on this stat uses a variable that can't be resolved.
+    UnknownVariable(K, String),
+    /// A condition on this stat uses this script, which is not registered in the `ScriptHost`.
+    UnknownScript(K, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat_defs() -> StatDefinitions<u32> {
+        let mut health = StatDefinition::new(0, "Health".into(), "health".to_string(), 50.0);
+        health.min_value = Some(0.0);
+        health.max_value = Some(100.0);
+        let armor = StatDefinition::new(1, "Armor".into(), "armor".to_string(), 6.0);
+        StatDefinitions::from(vec![health, armor])
+    }
+
+    fn expr_condition(stat: u32, source: &str) -> StatCondition<u32> {
+        StatCondition::new(stat, StatConditionType::Expr(Expr::parse(source).unwrap()))
+    }
+
+    #[test]
+    fn expr_conditions() {
+        let defs = stat_defs();
+        let stats = defs.to_statset();
+        assert!(expr_condition(0, "percent == 0.5 && armor > 5").check(&stats, &defs));
+        assert!(!expr_condition(0, "value > max / 2").check(&stats, &defs));
+    }
+
+    #[test]
+    fn unresolved_variables_are_not_met() {
+        let defs = stat_defs();
+        let stats = defs.to_statset();
+        // Armor has no bounds, so `percent` can't be resolved.
+        let condition = expr_condition(1, "percent < 0.5");
+        assert!(!condition.check(&stats, &defs));
+        assert!(!StatConditionGroup::Condition(expr_condition(0, "mana > 1")).check(&stats, &defs));
+        assert_eq!(
+            defs.validate_condition(&condition, None),
+            vec![StatDefinitionError::UnknownVariable(
+                1,
+                "percent".to_string()
+            )]
+        );
+        assert!(defs
+            .validate_condition(&expr_condition(0, "percent < 0.5 || armor > 1"), None)
+            .is_empty());
+    }
+
+    #[test]
+    fn unknown_stats_are_not_set() {
+        let defs = stat_defs();
+        let mut stats = defs.to_statset();
+        assert!(stats.set(&2, 1.0, &defs).is_none());
+        let mut other_defs = stat_defs();
+        other_defs.defs.remove(&1);
+        assert!(stats.add_to(&1, 1.0, &other_defs).is_none());
+        assert!(stats.take_changes().is_empty());
+        let change = stats.add_to(&0, 60.0, &defs).unwrap();
+        assert_eq!(change.reached_bound, Some(StatBound::Max));
+    }
+
+    #[test]
+    fn percent_needs_a_non_empty_range() {
+        let mut defs = stat_defs();
+        defs.defs.get_mut(&0).unwrap().max_value = Some(0.0);
+        let stats = defs.to_statset();
+        let condition = expr_condition(0, "percent >= 0.0");
+        assert!(!condition.check(&stats, &defs));
+        assert_eq!(
+            defs.validate_condition(&condition, None),
+            vec![StatDefinitionError::UnknownVariable(
+                0,
+                "percent".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn unknown_script_conditions_are_reported() {
+        let defs = stat_defs();
+        let stats = defs.to_statset();
+        let condition = StatCondition::new(0, StatConditionType::Script("is_full".to_string()));
+        let unknown = vec![StatDefinitionError::UnknownScript(0, "is_full".to_string())];
+        assert_eq!(defs.validate_condition(&condition, None), unknown);
+        let mut scripts = ScriptHost::default();
+        assert_eq!(defs.validate_condition(&condition, Some(&scripts)), unknown);
+        assert_eq!(
+            condition.check_scripted(&stats, &defs, None),
+            Err(ScriptError::NoScriptHost("is_full".to_string()))
+        );
+        scripts.register_condition("is_full", |value, _, max| Some(value) == max);
+        assert!(defs
+            .validate_condition(&condition, Some(&scripts))
+            .is_empty());
+        assert!(!condition.check(&stats, &defs));
+        assert_eq!(
+            condition.check_scripted(&stats, &defs, Some(&scripts)),
+            Ok(false)
+        );
+    }
+}
@@ -1 +1,188 @@
+use crate::*;
+use alloc::collections::BTreeMap;
+use core::hash::Hash;
 
+/// A value that only increases, like the number of killed monsters.
+/// The increments are grouped in time buckets to allow time-windowed queries.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct Counter {
+    /// The sum of all the increments.
+    #[new(default)]
+    pub total: f64,
+    /// The sum of the increments of each time bucket, mapped by bucket index.
+    #[new(default)]
+    pub buckets: BTreeMap<i64, f64>,
+}
+
+impl Counter {
+    fn add(&mut self, amount: f64, bucket: i64) {
+        self.total += amount;
+        *self.buckets.entry(bucket).or_insert(0.0) += amount;
+    }
+
+    fn between(&self, from: i64, to: i64) -> f64 {
+        if from > to {
+            return 0.0;
+        }
+        self.buckets.range(from..=to).map(|(_, v)| v).sum()
+    }
+}
+
+/// Tracks counters and gauges, per user and globally.
+///
+/// Counters only increase and can be queried over a time window.
+/// Gauges hold the current value of something, like the number of owned houses.
+///
+/// Times are in seconds and must all come from the same clock.
+///
+/// # Generics
+/// - M: Metric Key, like "mobs_killed"
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Statistics<M: Hash + Eq> {
+    /// The duration of a time bucket of the counters, in seconds.
+    /// Time-windowed queries are precise up to a bucket.
+    pub bucket_duration: f64,
+    /// The counters of each user.
+    #[new(default)]
+    pub counters: HashMap<M, HashMap<i32, Counter>>,
+    /// The counters of all users combined, including the increments that are not tied to a
+    /// user.
+    #[new(default)]
+    pub global_counters: HashMap<M, Counter>,
+    /// The gauges of each user.
+    #[new(default)]
+    pub gauges: HashMap<M, HashMap<i32, f64>>,
+}
+
+impl<M: Hash + Eq + Clone> Statistics<M> {
+    fn bucket(&self, time: f64) -> i64 {
+        (time / self.bucket_duration).floor() as i64
+    }
+
+    /// Increments the counter of the user and the global counter.
+    /// Use None as the user for increments that are not tied to a user.
+    pub fn increment(&mut self, metric: &M, user: Option<i32>, amount: f64, now: f64) {
+        let bucket = self.bucket(now);
+        if let Some(user) = user {
+            self.counters
+                .entry(metric.clone())
+                .or_default()
+                .entry(user)
+                .or_default()
+                .add(amount, bucket);
+        }
+        self.global_counters
+            .entry(metric.clone())
+            .or_default()
+            .add(amount, bucket);
+    }
+
+    /// Returns the total of the counter of the user.
+    pub fn counter(&self, metric: &M, user: i32) -> f64 {
+        self.counters
+            .get(metric)
+            .and_then(|c| c.get(&user))
+            .map(|c| c.total)
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the total of the global counter.
+    pub fn global_counter(&self, metric: &M) -> f64 {
+        self.global_counters
+            .get(metric)
+            .map(|c| c.total)
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the increments of the counter between `from` and `to`.
+    /// Use None as the user to query the global counter.
+    /// For example, `counter_between(metric, user, now - 86400.0, now)` returns the increments
+    /// of the last day.
+    /// Returns 0 if `from` is after `to`.
+    pub fn counter_between(&self, metric: &M, user: Option<i32>, from: f64, to: f64) -> f64 {
+        let counter = match user {
+            Some(user) => self.counters.get(metric).and_then(|c| c.get(&user)),
+            None => self.global_counters.get(metric),
+        };
+        counter
+            .map(|c| c.between(self.bucket(from), self.bucket(to)))
+            .unwrap_or(0.0)
+    }
+
+    /// Returns a function giving the counters of the user.
+    /// Can be used to evaluate `AchievementCriterion::Counter`.
+    pub fn counters_of(&self, user: i32) -> impl Fn(&M) -> f64 + '_ {
+        move |metric| self.counter(metric, user)
+    }
+
+    /// Removes the time buckets of the counters older than `before`.
+    /// The totals are kept.
+    pub fn prune(&mut self, before: f64) {
+        let first = self.bucket(before);
+        let counters = self
+            .counters
+            .values_mut()
+            .flat_map(|c| c.values_mut())
+            .chain(self.global_counters.values_mut());
+        for counter in counters {
+            counter.buckets = counter.buckets.split_off(&first);
+        }
+    }
+
+    /// Sets the gauge of the user.
+    pub fn set_gauge(&mut self, metric: &M, user: i32, value: f64) {
+        self.gauges
+            .entry(metric.clone())
+            .or_default()
+            .insert(user, value);
+    }
+
+    /// Returns the gauge of the user, if it was set.
+    pub fn gauge(&self, metric: &M, user: i32) -> Option<f64> {
+        self.gauges.get(metric).and_then(|g| g.get(&user)).copied()
+    }
+
+    /// Returns the sum of the gauges of all users.
+    pub fn gauge_sum(&self, metric: &M) -> f64 {
+        self.gauges
+            .get(metric)
+            .map(|g| g.values().sum())
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the average of the gauges of all users, if any is set.
+    pub fn gauge_average(&self, metric: &M) -> Option<f64> {
+        self.gauges
+            .get(metric)
+            .filter(|g| !g.is_empty())
+            .map(|g| g.values().sum::<f64>() / g.len() as f64)
+    }
+
+    /// Returns the users with their value for the metric: the total of the counter if the
+    /// metric is a counter, otherwise the gauge.
+    /// Can be used to fill a `Leaderboard`.
+    pub fn values_of(&self, metric: &M) -> Vec<(i32, f64)> {
+        if let Some(counters) = self.counters.get(metric) {
+            counters.iter().map(|(u, c)| (*u, c.total)).collect()
+        } else if let Some(gauges) = self.gauges.get(metric) {
+            gauges.iter().map(|(u, v)| (*u, *v)).collect()
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_between_reversed_window_is_empty() {
+        let mut stats = Statistics::new(10.0);
+        stats.increment(&"kills", Some(1), 2.0, 5.0);
+        stats.increment(&"kills", Some(1), 3.0, 25.0);
+        assert_eq!(stats.counter_between(&"kills", Some(1), 0.0, 30.0), 5.0);
+        assert_eq!(stats.counter_between(&"kills", Some(1), 20.0, 30.0), 3.0);
+        assert_eq!(stats.counter_between(&"kills", None, 30.0, 0.0), 0.0);
+    }
+}
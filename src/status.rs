@@ -0,0 +1,138 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// A condition deciding if a status is active.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StatusCondition<K, E> {
+    /// An instance of this effector is active.
+    Effector(E),
+    /// An active effector has this tag.
+    EffectorTag(String),
+    /// The stat condition is met.
+    Stat(StatCondition<K>),
+}
+
+impl<K: Hash + Eq + Debug, E: Hash + Eq> StatusCondition<K, E> {
+    /// Checks if the condition is met.
+    pub fn check(
+        &self,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        effectors: &EffectorSet<E>,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> bool {
+        match self {
+            StatusCondition::Effector(key) => {
+                effectors.effectors.iter().any(|e| e.effector_key == *key)
+            }
+            StatusCondition::EffectorTag(tag) => effectors.has_tag(tag, effector_defs),
+            StatusCondition::Stat(c) => c.check(stats, stat_defs),
+        }
+    }
+}
+
+/// Status conditions combined together, like "stunned or (rooted and not flying)".
+pub type StatusConditionGroup<K, E> = ConditionGroup<StatusCondition<K, E>>;
+
+impl<K: Hash + Eq + Debug, E: Hash + Eq> ConditionGroup<StatusCondition<K, E>> {
+    /// Checks if the conditions are met.
+    pub fn check(
+        &self,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        effectors: &EffectorSet<E>,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> bool {
+        self.check_with(&|c| c.check(stats, stat_defs, effectors, effector_defs))
+    }
+}
+
+/// The rules deciding which statuses are active.
+/// For example: "Stunned" when the "stun" effector is active, or "Exhausted" when the stamina
+/// is below 10%.
+///
+/// # Generics
+/// - T: Status Key
+/// - K: Stat Key
+/// - E: Effector Key
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct StatusRules<T, K, E> {
+    /// The statuses and the condition activating them.
+    /// A status can appear multiple times, in which case it is active if any of its conditions
+    /// is met.
+    pub rules: Vec<(T, StatusConditionGroup<K, E>)>,
+}
+
+impl<T: Hash + Eq + Clone, K: Hash + Eq + Debug, E: Hash + Eq> StatusRules<T, K, E> {
+    /// Returns the statuses whose condition is met.
+    pub fn evaluate(
+        &self,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        effectors: &EffectorSet<E>,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> HashSet<T> {
+        self.rules
+            .iter()
+            .filter(|(_, c)| c.check(stats, stat_defs, effectors, effector_defs))
+            .map(|(t, _)| t.clone())
+            .collect()
+    }
+}
+
+/// The statuses currently active on an entity.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct StatusFlags<T: Hash + Eq> {
+    /// The active statuses.
+    #[new(default)]
+    pub active: HashSet<T>,
+}
+
+impl<T: Hash + Eq> Default for StatusFlags<T> {
+    fn default() -> Self {
+        Self {
+            active: HashSet::default(),
+        }
+    }
+}
+
+impl<T: Hash + Eq + Clone> StatusFlags<T> {
+    /// Checks if the status is active.
+    pub fn has(&self, status: &T) -> bool {
+        self.active.contains(status)
+    }
+
+    /// Evaluates the rules again and returns the statuses that were gained or lost since the
+    /// last update.
+    pub fn update<K: Hash + Eq + Debug, E: Hash + Eq>(
+        &mut self,
+        rules: &StatusRules<T, K, E>,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        effectors: &EffectorSet<E>,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> Vec<StatusEvent<T>> {
+        let active = rules.evaluate(stats, stat_defs, effectors, effector_defs);
+        let mut events = active
+            .difference(&self.active)
+            .map(|t| StatusEvent::Gained(t.clone()))
+            .collect::<Vec<_>>();
+        events.extend(
+            self.active
+                .difference(&active)
+                .map(|t| StatusEvent::Lost(t.clone())),
+        );
+        self.active = active;
+        events
+    }
+}
+
+/// A change of the active statuses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusEvent<T> {
+    /// The status became active.
+    Gained(T),
+    /// The status is no longer active.
+    Lost(T),
+}
@@ -0,0 +1,28 @@
+//! Fixtures shared by the tests of the different modules.
+
+use crate::*;
+
+/// Definitions containing a single item, apples of key 1 stacking up to 10.
+pub fn item_defs() -> ItemDefinitions<u32, (), ()> {
+    ItemDefinitions::from(vec![ItemDefinition::new(
+        1,
+        (),
+        "apple".into(),
+        "Apple".to_string(),
+        "".into(),
+        Some(10),
+        None,
+    )])
+}
+
+/// Definitions containing a single currency, gold of key 0.
+pub fn currency_defs() -> CurrencyDefinitions<u8> {
+    CurrencyDefinitions::from(vec![CurrencyDefinition::new(0, "Gold".to_string(), 1.0)])
+}
+
+/// A player with an empty inventory of one slot and a wallet holding this amount of gold.
+pub fn player(gold: i64) -> (Inventory<u32, (), ()>, Wallet<u8>) {
+    let mut wallet = Wallet::new();
+    wallet.add(&0, gold, &currency_defs()).unwrap();
+    (Inventory::new_fixed(1), wallet)
+}
@@ -0,0 +1,220 @@
+use crate::*;
+use core::hash::Hash;
+
+/// A taunt forcing the owner of a `ThreatTable` to target the taunter.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Taunt<Id> {
+    /// The entity that taunted.
+    pub taunter: Id,
+    /// The timer after which the taunt ends.
+    pub timer: Timer,
+}
+
+/// The threat, or aggro, generated by each entity towards the owner of this table, like a
+/// monster choosing who to attack.
+///
+/// # Generics
+/// - Id: Entity Id
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct ThreatTable<Id: Hash + Eq> {
+    /// The threat generated by each entity.
+    #[new(default)]
+    pub threat: HashMap<Id, f64>,
+    /// The fraction of the threat lost each second, between 0.0 and 1.0.
+    pub decay_per_second: f64,
+    /// The threat generated by each point of healing.
+    pub healing_multiplier: f64,
+    /// The current target is only replaced when another entity has more than its threat
+    /// multiplied by this value. Use 1.0 to always target the entity with the most threat.
+    pub switch_threshold: f64,
+    /// The active taunt.
+    #[new(default)]
+    #[serde(default)]
+    pub taunt: Option<Taunt<Id>>,
+    /// The current target.
+    #[new(default)]
+    #[serde(default)]
+    pub current: Option<Id>,
+    /// When each entity started generating threat, relative to the other entities.
+    /// Entities with the same threat are ranked in the order they started generating threat.
+    #[new(default)]
+    #[serde(default)]
+    pub sequences: HashMap<Id, u64>,
+    /// The sequence given to the next entity generating threat.
+    #[new(default)]
+    #[serde(default)]
+    next_sequence: u64,
+}
+
+impl<Id: Hash + Eq + Clone> ThreatTable<Id> {
+    /// Adds threat generated by damage dealt by the attacker.
+    pub fn add_damage(&mut self, attacker: &Id, damage: f64) {
+        self.add_threat(attacker, damage);
+    }
+
+    /// Adds threat generated by healing done by the healer.
+    pub fn add_healing(&mut self, healer: &Id, healing: f64) {
+        self.add_threat(healer, healing * self.healing_multiplier);
+    }
+
+    /// Adds threat to the entity. Negative values reduce it, without going under 0.
+    pub fn add_threat(&mut self, id: &Id, amount: f64) {
+        let threat = self.entry(id);
+        *threat = (*threat + amount).max(0.0);
+        self.retarget();
+    }
+
+    /// Returns the threat of the entity.
+    pub fn threat_of(&self, id: &Id) -> f64 {
+        self.threat.get(id).copied().unwrap_or(0.0)
+    }
+
+    /// Removes the entity from the table, for example when it dies or leaves combat.
+    pub fn remove(&mut self, id: &Id) {
+        self.threat.remove(id);
+        self.sequences.remove(id);
+        if self
+            .taunt
+            .as_ref()
+            .map(|t| t.taunter == *id)
+            .unwrap_or(false)
+        {
+            self.taunt = None;
+        }
+        if self.current.as_ref() == Some(id) {
+            self.current = None;
+        }
+        self.retarget();
+    }
+
+    /// Removes all the entities and the taunt.
+    pub fn clear(&mut self) {
+        self.threat.clear();
+        self.sequences.clear();
+        self.taunt = None;
+        self.current = None;
+    }
+
+    /// Forces the owner of the table to target the taunter for the duration, in seconds.
+    /// The threat of the taunter is raised to the highest threat of the table.
+    pub fn taunt(&mut self, taunter: &Id, duration: f64) {
+        let highest = self.threat.values().cloned().fold(0.0, f64::max);
+        let threat = self.entry(taunter);
+        *threat = threat.max(highest);
+        self.taunt = Some(Taunt::new(taunter.clone(), Timer::once(duration)));
+        self.retarget();
+    }
+
+    /// Decays the threat and advances the taunt by `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f64) {
+        let factor = (1.0 - self.decay_per_second * delta_time).max(0.0);
+        for threat in self.threat.values_mut() {
+            *threat *= factor;
+        }
+        if let Some(taunt) = self.taunt.as_mut() {
+            if taunt.timer.update(delta_time) > 0 {
+                self.taunt = None;
+            }
+        }
+        self.retarget();
+    }
+
+    /// Returns the entity that should be targeted.
+    /// A taunting entity is always targeted. Otherwise, the current target is kept until
+    /// another entity generates enough threat. See `ThreatTable::switch_threshold`.
+    pub fn current_target(&self) -> Option<&Id> {
+        self.current.as_ref()
+    }
+
+    /// Returns the entities and their threat, sorted from the highest threat to the lowest.
+    /// Entities with the same threat are sorted by when they started generating threat.
+    pub fn ranking(&self) -> Vec<(&Id, f64)> {
+        let mut ranking = self.threat.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>();
+        let sequence = |id: &Id| self.sequences.get(id).copied().unwrap_or(u64::MAX);
+        ranking.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(core::cmp::Ordering::Equal)
+                .then_with(|| sequence(a.0).cmp(&sequence(b.0)))
+        });
+        ranking
+    }
+
+    /// Returns the threat of the entity, adding it to the table if needed.
+    fn entry(&mut self, id: &Id) -> &mut f64 {
+        if !self.sequences.contains_key(id) {
+            self.sequences.insert(id.clone(), self.next_sequence);
+            self.next_sequence += 1;
+        }
+        self.threat.entry(id.clone()).or_default()
+    }
+
+    fn retarget(&mut self) {
+        if let Some(taunt) = self.taunt.as_ref() {
+            self.current = Some(taunt.taunter.clone());
+            return;
+        }
+        let highest = self
+            .ranking()
+            .first()
+            .map(|(id, threat)| ((*id).clone(), *threat));
+        let (id, threat) = match highest {
+            Some(h) => h,
+            None => {
+                self.current = None;
+                return;
+            }
+        };
+        let current_threat = self.current.as_ref().and_then(|c| self.threat.get(c));
+        match current_threat {
+            Some(current) if threat <= current * self.switch_threshold => {}
+            _ => self.current = Some(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_switches_past_the_threshold() {
+        let mut table = ThreatTable::<u32>::new(0.0, 0.5, 1.1);
+        table.add_damage(&1, 100.0);
+        table.add_damage(&2, 105.0);
+        assert_eq!(table.current_target(), Some(&1));
+        table.add_healing(&2, 20.0);
+        assert_eq!(table.current_target(), Some(&2));
+        table.remove(&2);
+        assert_eq!(table.current_target(), Some(&1));
+    }
+
+    #[test]
+    fn ties_are_ranked_by_first_threat() {
+        let mut table = ThreatTable::<u32>::new(0.0, 1.0, 1.0);
+        for id in (0..10).rev() {
+            table.add_damage(&id, 10.0);
+        }
+        let ranking = table
+            .ranking()
+            .into_iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        assert_eq!(ranking, (0..10).rev().collect::<Vec<_>>());
+        assert_eq!(table.current_target(), Some(&9));
+    }
+
+    #[test]
+    fn taunts_force_the_target_until_they_end() {
+        let mut table = ThreatTable::<u32>::new(0.5, 1.0, 1.0);
+        table.add_damage(&1, 100.0);
+        table.taunt(&2, 1.0);
+        assert_eq!(table.current_target(), Some(&2));
+        assert_eq!(table.threat_of(&2), 100.0);
+        table.add_damage(&1, 50.0);
+        assert_eq!(table.current_target(), Some(&2));
+        table.update(1.0);
+        assert!(table.taunt.is_none());
+        assert_eq!(table.threat_of(&1), 75.0);
+        assert_eq!(table.current_target(), Some(&1));
+    }
+}
@@ -1,6 +1,11 @@
-// TODO consider if the tier stuff is useful at all.
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
 /// Tiered element.
 /// Simply adds a numerical value to any element.
+/// Upgrading to the next tier uses the requirements found in `TierDefinitions`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
 pub struct Tiered<T> {
     /// The numerical tier.
     pub tier: u32,
@@ -8,8 +13,173 @@ pub struct Tiered<T> {
     pub element: T,
 }
 
+impl<T> Tiered<T> {
+    /// Upgrades the element to the next tier if the requirements of its `TierDefinition` are
+    /// met. The items and currencies required are consumed.
+    /// Returns the new tier.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * MaxTier: There is no definition for the next tier.
+    /// * ConditionsNotMet: The stat conditions of the next tier are not met.
+    /// * NotEnoughItems: The inventory doesn't have the required items.
+    /// * Wallet: The wallet can't pay the required currencies.
+    pub fn try_upgrade_tier<
+        K: Hash + Eq + Debug,
+        I: Hash + Eq + Clone + Debug,
+        C: Hash + Eq + Clone,
+        E,
+        IT: SlotType,
+        CD: Default + Clone + Debug + PartialEq,
+    >(
+        &mut self,
+        tier_defs: &TierDefinitions<K, I, C, E>,
+        stats: &StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        inventory: &mut Inventory<I, IT, CD>,
+        wallet: &mut Wallet<C>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<u32, TierError<C>> {
+        let next = self.tier + 1;
+        let def = tier_defs.defs.get(&next).ok_or(TierError::MaxTier)?;
+        if !def
+            .stat_conditions
+            .iter()
+            .all(|c| c.check(stats, stat_defs))
+        {
+            return Err(TierError::ConditionsNotMet);
+        }
+        // Keys listed more than once must be present in their total quantity.
+        if !inventory.has_quantities(&def.items) {
+            return Err(TierError::NotEnoughItems);
+        }
+        wallet
+            .pay(&def.currencies, currency_defs)
+            .map_err(TierError::Wallet)?;
+        inventory
+            .delete_keys(&def.items)
+            .expect("Failed to remove the items required by the tier. This is a bug.");
+        self.tier = next;
+        Ok(next)
+    }
+}
+
+/// The requirements to reach a tier and the bonuses granted while at this tier.
+///
+/// # Generics
+/// - K: Stat Key
+/// - I: Item Key
+/// - C: Currency Key
+/// - E: Effector Key
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct TierDefinition<K, I, C, E> {
+    /// The tier this definition is for.
+    pub tier: u32,
+    /// The stat conditions required to reach this tier.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub stat_conditions: Vec<StatCondition<K>>,
+    /// The items consumed when reaching this tier.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub items: Vec<(I, usize)>,
+    /// The currencies paid when reaching this tier.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub currencies: Vec<(C, i64)>,
+    /// The effectors granted to elements of this tier.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default = "Vec::new")]
+    pub stat_effectors: Vec<E>,
+    /// The magnitude of the granted effectors. See `EffectorInstance::magnitude`.
+    pub magnitude: f64,
+}
+
+/// The definitions of all known tiers, mapped by tier.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct TierDefinitions<K, I, C, E> {
+    /// The definitions.
+    pub defs: HashMap<u32, TierDefinition<K, I, C, E>>,
+}
+
+impl<K, I, C, E> Default for TierDefinitions<K, I, C, E> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<K, I, C, E> From<Vec<TierDefinition<K, I, C, E>>> for TierDefinitions<K, I, C, E> {
+    fn from(t: Vec<TierDefinition<K, I, C, E>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.tier, s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<K, I, C, E: Hash + Eq + Clone> TierDefinitions<K, I, C, E> {
+    /// Returns the highest defined tier, or 0 if there is none.
+    pub fn max_tier(&self) -> u32 {
+        self.defs.keys().copied().max().unwrap_or(0)
+    }
+
+    /// Adds the effectors of this tier to the `EffectorSet`, scaled by the magnitude of the
+    /// tier.
+    /// Does nothing if the tier is not defined.
+    pub fn apply_bonuses<S>(
+        &self,
+        tier: u32,
+        effectors: &mut EffectorSet<E>,
+        effector_defs: &EffectorDefinitions<S, E>,
+    ) {
+        if let Some(def) = self.defs.get(&tier) {
+            for e in def.stat_effectors.iter() {
+                effectors.add_with_magnitude(e, def.magnitude, effector_defs);
+            }
+        }
+    }
+
+    /// Removes the effectors added by `TierDefinitions::apply_bonuses` for this tier.
+    /// Use this before applying the bonuses of the new tier after an upgrade.
+    pub fn remove_bonuses(&self, tier: u32, effectors: &mut EffectorSet<E>) {
+        if let Some(def) = self.defs.get(&tier) {
+            for e in def.stat_effectors.iter() {
+                if let Some(idx) = effectors
+                    .effectors
+                    .iter()
+                    .position(|i| i.effector_key == *e && i.magnitude == def.magnitude)
+                {
+                    effectors.effectors.swap_remove(idx);
+                }
+            }
+        }
+    }
+}
+
+/// The errors that can happen when upgrading the tier of an element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TierError<C> {
+    /// There is no definition for the next tier.
+    MaxTier,
+    /// The stat conditions of the next tier are not met.
+    ConditionsNotMet,
+    /// The inventory doesn't have the items required by the next tier.
+    NotEnoughItems,
+    /// The wallet can't pay the currencies required by the next tier.
+    Wallet(WalletError<C>),
+}
+
 /// A levelable element.
 /// It can be anything: an item, a player, a monster, a skill.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
 pub struct Leveled<T: LevelFor> {
     /// The experience that this has accumulated.
     pub accumulated_xp: u32,
@@ -22,6 +192,20 @@ impl<T: LevelFor> Leveled<T> {
     pub fn level(&self) -> u32 {
         self.element.level_for_xp(self.accumulated_xp)
     }
+
+    /// Adds experience and returns an event for each level gained.
+    pub fn add_xp(&mut self, amount: u32) -> Vec<LevelUpEvent> {
+        let before = self.level();
+        self.accumulated_xp = self.accumulated_xp.saturating_add(amount);
+        (before + 1..=self.level()).map(LevelUpEvent::new).collect()
+    }
+}
+
+/// Emitted when a `Leveled` element reaches a new level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, new)]
+pub struct LevelUpEvent {
+    /// The level that was reached.
+    pub level: u32,
 }
 
 /// A trait that can calculate the level for something that can accumulate experience.
@@ -30,3 +214,169 @@ pub trait LevelFor {
     /// Returns the level that you have using the amount of experience.
     fn level_for_xp(&self, xp: u32) -> u32;
 }
+
+/// Common ways of calculating the level from the experience.
+/// All curves start at level 1 with 0 experience.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LevelCurve {
+    /// Each level requires the same amount of experience.
+    Linear {
+        /// The experience required for each level.
+        xp_per_level: u32,
+    },
+    /// Each level requires more experience than the previous one.
+    Exponential {
+        /// The experience required to go from level 1 to level 2.
+        base_xp: u32,
+        /// The multiplier applied to the required experience at each level.
+        factor: f64,
+    },
+    /// The total experience required to reach each level, starting with level 2.
+    /// The level can't go higher than the size of the table + 1.
+    Table(Vec<u32>),
+}
+
+impl LevelFor for LevelCurve {
+    fn level_for_xp(&self, xp: u32) -> u32 {
+        match self {
+            LevelCurve::Linear { xp_per_level } => {
+                if *xp_per_level == 0 {
+                    1
+                } else {
+                    1 + xp / xp_per_level
+                }
+            }
+            LevelCurve::Exponential { base_xp, factor } => {
+                let mut level = 1;
+                let mut required = f64::from(*base_xp);
+                let mut remaining = f64::from(xp);
+                while required > 0.0 && remaining >= required {
+                    remaining -= required;
+                    required *= factor;
+                    level += 1;
+                }
+                level
+            }
+            LevelCurve::Table(table) => 1 + table.iter().filter(|t| **t <= xp).count() as u32,
+        }
+    }
+}
+
+/// Something granted when reaching a level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LevelReward<K, I> {
+    /// Adds a value to the base value of a stat.
+    /// Stat points and skill points can be represented using stats.
+    Stat(K, f64),
+    /// Gives this quantity of the item.
+    Item(I, usize),
+}
+
+/// The rewards granted when reaching levels.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct LevelRewards<K, I> {
+    /// The rewards granted for each new level.
+    pub every_level: Vec<LevelReward<K, I>>,
+    /// The rewards granted when reaching specific levels.
+    pub per_level: HashMap<u32, Vec<LevelReward<K, I>>>,
+}
+
+impl<K: Hash + Eq + Clone + Debug, I: Hash + Eq + Clone + Debug> LevelRewards<K, I> {
+    /// Returns the rewards for reaching the level.
+    pub fn rewards_for(&self, level: u32) -> impl Iterator<Item = &LevelReward<K, I>> {
+        self.every_level
+            .iter()
+            .chain(self.per_level.get(&level).into_iter().flatten())
+    }
+
+    /// Grants the rewards of each level up event.
+    /// Stats are added to the `StatSet` and items are inserted into the `Inventory`.
+    ///
+    /// Errors:
+    /// See `Inventory::insert`. The rewards granted before the error are kept.
+    pub fn grant<IT: SlotType, CD: Default + Clone + Debug + PartialEq, D: Default>(
+        &self,
+        events: &[LevelUpEvent],
+        stats: &mut StatSet<K>,
+        stat_defs: &StatDefinitions<K>,
+        inventory: &mut Inventory<I, IT, CD>,
+        item_defs: &ItemDefinitions<I, IT, D>,
+    ) -> Result<(), ItemError<I, CD>> {
+        for event in events {
+            for reward in self.rewards_for(event.level) {
+                match reward {
+                    LevelReward::Stat(key, value) => {
+                        stats.add_to(key, *value, stat_defs);
+                    }
+                    LevelReward::Item(key, quantity) => {
+                        inventory.insert(ItemInstance::new(key.clone(), *quantity), item_defs)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrade_adds_up_duplicate_items() {
+        let item_defs = ItemDefinitions::<u32, (), ()>::from(vec![ItemDefinition::new(
+            1,
+            (),
+            "ore".into(),
+            "Ore".to_string(),
+            "".into(),
+            Some(10),
+            None,
+        )]);
+        let currency_defs =
+            CurrencyDefinitions::from(vec![CurrencyDefinition::new(0u8, "Gold".to_string(), 1.0)]);
+        let mut def = TierDefinition::<u32, u32, u8, u32>::new(1, 1.0);
+        def.items = vec![(1, 2), (1, 2)];
+        def.currencies = vec![(0, 10)];
+        let tier_defs = TierDefinitions::from(vec![def]);
+        let stat_defs = StatDefinitions::default();
+        let stats = stat_defs.to_statset();
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(1);
+        inventory
+            .insert(ItemInstance::new(1, 3), &item_defs)
+            .unwrap();
+        let mut wallet = Wallet::new();
+        wallet.add(&0, 10, &currency_defs).unwrap();
+        let mut tiered = Tiered::new(0, ());
+        assert!(matches!(
+            tiered.try_upgrade_tier(
+                &tier_defs,
+                &stats,
+                &stat_defs,
+                &mut inventory,
+                &mut wallet,
+                &currency_defs
+            ),
+            Err(TierError::NotEnoughItems)
+        ));
+        assert_eq!(wallet.balance(&0), 10);
+        inventory
+            .insert(ItemInstance::new(1, 1), &item_defs)
+            .unwrap();
+        assert_eq!(
+            tiered
+                .try_upgrade_tier(
+                    &tier_defs,
+                    &stats,
+                    &stat_defs,
+                    &mut inventory,
+                    &mut wallet,
+                    &currency_defs
+                )
+                .unwrap(),
+            1
+        );
+        assert!(!inventory.has(&1));
+        assert_eq!(wallet.balance(&0), 0);
+    }
+}
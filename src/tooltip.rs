@@ -0,0 +1,153 @@
+use crate::*;
+use core::hash::Hash;
+
+/// A line of a `Tooltip`. UIs choose how to display each kind of line.
+///
+/// # Generics
+/// - K: Stat Key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TooltipLine<K> {
+    /// The name of the item, with the names of its affixes.
+    Name {
+        /// The names of the prefixes, displayed before the name.
+        prefixes: Vec<String>,
+        /// The name of the item.
+        name: LocalizedText,
+        /// The names of the suffixes, displayed after the name.
+        suffixes: Vec<String>,
+    },
+    /// The rarity of the item.
+    Rarity {
+        /// The display name of the rarity.
+        name: String,
+        /// The color of the rarity, in RGB.
+        color: (u8, u8, u8),
+    },
+    /// A change to a stat granted by the item, its affixes or its effectors.
+    StatBonus {
+        /// The modified stat.
+        stat: K,
+        /// How the stat is modified.
+        effect: EffectorType,
+    },
+    /// The durability of the item.
+    Durability {
+        /// The remaining durability.
+        current: usize,
+        /// The maximum durability.
+        maximum: usize,
+    },
+    /// The description of the item.
+    Flavor(LocalizedText),
+}
+
+/// The structured description of an item, ready to be rendered by a UI.
+/// See `TooltipBuilder`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+pub struct Tooltip<K> {
+    /// The lines, in display order.
+    pub lines: Vec<TooltipLine<K>>,
+}
+
+/// Composes the `Tooltip` of an item from its definition, rarity, affixes and effectors.
+///
+/// The lines are ordered as: name, rarity, stat bonuses, durability then flavor.
+///
+/// # Generics
+/// - K: Stat Key
+#[derive(Debug, Clone)]
+pub struct TooltipBuilder<K> {
+    prefixes: Vec<String>,
+    name: LocalizedText,
+    suffixes: Vec<String>,
+    rarity: Option<(String, (u8, u8, u8))>,
+    bonuses: Vec<(K, EffectorType)>,
+    durability: Option<(usize, usize)>,
+    flavor: LocalizedText,
+}
+
+impl<K: Clone> TooltipBuilder<K> {
+    /// Starts the tooltip of the item using its name, description and durability.
+    pub fn item<I, U: Default, S, D: Default>(
+        item: &ItemInstance<I, U>,
+        item_def: &ItemDefinition<I, S, D>,
+    ) -> Self {
+        Self {
+            prefixes: vec![],
+            name: item_def.name.clone(),
+            suffixes: vec![],
+            rarity: None,
+            bonuses: vec![],
+            durability: item
+                .durability
+                .map(|d| (d, item_def.maximum_durability.unwrap_or(d))),
+            flavor: item_def.description.clone(),
+        }
+    }
+
+    /// Adds the rarity of the item.
+    pub fn rarity<R>(mut self, rarity: &RarityDefinition<R>) -> Self {
+        self.rarity = Some((rarity.name.clone(), rarity.color));
+        self
+    }
+
+    /// Adds the names of the affixes to the name of the item, and the effects of their
+    /// effectors to the stat bonuses.
+    pub fn affixes<A: Hash + Eq, E: Hash + Eq, S>(
+        mut self,
+        affixes: &[A],
+        affix_defs: &AffixDefinitions<A, E, S>,
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> Self {
+        for a in affixes {
+            let def = affix_defs
+                .defs
+                .get(a)
+                .expect("Tried to get unknown affix key.");
+            match def.kind {
+                AffixKind::Prefix => self.prefixes.push(def.name.clone()),
+                AffixKind::Suffix => self.suffixes.push(def.name.clone()),
+            }
+            self = self.effectors(&def.stat_effectors, effector_defs);
+        }
+        self
+    }
+
+    /// Adds the effects of the effectors granted by the item to the stat bonuses.
+    pub fn effectors<E: Hash + Eq>(
+        mut self,
+        effectors: &[E],
+        effector_defs: &EffectorDefinitions<K, E>,
+    ) -> Self {
+        for e in effectors {
+            let def = effector_defs
+                .defs
+                .get(e)
+                .expect("Tried to get unknown effector key.");
+            self.bonuses.extend(def.effects.iter().cloned());
+        }
+        self
+    }
+
+    /// Creates the tooltip.
+    pub fn build(self) -> Tooltip<K> {
+        let mut lines = vec![TooltipLine::Name {
+            prefixes: self.prefixes,
+            name: self.name,
+            suffixes: self.suffixes,
+        }];
+        if let Some((name, color)) = self.rarity {
+            lines.push(TooltipLine::Rarity { name, color });
+        }
+        lines.extend(
+            self.bonuses
+                .into_iter()
+                .map(|(stat, effect)| TooltipLine::StatBonus { stat, effect }),
+        );
+        if let Some((current, maximum)) = self.durability {
+            lines.push(TooltipLine::Durability { current, maximum });
+        }
+        lines.push(TooltipLine::Flavor(self.flavor));
+        Tooltip::new(lines)
+    }
+}
@@ -0,0 +1,342 @@
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// One of the two participants of a `TradeSession`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradeSide {
+    /// The participant who started the trade.
+    First,
+    /// The other participant.
+    Second,
+}
+
+impl TradeSide {
+    /// Returns the other participant.
+    pub fn other(self) -> TradeSide {
+        match self {
+            TradeSide::First => TradeSide::Second,
+            TradeSide::Second => TradeSide::First,
+        }
+    }
+}
+
+/// What a participant gives in a `TradeSession`.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct TradeOffer<K, C> {
+    /// The items given, with their quantity.
+    #[new(default)]
+    pub items: Vec<(K, usize)>,
+    /// The currencies given, with their amount.
+    #[new(default)]
+    pub currencies: Vec<(C, i64)>,
+    /// Whether the participant accepts the current state of the trade.
+    #[new(default)]
+    pub ready: bool,
+}
+
+/// The state of a `TradeSession`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradeState {
+    /// The participants are staging their offers.
+    Open,
+    /// The trade was completed.
+    Completed,
+    /// The trade was cancelled.
+    Cancelled,
+}
+
+/// A trade between two participants.
+/// Both participants stage the items and currencies they give, then mark themselves as ready.
+/// Changing an offer resets the ready flag of both participants.
+/// Once both are ready, the trade can be committed: everything is exchanged at once, or
+/// nothing changes.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct TradeSession<K, C> {
+    /// The offer of the first participant.
+    #[new(value = "TradeOffer::new()")]
+    pub first: TradeOffer<K, C>,
+    /// The offer of the second participant.
+    #[new(value = "TradeOffer::new()")]
+    pub second: TradeOffer<K, C>,
+    /// The state of the trade.
+    #[new(value = "TradeState::Open")]
+    pub state: TradeState,
+}
+
+impl<K: PartialEq + Clone + Debug + Hash + Eq, C: Hash + Eq + Clone> TradeSession<K, C> {
+    /// Returns the offer of the participant.
+    pub fn offer(&self, side: TradeSide) -> &TradeOffer<K, C> {
+        match side {
+            TradeSide::First => &self.first,
+            TradeSide::Second => &self.second,
+        }
+    }
+
+    fn offer_mut(&mut self, side: TradeSide) -> Result<&mut TradeOffer<K, C>, TradeError<C>> {
+        if self.state != TradeState::Open {
+            return Err(TradeError::NotOpen);
+        }
+        self.first.ready = false;
+        self.second.ready = false;
+        Ok(match side {
+            TradeSide::First => &mut self.first,
+            TradeSide::Second => &mut self.second,
+        })
+    }
+
+    /// Adds items to the offer of the participant.
+    ///
+    /// Errors:
+    /// * NotOpen: The trade is completed or cancelled.
+    pub fn stage_item(
+        &mut self,
+        side: TradeSide,
+        key: K,
+        quantity: usize,
+    ) -> Result<(), TradeError<C>> {
+        let offer = self.offer_mut(side)?;
+        if let Some(staged) = offer.items.iter_mut().find(|(k, _)| *k == key) {
+            staged.1 += quantity;
+        } else {
+            offer.items.push((key, quantity));
+        }
+        Ok(())
+    }
+
+    /// Removes items from the offer of the participant.
+    ///
+    /// Errors:
+    /// * NotOpen: The trade is completed or cancelled.
+    pub fn unstage_item(
+        &mut self,
+        side: TradeSide,
+        key: &K,
+        quantity: usize,
+    ) -> Result<(), TradeError<C>> {
+        let offer = self.offer_mut(side)?;
+        if let Some(staged) = offer.items.iter_mut().find(|(k, _)| k == key) {
+            staged.1 = staged.1.saturating_sub(quantity);
+        }
+        offer.items.retain(|(_, q)| *q > 0);
+        Ok(())
+    }
+
+    /// Sets the amount of a currency in the offer of the participant.
+    /// An amount of 0 removes the currency from the offer.
+    ///
+    /// Errors:
+    /// * NotOpen: The trade is completed or cancelled.
+    pub fn stage_currency(
+        &mut self,
+        side: TradeSide,
+        currency: C,
+        amount: i64,
+    ) -> Result<(), TradeError<C>> {
+        let offer = self.offer_mut(side)?;
+        offer.currencies.retain(|(c, _)| *c != currency);
+        if amount != 0 {
+            offer.currencies.push((currency, amount));
+        }
+        Ok(())
+    }
+
+    /// Sets whether the participant accepts the current state of the trade.
+    ///
+    /// Errors:
+    /// * NotOpen: The trade is completed or cancelled.
+    pub fn set_ready(&mut self, side: TradeSide, ready: bool) -> Result<(), TradeError<C>> {
+        if self.state != TradeState::Open {
+            return Err(TradeError::NotOpen);
+        }
+        match side {
+            TradeSide::First => self.first.ready = ready,
+            TradeSide::Second => self.second.ready = ready,
+        }
+        Ok(())
+    }
+
+    /// Checks if both participants are ready.
+    pub fn is_ready(&self) -> bool {
+        self.first.ready && self.second.ready
+    }
+
+    /// Cancels the trade.
+    ///
+    /// Errors:
+    /// * NotOpen: The trade is already completed or cancelled.
+    pub fn cancel(&mut self) -> Result<(), TradeError<C>> {
+        if self.state != TradeState::Open {
+            return Err(TradeError::NotOpen);
+        }
+        self.state = TradeState::Cancelled;
+        Ok(())
+    }
+
+    /// Exchanges the offers of both participants.
+    /// Either everything is exchanged, or nothing changes.
+    ///
+    /// Errors:
+    /// * NotOpen: The trade is completed or cancelled.
+    /// * NotReady: A participant is not ready.
+    /// * NotEnoughItems: The participant doesn't have the items they offered.
+    /// * InventoryFull: The received items don't fit in the inventory of the participant.
+    /// * Wallet: The wallet of the participant can't pay or receive the currencies.
+    pub fn commit<S: SlotType + Clone, U: Default + Clone + Debug + PartialEq, D: Default>(
+        &mut self,
+        first: (&mut Inventory<K, S, U>, &mut Wallet<C>),
+        second: (&mut Inventory<K, S, U>, &mut Wallet<C>),
+        item_defs: &ItemDefinitions<K, S, D>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), TradeError<C>> {
+        if self.state != TradeState::Open {
+            return Err(TradeError::NotOpen);
+        }
+        if !self.is_ready() {
+            return Err(TradeError::NotReady);
+        }
+        let mut first_new = (first.0.clone(), first.1.clone());
+        let mut second_new = (second.0.clone(), second.1.clone());
+        self.give(
+            TradeSide::First,
+            &mut first_new,
+            &mut second_new,
+            item_defs,
+            currency_defs,
+        )?;
+        self.give(
+            TradeSide::Second,
+            &mut second_new,
+            &mut first_new,
+            item_defs,
+            currency_defs,
+        )?;
+        *first.0 = first_new.0;
+        *first.1 = first_new.1;
+        *second.0 = second_new.0;
+        *second.1 = second_new.1;
+        self.state = TradeState::Completed;
+        Ok(())
+    }
+
+    /// Moves the offer of `side` from `from` to `to`.
+    fn give<S: SlotType + Clone, U: Default + Clone + Debug + PartialEq, D: Default>(
+        &self,
+        side: TradeSide,
+        from: &mut (Inventory<K, S, U>, Wallet<C>),
+        to: &mut (Inventory<K, S, U>, Wallet<C>),
+        item_defs: &ItemDefinitions<K, S, D>,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), TradeError<C>> {
+        let offer = self.offer(side);
+        for (key, quantity) in offer.items.iter() {
+            let items = from
+                .0
+                .delete_key(key, *quantity)
+                .map_err(|_| TradeError::NotEnoughItems(side))?;
+            to.0.insert(items, item_defs)
+                .map_err(|_| TradeError::InventoryFull(side.other()))?;
+        }
+        for (currency, amount) in offer.currencies.iter() {
+            from.1
+                .remove(currency, *amount, currency_defs)
+                .map_err(|e| TradeError::Wallet(side, e))?;
+            to.1.add(currency, *amount, currency_defs)
+                .map_err(|e| TradeError::Wallet(side.other(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// The errors that can happen when using a `TradeSession`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TradeError<C> {
+    /// The trade is completed or cancelled.
+    NotOpen,
+    /// A participant is not ready.
+    NotReady,
+    /// This participant doesn't have the items they offered.
+    NotEnoughItems(TradeSide),
+    /// The received items don't fit in the inventory of this participant.
+    InventoryFull(TradeSide),
+    /// The wallet of this participant can't pay or receive the currencies.
+    Wallet(TradeSide, WalletError<C>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn commit_exchanges_offers() {
+        let (item_defs, currency_defs) = (item_defs(), currency_defs());
+        let mut first = (Inventory::<u32, (), ()>::new_fixed(2), Wallet::new());
+        let mut second = (Inventory::<u32, (), ()>::new_fixed(2), Wallet::new());
+        first.0.insert(ItemInstance::new(1, 5), &item_defs).unwrap();
+        second.1.add(&0, 100, &currency_defs).unwrap();
+        let mut trade = TradeSession::new();
+        trade.stage_item(TradeSide::First, 1, 3).unwrap();
+        trade.stage_currency(TradeSide::Second, 0, 40).unwrap();
+        trade.set_ready(TradeSide::First, true).unwrap();
+        assert_eq!(
+            trade.commit(
+                (&mut first.0, &mut first.1),
+                (&mut second.0, &mut second.1),
+                &item_defs,
+                &currency_defs
+            ),
+            Err(TradeError::NotReady)
+        );
+        trade.set_ready(TradeSide::Second, true).unwrap();
+        trade
+            .commit(
+                (&mut first.0, &mut first.1),
+                (&mut second.0, &mut second.1),
+                &item_defs,
+                &currency_defs,
+            )
+            .unwrap();
+        assert_eq!(first.0.get(0).as_ref().unwrap().quantity, 2);
+        assert_eq!(second.0.get(0).as_ref().unwrap().quantity, 3);
+        assert_eq!(first.1.balance(&0), 40);
+        assert_eq!(second.1.balance(&0), 60);
+        assert_eq!(trade.state, TradeState::Completed);
+        assert_eq!(
+            trade.stage_item(TradeSide::First, 1, 1),
+            Err(TradeError::NotOpen)
+        );
+    }
+
+    #[test]
+    fn failed_commit_changes_nothing() {
+        let (item_defs, currency_defs) = (item_defs(), currency_defs());
+        let mut first = (Inventory::<u32, (), ()>::new_fixed(2), Wallet::new());
+        let mut second = (Inventory::<u32, (), ()>::new_fixed(2), Wallet::new());
+        first.0.insert(ItemInstance::new(1, 5), &item_defs).unwrap();
+        second.1.add(&0, 10, &currency_defs).unwrap();
+        let mut trade = TradeSession::new();
+        trade.stage_item(TradeSide::First, 1, 3).unwrap();
+        trade.stage_currency(TradeSide::Second, 0, 40).unwrap();
+        trade.set_ready(TradeSide::First, true).unwrap();
+        trade.set_ready(TradeSide::Second, true).unwrap();
+        assert!(matches!(
+            trade.commit(
+                (&mut first.0, &mut first.1),
+                (&mut second.0, &mut second.1),
+                &item_defs,
+                &currency_defs
+            ),
+            Err(TradeError::Wallet(TradeSide::Second, _))
+        ));
+        assert_eq!(first.0.get(0).as_ref().unwrap().quantity, 5);
+        assert!(second.0.get(0).is_none());
+        assert_eq!(first.1.balance(&0), 0);
+        assert_eq!(second.1.balance(&0), 10);
+        assert_eq!(trade.state, TradeState::Open);
+        // Changing an offer resets the ready flags.
+        trade.unstage_item(TradeSide::First, &1, 3).unwrap();
+        assert!(trade.offer(TradeSide::First).items.is_empty());
+        assert!(!trade.is_ready());
+    }
+}
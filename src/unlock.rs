@@ -1,13 +1,15 @@
-use crate::{StatCondition, UseMode};
-use std::collections::HashMap;
-use std::hash::Hash;
+use crate::*;
+use core::fmt::Debug;
+use core::hash::Hash;
 
 /// An unlockable element.
 /// It can be unlocked to access the inner value if all conditions are met:
 /// - `StatCondition`s
 /// - Item Conditions
 /// - Dependant unlockables were previously unlocked.
+/// - `ClockCondition`s, when a `GameClock` is provided.
 #[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
 pub struct Unlockable<U, K, S, I> {
     /// The key of this unlockable.
     pub id: U,
@@ -25,6 +27,11 @@ pub struct Unlockable<U, K, S, I> {
     /// If Unlockable B depends on A, then A must be unlocked before B can be unlocked.
     #[new(default)]
     pub unlock_dependencies: Vec<U>,
+    /// The conditions on the time of the `GameClock` required to unlock this element.
+    #[new(default)]
+    #[builder(default)]
+    #[serde(default)]
+    pub unlock_clock_conditions: Vec<ClockConditionGroup>,
 }
 
 impl<U, K, S, I> Unlockable<U, K, S, I> {
@@ -60,24 +67,132 @@ impl<U, K, S, I> Unlockable<U, K, S, I> {
 
     /// Inserts a new value without changing the lock.
     /// Returns the previous inner value.
-    pub fn set(&mut self, new: K) {
-        self.inner = new;
+    pub fn set(&mut self, new: K) -> K {
+        core::mem::replace(&mut self.inner, new)
     }
 
     /// Locks the inner value.
-    pub fn lock(&mut self) {
-        self.is_unlocked = true;
+    /// Returns true if it was unlocked.
+    pub fn lock(&mut self) -> bool {
+        let changed = self.is_unlocked;
+        self.is_unlocked = false;
+        changed
     }
 
-    /// Unlocks the inner value.
-    pub fn unlock(&mut self) {
-        self.is_unlocked = false;
+    /// Unlocks the inner value without checking the conditions.
+    /// Returns true if it was locked.
+    /// See `Unlockable::unlock_with` to unlock only if the conditions are met.
+    pub fn unlock(&mut self) -> bool {
+        let changed = !self.is_unlocked;
+        self.is_unlocked = true;
+        changed
     }
 
-    /// Verifies if the inner value is locked.
+    /// Verifies if the inner value is unlocked.
     pub fn is_unlocked(&self) -> bool {
         self.is_unlocked
     }
+
+    /// Checks if the clock conditions to unlock this element are met.
+    pub fn check_clock_conditions(&self, clock: &GameClock) -> bool {
+        self.unlock_clock_conditions.iter().all(|c| c.check(clock))
+    }
+}
+
+impl<U: Clone, K, S: Hash + Eq + Clone + Debug, I: Hash + Eq + Clone + Debug>
+    Unlockable<U, K, S, I>
+{
+    /// Checks if all the conditions to unlock this element are met.
+    /// `is_unlocked` must return whether the dependency with the provided id is unlocked.
+    ///
+    /// Errors:
+    /// * AlreadyUnlocked: This element is already unlocked.
+    /// * MissingDependency: This dependency is not unlocked yet.
+    /// * StatConditionNotMet: This stat condition is not met.
+    /// * Script: A script condition can't be checked using the `ScriptHost`.
+    /// * ItemConditionNotMet: This quantity of the item is not in the inventory. The quantities
+    ///   of items listed more than once are added together.
+    /// * ClockConditionNotMet: This clock condition is not met. A `clock` of None means that the
+    ///   clock conditions are not met.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_unlock<
+        F: Fn(&U) -> bool,
+        IT: SlotType,
+        CD: PartialEq + Default + Clone + Debug,
+    >(
+        &self,
+        is_unlocked: F,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        inventory: &Inventory<I, IT, CD>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Result<(), UnlockError<U, S, I>> {
+        if self.is_unlocked {
+            return Err(UnlockError::AlreadyUnlocked);
+        }
+        if let Some(dep) = self.unlock_dependencies.iter().find(|d| !is_unlocked(d)) {
+            return Err(UnlockError::MissingDependency(dep.clone()));
+        }
+        for c in self.unlock_stat_conditions.iter() {
+            if !c
+                .check_scripted(stats, stat_defs, scripts)
+                .map_err(UnlockError::Script)?
+            {
+                return Err(UnlockError::StatConditionNotMet(c.clone()));
+            }
+        }
+        for (key, _, _) in self.unlock_item_conditions.iter() {
+            let total = self
+                .unlock_item_conditions
+                .iter()
+                .filter(|(k, _, _)| k == key)
+                .map(|(_, quantity, _)| quantity)
+                .sum();
+            if !inventory.has_quantity(key, total) {
+                return Err(UnlockError::ItemConditionNotMet(key.clone(), total));
+            }
+        }
+        if let Some(c) = self.unlock_clock_conditions.iter().find(|c| match clock {
+            Some(clock) => !c.check(clock),
+            None => true,
+        }) {
+            return Err(UnlockError::ClockConditionNotMet(c.clone()));
+        }
+        Ok(())
+    }
+
+    /// Unlocks this element if all the conditions are met.
+    /// The items are then used according to their `UseMode`.
+    /// Nothing changes if an error happens.
+    /// `is_unlocked` must return whether the dependency with the provided id is unlocked.
+    ///
+    /// Errors:
+    /// See `Unlockable::check_unlock`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unlock_with<
+        F: Fn(&U) -> bool,
+        IT: SlotType + Clone,
+        CD: PartialEq + Default + Clone + Debug,
+    >(
+        &mut self,
+        is_unlocked: F,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        inventory: &mut Inventory<I, IT, CD>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Result<(), UnlockError<U, S, I>> {
+        self.check_unlock(is_unlocked, stats, stat_defs, inventory, clock, scripts)?;
+        let mut used = inventory.clone();
+        for (key, quantity, use_mode) in self.unlock_item_conditions.iter() {
+            used.use_key(key, *quantity, use_mode)
+                .map_err(|_| UnlockError::ItemConditionNotMet(key.clone(), *quantity))?;
+        }
+        *inventory = used;
+        self.unlock();
+        Ok(())
+    }
 }
 
 /// A structure holding all unlockables.
@@ -86,4 +201,265 @@ pub struct Unlockables<U: Hash + Eq, K, S, I> {
     /// The unlockables we are holding.
     pub unlockables: HashMap<U, Unlockable<U, K, S, I>>,
 }
-// TODO impl to try unlock and to try unlock recursively.
+
+impl<U: Hash + Eq + Clone, K, S, I> Unlockables<U, K, S, I> {
+    /// Checks if the unlockable exists and is unlocked.
+    pub fn is_unlocked(&self, id: &U) -> bool {
+        self.unlockables
+            .get(id)
+            .map(|u| u.is_unlocked())
+            .unwrap_or(false)
+    }
+
+    /// Checks if the unlockable is locked and all its dependencies are unlocked.
+    /// The stat and item conditions are not checked.
+    pub fn is_available(&self, id: &U) -> bool {
+        self.unlockables
+            .get(id)
+            .map(|u| !u.is_unlocked() && u.unlock_dependencies.iter().all(|d| self.is_unlocked(d)))
+            .unwrap_or(false)
+    }
+
+    /// Returns the ids of all the available unlockables.
+    /// See `Unlockables::is_available`.
+    pub fn available(&self) -> Vec<&U> {
+        self.unlockables
+            .keys()
+            .filter(|id| self.is_available(id))
+            .collect()
+    }
+
+    /// Returns the ids of the unlockables directly depending on this one.
+    pub fn dependents_of(&self, id: &U) -> Vec<&U> {
+        self.unlockables
+            .values()
+            .filter(|u| u.unlock_dependencies.contains(id))
+            .map(|u| &u.id)
+            .collect()
+    }
+
+    /// Returns the ids of the unlockables without dependencies.
+    pub fn roots(&self) -> Vec<&U> {
+        self.unlockables
+            .values()
+            .filter(|u| u.unlock_dependencies.is_empty())
+            .map(|u| &u.id)
+            .collect()
+    }
+
+    /// Iterates over the unlockables such that each unlockable comes after all its
+    /// dependencies.
+    /// Unlockables that are part of a dependency cycle or that depend on unknown unlockables are
+    /// skipped.
+    pub fn iter_sorted(&self) -> impl Iterator<Item = &Unlockable<U, K, S, I>> {
+        let mut remaining = self
+            .unlockables
+            .iter()
+            .map(|(id, u)| {
+                let deps = u.unlock_dependencies.iter().collect::<HashSet<_>>().len();
+                (id, deps)
+            })
+            .collect::<HashMap<_, _>>();
+        let mut ready = remaining
+            .iter()
+            .filter(|(_, deps)| **deps == 0)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        let mut sorted = vec![];
+        while let Some(id) = ready.pop() {
+            remaining.remove(id);
+            sorted.push(&self.unlockables[id]);
+            for dependent in self.dependents_of(id) {
+                if let Some(deps) = remaining.get_mut(dependent) {
+                    *deps -= 1;
+                    if *deps == 0 {
+                        ready.push(dependent);
+                    }
+                }
+            }
+        }
+        sorted.into_iter()
+    }
+}
+
+impl<U: Hash + Eq + Clone, K, S: Hash + Eq + Clone + Debug, I: Hash + Eq + Clone + Debug>
+    Unlockables<U, K, S, I>
+{
+    /// Attempts to unlock the unlockable.
+    /// See `Unlockable::unlock_with`.
+    ///
+    /// Errors:
+    /// * UnknownUnlockable: No unlockable uses this id.
+    /// * See `Unlockable::unlock_with`.
+    pub fn try_unlock<IT: SlotType + Clone, CD: PartialEq + Default + Clone + Debug>(
+        &mut self,
+        id: &U,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        inventory: &mut Inventory<I, IT, CD>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Result<(), UnlockError<U, S, I>> {
+        let mut unlockable = self
+            .unlockables
+            .remove(id)
+            .ok_or(UnlockError::UnknownUnlockable)?;
+        let result = unlockable.unlock_with(
+            |d| self.is_unlocked(d),
+            stats,
+            stat_defs,
+            inventory,
+            clock,
+            scripts,
+        );
+        self.unlockables.insert(id.clone(), unlockable);
+        result
+    }
+
+    /// Attempts to unlock all the locked dependencies of the unlockable, then the unlockable
+    /// itself.
+    /// Returns the ids of the unlocked unlockables, in the order they were unlocked.
+    ///
+    /// Errors:
+    /// See `Unlockables::try_unlock`. The unlockables unlocked before the error stay unlocked.
+    pub fn try_unlock_recursive<IT: SlotType + Clone, CD: PartialEq + Default + Clone + Debug>(
+        &mut self,
+        id: &U,
+        stats: &StatSet<S>,
+        stat_defs: &StatDefinitions<S>,
+        inventory: &mut Inventory<I, IT, CD>,
+        clock: Option<&GameClock>,
+        scripts: Option<&ScriptHost<S>>,
+    ) -> Result<Vec<U>, UnlockError<U, S, I>> {
+        let mut chain = self.missing_prerequisites(id);
+        chain.push(id.clone());
+        for u in chain.iter() {
+            self.try_unlock(u, stats, stat_defs, inventory, clock, scripts)?;
+        }
+        Ok(chain)
+    }
+
+    /// Returns all the locked unlockables that must be unlocked before this one, directly or
+    /// indirectly, in an order in which they can be unlocked.
+    pub fn missing_prerequisites(&self, id: &U) -> Vec<U> {
+        let mut missing = vec![];
+        let mut visited = vec![id.clone()];
+        self.collect_missing(id, &mut visited, &mut missing);
+        missing
+    }
+
+    fn collect_missing(&self, id: &U, visited: &mut Vec<U>, missing: &mut Vec<U>) {
+        if let Some(unlockable) = self.unlockables.get(id) {
+            for dep in unlockable.unlock_dependencies.iter() {
+                if visited.contains(dep) {
+                    continue;
+                }
+                visited.push(dep.clone());
+                if !self.is_unlocked(dep) {
+                    self.collect_missing(dep, visited, missing);
+                    missing.push(dep.clone());
+                }
+            }
+        }
+    }
+}
+
+/// The errors that can happen when unlocking an `Unlockable`.
+/// They describe which condition failed, so that it can be shown to the user.
+#[derive(Debug, Clone)]
+pub enum UnlockError<U, S, I> {
+    /// No unlockable uses this id.
+    UnknownUnlockable,
+    /// The unlockable is already unlocked.
+    AlreadyUnlocked,
+    /// This dependency must be unlocked first.
+    MissingDependency(U),
+    /// This stat condition is not met.
+    StatConditionNotMet(StatCondition<S>),
+    /// A script condition can't be checked.
+    Script(ScriptError),
+    /// This quantity of the item is required in the inventory.
+    ItemConditionNotMet(I, usize),
+    /// This clock condition is not met.
+    ClockConditionNotMet(ClockConditionGroup),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlock_adds_up_duplicate_items() {
+        let item_defs = ItemDefinitions::<u32, (), ()>::from(vec![ItemDefinition::new(
+            1,
+            (),
+            "page".into(),
+            "Page".to_string(),
+            "".into(),
+            Some(10),
+            None,
+        )]);
+        let stat_defs = StatDefinitions::<u32>::default();
+        let stats = stat_defs.to_statset();
+        let mut unlockable = Unlockable::<u32, (), u32, u32>::new(1, (), false);
+        unlockable.unlock_item_conditions =
+            vec![(1, 2, UseMode::Consume), (1, 2, UseMode::Consume)];
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(1);
+        inventory
+            .insert(ItemInstance::new(1, 3), &item_defs)
+            .unwrap();
+        assert!(matches!(
+            unlockable.unlock_with(|_| true, &stats, &stat_defs, &mut inventory, None, None),
+            Err(UnlockError::ItemConditionNotMet(1, 4))
+        ));
+        assert_eq!(inventory.get(0).as_ref().unwrap().quantity, 3);
+        assert!(!unlockable.is_unlocked());
+        inventory
+            .insert(ItemInstance::new(1, 1), &item_defs)
+            .unwrap();
+        unlockable
+            .unlock_with(|_| true, &stats, &stat_defs, &mut inventory, None, None)
+            .unwrap();
+        assert!(unlockable.is_unlocked() && !inventory.has(&1));
+    }
+
+    #[test]
+    fn unlock_checks_clock_conditions() {
+        let stat_defs = StatDefinitions::<u32>::default();
+        let stats = stat_defs.to_statset();
+        let mut unlockable = Unlockable::<u32, (), u32, u32>::new(1, (), false);
+        unlockable.unlock_clock_conditions = vec![ClockCondition::Day.into()];
+        let mut inventory = Inventory::<u32, (), ()>::new_fixed(1);
+        let mut clock = GameClock::new(100.0, 7, 30, 4, 0.25, 0.75);
+        assert!(matches!(
+            unlockable.unlock_with(
+                |_| true,
+                &stats,
+                &stat_defs,
+                &mut inventory,
+                Some(&clock),
+                None
+            ),
+            Err(UnlockError::ClockConditionNotMet(
+                ConditionGroup::Condition(ClockCondition::Day)
+            ))
+        ));
+        clock.update(50.0);
+        // The conditions can't be met without a clock.
+        assert!(matches!(
+            unlockable.unlock_with(|_| true, &stats, &stat_defs, &mut inventory, None, None),
+            Err(UnlockError::ClockConditionNotMet(_))
+        ));
+        unlockable
+            .unlock_with(
+                |_| true,
+                &stats,
+                &stat_defs,
+                &mut inventory,
+                Some(&clock),
+                None,
+            )
+            .unwrap();
+        assert!(unlockable.is_unlocked());
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
 /// Base struct for the user of a game.
 #[derive(Debug, Clone, Serialize, Deserialize, new)]
 pub struct User<T> {
@@ -1,6 +1,10 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
 // TODO think of other properties that are particular to groups.
 /// A clan.
 /// This is a group of multiple users.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
 pub struct UserGroup {
     /// The id of this clan.
     pub id: i32,
@@ -1,3 +1,273 @@
-// ban
-// kick
-// mute
+use crate::*;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::Rng;
+
+/// A ban preventing a user from authenticating.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct Ban {
+    /// Why the user was banned.
+    pub reason: String,
+    /// The time at which the ban ends, in seconds.
+    /// None means that the ban is permanent.
+    pub expires_at: Option<f64>,
+}
+
+impl Ban {
+    /// Checks if the ban still applies.
+    pub fn is_active(&self, now: f64) -> bool {
+        self.expires_at.map(|e| now < e).unwrap_or(true)
+    }
+}
+
+/// A registered user with its moderation state.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct UserAccount<T> {
+    /// The user.
+    pub user: User<T>,
+    /// Whether the user was deleted. Deleted users are kept so that they can be restored.
+    #[new(default)]
+    pub deleted: bool,
+    /// The ban of the user, if any.
+    #[new(default)]
+    pub ban: Option<Ban>,
+}
+
+/// Registers users, allocates their ids and keeps track of bans and authentication tokens.
+/// Names are unique among the users that are not deleted.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct UserManager<T> {
+    /// The accounts mapped by user id.
+    #[new(default)]
+    accounts: HashMap<i32, UserAccount<T>>,
+    /// The ids of the users that are not deleted, mapped by name.
+    #[new(default)]
+    names: HashMap<String, i32>,
+    /// The id given to the next registered user.
+    #[new(default)]
+    next_id: i32,
+    /// The authentication tokens and the id of the user they belong to.
+    /// Tokens are not saved.
+    #[new(default)]
+    #[serde(skip)]
+    tokens: HashMap<String, i32>,
+}
+
+impl<T> Default for UserManager<T> {
+    fn default() -> Self {
+        UserManager::new()
+    }
+}
+
+impl<T> UserManager<T> {
+    /// Registers a new user and returns its id.
+    ///
+    /// Errors:
+    /// * NameTaken: Another user uses this name.
+    pub fn register(&mut self, name: String, data: T) -> Result<i32, UserError> {
+        if self.names.contains_key(&name) {
+            return Err(UserError::NameTaken);
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.names.insert(name.clone(), id);
+        self.accounts
+            .insert(id, UserAccount::new(User::new(id, name, data)));
+        Ok(id)
+    }
+
+    /// Returns the account of the user, even if it is deleted.
+    pub fn account(&self, id: i32) -> Option<&UserAccount<T>> {
+        self.accounts.get(&id)
+    }
+
+    /// Returns the user with this id, unless it is deleted.
+    pub fn get(&self, id: i32) -> Option<&User<T>> {
+        self.accounts
+            .get(&id)
+            .filter(|a| !a.deleted)
+            .map(|a| &a.user)
+    }
+
+    /// Returns the user with this id mutably, unless it is deleted.
+    /// Use `UserManager::rename` to change the name.
+    pub fn get_mut(&mut self, id: i32) -> Option<&mut User<T>> {
+        self.accounts
+            .get_mut(&id)
+            .filter(|a| !a.deleted)
+            .map(|a| &mut a.user)
+    }
+
+    /// Returns the user with this name, unless it is deleted.
+    pub fn get_by_name(&self, name: &str) -> Option<&User<T>> {
+        self.names.get(name).and_then(|id| self.get(*id))
+    }
+
+    /// Iterates over the users that are not deleted.
+    pub fn iter(&self) -> impl Iterator<Item = &User<T>> {
+        self.accounts
+            .values()
+            .filter(|a| !a.deleted)
+            .map(|a| &a.user)
+    }
+
+    /// Changes the name of the user.
+    ///
+    /// Errors:
+    /// * UnknownUser: No user uses this id, or it is deleted.
+    /// * NameTaken: Another user uses this name.
+    pub fn rename(&mut self, id: i32, name: String) -> Result<(), UserError> {
+        if self
+            .names
+            .get(&name)
+            .map(|other| *other != id)
+            .unwrap_or(false)
+        {
+            return Err(UserError::NameTaken);
+        }
+        let user = self.get_mut(id).ok_or(UserError::UnknownUser)?;
+        let old = core::mem::replace(&mut user.name, name.clone());
+        self.names.remove(&old);
+        self.names.insert(name, id);
+        Ok(())
+    }
+
+    /// Deletes the user. Its name can be used by other users.
+    /// The tokens of the user are revoked.
+    ///
+    /// Errors:
+    /// * UnknownUser: No user uses this id, or it is already deleted.
+    pub fn delete(&mut self, id: i32) -> Result<(), UserError> {
+        let account = self
+            .accounts
+            .get_mut(&id)
+            .filter(|a| !a.deleted)
+            .ok_or(UserError::UnknownUser)?;
+        account.deleted = true;
+        self.names.remove(&account.user.name);
+        self.revoke_tokens(id);
+        Ok(())
+    }
+
+    /// Restores a deleted user.
+    ///
+    /// Errors:
+    /// * UnknownUser: No deleted user uses this id.
+    /// * NameTaken: Another user took the name of the deleted user.
+    pub fn restore(&mut self, id: i32) -> Result<(), UserError> {
+        let account = self
+            .accounts
+            .get_mut(&id)
+            .filter(|a| a.deleted)
+            .ok_or(UserError::UnknownUser)?;
+        if self.names.contains_key(&account.user.name) {
+            return Err(UserError::NameTaken);
+        }
+        account.deleted = false;
+        self.names.insert(account.user.name.clone(), id);
+        Ok(())
+    }
+
+    /// Bans the user, replacing its current ban.
+    /// The tokens of the user are revoked.
+    ///
+    /// Errors:
+    /// * UnknownUser: No user uses this id.
+    pub fn ban(&mut self, id: i32, ban: Ban) -> Result<(), UserError> {
+        self.accounts
+            .get_mut(&id)
+            .ok_or(UserError::UnknownUser)?
+            .ban = Some(ban);
+        self.revoke_tokens(id);
+        Ok(())
+    }
+
+    /// Removes the ban of the user.
+    /// Returns the removed ban, if any.
+    pub fn unban(&mut self, id: i32) -> Option<Ban> {
+        self.accounts.get_mut(&id).and_then(|a| a.ban.take())
+    }
+
+    /// Returns the ban of the user if it still applies.
+    pub fn active_ban(&self, id: i32, now: f64) -> Option<&Ban> {
+        self.accounts
+            .get(&id)
+            .and_then(|a| a.ban.as_ref())
+            .filter(|b| b.is_active(now))
+    }
+
+    /// Creates a new random authentication token for the user.
+    ///
+    /// Errors:
+    /// * UnknownUser: No user uses this id, or it is deleted.
+    /// * Banned: The user is banned.
+    #[cfg(feature = "std")]
+    pub fn issue_token(&mut self, id: i32, now: f64) -> Result<String, UserError> {
+        self.issue_token_with(id, now, &mut thread_rng())
+    }
+
+    /// Same as `UserManager::issue_token`, using the provided random number generator.
+    /// The generator must be cryptographically secure, otherwise the tokens can be guessed.
+    ///
+    /// Errors:
+    /// See `UserManager::issue_token`.
+    pub fn issue_token_with<G: Rng>(
+        &mut self,
+        id: i32,
+        now: f64,
+        rng: &mut G,
+    ) -> Result<String, UserError> {
+        self.check_can_authenticate(id, now)?;
+        let token = (0..32)
+            .map(|_| format!("{:02x}", rng.gen::<u8>()))
+            .collect::<String>();
+        self.tokens.insert(token.clone(), id);
+        Ok(token)
+    }
+
+    /// Returns the id of the user the token belongs to.
+    ///
+    /// Errors:
+    /// * InvalidToken: The token doesn't exist or was revoked.
+    /// * UnknownUser: The user is deleted.
+    /// * Banned: The user is banned.
+    pub fn authenticate(&self, token: &str, now: f64) -> Result<i32, UserError> {
+        let id = *self.tokens.get(token).ok_or(UserError::InvalidToken)?;
+        self.check_can_authenticate(id, now)?;
+        Ok(id)
+    }
+
+    /// Revokes the token.
+    /// Returns true if it existed.
+    pub fn revoke_token(&mut self, token: &str) -> bool {
+        self.tokens.remove(token).is_some()
+    }
+
+    /// Revokes all the tokens of the user.
+    pub fn revoke_tokens(&mut self, id: i32) {
+        self.tokens.retain(|_, user| *user != id);
+    }
+
+    fn check_can_authenticate(&self, id: i32, now: f64) -> Result<(), UserError> {
+        if self.get(id).is_none() {
+            return Err(UserError::UnknownUser);
+        }
+        if self.active_ban(id, now).is_some() {
+            return Err(UserError::Banned);
+        }
+        Ok(())
+    }
+}
+
+/// The errors that can happen when using the `UserManager`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserError {
+    /// No user uses this id.
+    UnknownUser,
+    /// Another user uses this name.
+    NameTaken,
+    /// The user is banned.
+    Banned,
+    /// The authentication token doesn't exist or was revoked.
+    InvalidToken,
+}
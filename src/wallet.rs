@@ -0,0 +1,340 @@
+use crate::*;
+use core::hash::Hash;
+
+/// The definition of a currency.
+#[derive(Debug, Clone, Serialize, Deserialize, new, Builder)]
+#[cfg_attr(not(feature = "std"), builder(no_std))]
+pub struct CurrencyDefinition<C> {
+    /// The key of the currency.
+    pub key: C,
+    /// The display name of the currency.
+    pub name: String,
+    /// The minimum amount of this currency a `Wallet` can hold.
+    /// Use a negative value to allow debt.
+    #[new(default)]
+    #[builder(default)]
+    pub min_value: i64,
+    /// The maximum amount of this currency a `Wallet` can hold, if any.
+    #[new(default)]
+    #[builder(default)]
+    pub max_value: Option<i64>,
+    /// The value of one unit of this currency, relative to the other currencies.
+    /// Used to exchange currencies.
+    pub value: f64,
+}
+
+/// The definitions of all known currencies.
+#[derive(Debug, Clone, Serialize, Deserialize, new)]
+pub struct CurrencyDefinitions<C: Hash + Eq> {
+    /// The definitions.
+    pub defs: HashMap<C, CurrencyDefinition<C>>,
+}
+
+impl<C: Hash + Eq> Default for CurrencyDefinitions<C> {
+    fn default() -> Self {
+        Self {
+            defs: HashMap::default(),
+        }
+    }
+}
+
+impl<C: Hash + Eq + Clone> From<Vec<CurrencyDefinition<C>>> for CurrencyDefinitions<C> {
+    fn from(t: Vec<CurrencyDefinition<C>>) -> Self {
+        let defs = t
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect::<HashMap<_, _>>();
+        Self::new(defs)
+    }
+}
+
+impl<C: Hash + Eq> CurrencyDefinitions<C> {
+    fn get(&self, currency: &C) -> &CurrencyDefinition<C> {
+        self.defs
+            .get(currency)
+            .expect("Tried to get unknown currency key.")
+    }
+
+    /// Converts an amount of a currency into another currency, using their values.
+    /// The result is rounded down.
+    pub fn exchange_rate(&self, from: &C, to: &C, amount: i64) -> i64 {
+        (amount as f64 * self.get(from).value / self.get(to).value).floor() as i64
+    }
+}
+
+/// Holds amounts of multiple currencies.
+/// Amounts passed to the methods can't be negative.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, new)]
+pub struct Wallet<C: Hash + Eq> {
+    /// The amount of each currency held.
+    /// Missing currencies are considered to be at 0.
+    #[new(default)]
+    pub balances: HashMap<C, i64>,
+}
+
+impl<C: Hash + Eq + Clone> Wallet<C> {
+    /// Returns the amount held of this currency.
+    pub fn balance(&self, currency: &C) -> i64 {
+        *self.balances.get(currency).unwrap_or(&0)
+    }
+
+    /// Checks if the amount can be added without going over the maximum of the currency.
+    pub fn can_add(
+        &self,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> bool {
+        self.check_add(currency, amount, currency_defs).is_ok()
+    }
+
+    /// Checks if the amount can be removed without going under the minimum of the currency.
+    pub fn can_remove(
+        &self,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> bool {
+        self.check_remove(currency, amount, currency_defs).is_ok()
+    }
+
+    /// Adds the amount of currency and returns the new balance.
+    ///
+    /// Errors:
+    /// * AboveMaximum: The balance would go over the maximum of the currency.
+    /// * NegativeAmount: The amount is negative.
+    pub fn add(
+        &mut self,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<i64, WalletError<C>> {
+        let new = self.check_add(currency, amount, currency_defs)?;
+        self.balances.insert(currency.clone(), new);
+        Ok(new)
+    }
+
+    /// Removes the amount of currency and returns the new balance.
+    ///
+    /// Errors:
+    /// * NotEnough: The balance would go under the minimum of the currency.
+    /// * NegativeAmount: The amount is negative.
+    pub fn remove(
+        &mut self,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<i64, WalletError<C>> {
+        let new = self.check_remove(currency, amount, currency_defs)?;
+        self.balances.insert(currency.clone(), new);
+        Ok(new)
+    }
+
+    /// Moves the amount of currency from this wallet to the other wallet.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotEnough: This wallet doesn't hold enough of the currency.
+    /// * AboveMaximum: The other wallet would go over the maximum of the currency.
+    /// * NegativeAmount: The amount is negative.
+    pub fn transfer(
+        &mut self,
+        other: &mut Wallet<C>,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), WalletError<C>> {
+        self.check_remove(currency, amount, currency_defs)?;
+        other.check_add(currency, amount, currency_defs)?;
+        self.remove(currency, amount, currency_defs)?;
+        other.add(currency, amount, currency_defs)?;
+        Ok(())
+    }
+
+    /// Converts an amount of a currency into another currency, using their values.
+    /// Returns the amount received.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotEnough: This wallet doesn't hold enough of the `from` currency.
+    /// * AboveMaximum: This wallet would go over the maximum of the `to` currency.
+    /// * NegativeAmount: The amount is negative.
+    pub fn exchange(
+        &mut self,
+        from: &C,
+        to: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<i64, WalletError<C>> {
+        let received = currency_defs.exchange_rate(from, to, amount);
+        self.check_remove(from, amount, currency_defs)?;
+        self.check_add(to, received, currency_defs)?;
+        self.remove(from, amount, currency_defs)?;
+        self.add(to, received, currency_defs)?;
+        Ok(received)
+    }
+
+    /// Checks if all the amounts of the price can be removed from this wallet.
+    pub fn can_afford(&self, price: &[(C, i64)], currency_defs: &CurrencyDefinitions<C>) -> bool {
+        self.check_price(price, currency_defs).is_ok()
+    }
+
+    /// Removes all the amounts of the price from this wallet.
+    /// Nothing changes if an error happens.
+    ///
+    /// Errors:
+    /// * NotEnough: This wallet doesn't hold enough of this currency.
+    /// * NegativeAmount: The amount is negative.
+    pub fn pay(
+        &mut self,
+        price: &[(C, i64)],
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), WalletError<C>> {
+        self.check_price(price, currency_defs)?;
+        for (currency, amount) in price {
+            self.remove(currency, *amount, currency_defs)?;
+        }
+        Ok(())
+    }
+
+    fn check_add(
+        &self,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<i64, WalletError<C>> {
+        if amount < 0 {
+            return Err(WalletError::NegativeAmount(currency.clone()));
+        }
+        let new = self
+            .balance(currency)
+            .checked_add(amount)
+            .ok_or_else(|| WalletError::AboveMaximum(currency.clone()))?;
+        match currency_defs.get(currency).max_value {
+            Some(max) if new > max => Err(WalletError::AboveMaximum(currency.clone())),
+            _ => Ok(new),
+        }
+    }
+
+    fn check_remove(
+        &self,
+        currency: &C,
+        amount: i64,
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<i64, WalletError<C>> {
+        if amount < 0 {
+            return Err(WalletError::NegativeAmount(currency.clone()));
+        }
+        let new = self
+            .balance(currency)
+            .checked_sub(amount)
+            .ok_or_else(|| WalletError::NotEnough(currency.clone()))?;
+        if new < currency_defs.get(currency).min_value {
+            Err(WalletError::NotEnough(currency.clone()))
+        } else {
+            Ok(new)
+        }
+    }
+
+    /// Checks the price as a whole, in case the same currency is present multiple times.
+    fn check_price(
+        &self,
+        price: &[(C, i64)],
+        currency_defs: &CurrencyDefinitions<C>,
+    ) -> Result<(), WalletError<C>> {
+        let mut total = HashMap::new();
+        for (currency, amount) in price {
+            if *amount < 0 {
+                return Err(WalletError::NegativeAmount(currency.clone()));
+            }
+            *total.entry(currency).or_insert(0) += amount;
+        }
+        for (currency, amount) in total {
+            self.check_remove(currency, amount, currency_defs)?;
+        }
+        Ok(())
+    }
+}
+
+/// The errors that can happen when using a `Wallet`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletError<C> {
+    /// Not enough of this currency is held.
+    NotEnough(C),
+    /// The maximum amount of this currency would be exceeded.
+    AboveMaximum(C),
+    /// A negative amount of this currency was given.
+    NegativeAmount(C),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defs() -> CurrencyDefinitions<u8> {
+        let mut gems = CurrencyDefinition::new(1, "Gems".to_string(), 10.0);
+        gems.max_value = Some(100);
+        CurrencyDefinitions::from(vec![
+            CurrencyDefinition::new(0, "Gold".to_string(), 1.0),
+            gems,
+        ])
+    }
+
+    #[test]
+    fn negative_amounts_are_rejected() {
+        let defs = defs();
+        let mut a = Wallet::new();
+        let mut b = Wallet::new();
+        a.add(&0, 50, &defs).unwrap();
+        b.add(&0, 50, &defs).unwrap();
+        assert_eq!(a.add(&0, -10, &defs), Err(WalletError::NegativeAmount(0)));
+        assert_eq!(
+            a.remove(&0, -10, &defs),
+            Err(WalletError::NegativeAmount(0))
+        );
+        assert_eq!(
+            a.transfer(&mut b, &0, -10, &defs),
+            Err(WalletError::NegativeAmount(0))
+        );
+        assert_eq!(
+            a.pay(&[(0, 20), (0, -20)], &defs),
+            Err(WalletError::NegativeAmount(0))
+        );
+        assert_eq!(a.balance(&0), 50);
+        assert_eq!(b.balance(&0), 50);
+    }
+
+    #[test]
+    fn transfer_is_atomic() {
+        let defs = defs();
+        let mut a = Wallet::new();
+        let mut b = Wallet::new();
+        a.add(&1, 50, &defs).unwrap();
+        b.add(&1, 80, &defs).unwrap();
+        assert_eq!(
+            a.transfer(&mut b, &1, 30, &defs),
+            Err(WalletError::AboveMaximum(1))
+        );
+        assert_eq!(
+            a.transfer(&mut b, &1, 60, &defs),
+            Err(WalletError::NotEnough(1))
+        );
+        a.transfer(&mut b, &1, 20, &defs).unwrap();
+        assert_eq!(a.balance(&1), 30);
+        assert_eq!(b.balance(&1), 100);
+    }
+
+    #[test]
+    fn pay_checks_the_whole_price() {
+        let defs = defs();
+        let mut a = Wallet::new();
+        a.add(&0, 30, &defs).unwrap();
+        assert!(!a.can_afford(&[(0, 20), (0, 20)], &defs));
+        assert_eq!(
+            a.pay(&[(0, 20), (0, 20)], &defs),
+            Err(WalletError::NotEnough(0))
+        );
+        a.pay(&[(0, 20), (0, 5)], &defs).unwrap();
+        assert_eq!(a.balance(&0), 5);
+    }
+}